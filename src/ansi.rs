@@ -0,0 +1,239 @@
+use std::time::{Duration, Instant};
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+
+/// Just enough of a terminal to make sense of the escape sequences programs send to colour
+/// their output or redraw a progress bar. Only the line being written is tracked: colours and
+/// anything that moves between lines are dropped, and cursor moves within the line are followed.
+#[derive(Default)]
+pub struct Terminal {
+    /// The start of an escape sequence that was split across reads.
+    pending: String,
+    /// Where the next char goes in the current line, if not at the end of it.
+    col: Option<usize>,
+    /// When the command that's running now was sent. The shell marks the end of each command
+    /// with its exit status, and we note how it went there.
+    started: Option<Instant>,
+    /// The exit status of the last command to finish, if it had one.
+    pub last_status: Option<i32>,
+    /// Whether the shell marks the end of commands at all; older rcfiles don't.
+    pub marks_commands: bool,
+}
+
+/// A one-line summary of how a command went: `✓ 2.3s`, or `✗ exit 1 0.4s`.
+pub fn annotation(status: Option<i32>, elapsed: Duration) -> String {
+    let secs = elapsed.as_secs_f32();
+    let time = if secs < 60.0 {
+        format!("{secs:.1}s")
+    } else {
+        let secs = elapsed.as_secs();
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    };
+    match status {
+        Some(0) => format!("✓ {time}"),
+        Some(code) => format!("✗ exit {code} {time}"),
+        None => format!("? {time}"),
+    }
+}
+
+impl Terminal {
+    /// Note that a command was just sent to the shell, to time it until the shell marks its end.
+    pub fn command_sent(&mut self) {
+        self.started = Some(Instant::now());
+    }
+
+    /// Whether a command we sent is still running, as far as the shell has told us.
+    pub fn running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// Apply a chunk of output to the last line written so far, returning the text to replace
+    /// that line with: the line as updated, followed by any new lines.
+    pub fn apply(&mut self, last_line: &str, chunk: &str) -> String {
+        let mut output = String::new();
+        let mut line: Vec<char> = last_line.chars().collect();
+        let mut col = self.col.unwrap_or(line.len()).min(line.len());
+
+        let input = std::mem::take(&mut self.pending) + chunk;
+        let mut chars = input.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\n' => {
+                    output.extend(line.drain(..));
+                    output.push('\n');
+                    col = 0;
+                }
+                '\r' => col = 0,
+                '\x08' => col = col.saturating_sub(1),
+                BEL => {}
+                ESC => {
+                    let mut sequence = String::from(ESC);
+                    let complete = match chars.next() {
+                        Some('[') => {
+                            sequence.push('[');
+                            // Parameters and intermediates, then a single final byte.
+                            let mut params = String::new();
+                            let mut done = None;
+                            for c in chars.by_ref() {
+                                sequence.push(c);
+                                if ('\x40'..='\x7e').contains(&c) {
+                                    done = Some(c);
+                                    break;
+                                }
+                                params.push(c);
+                            }
+                            if let Some(command) = done {
+                                csi(command, &params, &mut line, &mut col);
+                            }
+                            done.is_some()
+                        }
+                        Some(']') => {
+                            // An OS command, like setting the window title: ends with BEL or ST.
+                            sequence.push(']');
+                            let mut body = String::new();
+                            let mut done = false;
+                            while let Some(c) = chars.next() {
+                                sequence.push(c);
+                                if c == BEL || (c == ESC && chars.next_if_eq(&'\\').is_some()) {
+                                    done = true;
+                                    break;
+                                }
+                                body.push(c);
+                            }
+                            // The shell's mark for the end of a command, with its exit status.
+                            if let Some(status) = body.strip_prefix("133;D").filter(|_| done) {
+                                self.marks_commands = true;
+                                if let Some(started) = self.started.take() {
+                                    let status = status.trim_start_matches(';').parse().ok();
+                                    self.last_status = status;
+                                    if !line.is_empty() {
+                                        output.extend(line.drain(..));
+                                        output.push('\n');
+                                    }
+                                    output.push_str(&annotation(status, started.elapsed()));
+                                    output.push('\n');
+                                    col = 0;
+                                }
+                            }
+                            done
+                        }
+                        // Some other two-char sequence; nothing we need to act on.
+                        Some(_) => true,
+                        None => false,
+                    };
+                    if !complete {
+                        self.pending = sequence;
+                    }
+                }
+                c if c.is_control() && c != '\t' => {}
+                c => {
+                    if col < line.len() {
+                        line[col] = c;
+                    } else {
+                        line.resize(col, ' ');
+                        line.push(c);
+                    }
+                    col += 1;
+                }
+            }
+        }
+
+        self.col = if col < line.len() { Some(col) } else { None };
+        output.extend(line);
+        output
+    }
+}
+
+/// Handle a control sequence (`ESC [ params command`) within the current line.
+fn csi(command: char, params: &str, line: &mut Vec<char>, col: &mut usize) {
+    let n = params
+        .split(';')
+        .next()
+        .and_then(|p| p.parse::<usize>().ok())
+        .unwrap_or(0);
+    match command {
+        // Cursor forward, back, and to a column; counts default to one.
+        'C' => {
+            *col += n.max(1);
+            if *col > line.len() {
+                line.resize(*col, ' ');
+            }
+        }
+        'D' => *col = col.saturating_sub(n.max(1)),
+        'G' => {
+            *col = n.max(1) - 1;
+            if *col > line.len() {
+                line.resize(*col, ' ');
+            }
+        }
+        // Erase in line: to the end, to the start, or all of it.
+        'K' => match n {
+            0 => line.truncate(*col),
+            1 => {
+                let end = (*col).min(line.len());
+                line[..end].fill(' ');
+            }
+            _ => {
+                line.clear();
+                line.resize(*col, ' ');
+            }
+        },
+        // Colours, and moves we can't follow in a line-based buffer.
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terminal() {
+        let mut term = Terminal::default();
+        assert_eq!(
+            term.apply("$ ", "ls\n\x1b[01;34mdir\x1b[0m  file\n"),
+            "$ ls\ndir  file\n"
+        );
+
+        // A progress bar, redrawn in place, with a sequence split across reads.
+        let mut term = Terminal::default();
+        let line = term.apply("", "10%\r");
+        assert_eq!(line, "10%");
+        let line = term.apply(&line, "5\x1b[");
+        assert_eq!(line, "50%");
+        let line = term.apply(&line, "K\r100%\r\n");
+        assert_eq!(line, "100%\n");
+
+        let mut term = Terminal::default();
+        assert_eq!(term.apply("", "\x1b]0;title\x07abc\x08\x08X"), "aXc");
+        assert_eq!(term.apply("aXc", "\x1b[2GY\x1b[K"), "aY");
+    }
+
+    #[test]
+    fn test_command_marks() {
+        // The end of a command gets a note, but only if we sent one.
+        let mut term = Terminal::default();
+        assert_eq!(term.apply("", "\x1b]133;D;0\x07$ "), "$ ");
+        assert!(term.marks_commands);
+        assert_eq!(term.last_status, None);
+        term.command_sent();
+        assert!(term.running());
+        assert_eq!(
+            term.apply("", "oops\x1b]133;D;2\x1b\\$ "),
+            "oops\n✗ exit 2 0.0s\n$ "
+        );
+        assert_eq!(term.last_status, Some(2));
+        assert!(!term.running());
+    }
+
+    #[test]
+    fn test_annotation() {
+        assert_eq!(annotation(Some(0), Duration::from_millis(2345)), "✓ 2.3s");
+        assert_eq!(
+            annotation(Some(1), Duration::from_millis(400)),
+            "✗ exit 1 0.4s"
+        );
+        assert_eq!(annotation(Some(0), Duration::from_secs(65)), "✓ 1m05s");
+    }
+}
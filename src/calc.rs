@@ -0,0 +1,174 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_spaces(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_spaces();
+        self.chars.peek().copied()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.chars.next();
+                Ok(())
+            }
+            Some(c) => Err(format!("expected '{expected}' but found '{c}'")),
+            None => Err(format!("expected '{expected}'")),
+        }
+    }
+
+    fn sum(&mut self) -> Result<f64, String> {
+        let mut value = self.product()?;
+        loop {
+            match self.peek() {
+                Some('+') => {
+                    self.chars.next();
+                    value += self.product()?;
+                }
+                Some('-' | '−') => {
+                    self.chars.next();
+                    value -= self.product()?;
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<f64, String> {
+        let mut value = self.power()?;
+        loop {
+            match self.peek() {
+                Some('*' | 'x' | '×') => {
+                    self.chars.next();
+                    value *= self.power()?;
+                }
+                Some('/' | '÷') => {
+                    self.chars.next();
+                    let divisor = self.power()?;
+                    if divisor == 0.0 {
+                        return Err("division by zero".to_string());
+                    }
+                    value /= divisor;
+                }
+                Some('%') => {
+                    self.chars.next();
+                    let divisor = self.power()?;
+                    if divisor == 0.0 {
+                        return Err("division by zero".to_string());
+                    }
+                    value %= divisor;
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    fn power(&mut self) -> Result<f64, String> {
+        let base = self.unary()?;
+        if self.peek() == Some('^') {
+            self.chars.next();
+            // Right-associative, so 2^3^2 is 2^9.
+            let exponent = self.power()?;
+            return Ok(base.powf(exponent));
+        }
+        Ok(base)
+    }
+
+    fn unary(&mut self) -> Result<f64, String> {
+        match self.peek() {
+            Some('-' | '−') => {
+                self.chars.next();
+                Ok(-self.unary()?)
+            }
+            Some('+') => {
+                self.chars.next();
+                self.unary()
+            }
+            _ => self.atom(),
+        }
+    }
+
+    fn atom(&mut self) -> Result<f64, String> {
+        match self.peek() {
+            Some('(') => {
+                self.chars.next();
+                let value = self.sum()?;
+                self.expect(')')?;
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let mut number = String::new();
+                while let Some(c) = self
+                    .chars
+                    .next_if(|c| c.is_ascii_digit() || *c == '.' || *c == ',')
+                {
+                    // Allow thousands separators, like 1,000.
+                    if c != ',' {
+                        number.push(c);
+                    }
+                }
+                number
+                    .parse()
+                    .map_err(|_| format!("'{number}' is not a number"))
+            }
+            Some(c) => Err(format!("unexpected '{c}'")),
+            None => Err("expected a number".to_string()),
+        }
+    }
+}
+
+/// Evaluate an arithmetic expression: `+ - * / % ^`, parentheses, and decimal numbers.
+/// Handwriting tends to use `x` and `÷` as well, so those are accepted too. A trailing `=` is
+/// ignored, so `2 + 2 =` is fine.
+pub fn evaluate(expression: &str) -> Result<f64, String> {
+    let expression = expression.trim().trim_end_matches('=');
+    let mut parser = Parser {
+        chars: expression.chars().peekable(),
+    };
+    let value = parser.sum()?;
+    match parser.peek() {
+        None => Ok(value),
+        Some(c) => Err(format!("unexpected '{c}'")),
+    }
+}
+
+/// Format a result without any float noise: whole numbers print without a decimal point,
+/// and everything else is rounded to a reasonable number of places.
+pub fn format(value: f64) -> String {
+    if value.is_finite() && value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        let rounded = format!("{value:.8}");
+        let trimmed = rounded.trim_end_matches('0').trim_end_matches('.');
+        trimmed.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        let eval = |s: &str| evaluate(s).map(format);
+        assert_eq!(eval("1 + 2 * 3"), Ok("7".to_string()));
+        assert_eq!(eval("(1 + 2) * 3 ="), Ok("9".to_string()));
+        assert_eq!(eval("2^3^2"), Ok("512".to_string()));
+        assert_eq!(eval("-4 + 10 / 4"), Ok("-1.5".to_string()));
+        assert_eq!(eval("17 % 5"), Ok("2".to_string()));
+        assert_eq!(eval("3 x 4 ÷ 2"), Ok("6".to_string()));
+        assert_eq!(eval("1,000 + 0.1 + 0.2"), Ok("1000.3".to_string()));
+        assert!(eval("1 / 0").is_err());
+        assert!(eval("(1 + 2").is_err());
+        assert!(eval("1 + two").is_err());
+    }
+}
@@ -0,0 +1,117 @@
+use crate::config::CleanupConfig;
+
+/// What a cleanup changed, for reporting back to the user.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Cleanup {
+    pub trailing: usize,
+    pub indents: usize,
+    pub blank_lines: usize,
+}
+
+impl Cleanup {
+    pub fn is_empty(&self) -> bool {
+        self == &Cleanup::default()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "Cleaned up {} trailing whitespace, {} indents, {} blank lines",
+            self.trailing, self.indents, self.blank_lines
+        )
+    }
+}
+
+/// Rewrite leading whitespace to the configured style, keeping its width.
+fn normalize_indent(line: &str, config: &CleanupConfig) -> String {
+    let tab_width = config.tab_width.max(1);
+    let body = line.trim_start_matches([' ', '\t']);
+    let indent = &line[..(line.len() - body.len())];
+    let mut width = 0;
+    for c in indent.chars() {
+        if c == '\t' {
+            width = (width / tab_width + 1) * tab_width;
+        } else {
+            width += 1;
+        }
+    }
+    let mut result = if config.use_tabs {
+        let mut tabs = "\t".repeat(width / tab_width);
+        tabs.push_str(&" ".repeat(width % tab_width));
+        tabs
+    } else {
+        " ".repeat(width)
+    };
+    result.push_str(body);
+    result
+}
+
+/// Strip trailing whitespace, normalize indentation, and squeeze runs of three or more blank
+/// lines down to one.
+pub fn clean(text: &str, config: &CleanupConfig) -> (String, Cleanup) {
+    let mut cleanup = Cleanup::default();
+    let mut lines: Vec<String> = vec![];
+    let mut blank_run = 0;
+
+    for line in text.split('\n') {
+        let trimmed = line.trim_end();
+        if trimmed.len() != line.len() {
+            cleanup.trailing += 1;
+        }
+        if trimmed.is_empty() {
+            blank_run += 1;
+            lines.push(String::new());
+            continue;
+        }
+        if blank_run >= 3 {
+            lines.truncate(lines.len() - blank_run + 1);
+            cleanup.blank_lines += blank_run - 1;
+        }
+        blank_run = 0;
+
+        let indented = normalize_indent(trimmed, config);
+        if indented != trimmed {
+            cleanup.indents += 1;
+        }
+        lines.push(indented);
+    }
+    if blank_run >= 3 {
+        lines.truncate(lines.len() - blank_run + 1);
+        cleanup.blank_lines += blank_run - 1;
+    }
+
+    (lines.join("\n"), cleanup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean() {
+        let config = CleanupConfig {
+            tab_width: 4,
+            use_tabs: false,
+        };
+        let text = "one  \n\tindented\n\n\n\n  \ntwo\n\nthree";
+        let (cleaned, cleanup) = clean(text, &config);
+        assert_eq!(cleaned, "one\n    indented\n\ntwo\n\nthree");
+        assert_eq!(
+            cleanup,
+            Cleanup {
+                trailing: 2,
+                indents: 1,
+                blank_lines: 3
+            }
+        );
+
+        let config = CleanupConfig {
+            tab_width: 4,
+            use_tabs: true,
+        };
+        let (cleaned, _) = clean("      six", &config);
+        assert_eq!(cleaned, "\t  six");
+        let (cleaned, cleanup) = clean(&cleaned, &config);
+        assert_eq!(cleaned, "\t  six");
+        assert!(cleanup.is_empty());
+    }
+}
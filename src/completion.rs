@@ -0,0 +1,89 @@
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// Don't bother offering more than this many candidates; a longer prefix will narrow it down.
+const MAX_CANDIDATES: usize = 64;
+
+/// The word at the end of the input that we'd complete, and whether it's in command position:
+/// the start of the line or just after a `|`, `;`, or `&`.
+pub fn last_word(input: &str) -> (&str, bool) {
+    let start = input
+        .rfind(|c: char| c.is_whitespace() || "|;&".contains(c))
+        .map_or(0, |i| {
+            i + input[i..].chars().next().map_or(1, char::len_utf8)
+        });
+    let before = input[..start].trim_end();
+    let is_command = before.is_empty() || before.ends_with(['|', ';', '&']);
+    (&input[start..], is_command)
+}
+
+/// Ask bash what the word could complete to, as it would when you hit tab: commands at the
+/// start of the line and paths everywhere else. Directories get a trailing slash.
+pub fn candidates(working_dir: &Path, word: &str, is_command: bool) -> io::Result<Vec<String>> {
+    let action = if is_command && !word.contains('/') {
+        "-c"
+    } else {
+        "-f"
+    };
+    // The word is passed as an argument, not spliced into the script, so it can't run anything.
+    let output = Command::new("/bin/bash")
+        .args([
+            "-c",
+            &format!("compgen {action} -- \"$1\""),
+            "compgen",
+            word,
+        ])
+        .current_dir(working_dir)
+        .output()?;
+    let mut candidates: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| {
+            if working_dir.join(line).is_dir() {
+                format!("{line}/")
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+    candidates.truncate(MAX_CANDIDATES);
+    Ok(candidates)
+}
+
+/// The longest prefix that all the candidates share.
+pub fn common_prefix(candidates: &[String]) -> &str {
+    let first = match candidates.first() {
+        Some(first) => first.as_str(),
+        None => return "",
+    };
+    let mut end = first.len();
+    for candidate in &candidates[1..] {
+        end = first[..end]
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(end.min(candidate.len()), |((i, _), _)| i);
+    }
+    &first[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completion() {
+        assert_eq!(last_word("gi"), ("gi", true));
+        assert_eq!(last_word("cat src/ma"), ("src/ma", false));
+        assert_eq!(last_word("ls | gr"), ("gr", true));
+        assert_eq!(last_word("ls "), ("", false));
+
+        let words = |ws: &[&str]| ws.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        assert_eq!(common_prefix(&words(&["main.rs", "markdown.rs"])), "ma");
+        assert_eq!(common_prefix(&words(&["src/", "src/a"])), "src/");
+        assert_eq!(common_prefix(&words(&["only"])), "only");
+        assert_eq!(common_prefix(&[]), "");
+    }
+}
@@ -1,21 +1,174 @@
+use crate::util::parse_code_point;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(default)]
 pub struct Config {
     pub cell_height: i32,
     pub extra_chars: Vec<String>,
+    pub palette: Vec<String>,
     pub experimental: bool,
+    pub tab_strip: bool,
+    pub nav_panel: String,
+    pub past_end: String,
+    pub autosave_secs: u64,
+    pub save_on_switch: bool,
+    pub save_on_suspend: bool,
+    pub verify_saves: bool,
+    pub review_saves: bool,
+    pub backup_saves: bool,
+    pub highlight: bool,
+    pub record_secs: u64,
+    pub journal: String,
+    pub dictionary: String,
+    pub starter_templates: String,
+    pub template_profiles: Vec<String>,
+    pub print: PrintConfig,
+    pub memory: MemoryConfig,
+    pub cleanup: CleanupConfig,
+    pub shell: ShellConfig,
+    /// Formatter commands, by file extension.
+    pub formatters: BTreeMap<String, String>,
 }
 
+/// Page layout for exported documents.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(default)]
+pub struct PrintConfig {
+    pub page_lines: usize,
+    pub margin_lines: usize,
+    pub margin_cols: usize,
+    pub header: String,
+    pub footer: String,
+    pub line_numbers: bool,
+}
+
+impl Default for PrintConfig {
+    fn default() -> Self {
+        PrintConfig {
+            page_lines: 66,
+            margin_lines: 3,
+            margin_cols: 4,
+            header: "{title}".to_string(),
+            footer: "{date}  page {page} of {pages}".to_string(),
+            line_numbers: false,
+        }
+    }
+}
+
+/// Caps on how much memory long sessions are allowed to hold on to.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(default)]
+pub struct MemoryConfig {
+    pub shell_output_kb: usize,
+    pub shell_output_lines: usize,
+    pub atlas_entries: usize,
+    pub live_tabs: usize,
+    pub large_file_kb: u64,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        MemoryConfig {
+            shell_output_kb: 4096,
+            shell_output_lines: 10000,
+            atlas_entries: 4096,
+            live_tabs: 8,
+            large_file_kb: 4096,
+        }
+    }
+}
+
+/// How the "clean up whitespace" command should indent lines.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(default)]
+pub struct CleanupConfig {
+    pub tab_width: usize,
+    pub use_tabs: bool,
+}
+
+impl Default for CleanupConfig {
+    fn default() -> Self {
+        CleanupConfig {
+            tab_width: 4,
+            use_tabs: false,
+        }
+    }
+}
+
+/// The environment that shells are started with, on top of Sill's own.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(default)]
+pub struct ShellConfig {
+    pub path: Vec<String>,
+    pub lang: String,
+    pub term: String,
+}
+
+impl Default for ShellConfig {
+    fn default() -> Self {
+        ShellConfig {
+            path: vec!["/opt/bin".to_string(), "/opt/sbin".to_string()],
+            lang: String::new(),
+            term: "dumb".to_string(),
+        }
+    }
+}
+
+impl ShellConfig {
+    /// The `PATH` for a shell: the configured directories, then the existing ones that aren't
+    /// among them.
+    pub fn path_var(&self, existing: &str) -> String {
+        let mut dirs: Vec<&str> = self.path.iter().map(|d| d.as_str()).collect();
+        for dir in existing.split(':').filter(|d| !d.is_empty()) {
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+        dirs.join(":")
+    }
+}
+
+/// The settings a workspace's `.sill.toml` may change. A folder can come from anywhere, so
+/// anything that runs commands or sets up shells, like `formatters` or `shell`, or that points
+/// at files elsewhere, stays as the user configured it.
+const WORKSPACE_KEYS: &[&str] = &[
+    "extra_chars",
+    "palette",
+    "past_end",
+    "autosave_secs",
+    "highlight",
+    "print",
+    "cleanup",
+];
+
 impl Config {
+    /// Apply the settings in a workspace's `.sill.toml` that it's allowed to change, returning
+    /// the names of any others, which are left alone.
+    pub fn with_workspace_overrides(
+        &self,
+        overrides: &str,
+    ) -> anyhow::Result<(Config, Vec<String>)> {
+        let mut allowed: toml::value::Table = toml::from_str(overrides)?;
+        let ignored: Vec<String> = allowed
+            .keys()
+            .filter(|key| !WORKSPACE_KEYS.contains(&key.as_str()))
+            .cloned()
+            .collect();
+        for key in &ignored {
+            allowed.remove(key);
+        }
+        let mut config = toml::Value::try_from(self)?;
+        merge(&mut config, toml::Value::Table(allowed));
+        Ok((config.try_into()?, ignored))
+    }
+
     // TODO: split out ConfigFile struct to handle errors properly here.
     pub fn extra_chars<'a>(&'a self) -> impl Iterator<Item = char> + 'a {
         self.extra_chars.iter().filter_map(|s| {
-            if let Some(hex) = s.strip_prefix("U+") {
-                u32::from_str_radix(hex, 16)
-                    .ok()
-                    .and_then(|i| i.try_into().ok())
+            if s.starts_with("U+") {
+                parse_code_point(s)
             } else {
                 let mut chars = s.chars();
                 let result = chars.next();
@@ -28,12 +181,85 @@ impl Config {
     }
 }
 
+fn merge(base: &mut toml::Value, overrides: toml::Value) {
+    match (base, overrides) {
+        (toml::Value::Table(base), toml::Value::Table(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Config {
             cell_height: 40,
             extra_chars: vec![],
+            palette: [
+                "`", "~", "^", "|", "\\", "{", "}", "<", ">", "—", "…", "→", "°", "•",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
             experimental: false,
+            tab_strip: false,
+            nav_panel: String::new(),
+            past_end: "spaces".to_string(),
+            autosave_secs: 30,
+            save_on_switch: false,
+            save_on_suspend: false,
+            verify_saves: false,
+            review_saves: false,
+            backup_saves: false,
+            highlight: true,
+            record_secs: 0,
+            journal: "journal.md".to_string(),
+            dictionary: String::new(),
+            starter_templates: "print".to_string(),
+            template_profiles: vec![],
+            print: PrintConfig::default(),
+            memory: MemoryConfig::default(),
+            cleanup: CleanupConfig::default(),
+            shell: ShellConfig::default(),
+            formatters: BTreeMap::from([
+                ("py".to_string(), "black -q -".to_string()),
+                ("rs".to_string(), "rustfmt --edition 2021".to_string()),
+            ]),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_var() {
+        let shell = ShellConfig::default();
+        assert_eq!(
+            shell.path_var("/usr/bin:/opt/bin:/bin"),
+            "/opt/bin:/opt/sbin:/usr/bin:/bin"
+        );
+        assert_eq!(shell.path_var(""), "/opt/bin:/opt/sbin");
+    }
+
+    #[test]
+    fn test_workspace_overrides() {
+        let overrides =
+            "highlight = false\nformatters = { md = \"rm -rf ~\" }\n[shell]\npath = [\".\"]";
+        let (config, ignored) = Config::default()
+            .with_workspace_overrides(overrides)
+            .unwrap();
+        assert!(!config.highlight);
+        assert_eq!(config.formatters, Config::default().formatters);
+        assert_eq!(config.shell, Config::default().shell);
+        assert_eq!(ignored, vec!["formatters", "shell"]);
+    }
+}
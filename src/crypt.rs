@@ -0,0 +1,150 @@
+use crate::pty::{self, Pty};
+use crate::util;
+use anyhow::{anyhow, Context};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use std::thread;
+
+/// The tools we can use to encrypt files, picked by extension. Both are driven with a
+/// passphrase, and plaintext only ever passes through pipes, never the disk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cipher {
+    Gpg,
+    Age,
+}
+
+impl Cipher {
+    pub fn for_path(path: &Path) -> Option<Cipher> {
+        match path.extension()?.to_str()? {
+            "gpg" => Some(Cipher::Gpg),
+            "age" => Some(Cipher::Age),
+            _ => None,
+        }
+    }
+
+    fn command(self) -> Command {
+        match self {
+            Cipher::Gpg => {
+                // The passphrase is the first line on stdin, and the agent shouldn't hang on to it.
+                let mut command = Command::new("gpg");
+                command.args([
+                    "--batch",
+                    "--yes",
+                    "--quiet",
+                    "--no-symkey-cache",
+                    "--pinentry-mode",
+                    "loopback",
+                    "--passphrase-fd",
+                    "0",
+                ]);
+                command
+            }
+            Cipher::Age => Command::new("age"),
+        }
+    }
+}
+
+/// Run the command with the given input, and any prompts for the passphrase answered. `age`
+/// only reads passphrases from a terminal, so it gets a pseudo-terminal of its own to read them
+/// from; `gpg` takes the passphrase ahead of the input.
+fn run(
+    cipher: Cipher,
+    mut command: Command,
+    passphrase: &str,
+    prompts: usize,
+    input: &str,
+) -> anyhow::Result<Output> {
+    let mut stdin = String::new();
+    let mut terminal = None;
+    match cipher {
+        Cipher::Gpg => {
+            stdin.push_str(passphrase);
+            stdin.push('\n');
+            command.stderr(Stdio::piped());
+        }
+        Cipher::Age => {
+            let (pty, tty) = Pty::open()?;
+            command.stderr(Stdio::from(tty));
+            pty::controlled_by(&mut command, 2);
+            terminal = Some(pty);
+        }
+    }
+    stdin.push_str(input);
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("running {:?}", command.get_program()))?;
+    if let Some(pty) = &mut terminal {
+        for _ in 0..prompts {
+            writeln!(pty.writer(), "{passphrase}")?;
+        }
+    }
+    let mut child_stdin = child.stdin.take().expect("piped stdin");
+    let writer = thread::spawn(move || child_stdin.write_all(stdin.as_bytes()));
+    let output = child.wait_with_output()?;
+    writer
+        .join()
+        .map_err(|_| anyhow!("writing to {:?} panicked", command.get_program()))??;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        let message = message.trim().lines().last().unwrap_or("wrong passphrase?");
+        return Err(anyhow!("{message}"));
+    }
+    Ok(output)
+}
+
+/// Decrypt the file at the path.
+pub fn decrypt(cipher: Cipher, path: &Path, passphrase: &str) -> anyhow::Result<String> {
+    let mut command = cipher.command();
+    command.arg("--decrypt").arg(path);
+    let output = run(cipher, command, passphrase, 1, "")?;
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Encrypt the contents to the file at the path. The ciphertext comes back over a pipe and is
+/// written with `util::write_atomic`, so a failure can't leave the original half-written.
+pub fn encrypt(
+    cipher: Cipher,
+    path: &Path,
+    passphrase: &str,
+    contents: &str,
+) -> anyhow::Result<()> {
+    let mut command = cipher.command();
+    match cipher {
+        Cipher::Gpg => command.arg("--symmetric"),
+        Cipher::Age => command.arg("--passphrase"),
+    };
+    // age asks for the passphrase twice, to confirm it.
+    let output = run(cipher, command, passphrase, 2, contents)?;
+    util::write_atomic(path, &output.stdout)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_gpg_round_trip() {
+        if Command::new("gpg").arg("--version").output().is_err() {
+            return;
+        }
+        let dir = TempDir::new("crypt");
+        let path = dir.join("secret.txt.gpg");
+        assert_eq!(Cipher::for_path(&path), Some(Cipher::Gpg));
+
+        encrypt(Cipher::Gpg, &path, "hunter2", "dear diary\n").unwrap();
+        let ciphertext = fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&ciphertext).contains("diary"));
+        assert_eq!(
+            decrypt(Cipher::Gpg, &path, "hunter2").unwrap(),
+            "dear diary\n"
+        );
+        assert!(decrypt(Cipher::Gpg, &path, "wrong").is_err());
+    }
+}
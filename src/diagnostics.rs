@@ -0,0 +1,84 @@
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+/// One thing that commonly goes wrong on the device, and whether it has.
+#[derive(Clone, Debug)]
+pub struct Check {
+    pub name: &'static str,
+    /// What's wrong, if anything.
+    pub problem: Option<String>,
+    /// What to do about it, if there's a problem.
+    pub fix: &'static str,
+}
+
+impl Check {
+    pub fn new(name: &'static str, result: Result<(), String>, fix: &'static str) -> Check {
+        Check {
+            name,
+            problem: result.err(),
+            fix,
+        }
+    }
+}
+
+/// Whether there's a program at the path that we can run.
+pub fn executable(path: &Path) -> Result<(), String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    if !metadata.is_file() || metadata.permissions().mode() & 0o111 == 0 {
+        return Err(format!("{} isn't an executable file", path.display()));
+    }
+    Ok(())
+}
+
+/// Whether we can create files in the directory, creating it first if need be. Permissions
+/// alone don't tell: a full disk or a read-only mount only shows up on writing.
+pub fn writable_dir(dir: &Path) -> Result<(), String> {
+    let fail = |e: std::io::Error| format!("{}: {e}", dir.display());
+    fs::create_dir_all(dir).map_err(fail)?;
+    let probe = dir.join(".sill-write-test");
+    fs::write(&probe, b"ok").map_err(fail)?;
+    fs::remove_file(&probe).map_err(fail)
+}
+
+/// The results as text, problems first, for a report tab or the terminal.
+pub fn report(checks: &[Check]) -> String {
+    let problems = checks.iter().filter(|c| c.problem.is_some()).count();
+    let mut report = match problems {
+        0 => "Everything looks fine.\n\n".to_string(),
+        1 => "Found 1 problem.\n\n".to_string(),
+        n => format!("Found {n} problems.\n\n"),
+    };
+    for check in checks.iter().filter(|c| c.problem.is_some()) {
+        let problem = check.problem.as_deref().unwrap_or_default();
+        report.push_str(&format!("✗ {}: {problem}\n  {}\n", check.name, check.fix));
+    }
+    for check in checks.iter().filter(|c| c.problem.is_none()) {
+        report.push_str(&format!("✓ {}\n", check.name));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    #[test]
+    fn test_diagnostics() {
+        assert!(executable(Path::new("/bin/sh")).is_ok());
+        assert!(executable(Path::new("/nonexistent/bash")).is_err());
+        assert!(executable(Path::new("/etc")).is_err());
+        let dir = TempDir::new("diagnostics");
+        assert!(writable_dir(&dir.join("data")).is_ok());
+
+        let checks = [
+            Check::new("shell", Ok(()), "install a shell"),
+            Check::new("templates", Err("bad JSON".to_string()), "restore them"),
+        ];
+        assert_eq!(
+            report(&checks),
+            "Found 1 problem.\n\n✗ templates: bad JSON\n  restore them\n✓ shell\n"
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// How much worse than the best match, relative to its score, a letter can score and still be
+/// swapped in to make a word.
+const MAX_PENALTY: f32 = 0.5;
+
+/// A list of known words, for fixing up handwriting that was recognized as something that isn't
+/// one.
+pub struct Dictionary {
+    words: HashSet<String>,
+}
+
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphabetic() || c == '\''
+}
+
+impl Dictionary {
+    pub fn new<'a>(words: impl IntoIterator<Item = &'a str>) -> Dictionary {
+        Dictionary {
+            words: words
+                .into_iter()
+                .map(|w| w.trim())
+                .filter(|w| !w.is_empty())
+                .map(String::from)
+                .collect(),
+        }
+    }
+
+    /// Load a wordlist with one word per line, like `/usr/share/dict/words`.
+    pub fn load(path: &Path) -> io::Result<Dictionary> {
+        Ok(Dictionary::new(fs::read_to_string(path)?.lines()))
+    }
+
+    /// Whether the word is in the list, as written or in lower case.
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains(word) || self.words.contains(&word.to_lowercase())
+    }
+
+    /// Given the recognizer's guesses for each letter of a word, best first with their scores,
+    /// find the single letter to change that turns it into a known word. Of all the ways to do
+    /// that, this picks the one the recognizer thought was the closest call.
+    pub fn correct(&self, letters: &[Vec<(char, f32)>]) -> Option<(usize, char)> {
+        let word: Vec<char> = letters.iter().filter_map(|l| Some(l.first()?.0)).collect();
+        if word.len() < 3 || word.len() != letters.len() {
+            return None;
+        }
+        if self.contains(&word.iter().collect::<String>()) {
+            return None;
+        }
+        let mut best: Option<(f32, usize, char)> = None;
+        for (i, guesses) in letters.iter().enumerate() {
+            let (_, score) = guesses[0];
+            for &(c, alternative) in &guesses[1..] {
+                let penalty = (alternative - score) / score.max(f32::EPSILON);
+                if !is_word_char(c) || penalty > MAX_PENALTY {
+                    continue;
+                }
+                if best.is_some_and(|(p, _, _)| p <= penalty) {
+                    continue;
+                }
+                let mut fixed = word.clone();
+                fixed[i] = c;
+                if self.contains(&fixed.iter().collect::<String>()) {
+                    best = Some((penalty, i, c));
+                }
+            }
+        }
+        best.map(|(_, i, c)| (i, c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_correct() {
+        let dictionary = Dictionary::new(["hello", "cat", "cot"]);
+        let sure = |c: char| vec![(c, 1.0)];
+        assert!(dictionary.contains("Hello"));
+
+        // "hellc": the c was nearly an o.
+        let mut letters: Vec<_> = "hell".chars().map(sure).collect();
+        letters.push(vec![('c', 1.0), ('o', 1.2)]);
+        assert_eq!(dictionary.correct(&letters), Some((4, 'o')));
+
+        // Too far off to be worth the guess.
+        letters[4] = vec![('c', 1.0), ('o', 2.0)];
+        assert_eq!(dictionary.correct(&letters), None);
+
+        // "cut" could be "cat" or "cot"; the closer call wins.
+        let letters = vec![
+            sure('c'),
+            vec![('u', 1.0), ('o', 1.3), ('a', 1.1)],
+            sure('t'),
+        ];
+        assert_eq!(dictionary.correct(&letters), Some((1, 'a')));
+
+        // Already a word.
+        let letters: Vec<_> = "cat".chars().map(sure).collect();
+        assert_eq!(dictionary.correct(&letters), None);
+    }
+}
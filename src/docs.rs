@@ -0,0 +1,108 @@
+use crate::util;
+use anyhow::{anyhow, bail};
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Whether the text looks like the name of a command we could look up: no spaces, paths, or
+/// anything else a shell would treat specially.
+pub fn is_topic(text: &str) -> bool {
+    !text.is_empty()
+        && !text.starts_with('-')
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._+-".contains(c))
+}
+
+/// How long to let a command run to print its `--help` before giving up on it.
+const HELP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The manual page for a command, as plain text `cols` wide, if there is one.
+pub fn manual(topic: &str, path: &str, cols: usize) -> Option<String> {
+    if !is_topic(topic) {
+        return None;
+    }
+    let output = Command::new("man")
+        .arg(topic)
+        .env("PATH", path)
+        .env("MANPAGER", "cat")
+        .env("PAGER", "cat")
+        .env("MANWIDTH", cols.to_string())
+        .env("GROFF_NO_SGR", "1")
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    (output.status.success() && !output.stdout.is_empty())
+        .then(|| strip_overstrike(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// The program a command names, found on the `PATH` the way the shell would find it.
+pub fn which(topic: &str, path: &str) -> Option<PathBuf> {
+    if !is_topic(topic) {
+        return None;
+    }
+    env::split_paths(path)
+        .map(|dir| dir.join(topic))
+        .find(|program| {
+            fs::metadata(program).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        })
+}
+
+/// Whatever a command prints for `--help`, as the fallback when there's no manual page. Many
+/// small devices ship without `man`, so that's the common case. This runs the program, so
+/// only do it when asked to, and it's killed if it doesn't finish in a few seconds.
+pub fn help(topic: &str, path: &str, cols: usize) -> anyhow::Result<String> {
+    let program = which(topic, path).ok_or_else(|| anyhow!("{topic} isn't a program"))?;
+    let mut command = Command::new(&program);
+    command
+        .arg("--help")
+        .env("PATH", path)
+        .env("COLUMNS", cols.to_string());
+    let output = match util::run_with_timeout(&mut command, None, HELP_TIMEOUT) {
+        Ok(output) => output,
+        Err(e) if e.kind() == ErrorKind::TimedOut => bail!("{topic} --help {e}"),
+        Err(e) => bail!("running {}: {e}", program.display()),
+    };
+    // Plenty of commands print their help to stderr, and exit non-zero for good measure.
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    if text.trim().is_empty() {
+        bail!("no documentation for {topic}");
+    }
+    Ok(strip_overstrike(&text))
+}
+
+/// Drop the backspaces that manual pages use for bold and underline: `X\bX` is a bold X, and
+/// `_\bX` an underlined one.
+fn strip_overstrike(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '\u{8}' {
+            result.pop();
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_docs() {
+        assert!(is_topic("ls"));
+        assert!(is_topic("git-log"));
+        assert!(!is_topic("rm -rf"));
+        assert!(!is_topic("/bin/ls"));
+        assert!(!is_topic("--help"));
+        assert_eq!(strip_overstrike("N\u{8}NA\u{8}AME _\u{8}x"), "NAME x");
+        assert_eq!(which("sh", "/nowhere:/bin"), Some(PathBuf::from("/bin/sh")));
+        assert_eq!(which("../bin/sh", "/bin"), None);
+    }
+}
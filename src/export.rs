@@ -0,0 +1,470 @@
+use crate::pdf::{self, Image, Pdf};
+use crate::PrintConfig;
+use armrest::ink::Ink;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Files a figure may point to when its target has no extension, in order of preference.
+const FIGURE_EXTENSIONS: &[&str] = &["svg", "png"];
+
+/// A `![caption](target)` directive on a line of its own, referencing a sketch or image file.
+pub struct Figure<'a> {
+    pub caption: &'a str,
+    pub target: &'a str,
+}
+
+impl Figure<'_> {
+    pub fn parse(line: &str) -> Option<Figure<'_>> {
+        let rest = line.trim().strip_prefix("![")?;
+        let (caption, rest) = rest.split_once("](")?;
+        let target = rest.strip_suffix(')')?;
+        if target.is_empty() || target.contains(')') {
+            return None;
+        }
+        Some(Figure { caption, target })
+    }
+
+    /// Find the file this figure refers to, relative to the document's directory.
+    pub fn resolve(&self, dir: &Path) -> Option<PathBuf> {
+        let path = dir.join(self.target);
+        if path.is_file() {
+            return Some(path);
+        }
+        FIGURE_EXTENSIONS
+            .iter()
+            .map(|ext| path.with_extension(ext))
+            .find(|p| p.is_file())
+    }
+}
+
+/// Whether a buffer line is a figure directive; cheap enough to call while rendering.
+pub fn is_figure_line(line: &[char]) -> bool {
+    let first = line.iter().find(|c| !c.is_whitespace());
+    first == Some(&'!') && Figure::parse(&line.iter().collect::<String>()).is_some()
+}
+
+/// Replace figure directives with a plain-text placeholder naming the file they refer to.
+pub fn expand_figures(text: &str, dir: &Path) -> String {
+    let mut result = String::with_capacity(text.len());
+    for (i, line) in text.split('\n').enumerate() {
+        if i != 0 {
+            result.push('\n');
+        }
+        match Figure::parse(line) {
+            Some(figure) => match figure.resolve(dir) {
+                Some(path) => {
+                    let file = path.file_name().unwrap_or_default().to_string_lossy();
+                    result.push_str(&format!("[figure: {} ({})]", figure.caption, file));
+                }
+                None => result.push_str(&format!("[missing figure: {}]", figure.target)),
+            },
+            None => result.push_str(line),
+        }
+    }
+    result
+}
+
+/// The values that can be substituted into a page header or footer.
+struct PageInfo<'a> {
+    title: &'a str,
+    date: &'a str,
+    page: usize,
+    pages: usize,
+}
+
+impl PageInfo<'_> {
+    fn expand(&self, template: &str) -> String {
+        template
+            .replace("{title}", self.title)
+            .replace("{date}", self.date)
+            .replace("{page}", &self.page.to_string())
+            .replace("{pages}", &self.pages.to_string())
+    }
+}
+
+/// Render some ink as an SVG document, cropped to the bounds of the ink.
+pub fn ink_to_svg(ink: &Ink) -> String {
+    const PADDING: f32 = 10.0;
+    let width = ink.x_range.max - ink.x_range.min + PADDING * 2.0;
+    let height = ink.y_range.max - ink.y_range.min + PADDING * 2.0;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width:.0}\" height=\"{height:.0}\">\n"
+    );
+    for stroke in ink.strokes() {
+        let points: Vec<String> = stroke
+            .iter()
+            .map(|p| {
+                let x = p.x - ink.x_range.min + PADDING;
+                let y = p.y - ink.y_range.min + PADDING;
+                format!("{x:.1},{y:.1}")
+            })
+            .collect();
+        svg.push_str(&format!(
+            "  <polyline points=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"3\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/>\n",
+            points.join(" ")
+        ));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// A drawing read back from an SVG written by `ink_to_svg`.
+pub struct Sketch {
+    pub width: f32,
+    pub height: f32,
+    pub strokes: Vec<Vec<(f32, f32)>>,
+}
+
+/// The value of the first `name="..."` attribute in the string.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!(" {name}=\""))? + name.len() + 3;
+    let len = tag[start..].find('"')?;
+    Some(&tag[start..start + len])
+}
+
+/// Parse the SVGs we write for sketches. This is not a general SVG parser: anything that isn't
+/// a polyline is ignored.
+pub fn parse_sketch(svg: &str) -> Option<Sketch> {
+    let width = attribute(svg, "width")?.parse().ok()?;
+    let height = attribute(svg, "height")?.parse().ok()?;
+    let strokes = svg
+        .split("<polyline")
+        .skip(1)
+        .filter_map(|tag| {
+            attribute(tag, "points")?
+                .split_whitespace()
+                .map(|point| {
+                    let (x, y) = point.split_once(',')?;
+                    Some((x.parse().ok()?, y.parse().ok()?))
+                })
+                .collect()
+        })
+        .collect();
+    Some(Sketch {
+        width,
+        height,
+        strokes,
+    })
+}
+
+/// Split a document into pages of at most `body_lines` lines. Always returns at least one page.
+pub fn pages(text: &str, body_lines: usize) -> Vec<Vec<&str>> {
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.is_empty() {
+        return vec![vec![]];
+    }
+    lines
+        .chunks(body_lines.max(1))
+        .map(|chunk| chunk.to_vec())
+        .collect()
+}
+
+impl PrintConfig {
+    /// The number of lines of document text that fit on each page, after margins, headers and
+    /// footers are accounted for.
+    pub fn body_lines(&self) -> usize {
+        let furniture = |template: &str| if template.is_empty() { 0 } else { 2 };
+        self.page_lines
+            .saturating_sub(
+                self.margin_lines * 2 + furniture(&self.header) + furniture(&self.footer),
+            )
+            .max(1)
+    }
+}
+
+/// Lay out a document as paginated plain text, with pages separated by form feeds.
+pub fn paginate(text: &str, title: &str, date: &str, config: &PrintConfig) -> String {
+    let body_lines = config.body_lines();
+    let pages = pages(text, body_lines);
+    let indent = " ".repeat(config.margin_cols);
+    let mut result = String::new();
+
+    let push_line = |result: &mut String, line: &str| {
+        if !line.is_empty() {
+            result.push_str(&indent);
+            result.push_str(line);
+        }
+        result.push('\n');
+    };
+
+    for (i, page) in pages.iter().enumerate() {
+        let info = PageInfo {
+            title,
+            date,
+            page: i + 1,
+            pages: pages.len(),
+        };
+        if i != 0 {
+            result.push('\x0c');
+        }
+        for _ in 0..config.margin_lines {
+            result.push('\n');
+        }
+        if !config.header.is_empty() {
+            push_line(&mut result, &info.expand(&config.header));
+            result.push('\n');
+        }
+        for line in page {
+            push_line(&mut result, line);
+        }
+        if !config.footer.is_empty() {
+            // Pad short pages so the footer always lands in the same place.
+            for _ in page.len()..body_lines {
+                result.push('\n');
+            }
+            result.push('\n');
+            push_line(&mut result, &info.expand(&config.footer));
+        }
+        for _ in 0..config.margin_lines {
+            result.push('\n');
+        }
+    }
+
+    result
+}
+
+/// US Letter, in points.
+const PDF_PAGE: (f32, f32) = (612.0, 792.0);
+
+/// A block of the document as laid out in a PDF: a line of text, or a figure taking up a few
+/// lines' worth of space.
+enum PdfBlock {
+    Line(String),
+    Figure {
+        number: String,
+        sketch: Sketch,
+        scale: f32,
+        rows: usize,
+    },
+    Image {
+        number: String,
+        image: usize,
+        width: f32,
+        height: f32,
+        rows: usize,
+    },
+}
+
+/// What a figure line points to, if it's something that can be drawn.
+enum Drawing {
+    Sketch(Sketch),
+    Image(Image),
+}
+
+fn read_drawing(path: &Path) -> Option<Drawing> {
+    if path.extension().is_some_and(|ext| ext == "png") {
+        Image::read_png(path).ok().map(Drawing::Image)
+    } else {
+        parse_sketch(&fs::read_to_string(path).ok()?).map(Drawing::Sketch)
+    }
+}
+
+/// Lay out a document as a PDF, using the same page layout as `paginate`. Figures that point to
+/// sketches or PNG images are drawn in place, scaled to fit the page; figures that can't be read
+/// get a placeholder.
+pub fn paginate_pdf(text: &str, dir: &Path, title: &str, date: &str, config: &PrintConfig) -> Pdf {
+    let (page_width, page_height) = PDF_PAGE;
+    let leading = page_height / config.page_lines.max(1) as f32;
+    let font_size = leading / 1.2;
+    let char_width = font_size * pdf::char_width();
+    let left = config.margin_cols as f32 * char_width;
+    let body_lines = config.body_lines();
+    let number_width = text.lines().count().max(1).to_string().len();
+
+    let mut pdf = Pdf::new(page_width, page_height);
+    let mut blocks = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let number = if config.line_numbers {
+            format!("{:>number_width$}  ", i + 1)
+        } else {
+            String::new()
+        };
+        let drawing = Figure::parse(line).and_then(|figure| read_drawing(&figure.resolve(dir)?));
+        // Shrink anything too big for the page, a point to each of the figure's pixels.
+        let max_width = page_width - left * 2.0 - number.len() as f32 * char_width;
+        let max_height = body_lines as f32 * leading;
+        let fit = |width: f32, height: f32| {
+            let scale = (max_width / width).min(max_height / height).min(1.0);
+            let rows = (height * scale / leading).ceil() as usize;
+            (scale, rows.clamp(1, body_lines))
+        };
+        match drawing {
+            Some(Drawing::Sketch(sketch)) => {
+                let (scale, rows) = fit(sketch.width, sketch.height);
+                blocks.push(PdfBlock::Figure {
+                    number,
+                    sketch,
+                    scale,
+                    rows,
+                });
+            }
+            Some(Drawing::Image(image)) => {
+                let (width, height) = (image.width as f32, image.height as f32);
+                let (scale, rows) = fit(width, height);
+                blocks.push(PdfBlock::Image {
+                    number,
+                    image: pdf.add_image(image),
+                    width: width * scale,
+                    height: height * scale,
+                    rows,
+                });
+            }
+            None => blocks.push(PdfBlock::Line(format!(
+                "{number}{}",
+                expand_figures(line, dir)
+            ))),
+        }
+    }
+
+    // Fill pages a block at a time, so figures are never split across pages.
+    let mut pages: Vec<Vec<PdfBlock>> = vec![vec![]];
+    let mut used = 0;
+    for block in blocks {
+        let rows = match &block {
+            PdfBlock::Line(_) => 1,
+            PdfBlock::Figure { rows, .. } | PdfBlock::Image { rows, .. } => *rows,
+        };
+        if used + rows > body_lines && used > 0 {
+            pages.push(vec![]);
+            used = 0;
+        }
+        used += rows;
+        pages.last_mut().unwrap().push(block);
+    }
+
+    let page_count = pages.len();
+    for (i, blocks) in pages.into_iter().enumerate() {
+        let info = PageInfo {
+            title,
+            date,
+            page: i + 1,
+            pages: page_count,
+        };
+        let page = pdf.add_page();
+        let baseline = |row: usize| (row as f32 + 0.8) * leading;
+        let mut row = config.margin_lines;
+        if !config.header.is_empty() {
+            page.text(left, baseline(row), font_size, &info.expand(&config.header));
+            row += 2;
+        }
+        let body_start = row;
+        for block in blocks {
+            match block {
+                PdfBlock::Line(line) => {
+                    if !line.is_empty() {
+                        page.text(left, baseline(row), font_size, &line);
+                    }
+                    row += 1;
+                }
+                PdfBlock::Figure {
+                    number,
+                    sketch,
+                    scale,
+                    rows,
+                } => {
+                    if !number.is_empty() {
+                        page.text(left, baseline(row), font_size, number.trim_end());
+                    }
+                    let x = left + number.len() as f32 * char_width;
+                    let y = row as f32 * leading;
+                    for stroke in &sketch.strokes {
+                        let points: Vec<(f32, f32)> = stroke
+                            .iter()
+                            .map(|(px, py)| (x + px * scale, y + py * scale))
+                            .collect();
+                        page.polyline(&points, (3.0 * scale).max(0.5));
+                    }
+                    row += rows;
+                }
+                PdfBlock::Image {
+                    number,
+                    image,
+                    width,
+                    height,
+                    rows,
+                } => {
+                    if !number.is_empty() {
+                        page.text(left, baseline(row), font_size, number.trim_end());
+                    }
+                    let x = left + number.len() as f32 * char_width;
+                    page.image(image, x, row as f32 * leading, width, height);
+                    row += rows;
+                }
+            }
+        }
+        if !config.footer.is_empty() {
+            let footer_row = body_start + body_lines + 1;
+            page.text(
+                left,
+                baseline(footer_row),
+                font_size,
+                &info.expand(&config.footer),
+            );
+        }
+    }
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    #[test]
+    fn test_parse_figure() {
+        let figure = Figure::parse("  ![a sketch](sketch1)").expect("parsing figure");
+        assert_eq!(figure.caption, "a sketch");
+        assert_eq!(figure.target, "sketch1");
+        assert!(Figure::parse("see ![a sketch](sketch1)").is_none());
+        assert!(Figure::parse("![empty]()").is_none());
+    }
+
+    #[test]
+    fn test_parse_sketch() {
+        let svg = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"120\" height=\"40\">\n  <polyline points=\"10.0,10.0 20.5,30.0\" fill=\"none\"/>\n</svg>\n";
+        let sketch = parse_sketch(svg).expect("parsing sketch");
+        assert_eq!((sketch.width, sketch.height), (120.0, 40.0));
+        assert_eq!(sketch.strokes, vec![vec![(10.0, 10.0), (20.5, 30.0)]]);
+    }
+
+    #[test]
+    fn test_pdf_image() {
+        let dir = TempDir::new("export");
+        let file = fs::File::create(dir.join("photo.png")).unwrap();
+        let mut encoder = png::Encoder::new(file, 2, 1);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let pixels = [255, 0, 0, 255, 0, 0, 0, 0];
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&pixels)
+            .unwrap();
+
+        let pdf = paginate_pdf("![a photo](photo)", &dir, "", "", &PrintConfig::default());
+        let pdf = pdf.to_bytes();
+        let pdf = String::from_utf8_lossy(&pdf);
+        assert!(pdf.contains("/Subtype /Image /Width 2 /Height 1 /ColorSpace /DeviceRGB"));
+        assert!(pdf.contains("/XObject << /Im0 8 0 R >>"));
+        assert!(pdf.contains(" cm /Im0 Do Q"));
+        assert!(!pdf.contains("[figure:"));
+    }
+
+    #[test]
+    fn test_paginate() {
+        let config = PrintConfig {
+            page_lines: 8,
+            margin_lines: 1,
+            margin_cols: 2,
+            header: "{title}".to_string(),
+            footer: "{page}/{pages}".to_string(),
+            line_numbers: false,
+        };
+        assert_eq!(config.body_lines(), 2);
+        let printed = paginate("one\ntwo\nthree", "notes", "2022-11-09", &config);
+        assert_eq!(
+            printed,
+            "\n  notes\n\n  one\n  two\n\n  1/2\n\n\x0c\n  notes\n\n  three\n\n\n  2/2\n\n"
+        );
+    }
+}
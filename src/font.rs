@@ -4,8 +4,14 @@ use rusttype::{Font, Scale};
 
 use std::hash::Hash;
 
-pub static FONT: Lazy<Font<'static>> = Lazy::new(|| {
-    let font_bytes: &[u8] = include_bytes!("../fonts/Inconsolata-Regular.ttf");
+/// The monospace font text is drawn on the grid with; also embedded in exported PDFs.
+pub const FONT_BYTES: &[u8] = include_bytes!("../fonts/Inconsolata-Regular.ttf");
+
+pub static FONT: Lazy<Font<'static>> = Lazy::new(|| Font::from_bytes(FONT_BYTES).unwrap());
+
+/// A proportional font, for places where we're not laying text out on the grid.
+pub static READER_FONT: Lazy<Font<'static>> = Lazy::new(|| {
+    let font_bytes: &[u8] = include_bytes!("../fonts/DejaVuSerif.ttf");
     Font::from_bytes(font_bytes).unwrap()
 });
 
@@ -24,6 +30,14 @@ pub fn text_literal(height: i32, text: &str) -> TextFragment {
         .to_fragment()
 }
 
+/// The width of a line of text in pixels, ignoring kerning.
+pub fn text_width(font: &Font, height: i32, text: &str) -> f32 {
+    let scale = Scale::uniform(height as f32);
+    text.chars()
+        .map(|c| font.glyph(c).scaled(scale).h_metrics().advance_width)
+        .sum()
+}
+
 #[derive(Hash, Clone, Copy)]
 pub struct Metrics {
     pub height: i32,
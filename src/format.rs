@@ -0,0 +1,59 @@
+use crate::util;
+use anyhow::{anyhow, Context};
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+/// Pipe the text through a formatter command, returning what it writes out. The command runs
+/// in the file's directory, so it can find any config it keeps there, and the file's path is
+/// passed as `$1` for formatters that want to know what they're formatting. It's killed if it
+/// hasn't finished within the timeout.
+pub fn run(command: &str, path: &Path, text: &str, timeout: Duration) -> anyhow::Result<String> {
+    let dir = path
+        .parent()
+        .filter(|d| d.is_dir())
+        .unwrap_or(Path::new("/"));
+    // As with completion, the path is an argument rather than part of the script.
+    let mut shell = Command::new("/bin/sh");
+    shell
+        .arg("-c")
+        .arg(command)
+        .arg("formatter")
+        .arg(path)
+        .current_dir(dir);
+    let output = match util::run_with_timeout(&mut shell, Some(text.as_bytes()), timeout) {
+        Ok(output) => output,
+        Err(e) if e.kind() == ErrorKind::TimedOut => return Err(anyhow!("{command:?} {e}")),
+        Err(e) => return Err(e).with_context(|| format!("running {command:?}")),
+    };
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        let message = message.trim().lines().next().unwrap_or("").to_string();
+        return Err(anyhow!("{command:?} failed: {message}"));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run() {
+        let path = Path::new("/tmp/notes.txt");
+        let timeout = Duration::from_secs(5);
+        assert_eq!(
+            run("tr a-z A-Z", path, "hello\n", timeout).unwrap(),
+            "HELLO\n"
+        );
+        assert_eq!(
+            run("basename \"$1\"", path, "", timeout).unwrap(),
+            "notes.txt\n"
+        );
+        let error = run("echo oops >&2; exit 1", path, "", timeout).unwrap_err();
+        assert!(error.to_string().ends_with("failed: oops"));
+        let error = run("sleep 10", path, "", Duration::from_millis(200)).unwrap_err();
+        assert!(error.to_string().ends_with("so it was stopped"));
+    }
+}
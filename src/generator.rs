@@ -0,0 +1,177 @@
+use anyhow::{anyhow, bail};
+
+const VARIANTS_DIRECTIVE: &str = "{{variants";
+
+/// Whether a document's first line marks it as a generator source.
+pub fn is_source(first_line: &[char]) -> bool {
+    first_line
+        .iter()
+        .copied()
+        .take(VARIANTS_DIRECTIVE.len())
+        .eq(VARIANTS_DIRECTIVE.chars())
+}
+
+enum Part<'a> {
+    Line(&'a str),
+    Pool {
+        pick: Option<usize>,
+        items: Vec<Vec<&'a str>>,
+    },
+}
+
+/// A tiny xorshift generator; we don't need much from our randomness here.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = (self.next() % (i as u64 + 1)) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+fn directive<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let inner = line.trim().strip_prefix("{{")?.strip_suffix("}}")?;
+    let rest = inner.strip_prefix(name)?;
+    if rest.is_empty() || rest.starts_with(' ') {
+        Some(rest.trim())
+    } else {
+        None
+    }
+}
+
+fn parse(source: &str) -> anyhow::Result<(usize, Vec<Part<'_>>)> {
+    let mut lines = source.lines().enumerate();
+    let variants = lines
+        .next()
+        .and_then(|(_, l)| directive(l, "variants"))
+        .ok_or_else(|| anyhow!("expected a {{{{variants N}}}} line at the top of the file"))?;
+    let variants: usize = variants
+        .parse()
+        .map_err(|_| anyhow!("invalid variant count: {variants:?}"))?;
+
+    let mut parts = vec![];
+    while let Some((line_no, line)) = lines.next() {
+        if let Some(pick) = directive(line, "pool") {
+            let pick = if pick.is_empty() {
+                None
+            } else {
+                Some(
+                    pick.parse()
+                        .map_err(|_| anyhow!("line {line_no}: invalid pool size {pick:?}"))?,
+                )
+            };
+            let mut items: Vec<Vec<&str>> = vec![];
+            let mut current = vec![];
+            let mut closed = false;
+            for (_, line) in lines.by_ref() {
+                if directive(line, "end").is_some() {
+                    closed = true;
+                    break;
+                } else if line.trim().is_empty() {
+                    if !current.is_empty() {
+                        items.push(std::mem::take(&mut current));
+                    }
+                } else {
+                    current.push(line);
+                }
+            }
+            if !closed {
+                bail!("line {line_no}: pool has no matching {{{{end}}}}");
+            }
+            if !current.is_empty() {
+                items.push(current);
+            }
+            parts.push(Part::Pool { pick, items });
+        } else {
+            parts.push(Part::Line(line));
+        }
+    }
+
+    Ok((variants, parts))
+}
+
+/// Expand a source document into shuffled variants, for eg. exams.
+///
+/// A source document starts with a `{{variants N}}` line, and may contain:
+/// - `{{pool K}}` ... `{{end}}` blocks, whose blank-line-separated items are shuffled, keeping K
+///   of them (or all of them, if K is omitted);
+/// - `{{n}}`, replaced with an incrementing number each time it appears;
+/// - `{{variant}}` and `{{date}}`.
+pub fn generate(source: &str, date: &str, seed: u64) -> anyhow::Result<Vec<String>> {
+    let (variants, parts) = parse(source)?;
+    // Xorshift gets stuck at zero, so make sure we never start there.
+    let mut rng = Rng(seed | 1);
+
+    let mut results = vec![];
+    for variant in 1..=variants {
+        let mut number = 0;
+        let mut output = String::new();
+        let mut push_line = |line: &str| {
+            let mut line = line
+                .replace("{{variant}}", &variant.to_string())
+                .replace("{{date}}", date);
+            while line.contains("{{n}}") {
+                number += 1;
+                line = line.replacen("{{n}}", &number.to_string(), 1);
+            }
+            output.push_str(&line);
+            output.push('\n');
+        };
+
+        for part in &parts {
+            match part {
+                Part::Line(line) => push_line(line),
+                Part::Pool { pick, items } => {
+                    let mut order: Vec<usize> = (0..items.len()).collect();
+                    rng.shuffle(&mut order);
+                    order.truncate(pick.unwrap_or(items.len()));
+                    for (i, index) in order.into_iter().enumerate() {
+                        if i != 0 {
+                            push_line("");
+                        }
+                        for line in &items[index] {
+                            push_line(line);
+                        }
+                    }
+                }
+            }
+        }
+        results.push(output);
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let source = "{{variants 2}}\nQuiz {{variant}}, {{date}}\n{{pool 1}}\n{{n}}. a\n\n{{n}}. b\n{{end}}\n{{n}}. c";
+        let variants = generate(source, "2022-11-09", 42).expect("generating variants");
+        assert_eq!(variants.len(), 2);
+        for (i, variant) in variants.iter().enumerate() {
+            let lines: Vec<&str> = variant.lines().collect();
+            assert_eq!(lines[0], format!("Quiz {}, 2022-11-09", i + 1));
+            assert!(lines[1] == "1. a" || lines[1] == "1. b");
+            assert_eq!(lines[2], "2. c");
+        }
+    }
+
+    #[test]
+    fn test_unclosed_pool() {
+        assert!(generate("{{variants 1}}\n{{pool}}\nq", "", 1).is_err());
+    }
+}
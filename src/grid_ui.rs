@@ -1,8 +1,9 @@
-use crate::{text_literal, Metrics, Vector2};
+use crate::{text_literal, text_width, Metrics, Vector2, FONT};
 use armrest::libremarkable::framebuffer::common::color;
 use armrest::libremarkable::framebuffer::FramebufferIO;
 use armrest::ui::{Cached, Canvas, Fragment, Side, View};
-use std::cell::RefCell;
+use rusttype::{point, Scale};
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::ops::Range;
 use std::rc::Rc;
@@ -131,25 +132,104 @@ impl Fragment for GridCell {
     }
 }
 
+/// The line numbers down the margin of a grid, one label to a row, right-aligned along the
+/// bottom of each. Drawn as one piece, so the margin only changes on screen when the rows
+/// showing do.
+#[derive(Hash, Clone, PartialEq, Eq)]
+pub struct LineNumbers {
+    pub label_height: i32,
+    pub row_height: i32,
+    pub labels: Vec<String>,
+}
+
+impl Fragment for LineNumbers {
+    fn draw(&self, canvas: &mut Canvas) {
+        let size = canvas.bounds().size();
+        let scale = Scale::uniform(self.label_height as f32);
+        let ascent = FONT.v_metrics(scale).ascent;
+        for (row, label) in self.labels.iter().enumerate() {
+            let x = size.x as f32 - text_width(&FONT, self.label_height, label);
+            let y = ((row as i32 + 1) * self.row_height - self.label_height) as f32 + ascent;
+            for glyph in FONT.layout(label, scale, point(x, y)) {
+                let bounds = match glyph.pixel_bounding_box() {
+                    Some(bounds) => bounds,
+                    None => continue,
+                };
+                glyph.draw(|gx, gy, v| {
+                    let (x, y) = (bounds.min.x + gx as i32, bounds.min.y + gy as i32);
+                    if v > 0.0 && (0..size.x).contains(&x) && (0..size.y).contains(&y) {
+                        canvas.write(x, y, color::GRAY((v * 255.0) as u8));
+                    }
+                });
+            }
+        }
+    }
+}
+
+type Cache<K> = RefCell<HashMap<K, Rc<Cached<K>>>>;
+
 pub struct Atlas {
-    cache: RefCell<HashMap<GridCell, Rc<Cached<GridCell>>>>,
+    cache: Cache<GridCell>,
+    labels: Cache<LineNumbers>,
+    /// How many entries either cache can hold before it's thrown away and started again.
+    max_entries: Cell<usize>,
+}
+
+/// Look up a fragment in one of the atlas caches, adding it if it's not there.
+fn cached<K: Fragment + Eq + Clone>(cache: &Cache<K>, key: K, max_entries: usize) -> Rc<Cached<K>> {
+    match cache.try_borrow_mut() {
+        Ok(mut cache) => {
+            if cache.len() >= max_entries && !cache.contains_key(&key) {
+                cache.clear();
+            }
+            let value = cache
+                .entry(key.clone())
+                .or_insert_with(|| Rc::new(Cached::new(key)));
+            Rc::clone(value)
+        }
+        // Again, shouldn't be common, but it's good to be prepared!
+        Err(_) => Rc::new(Cached::new(key)),
+    }
 }
 
 impl Atlas {
     pub fn new() -> Atlas {
         Atlas {
             cache: RefCell::new(Default::default()),
+            labels: RefCell::new(Default::default()),
+            max_entries: Cell::new(usize::MAX),
         }
     }
 
-    pub fn get_cell(&self, cell: GridCell) -> Rc<Cached<GridCell>> {
+    /// The number of cached grid cells and labels.
+    pub fn usage(&self) -> (usize, usize) {
+        let cells = self.cache.try_borrow().map_or(0, |c| c.len());
+        let labels = self.labels.try_borrow().map_or(0, |l| l.len());
+        (cells, labels)
+    }
+
+    /// Throw away the caches if they've grown past the given number of entries, and keep them
+    /// within it from now on; anything still on screen will be re-rendered on demand.
+    pub fn trim(&self, max_entries: usize) {
+        self.max_entries.set(max_entries.max(1));
         if let Ok(mut cache) = self.cache.try_borrow_mut() {
-            let value = cache.entry(cell).or_insert(Rc::new(Cached::new(cell)));
-            Rc::clone(value)
-        } else {
-            // Again, shouldn't be common, but it's good to be prepared!
-            Rc::new(Cached::new(cell))
+            if cache.len() > max_entries {
+                cache.clear();
+            }
         }
+        if let Ok(mut labels) = self.labels.try_borrow_mut() {
+            if labels.len() > max_entries {
+                labels.clear();
+            }
+        }
+    }
+
+    pub fn get_line_numbers(&self, numbers: LineNumbers) -> Rc<Cached<LineNumbers>> {
+        cached(&self.labels, numbers, self.max_entries.get())
+    }
+
+    pub fn get_cell(&self, cell: GridCell) -> Rc<Cached<GridCell>> {
+        cached(&self.cache, cell, self.max_entries.get())
     }
 }
 
@@ -0,0 +1,181 @@
+use std::path::Path;
+
+/// The languages we know how to highlight, picked by file extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    Shell,
+    Rust,
+    Python,
+    Markdown,
+}
+
+const SHELL_KEYWORDS: &[&str] = &[
+    "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac",
+    "in", "function", "return", "local", "export", "set", "exit",
+];
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "break", "const", "continue", "crate", "else", "enum", "fn", "for", "if", "impl", "in",
+    "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static",
+    "struct", "super", "trait", "type", "unsafe", "use", "where", "while",
+];
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "and", "as", "assert", "break", "class", "continue", "def", "del", "elif", "else", "except",
+    "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "not", "or", "pass",
+    "raise", "return", "try", "while", "with", "yield", "None", "True", "False",
+];
+
+impl Language {
+    pub fn from_path(path: &Path) -> Option<Language> {
+        let extension = path.extension()?.to_str()?;
+        match extension {
+            "sh" | "bash" | "bashrc" => Some(Language::Shell),
+            "rs" => Some(Language::Rust),
+            "py" => Some(Language::Python),
+            "md" | "markdown" => Some(Language::Markdown),
+            _ => None,
+        }
+    }
+
+    fn keywords(self) -> &'static [&'static str] {
+        match self {
+            Language::Shell => SHELL_KEYWORDS,
+            Language::Rust => RUST_KEYWORDS,
+            Language::Python => PYTHON_KEYWORDS,
+            Language::Markdown => &[],
+        }
+    }
+
+    fn quotes(self) -> &'static [char] {
+        match self {
+            Language::Shell | Language::Python => &['"', '\''],
+            // Single quotes are too often lifetimes to be worth guessing at.
+            Language::Rust => &['"'],
+            Language::Markdown => &['`'],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    Plain,
+    Keyword,
+    String,
+    Comment,
+}
+
+impl Style {
+    /// How heavily to draw a character in this style; see `GridCell`.
+    pub fn weight(self) -> u8 {
+        match self {
+            Style::Plain => 230,
+            Style::Keyword => 255,
+            Style::String => 170,
+            Style::Comment => 110,
+        }
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Style each char of a line. This looks at one line at a time, so constructs that span lines,
+/// like block comments, are only caught on the line they start on.
+pub fn highlight_line(language: Language, line: &[char]) -> Vec<Style> {
+    let mut styles = vec![Style::Plain; line.len()];
+
+    if language == Language::Markdown {
+        match line.iter().find(|c| !c.is_whitespace()) {
+            Some('#') => return vec![Style::Keyword; line.len()],
+            Some('>') => return vec![Style::Comment; line.len()],
+            _ => {}
+        }
+    }
+
+    let mut i = 0;
+    while i < line.len() {
+        let c = line[i];
+        let rest = &line[i..];
+        let starts_comment = match language {
+            Language::Shell => c == '#' && (i == 0 || line[i - 1].is_whitespace()),
+            Language::Python => c == '#',
+            Language::Rust => rest.starts_with(&['/', '/']),
+            Language::Markdown => false,
+        };
+        if starts_comment {
+            styles[i..].fill(Style::Comment);
+            break;
+        }
+        if language == Language::Rust && rest.starts_with(&['/', '*']) {
+            let end = (i + 2..line.len().saturating_sub(1))
+                .find(|j| line[*j] == '*' && line[j + 1] == '/')
+                .map_or(line.len(), |j| j + 2);
+            styles[i..end].fill(Style::Comment);
+            i = end;
+            continue;
+        }
+        if language.quotes().contains(&c) {
+            let mut end = i + 1;
+            while end < line.len() && line[end] != c {
+                if line[end] == '\\' && language != Language::Markdown {
+                    end += 1;
+                }
+                end += 1;
+            }
+            let end = (end + 1).min(line.len());
+            styles[i..end].fill(Style::String);
+            i = end;
+            continue;
+        }
+        if is_word(c) {
+            let end = (i..line.len())
+                .find(|j| !is_word(line[*j]))
+                .unwrap_or(line.len());
+            let word: String = line[i..end].iter().collect();
+            if language.keywords().contains(&word.as_str()) {
+                styles[i..end].fill(Style::Keyword);
+            }
+            i = end;
+            continue;
+        }
+        i += 1;
+    }
+    styles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styles(language: Language, line: &str) -> String {
+        let chars: Vec<char> = line.chars().collect();
+        highlight_line(language, &chars)
+            .into_iter()
+            .map(|s| match s {
+                Style::Plain => '.',
+                Style::Keyword => 'k',
+                Style::String => 's',
+                Style::Comment => 'c',
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_highlight_line() {
+        assert_eq!(
+            styles(Language::Rust, r#"let s = "a\"b"; // hi"#),
+            "kkk.....ssssss..ccccc"
+        );
+        assert_eq!(styles(Language::Rust, "fn /* x */ f()"), "kk.ccccccc....");
+        assert_eq!(styles(Language::Shell, "echo $# # done"), "........cccccc");
+        assert_eq!(styles(Language::Python, "if 'x': pass"), "kk.sss..kkkk");
+        assert_eq!(styles(Language::Markdown, "## Title"), "kkkkkkkk");
+        assert_eq!(styles(Language::Markdown, "run `ls`"), "....ssss");
+        assert_eq!(
+            Language::from_path(Path::new("notes/todo.md")),
+            Some(Language::Markdown)
+        );
+    }
+}
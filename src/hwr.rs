@@ -1,12 +1,13 @@
-use crate::{Config, Metrics, TextBuffer};
+use crate::{Clip, Config, Metrics};
 use armrest::dollar::Points;
 use armrest::ink::Ink;
 
+use crate::dictionary::Dictionary;
 use crate::util::rotate_queue;
 use serde::Deserialize;
 use serde::Serialize;
 use std::borrow::Cow;
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
 use std::collections::{BTreeMap, VecDeque};
 
 /// A set of characters that we always include in the template, even when not explicitly configured.
@@ -31,6 +32,20 @@ pub fn ink_to_points(ink: &Ink, metrics: &Metrics) -> Points {
     points
 }
 
+/// How to stretch ink drawn in a cell of the given `(width, height)` to fit the current grid,
+/// as separate horizontal and vertical factors; or `None` if it fits already. The font's width
+/// doesn't track its height exactly, so a single factor would skew the templates.
+fn cell_scale((width, height): (i32, i32), metrics: &Metrics) -> Option<(f32, f32)> {
+    if (width, height) == (metrics.width, metrics.height) {
+        None
+    } else {
+        Some((
+            metrics.width as f32 / width as f32,
+            metrics.height as f32 / height as f32,
+        ))
+    }
+}
+
 pub fn default_char_height() -> i32 {
     40
 }
@@ -41,9 +56,17 @@ pub fn default_char_height() -> i32 {
 pub struct TemplateFile<'a> {
     #[serde(default = "default_char_height")]
     template_height: i32,
+    /// The cell width the templates were drawn at. Older files don't record it, but it follows
+    /// from the height, since the grid is sized to the font.
+    #[serde(default)]
+    template_width: Option<i32>,
     templates: BTreeMap<char, Vec<Cow<'a, str>>>,
     #[serde(default)]
     candidate_templates: Vec<TemplateFileEntry<'a>>,
+    /// How many times each char has been written, so the recognizer can try the common ones
+    /// first.
+    #[serde(default)]
+    usage: BTreeMap<char, u64>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -59,8 +82,28 @@ impl<'a> Default for TemplateFile<'a> {
     }
 }
 
+/// The names of the template sets that ship with Sill, for new users to start from.
+pub const STARTER_TEMPLATES: &[&str] = &["print", "capitals"];
+
 impl<'a> TemplateFile<'a> {
-    pub fn new(stuff: &'a TextStuff, template_height: i32) -> TemplateFile<'a> {
+    /// One of the starter sets, by name. There's only one set of drawn templates; `capitals` is
+    /// the same set with the lowercase letters left out, so that `O`, `S` and `C` can't be taken
+    /// for their lowercase twins.
+    pub fn starter(name: &str) -> Option<TemplateFile<'a>> {
+        let mut file = TemplateFile::default();
+        match name {
+            "print" => {}
+            "capitals" => {
+                file.templates.retain(|c, _| !c.is_ascii_lowercase());
+                file.candidate_templates
+                    .retain(|t| !t.char.is_ascii_lowercase());
+            }
+            _ => return None,
+        }
+        Some(file)
+    }
+
+    pub fn new(stuff: &'a TextStuff, metrics: &Metrics) -> TemplateFile<'a> {
         let mut entries = BTreeMap::new();
         for ts in &stuff.templates {
             let strings: Vec<Cow<str>> = ts
@@ -85,9 +128,11 @@ impl<'a> TemplateFile<'a> {
             .collect();
 
         TemplateFile {
-            template_height,
+            template_height: metrics.height,
+            template_width: Some(metrics.width),
             templates: entries,
             candidate_templates,
+            usage: stuff.usage.clone(),
         }
     }
 }
@@ -153,6 +198,41 @@ impl CharRecognizer {
         }
     }
 
+    /// The best-scoring candidates for the query, best first, with their scores: lower is
+    /// better. Each char shows up at most once, with the score of its best template.
+    pub fn top_matches(&mut self, query: &Points, n: usize) -> Vec<(char, f32)> {
+        if n == 0 {
+            return vec![];
+        }
+        // Kept sorted and cut to `n`, so the last entry is the score to beat: any template that
+        // can't beat it, or its own char's best so far, is abandoned partway through.
+        let mut best: Vec<(char, f32, usize)> = vec![];
+        for (index, (template, c)) in self.templates.iter().zip(&self.chars).enumerate() {
+            let seen = best.iter().position(|(seen, _, _)| seen == c);
+            let mut bound = match best.get(n - 1) {
+                Some((_, score, _)) => *score,
+                None => f32::MAX,
+            };
+            if let Some(i) = seen {
+                bound = bound.min(best[i].1);
+            }
+            let score = query.distance(template, bound);
+            if score >= bound {
+                continue;
+            }
+            if let Some(i) = seen {
+                best.remove(i);
+            }
+            let at = best.partition_point(|(_, other, _)| *other <= score);
+            best.insert(at, (*c, score, index));
+            best.truncate(n);
+        }
+        if let Some((_, _, index)) = best.first() {
+            self.promote(*index);
+        }
+        best.into_iter().map(|(c, score, _)| (c, score)).collect()
+    }
+
     pub fn promote(&mut self, index: usize) {
         if index == 0 || index >= self.templates.len() {
             return;
@@ -162,14 +242,38 @@ impl CharRecognizer {
     }
 }
 
+/// How much better than the runner-up the best match has to score, as a fraction of the
+/// runner-up's score, for us to be sure of it.
+const SURE_MARGIN: f32 = 0.1;
+
+/// Whether the best of these matches, from `top_matches`, is clearly better than the rest.
+pub fn is_sure(matches: &[(char, f32)]) -> bool {
+    match matches {
+        [(_, best), (_, next), ..] => next - best >= next * SURE_MARGIN,
+        _ => true,
+    }
+}
+
 const NUM_CANDIDATES: usize = 64;
+const NUM_CLIPS: usize = 16;
+
+/// The chars that can be written large over a selection, as commands.
+const BIG_GLYPHS: &[char] = &[
+    'X', 'C', 'V', 'S', '>', '<', 'Q', 'N', 'P', '|', 'A', 'W', 'B', 'H', 'E', '{', '}', '+', 'U',
+    'T', 'R',
+];
 
 pub struct TextStuff {
     pub templates: Vec<CharTemplates>,
     pub char_recognizer: CharRecognizer,
     pub big_recognizer: CharRecognizer,
-    pub clipboard: Option<TextBuffer>,
+    pub clipboard: Option<Clip>,
+    /// Recent clips, oldest first.
+    pub clip_history: VecDeque<Clip>,
     pub candidate_templates: VecDeque<(Template, Points, char)>,
+    pub dictionary: Option<Dictionary>,
+    /// How many times each char has been recognized, over every session.
+    pub usage: BTreeMap<char, u64>,
 }
 
 impl TextStuff {
@@ -179,7 +283,10 @@ impl TextStuff {
             char_recognizer: CharRecognizer::new([]),
             big_recognizer: CharRecognizer::new([]),
             clipboard: None,
+            clip_history: VecDeque::new(),
             candidate_templates: VecDeque::new(),
+            dictionary: None,
+            usage: BTreeMap::new(),
         }
     }
 
@@ -191,25 +298,23 @@ impl TextStuff {
     ) {
         let TemplateFile {
             template_height,
+            template_width,
             mut templates,
             candidate_templates,
+            usage,
         } = template_file;
 
-        let scale = if template_height == metrics.height {
-            None
-        } else {
-            // if metrics.height is larger than the height of the serialized templates, scale up!
-            Some(metrics.height as f32 / template_height as f32)
-        };
+        let template_width = template_width.unwrap_or_else(|| Metrics::new(template_height).width);
+        let scale = cell_scale((template_width, template_height), metrics);
 
         let parse_template = |string: Cow<'_, str>| match scale {
             None => Template::from_string(string.into_owned()),
-            Some(scale) => {
+            Some((x_scale, y_scale)) => {
                 let original = Ink::from_string(&string);
                 let mut ink = Ink::new();
                 for stroke in original.strokes() {
                     for p in stroke {
-                        ink.push(p.x * scale, p.y * scale, p.z);
+                        ink.push(p.x * x_scale, p.y * y_scale, p.z);
                     }
                     ink.pen_up();
                 }
@@ -237,6 +342,7 @@ impl TextStuff {
         }
 
         self.templates = new_templates;
+        self.usage = usage;
 
         self.candidate_templates = candidate_templates
             .into_iter()
@@ -250,6 +356,43 @@ impl TextStuff {
         self.init_recognizer(metrics);
     }
 
+    /// Add the templates from another set, skipping any whose ink we already have, and return
+    /// how many were new. The other set's candidates are left out: they're still unconfirmed.
+    pub fn merge(&mut self, other: TextStuff) -> usize {
+        let mut count = 0;
+        for ct in other.templates {
+            let index = match self.templates.iter().position(|t| t.char == ct.char) {
+                Some(index) => index,
+                None => {
+                    self.templates.push(CharTemplates {
+                        char: ct.char,
+                        templates: vec![],
+                    });
+                    self.templates.len() - 1
+                }
+            };
+            let existing = &mut self.templates[index].templates;
+            for template in ct.templates {
+                if !existing.iter().any(|t| t.serialized == template.serialized) {
+                    existing.push(template);
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// Note that the char was written, for ordering the templates next time.
+    pub fn record_use(&mut self, c: char) {
+        *self.usage.entry(c).or_default() += 1;
+    }
+
+    /// Make a clip the active clipboard, and remember it in the history.
+    pub fn set_clipboard(&mut self, clip: Clip) {
+        rotate_queue(&mut self.clip_history, clip.clone(), NUM_CLIPS);
+        self.clipboard = Some(clip);
+    }
+
     pub fn on_overwrite(&mut self, ink: Ink, points: Points, best: char) {
         if self.char_recognizer.templates.is_empty() {
             return;
@@ -308,7 +451,11 @@ impl TextStuff {
         for ct in &mut self.templates {
             ct.templates.retain(|t| t.ink.len() > 1);
         }
-        self.char_recognizer = CharRecognizer::new(self.templates.iter().flat_map(|ct| {
+        // The most-written chars go first, so a typical stroke finds a good match early and the
+        // rest of the templates can be abandoned quickly.
+        let order = usage_order(&self.templates, &self.usage);
+        self.char_recognizer = CharRecognizer::new(order.into_iter().flat_map(|i| {
+            let ct = &self.templates[i];
             let c = ct.char;
             ct.templates
                 .iter()
@@ -317,7 +464,7 @@ impl TextStuff {
         self.big_recognizer = CharRecognizer::new(
             self.templates
                 .iter()
-                .filter(|ct| ['X', 'C', 'V', 'S', '>', '<', 'Q', 'N', 'P'].contains(&ct.char))
+                .filter(|ct| BIG_GLYPHS.contains(&ct.char))
                 .flat_map(|ct| {
                     let c = ct.char;
                     ct.templates
@@ -328,3 +475,47 @@ impl TextStuff {
         );
     }
 }
+
+/// The indices of the templates, most-used char first. Chars used equally often keep their order.
+fn usage_order(templates: &[CharTemplates], usage: &BTreeMap<char, u64>) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..templates.len()).collect();
+    order.sort_by_key(|i| Reverse(usage.get(&templates[*i].char).copied().unwrap_or(0)));
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_scale() {
+        let metrics = Metrics {
+            height: 60,
+            width: 25,
+            baseline: 45,
+        };
+        assert_eq!(cell_scale((25, 60), &metrics), None);
+        assert_eq!(cell_scale((20, 40), &metrics), Some((1.25, 1.5)));
+    }
+
+    #[test]
+    fn test_usage_order() {
+        let templates: Vec<_> = "abcd"
+            .chars()
+            .map(|char| CharTemplates {
+                char,
+                templates: vec![],
+            })
+            .collect();
+        let usage = BTreeMap::from([('c', 10), ('b', 2), ('d', 2)]);
+        assert_eq!(usage_order(&templates, &usage), vec![2, 1, 3, 0]);
+    }
+
+    #[test]
+    fn test_is_sure() {
+        assert!(is_sure(&[]));
+        assert!(is_sure(&[('a', 3.0)]));
+        assert!(is_sure(&[('a', 1.0), ('o', 2.0)]));
+        assert!(!is_sure(&[('a', 1.9), ('o', 2.0)]));
+    }
+}
@@ -27,7 +27,20 @@ pub enum InkType {
     Carat { at: Coord, ink: Ink },
     BigGlyph { token: Ink },
     LineTo { coord: Coord },
+    // A small tap on a carat mark; clears the selection.
+    Cancel,
+    // An arc from one word to the next: close up the space between them.
+    Join { start: Coord, end: Coord },
+    // A tall line cutting through a row at a cell boundary: split the word there with a space.
+    Split { at: Coord },
+    // A hook from after the end of a line down and back to the next: pull that line up.
+    JoinLines { at: Coord },
+    // A line down from a cell boundary that turns back left: break the line there.
+    SplitLine { at: Coord },
+    // Two horizontal strikes through the same row: delete the words they cover.
+    DeleteWords { start: Coord, end: Coord },
 }
+
 impl InkType {
     pub fn tokenize(metrics: &Metrics, ink: &Ink) -> HashMap<usize, Ink> {
         // Idea: if the center of a stroke is ~this close to the margin, it's ambiguous,
@@ -108,6 +121,24 @@ impl InkType {
         let min_x = ink.x_range.min / metrics.width as f32;
         let max_x = ink.x_range.max / metrics.width as f32;
 
+        if matches!(selection, &Selection::Normal) {
+            if let Some(hook) = line_hook(metrics, &ink) {
+                return Some(hook);
+            }
+            if let Some((row, start, end)) = arc_ends(metrics, &ink) {
+                return Some(InkType::Join {
+                    start: (row, start),
+                    end: (row, end),
+                });
+            }
+            if let Some((row, start, end)) = double_strike(metrics, &ink) {
+                return Some(InkType::DeleteWords {
+                    start: (row, start),
+                    end: (row, end),
+                });
+            }
+        }
+
         // Roughly: a strikethrough should be a single stroke that's mostly horizontal.
         if matches!(selection, &Selection::Normal)
             && (max_x - min_x) > 1.5
@@ -160,6 +191,36 @@ impl InkType {
 
         let center = (min_x + max_x) / 2.0;
 
+        // A tap right on top of a carat.
+        if (max_x - min_x) < 0.25 && (max_y - min_y) < 0.25 && center >= 0.0 {
+            let at = (row, center.round() as usize);
+            let on_carat = match selection {
+                Selection::Normal => false,
+                Selection::Single { carat } => *carat == at,
+                Selection::Range { start, end } | Selection::Block { start, end } => {
+                    *start == at || *end == at
+                }
+                Selection::Multi { carats } => carats.contains(&at),
+            };
+            if on_carat {
+                return Some(InkType::Cancel);
+            }
+        }
+
+        // A carat that reaches well into the rows above and below cuts the word in two.
+        if matches!(selection, &Selection::Normal)
+            && min_y < -0.5
+            && max_y > 1.5
+            && (max_x - min_x) < 0.5
+            && (center - center.round()).abs() < 0.3
+            && center.round() >= 0.0
+            && ink.strokes().count() == 1
+        {
+            return Some(InkType::Split {
+                at: (row, center.round() as usize),
+            });
+        }
+
         // Detect the carat!
         // Vertical, and very close to a cell boundary.
         if min_y < 0.1
@@ -184,20 +245,208 @@ impl InkType {
             return Some(InkType::Scratch { at: (row, col) });
         }
 
-        match selection {
-            Selection::Normal => {
-                let mut tokens: Vec<_> = Self::tokenize(metrics, &ink)
-                    .into_iter()
-                    .map(|(c, v)| ((row, c), v))
-                    .collect();
-                tokens.sort_by_key(|(k, _)| *k);
-                Some(InkType::Glyphs { tokens })
-            }
-            _ => {
-                let centroid = ink.centroid();
-                let ink = ink.translate(-centroid.to_vec());
-                Some(InkType::BigGlyph { token: ink })
-            }
+        let is_text = match selection {
+            Selection::Normal => true,
+            // With several carats, small writing that fits in a row is text to repeat at each one.
+            Selection::Multi { .. } => min_y > -0.25 && max_y < 1.25,
+            _ => false,
+        };
+        if is_text {
+            let mut tokens: Vec<_> = Self::tokenize(metrics, &ink)
+                .into_iter()
+                .map(|(c, v)| ((row, c), v))
+                .collect();
+            tokens.sort_by_key(|(k, _)| *k);
+            Some(InkType::Glyphs { tokens })
+        } else {
+            let centroid = ink.centroid();
+            let ink = ink.translate(-centroid.to_vec());
+            Some(InkType::BigGlyph { token: ink })
+        }
+    }
+}
+
+/// Classify a single stroke that runs down from one row and back to the left in the next: a
+/// straight drop from a cell boundary before turning is a line break there, and anything else
+/// is a hook joining the lines.
+fn line_hook(metrics: &Metrics, ink: &Ink) -> Option<InkType> {
+    let (width, height) = (metrics.width as f32, metrics.height as f32);
+    let mut strokes = ink.strokes();
+    let stroke = strokes.next()?;
+    if strokes.next().is_some() {
+        return None;
+    }
+    let (first, last) = (stroke.first()?, stroke.last()?);
+    let row = (first.y / height).max(0.0) as usize;
+    if (last.y / height).max(0.0) as usize != row + 1 || last.x > first.x - width {
+        return None;
+    }
+    let col = first.x / width;
+    let bottom = (row + 1) as f32 * height;
+    // Where the stroke had got to across by the time it reached the bottom of the first row.
+    let drift = stroke
+        .iter()
+        .take_while(|p| p.y < bottom)
+        .map(|p| (p.x - first.x).abs())
+        .fold(0.0, f32::max);
+    if (col - col.round()).abs() < 0.3 && drift < 0.4 * width {
+        Some(InkType::SplitLine {
+            at: (row, col.round() as usize),
+        })
+    } else if last.x < first.x - 1.5 * width {
+        Some(InkType::JoinLines {
+            at: (row, col.max(0.0) as usize),
+        })
+    } else {
+        None
+    }
+}
+
+/// If the ink is a single stroke that bows up or down between two points on about the same
+/// level, a cell or more apart, the columns it starts and ends in and the row they're on.
+fn arc_ends(metrics: &Metrics, ink: &Ink) -> Option<(usize, usize, usize)> {
+    let (width, height) = (metrics.width as f32, metrics.height as f32);
+    let mut strokes = ink.strokes();
+    let stroke = strokes.next()?;
+    if strokes.next().is_some() {
+        return None;
+    }
+    let (first, last) = (stroke.first()?, stroke.last()?);
+    let (left, right) = if first.x < last.x {
+        (first, last)
+    } else {
+        (last, first)
+    };
+    let span = right.x - left.x;
+    if span < 1.5 * width || (right.y - left.y).abs() > 0.4 * height {
+        return None;
+    }
+    // How far the stroke strays from the straight line between its ends; a strikethrough stays
+    // close, and anything much longer than the span is probably writing.
+    let sag = stroke
+        .iter()
+        .map(|p| {
+            let along = ((p.x - left.x) / span).clamp(0.0, 1.0);
+            (p.y - (left.y + along * (right.y - left.y))).abs()
+        })
+        .fold(0.0, f32::max);
+    if sag < 0.25 * height || ink.ink_len() > 2.0 * span {
+        return None;
+    }
+    let row = ((left.y + right.y) / 2.0 / height).max(0.0) as usize;
+    let col = |x: f32| (x / width).max(0.0) as usize;
+    Some((row, col(left.x), col(right.x)))
+}
+
+/// If the ink is two mostly-straight horizontal strokes, one over the other on the same row,
+/// the row and the columns they start and end at between them.
+fn double_strike(metrics: &Metrics, ink: &Ink) -> Option<(usize, usize, usize)> {
+    let (width, height) = (metrics.width as f32, metrics.height as f32);
+    let strokes: Vec<_> = ink.strokes().collect();
+    if strokes.len() != 2 {
+        return None;
+    }
+    let mut ranges = vec![];
+    for stroke in &strokes {
+        let min_x = stroke.iter().map(|p| p.x).fold(f32::MAX, f32::min);
+        let max_x = stroke.iter().map(|p| p.x).fold(f32::MIN, f32::max);
+        let min_y = stroke.iter().map(|p| p.y).fold(f32::MAX, f32::min);
+        let max_y = stroke.iter().map(|p| p.y).fold(f32::MIN, f32::max);
+        let len: f32 = stroke
+            .windows(2)
+            .map(|w| (w[1].x - w[0].x).hypot(w[1].y - w[0].y))
+            .sum();
+        if max_x - min_x < 1.5 * width
+            || max_y - min_y > 0.5 * height
+            || len > 1.2 * (max_x - min_x)
+        {
+            return None;
         }
+        ranges.push((min_x, max_x, (min_y + max_y) / 2.0));
+    }
+    let (a, b) = (ranges[0], ranges[1]);
+    let overlap = a.1.min(b.1) - a.0.max(b.0);
+    let row = |y: f32| (y / height).max(0.0) as usize;
+    if overlap < 0.5 * (a.1 - a.0).min(b.1 - b.0) || row(a.2) != row(b.2) {
+        return None;
+    }
+    let col = |x: f32| (x / width).round().max(0.0) as usize;
+    Some((row(a.2), col(a.0.min(b.0)), col(a.1.max(b.1))))
+}
+
+/// How far the pen can move during a tap, in pixels.
+const TAP_EXTENT: f32 = 2.0;
+/// How long a tap can last, in seconds.
+const TAP_SECS: f32 = 0.2;
+
+/// If the ink is a tap of the pen, rather than writing, the cell it landed in. A tap barely
+/// moves and is over quickly, so small marks like `.` or `'` still count as writing.
+pub fn tapped_cell(metrics: &Metrics, ink: &Ink) -> Option<Coord> {
+    let (width, height) = (metrics.width as f32, metrics.height as f32);
+    if ink.strokes().count() != 1
+        || ink.x_range.max - ink.x_range.min > TAP_EXTENT
+        || ink.y_range.max - ink.y_range.min > TAP_EXTENT
+        || ink.t_range.max - ink.t_range.min > TAP_SECS
+    {
+        return None;
+    }
+    let center = ink.centroid();
+    Some((
+        (center.y / height).max(0.0) as usize,
+        (center.x / width).max(0.0) as usize,
+    ))
+}
+
+/// How long the pen has to rest in one place to count as a long press, in seconds.
+const LONG_PRESS_SECS: f32 = 0.5;
+
+/// Whether the ink is the pen held down in one spot, rather than a tap or any writing.
+pub fn long_press(ink: &Ink) -> bool {
+    ink.strokes().count() == 1
+        && ink.x_range.max - ink.x_range.min < 10.0
+        && ink.y_range.max - ink.y_range.min < 10.0
+        && ink.t_range.max - ink.t_range.min >= LONG_PRESS_SECS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stroke(points: &[(f32, f32)]) -> Ink {
+        let mut ink = Ink::new();
+        for (i, &(x, y)) in points.iter().enumerate() {
+            ink.push(x, y, i as f32 * 0.01);
+        }
+        ink.pen_up();
+        ink
+    }
+
+    #[test]
+    fn test_line_hook() {
+        let metrics = Metrics {
+            height: 40,
+            width: 20,
+            baseline: 30,
+        };
+        let split = stroke(&[(60.0, 10.0), (61.0, 30.0), (60.0, 55.0), (20.0, 60.0)]);
+        assert!(matches!(
+            line_hook(&metrics, &split),
+            Some(InkType::SplitLine { at: (0, 3) })
+        ));
+        let join = stroke(&[(130.0, 20.0), (145.0, 30.0), (140.0, 50.0), (60.0, 60.0)]);
+        assert!(matches!(
+            line_hook(&metrics, &join),
+            Some(InkType::JoinLines { at: (0, 6) })
+        ));
+        // Too short a hook back, or one that stays on its row, isn't either.
+        let short = stroke(&[(130.0, 20.0), (145.0, 30.0), (120.0, 60.0)]);
+        assert!(line_hook(&metrics, &short).is_none());
+        let flat = stroke(&[(60.0, 10.0), (20.0, 20.0)]);
+        assert!(line_hook(&metrics, &flat).is_none());
+        let mut two = split.clone();
+        two.push(10.0, 10.0, 1.0);
+        two.push(15.0, 15.0, 1.1);
+        two.pen_up();
+        assert!(line_hook(&metrics, &two).is_none());
     }
 }
@@ -0,0 +1,89 @@
+/// The heading that starts each day's section of the journal.
+pub fn heading(date: &str) -> String {
+    format!("## {date}")
+}
+
+/// The date of a day's heading, if the line is one.
+fn day_of(line: &str) -> Option<&str> {
+    let date = line.strip_prefix("## ")?.trim_end();
+    let shape = date.len() == 10
+        && date.char_indices().all(|(i, c)| match i {
+            4 | 7 => c == '-',
+            _ => c.is_ascii_digit(),
+        });
+    shape.then_some(date)
+}
+
+/// Find the heading for the day, by row. If there isn't one, the error is where it belongs:
+/// days are kept in order, so that's just before the first later day, or the end of the file.
+pub fn locate(lines: &[&str], date: &str) -> Result<usize, usize> {
+    for (row, line) in lines.iter().enumerate() {
+        match day_of(line) {
+            Some(day) if day == date => return Ok(row),
+            Some(day) if day > date => return Err(row),
+            _ => {}
+        }
+    }
+    Err(lines.len())
+}
+
+/// The row just after the last non-blank line of the day that starts at `heading`, which is
+/// where the next entry goes.
+pub fn section_end(lines: &[&str], heading: usize) -> usize {
+    let end = lines[heading + 1..]
+        .iter()
+        .position(|l| day_of(l).is_some())
+        .map_or(lines.len(), |i| heading + 1 + i);
+    (heading + 1..end)
+        .rev()
+        .find(|row| !lines[*row].trim().is_empty())
+        .map_or(heading + 1, |row| row + 1)
+}
+
+/// The nearest day before or after the given one that has a section, with the row of its
+/// heading.
+pub fn adjacent(lines: &[&str], date: &str, later: bool) -> Option<(String, usize)> {
+    let days = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(row, line)| Some((day_of(line)?, row)));
+    let found = if later {
+        days.filter(|(day, _)| *day > date).min()
+    } else {
+        days.filter(|(day, _)| *day < date).max()
+    };
+    found.map(|(day, row)| (day.to_string(), row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal() {
+        let lines = vec![
+            "# Journal",
+            "",
+            "## 2024-03-01",
+            "- 09:00 coffee",
+            "",
+            "## 2024-03-04",
+            "- 18:30 dinner",
+            "## not a date",
+        ];
+        assert_eq!(locate(&lines, "2024-03-01"), Ok(2));
+        assert_eq!(locate(&lines, "2024-03-02"), Err(5));
+        assert_eq!(locate(&lines, "2024-04-01"), Err(8));
+        assert_eq!(section_end(&lines, 2), 4);
+        assert_eq!(section_end(&lines, 5), 8);
+        assert_eq!(
+            adjacent(&lines, "2024-03-02", false),
+            Some(("2024-03-01".to_string(), 2))
+        );
+        assert_eq!(
+            adjacent(&lines, "2024-03-01", true),
+            Some(("2024-03-04".to_string(), 5))
+        );
+        assert_eq!(adjacent(&lines, "2024-03-04", true), None);
+    }
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::util;
+
+/// Labels like `draft` or `done` attached to files, kept in an index of their own so the files
+/// themselves are left alone.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Labels {
+    files: BTreeMap<PathBuf, BTreeSet<String>>,
+}
+
+impl Labels {
+    pub fn load(path: &Path) -> io::Result<Labels> {
+        util::load_json(path)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        util::save_json(path, self)
+    }
+
+    /// The labels on a file, in order.
+    pub fn of(&self, path: &Path) -> Vec<&str> {
+        self.files
+            .get(path)
+            .map_or(vec![], |labels| labels.iter().map(String::as_str).collect())
+    }
+
+    /// Every label in use on any file.
+    pub fn all(&self) -> BTreeSet<&str> {
+        self.files.values().flatten().map(String::as_str).collect()
+    }
+
+    /// The files with the given label.
+    pub fn tagged<'a>(&'a self, label: &'a str) -> impl Iterator<Item = &'a Path> {
+        self.files
+            .iter()
+            .filter(move |(_, labels)| labels.contains(label))
+            .map(|(path, _)| path.as_path())
+    }
+
+    /// Add the label to the file if it doesn't have it, or take it off if it does.
+    pub fn toggle(&mut self, path: &Path, label: &str) {
+        let labels = self.files.entry(path.to_path_buf()).or_default();
+        if !labels.remove(label) {
+            labels.insert(label.to_string());
+        }
+        if labels.is_empty() {
+            self.files.remove(path);
+        }
+    }
+
+    /// Forget a deleted file's labels. Returns whether it had any.
+    pub fn removed(&mut self, path: &Path) -> bool {
+        self.files.remove(path).is_some()
+    }
+
+    /// Keep a file's labels when it's renamed.
+    pub fn moved(&mut self, from: &Path, to: &Path) {
+        if let Some(labels) = self.files.remove(from) {
+            self.files.insert(to.to_path_buf(), labels);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels() {
+        let mut labels = Labels::default();
+        let notes = Path::new("/notes/a.md");
+        let other = Path::new("/notes/b.md");
+        labels.toggle(notes, "draft");
+        labels.toggle(notes, "course-A");
+        labels.toggle(other, "draft");
+        assert_eq!(labels.of(notes), vec!["course-A", "draft"]);
+        assert_eq!(
+            labels.all().into_iter().collect::<Vec<_>>(),
+            vec!["course-A", "draft"]
+        );
+        assert_eq!(
+            labels.tagged("draft").collect::<Vec<_>>(),
+            vec![notes, other]
+        );
+
+        labels.toggle(other, "draft");
+        assert!(labels.of(other).is_empty());
+        labels.moved(notes, other);
+        assert_eq!(labels.of(other), vec!["course-A", "draft"]);
+
+        let json = serde_json::to_string(&labels).unwrap();
+        let loaded: Labels = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.of(other), vec!["course-A", "draft"]);
+
+        assert!(labels.removed(other));
+        assert!(!labels.removed(other));
+        assert!(labels.all().is_empty());
+    }
+}
@@ -0,0 +1,251 @@
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::ops::Range;
+use std::os::unix::fs::{FileExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// How much of the file to read at a time while indexing it.
+const CHUNK_SIZE: usize = 1 << 16;
+
+/// A run of lines in the document: either still on disk, by line number in the indexed file,
+/// or edited and kept in memory.
+#[derive(Clone, Debug)]
+enum Piece {
+    Disk(Range<usize>),
+    Memory(Vec<String>),
+}
+
+impl Piece {
+    fn len(&self) -> usize {
+        match self {
+            Piece::Disk(lines) => lines.len(),
+            Piece::Memory(lines) => lines.len(),
+        }
+    }
+
+    fn slice(&self, range: Range<usize>) -> Piece {
+        match self {
+            Piece::Disk(lines) => Piece::Disk(lines.start + range.start..lines.start + range.end),
+            Piece::Memory(lines) => Piece::Memory(lines[range].to_vec()),
+        }
+    }
+}
+
+/// A file too big to hold in memory all at once, indexed by where each line starts so any run
+/// of lines can be read back from disk when it's needed. Edits are laid over the top, so only
+/// the lines that have changed are kept in memory.
+#[derive(Clone)]
+pub struct LargeFile {
+    path: PathBuf,
+    /// The file as it was indexed. Holding on to it means the lines still read back the same
+    /// after the file's been replaced, say by saving over it.
+    file: Arc<File>,
+    /// The byte offset of the start of each line. Shared, since it's copied for every save.
+    starts: Arc<Vec<u64>>,
+    /// How much of the file has been indexed.
+    len: u64,
+    /// The document, as runs of lines from disk and edited lines in between.
+    pieces: Vec<Piece>,
+}
+
+impl LargeFile {
+    pub fn index(path: &Path) -> io::Result<LargeFile> {
+        let mut file = LargeFile {
+            path: path.to_path_buf(),
+            file: Arc::new(File::open(path)?),
+            starts: Arc::new(vec![0]),
+            len: 0,
+            pieces: vec![Piece::Disk(0..1)],
+        };
+        file.refresh()?;
+        Ok(file)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn lines(&self) -> usize {
+        self.pieces.iter().map(Piece::len).sum()
+    }
+
+    /// Whether any lines have been edited since the file was indexed.
+    pub fn edited(&self) -> bool {
+        self.pieces.iter().any(|p| matches!(p, Piece::Memory(_)))
+    }
+
+    /// Whether the file at the path isn't the one that was indexed any more, or has been cut
+    /// short, so the only way to catch up is to index it again from the start.
+    pub fn replaced(&self) -> bool {
+        match (fs::metadata(&self.path), self.file.metadata()) {
+            (Ok(now), Ok(indexed)) => {
+                now.dev() != indexed.dev() || now.ino() != indexed.ino() || now.len() < self.len
+            }
+            _ => true,
+        }
+    }
+
+    /// Index whatever's been added to the end of the file since last time, and add it to the
+    /// end of the document. Returns whether anything changed.
+    pub fn refresh(&mut self) -> io::Result<bool> {
+        let len = self.file.metadata()?.len();
+        if len <= self.len {
+            return Ok(false);
+        }
+        let indexed = self.starts.len();
+        let starts = Arc::make_mut(&mut self.starts);
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let mut offset = self.len;
+        while offset < len {
+            let read = self.file.read_at(&mut chunk, offset)?;
+            if read == 0 {
+                break;
+            }
+            for (i, byte) in chunk[..read].iter().enumerate() {
+                if *byte == b'\n' {
+                    starts.push(offset + i as u64 + 1);
+                }
+            }
+            offset += read as u64;
+        }
+        self.len = offset;
+        let lines = self.starts.len();
+        match self.pieces.last_mut() {
+            Some(Piece::Disk(last)) if last.end == indexed => last.end = lines,
+            _ => self.pieces.push(Piece::Disk(indexed..lines)),
+        }
+        Ok(true)
+    }
+
+    /// The text of the given lines. Bytes that aren't valid UTF-8 come back as replacement
+    /// characters rather than failing the whole read.
+    pub fn read(&self, rows: Range<usize>) -> io::Result<String> {
+        let mut parts = vec![];
+        let mut at = 0;
+        for piece in &self.pieces {
+            let start = rows.start.max(at);
+            let end = rows.end.min(at + piece.len());
+            if start < end {
+                parts.push(match piece.slice(start - at..end - at) {
+                    Piece::Disk(lines) => self.read_disk(lines)?,
+                    Piece::Memory(lines) => lines.join("\n"),
+                });
+            }
+            at += piece.len();
+        }
+        Ok(parts.join("\n"))
+    }
+
+    /// The whole document, with any edits.
+    pub fn contents(&self) -> io::Result<String> {
+        self.read(0..self.lines())
+    }
+
+    /// Put the given lines in place of the ones in `rows`, keeping them in memory.
+    pub fn replace_lines(&mut self, rows: Range<usize>, lines: Vec<String>) {
+        let mut before = vec![];
+        let mut after = vec![];
+        let mut at = 0;
+        for piece in &self.pieces {
+            let len = piece.len();
+            if at < rows.start {
+                before.push(piece.slice(0..len.min(rows.start - at)));
+            }
+            if at + len > rows.end {
+                after.push(piece.slice(rows.end.saturating_sub(at)..len));
+            }
+            at += len;
+        }
+        if !lines.is_empty() {
+            before.push(Piece::Memory(lines));
+        }
+        before.extend(after);
+        before.retain(|p| p.len() > 0);
+        self.pieces = before;
+    }
+
+    /// The text of the given lines of the file as it was indexed.
+    fn read_disk(&self, lines: Range<usize>) -> io::Result<String> {
+        let start = match self.starts.get(lines.start) {
+            Some(start) => *start,
+            None => return Ok(String::new()),
+        };
+        let end = self.starts.get(lines.end).map_or(self.len, |end| end - 1);
+        let mut bytes = vec![0; end.saturating_sub(start) as usize];
+        self.file.read_exact_at(&mut bytes, start)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+/// The index and the edits are far too much to be worth printing, and the edits could be
+/// anything; see `describe`.
+impl fmt::Debug for LargeFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({} lines)", self.path.display(), self.lines())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn test_large_file() {
+        let dir = TempDir::new("large-file");
+        let path = dir.join("test.log");
+        fs::write(&path, "one\ntwo\nthree\n").unwrap();
+        let mut file = LargeFile::index(&path).unwrap();
+        assert_eq!(file.lines(), 4);
+        assert_eq!(file.read(0..1).unwrap(), "one");
+        assert_eq!(file.read(1..3).unwrap(), "two\nthree");
+        assert_eq!(file.read(2..10).unwrap(), "three\n");
+        assert_eq!(file.read(10..12).unwrap(), "");
+
+        assert!(!file.refresh().unwrap());
+        let mut appending = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        appending.write_all(b"four\nfi").unwrap();
+        assert!(file.refresh().unwrap());
+        assert_eq!(file.lines(), 5);
+        assert_eq!(file.read(3..5).unwrap(), "four\nfi");
+        assert!(!file.replaced());
+
+        fs::write(&path, "short").unwrap();
+        assert!(file.replaced());
+        let file = LargeFile::index(&path).unwrap();
+        assert_eq!(file.lines(), 1);
+        assert_eq!(file.read(0..1).unwrap(), "short");
+    }
+
+    #[test]
+    fn test_large_file_edits() {
+        let dir = TempDir::new("large-file-edits");
+        let path = dir.join("test.log");
+        fs::write(&path, "one\ntwo\nthree\nfour").unwrap();
+        let mut file = LargeFile::index(&path).unwrap();
+        file.replace_lines(
+            1..3,
+            vec!["2".to_string(), "2.5".to_string(), "3".to_string()],
+        );
+        assert!(file.edited());
+        assert_eq!(file.lines(), 5);
+        assert_eq!(file.read(0..2).unwrap(), "one\n2");
+        assert_eq!(file.contents().unwrap(), "one\n2\n2.5\n3\nfour");
+
+        file.replace_lines(0..1, vec![]);
+        file.replace_lines(3..4, vec!["4".to_string()]);
+        assert_eq!(file.contents().unwrap(), "2\n2.5\n3\n4");
+
+        // The edits still read back the same once the file's been replaced.
+        let saved = dir.join("test.new");
+        fs::write(&saved, file.contents().unwrap()).unwrap();
+        fs::rename(&saved, &path).unwrap();
+        assert!(file.replaced());
+        assert_eq!(file.contents().unwrap(), "2\n2.5\n3\n4");
+    }
+}
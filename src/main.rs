@@ -1,11 +1,14 @@
 use std::borrow::{Borrow, Cow};
-use std::collections::{BTreeMap, VecDeque};
-use std::fmt::Display;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::{self, Display};
 use std::fs::File;
-use std::io::{ErrorKind, Read, Write};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
-use std::process::{Child, Stdio};
+use std::process::Child;
 use std::rc::Rc;
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, SystemTime};
 use std::{env, fs, io, process, thread};
 
 use armrest::app;
@@ -20,23 +23,72 @@ use once_cell::sync::Lazy;
 use xdg::BaseDirectories;
 
 use config::*;
+use crypt::Cipher;
+use dictionary::Dictionary;
+use export::*;
 use font::*;
 use grid_ui::*;
+use highlight::Language;
 use hwr::*;
 use ink_type::*;
+use large_file::LargeFile;
+use profile::ProfileFile;
+use reader::{LineStyle, ReaderLine};
+use recorder::Recorder;
+use session::{Session, SessionTab};
 use text_buffer::*;
 use text_window::*;
+use tree::FileTree;
+use util::{date_of, hex_dump, local_now, looks_binary, short_hash, today, write_atomic};
 use widgets::*;
+use workspace::Workspace;
 
+mod ansi;
+mod calc;
+mod cleanup;
+mod completion;
 mod config;
+mod crypt;
+mod diagnostics;
+mod dictionary;
+mod docs;
+mod export;
 mod font;
+mod format;
+mod generator;
 mod grid_ui;
+mod highlight;
 mod hwr;
 mod ink_type;
+mod journal;
+mod labels;
+mod large_file;
+mod markdown;
+mod merge;
+mod mounts;
+mod outline;
+mod pdf;
+mod practice;
+mod profile;
+mod pty;
+mod reader;
+mod recorder;
+mod reminders;
+mod rename;
+mod rope;
+mod run;
+mod session;
+mod sort;
+mod stats;
+mod suspend;
+mod tasks;
 mod text_buffer;
 mod text_window;
+mod tree;
 mod util;
 mod widgets;
+mod worksheet;
+mod workspace;
 
 static BASE_DIRS: Lazy<BaseDirectories> =
     Lazy::new(|| BaseDirectories::with_prefix(env!("CARGO_PKG_NAME")).unwrap());
@@ -51,13 +103,36 @@ const DEFAULT_CHAR_HEIGHT: i32 = 40;
 const TEMPLATE_FILE: &str = "templates.json";
 const CONFIG_FILE: &str = "sill.toml";
 const BASH_RC_FILE: &str = "sill.bashrc";
+const RECOVERY_DIR: &str = "recovery";
+const SESSION_FILE: &str = "session.json";
+const REMINDERS_FILE: &str = "reminders.json";
+const LABELS_FILE: &str = "labels.json";
+/// How often to look for reminders that have come due.
+const REMINDER_CHECK_SECS: u64 = 60;
+/// How often to look for new lines in files being followed.
+const FOLLOW_CHECK_SECS: u64 = 2;
+/// How often to look for drives being plugged in or taken out.
+const MOUNT_CHECK_SECS: u64 = 5;
+/// How long after an edit to save the session, so a burst of writing only saves it once.
+const SESSION_SAVE_SECS: u64 = 10;
+/// How long a formatter gets before it's stopped, so a hung one doesn't leave a thread behind.
+const FORMAT_TIMEOUT_SECS: u64 = 30;
+/// How many lines of a large file to keep in memory at once, around the ones on screen.
+const LARGE_FILE_WINDOW: usize = 2000;
+/// How much of a binary file to show in its hex view.
+const HEX_VIEW_BYTES: u64 = 64 * 1024;
+const CRASH_LOG: &str = "crash.log";
+const EVENT_LOG: &str = "events.log";
 
 const HELP_TEXT: &str = include_str!("../README.md");
 
+/// The message and location of the last panic, as reported by the panic hook; see `main`.
+static LAST_PANIC: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
+
 static APP_NAME: Lazy<String> =
     Lazy::new(|| format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")));
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum Msg {
     MetaPath { current_path: String },
     SwitchTab { tab: Tab },
@@ -66,25 +141,183 @@ pub enum Msg {
     Erase { ink: Ink },
     Swipe { towards: Side },
     Open { path: PathBuf },
+    OpenAt { path: PathBuf, row: usize },
     OpenShell { working_dir: PathBuf },
     Tab { id: usize, msg: TabMsg },
+    PressTab { id: usize, ink: Ink },
+    ReaderJump { id: usize, row: usize },
+    ClearSketch,
+    SaveSketch,
+    TemplatesLoaded,
+    MemoryReport,
+    CancelSelection,
+    Nav { key: NavKey },
+    ReplaceNext,
+    ReplaceAll,
+    Autosave,
+    SaveSession,
+    Suspend { signal: i32 },
+    RestoreSession,
+    DiscardSession,
+    UseClip { index: usize },
+    PasteClip { id: usize, index: usize },
+    PasteAllClips { id: usize },
+    InsertSnippet { id: usize, text: String },
+    SelectPath { path: PathBuf },
+    ClearSelectedPaths,
+    ApplyRename,
+    UseAlternative { id: usize, coord: Coord, c: char },
+    ClearClips,
+    ImportSheet,
+    ImportTemplates,
+    AddStarterTemplates,
+    Practice,
+    SkipPractice,
+    NextTemplateProfile,
+    ExportTemplates,
+    DeleteTemplate,
+    ExportProfile,
+    ImportProfile,
+    OpenJournal,
+    Unlock,
+    OpenWorkspace { root: PathBuf },
+    CloseWorkspace,
+    WorkspaceFind,
+    WorkspaceTasks,
+    WorkspaceFound(String, Vec<workspace::Match>),
+    ToggleTree,
+    ExpandTree { path: PathBuf },
+    PressInTree { path: PathBuf, ink: Ink },
+    TreePage { page: usize },
+    OpenFromTree { path: PathBuf },
+    CopyFile { path: PathBuf },
+    DeleteFile { path: PathBuf },
+    SaveEventLog,
+    Docs { topic: String },
+    DocsLoaded(String, Result<String, String>),
+    NoManual(String, Option<PathBuf>),
+    CheckReminders,
+    CheckMounts,
+    FollowFiles,
+    DismissReminder { index: usize },
+    FilterLabel(Option<String>),
+    ToggleLabel(PathBuf, String),
+    AddLabel(PathBuf),
+    Diagnose,
+    SelfTested(Vec<diagnostics::Check>),
+    Crashed { route: String },
     New,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum TabMsg {
-    ShellInput { stderr: bool, content: String },
+    ShellInput {
+        content: String,
+    },
     SubmitShell,
-    SaveAs { path: PathBuf },
+    /// A control character for the terminal, like `^C` to interrupt the running command.
+    Control(u8),
+    Complete,
+    UseCompletion(String),
+    SaveAs {
+        path: PathBuf,
+    },
+    /// In the merge view, keep the given side of one difference, or of all of them.
+    Pick {
+        index: usize,
+        pick: merge::Pick,
+    },
+    PickAll(merge::Pick),
+    /// Save the file as it's been put together in the merge view.
+    SaveMerged,
+    InsertFile {
+        path: PathBuf,
+    },
+    AppendTo {
+        path: PathBuf,
+    },
+    Export,
+    ExportPdf,
+    JournalEntry,
+    JournalDay {
+        later: bool,
+    },
+    Generate,
+    Stats,
+    Clean,
+    Calculate,
+    ToggleTask {
+        row: usize,
+    },
+    Wrap(bool),
+    Insert(bool),
+    ReadOnly(bool),
+    Follow(bool),
+    Sort(sort::SortOptions),
+    Read,
     Undo,
     Redo,
     Save,
+    Saved {
+        edits: usize,
+        result: Result<(), String>,
+        /// The size and hash of what was read back, if saves are verified.
+        verified: Option<String>,
+    },
+    Autosaved {
+        edits: usize,
+        result: Result<(), String>,
+    },
+    Recover,
+    NextTemplateProfile,
+    Format,
+    Review,
+    Reload,
+    KeepMine,
+    Run,
+    Formatted {
+        edits: usize,
+        result: Result<String, String>,
+    },
+    /// A large file has been indexed, as of the given edit count.
+    Indexed {
+        edits: usize,
+        result: Result<LargeFile, String>,
+    },
     Quit,
 }
 
+/// Views carry a lot of state that isn't worth logging; see `describe`.
+impl fmt::Debug for Tab {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.tab_id() {
+            Some(id) => write!(f, "{} {id}", self.route()),
+            None => f.write_str(self.route()),
+        }
+    }
+}
+
+/// A one-line summary of a message for the event log. Ink is written out the same way as in the
+/// templates file, so the strokes can be replayed.
+fn describe(message: &Msg) -> String {
+    match message {
+        Msg::Write { ink } => format!("Write {ink}"),
+        Msg::Erase { ink } => format!("Erase {ink}"),
+        Msg::SwitchTab {
+            tab: Tab::Sketch { id, ink },
+        } => format!("SwitchTab sketch {id} {ink}"),
+        other => format!("{other:?}"),
+    }
+}
+
 pub struct Meta {
     path_window: TextWindow,
     suggested: Vec<String>,
+    /// Files picked out of the suggestions, to rename together.
+    selected: BTreeSet<PathBuf>,
+    labels: labels::Labels,
+    /// When set, the suggestions are the files with this label instead.
+    label_filter: Option<String>,
 }
 
 impl Meta {
@@ -92,6 +325,9 @@ impl Meta {
         let mut new = Meta {
             path_window,
             suggested: vec![],
+            selected: BTreeSet::new(),
+            labels: labels::Labels::default(),
+            label_filter: None,
         };
 
         new.reload_suggestions();
@@ -100,7 +336,18 @@ impl Meta {
     }
 
     pub fn reload_suggestions(&mut self) {
-        self.suggested = suggestions(&self.path_window.buffer.content_string()).unwrap_or_default()
+        let current_path = self.path_window.buffer.content_string();
+        self.suggested = match &self.label_filter {
+            Some(label) => self
+                .labels
+                .tagged(label)
+                .filter_map(|path| path.to_str())
+                .filter(|path| path.starts_with(&current_path))
+                .take(NUM_SUGGESTIONS)
+                .map(String::from)
+                .collect(),
+            None => suggestions(&current_path).unwrap_or_default(),
+        }
     }
 }
 
@@ -114,6 +361,136 @@ pub enum Tab {
         contents: IndexedString,
         results: Vec<usize>,
     },
+    Sketch {
+        id: usize,
+        ink: Ink,
+    },
+    Commands {
+        id: usize,
+    },
+    Clips {
+        id: usize,
+    },
+    Palette {
+        id: usize,
+    },
+    /// Other guesses for a character that was just written.
+    Alternatives {
+        id: usize,
+        coord: Coord,
+        candidates: Vec<char>,
+    },
+    /// Asks for the passphrase to open an encrypted file, or to save a tab as one.
+    Passphrase {
+        path: PathBuf,
+        id: Option<usize>,
+    },
+    Tasks {
+        id: usize,
+    },
+    Contents {
+        id: usize,
+    },
+    /// Writing prompted characters, to see how the recognizer reads them.
+    Practice {
+        practice: practice::Practice,
+    },
+    /// A preview of renaming the selected files by the pattern in the search window.
+    Rename,
+    /// The labels on a file, and a place to write a new one.
+    Labels {
+        path: PathBuf,
+    },
+    /// The files in the open workspace, or the lines found in them.
+    Workspace {
+        found: Option<(String, Vec<workspace::Match>)>,
+        page: usize,
+    },
+    Completions {
+        id: usize,
+        candidates: Vec<String>,
+    },
+    Sort {
+        id: usize,
+        options: sort::SortOptions,
+    },
+    Replace {
+        id: usize,
+        token: String,
+        next: Coord,
+    },
+    /// Asks for a line number to scroll the tab to.
+    Goto {
+        id: usize,
+    },
+    Reader {
+        id: usize,
+        reader: reader::Reader,
+    },
+    /// The file on disk and the buffer differ; pick which side to keep before saving over it.
+    Merge {
+        id: usize,
+        path: PathBuf,
+        chunks: Vec<merge::Chunk>,
+        picks: Vec<merge::Pick>,
+        page: usize,
+    },
+}
+
+impl Tab {
+    fn route(&self) -> &'static str {
+        match self {
+            Tab::Meta => "meta",
+            Tab::Edit { .. } => "edit",
+            Tab::Template => "template",
+            Tab::Practice { .. } => "practice",
+            Tab::Search { .. } => "search",
+            Tab::Replace { .. } => "replace",
+            Tab::Goto { .. } => "goto",
+            Tab::Sketch { .. } => "sketch",
+            Tab::Commands { .. } => "commands",
+            Tab::Clips { .. } => "clips",
+            Tab::Palette { .. } => "palette",
+            Tab::Alternatives { .. } => "alternatives",
+            Tab::Passphrase { .. } => "passphrase",
+            Tab::Tasks { .. } => "tasks",
+            Tab::Contents { .. } => "contents",
+            Tab::Rename => "rename",
+            Tab::Labels { .. } => "labels",
+            Tab::Workspace { .. } => "workspace",
+            Tab::Completions { .. } => "completions",
+            Tab::Sort { .. } => "sort",
+            Tab::Reader { .. } => "reader",
+            Tab::Merge { .. } => "merge",
+        }
+    }
+    /// The id of the open tab this view belongs to, if any.
+    fn tab_id(&self) -> Option<usize> {
+        match self {
+            Tab::Meta
+            | Tab::Template
+            | Tab::Practice { .. }
+            | Tab::Rename
+            | Tab::Labels { .. }
+            | Tab::Workspace { .. } => None,
+            Tab::Passphrase { id, .. } => *id,
+            Tab::Edit(id)
+            | Tab::Search { id, .. }
+            | Tab::Sketch { id, .. }
+            | Tab::Commands { id }
+            | Tab::Clips { id }
+            | Tab::Palette { id }
+            | Tab::Alternatives { id, .. }
+            | Tab::Tasks { id }
+            | Tab::Contents { id }
+            | Tab::Completions { id, .. }
+            | Tab::Sort { id, .. }
+            | Tab::Replace { id, .. }
+            | Tab::Goto { id }
+            | Tab::Reader { id, .. }
+            | Tab::Merge { id, .. } => Some(*id),
+        }
+    }
 }
 
 type Coord = (usize, usize);
@@ -130,16 +507,51 @@ impl TabType {
             TabType::Shell(s) => &s.title,
         }
     }
+
+    /// Short status notes for the tab list, for tabs that might need attention.
+    fn badges(&self) -> Vec<&'static str> {
+        let mut badges = vec![];
+        match self {
+            TabType::Text(t) => {
+                if t.dirty {
+                    badges.push("unsaved");
+                }
+                if t.hibernated.is_some() {
+                    badges.push("hibernated");
+                }
+            }
+            TabType::Shell(s) => {
+                if s.busy {
+                    badges.push("running");
+                }
+                if s.unseen_output {
+                    badges.push("new output");
+                }
+            }
+        }
+        badges
+    }
 }
 
 struct ShellTab {
     title: String,
     child: Child,
+    pty: pty::Pty,
     shell_output: TextWindow,
     history: VecDeque<TextBuffer>,
+    busy: bool,
+    unseen_output: bool,
+    /// Interprets the escape sequences in the output, as a terminal would.
+    terminal: ansi::Terminal,
 }
 
 impl ShellTab {
+    /// Whether the last line of the transcript is on screen.
+    fn end_on_screen(&self) -> bool {
+        let output = &self.shell_output;
+        output.buffer.contents.len() <= output.origin.0 + output.dimensions.0
+    }
+
     pub fn working_dir(&self) -> anyhow::Result<PathBuf> {
         let id = self.child.id();
         let cwd_link = PathBuf::from(format!("/proc/{id}/cwd"));
@@ -157,6 +569,102 @@ impl ShellTab {
         }
     }
 
+    /// The last line of the input that hasn't been sent to the shell yet, and where it starts.
+    fn input_line(&self) -> (Coord, String) {
+        let output = &self.shell_output;
+        let end = output.buffer.end();
+        let start = (end.0, 0).max(output.frozen_until);
+        (start, output.buffer.copy(start, end).content_string())
+    }
+
+    /// Complete the last word of the input as far as all the candidates agree, returning them.
+    pub fn complete(&mut self) -> anyhow::Result<Vec<String>> {
+        let (_, line) = self.input_line();
+        let (word, is_command) = completion::last_word(&line);
+        let candidates = completion::candidates(&self.working_dir()?, word, is_command)?;
+        let prefix = completion::common_prefix(&candidates);
+        if candidates.len() == 1 && !prefix.ends_with('/') {
+            self.replace_last_word(&format!("{prefix} "));
+        } else if prefix.len() > word.len() {
+            self.replace_last_word(prefix);
+        }
+        Ok(candidates)
+    }
+
+    pub fn replace_last_word(&mut self, with: &str) {
+        let ((row, start), line) = self.input_line();
+        let (word, _) = completion::last_word(&line);
+        let col = start + line.chars().count() - word.chars().count();
+        let end = self.shell_output.buffer.end();
+        self.shell_output.replace(Replace {
+            from: (row, col),
+            until: end,
+            content: TextBuffer::from_string(with),
+        });
+    }
+
+    /// Drop the oldest lines of output until the buffer fits in the given number of bytes, and
+    /// of lines if that's not 0.
+    pub fn trim_output(&mut self, max_bytes: usize, max_lines: usize) {
+        let lines = self.shell_output.buffer.contents.len();
+        let extra_lines = match max_lines {
+            0 => 0,
+            max => lines.saturating_sub(max),
+        };
+        let (size, _) = self.shell_output.memory_size();
+        let mut excess = size.saturating_sub(max_bytes);
+        if excess == 0 && extra_lines == 0 {
+            return;
+        }
+        let mut rows = 0;
+        for line in self.shell_output.buffer.contents.iter() {
+            if excess == 0 {
+                break;
+            }
+            let line_size = line.capacity() * std::mem::size_of::<char>();
+            excess = excess.saturating_sub(line_size.max(1));
+            rows += 1;
+        }
+        self.shell_output.trim_frozen(rows.max(extra_lines));
+    }
+
+    /// Fit the shell to a grid of a different size: both the window it's drawn in, where the
+    /// output rewraps to the new width, and the terminal size that programs see. They're sent
+    /// `SIGWINCH`, and bash updates `LINES` and `COLUMNS` to match.
+    pub fn resize(&mut self, dimensions: Coord) -> io::Result<()> {
+        if self.shell_output.dimensions == dimensions {
+            return Ok(());
+        }
+        let at_end = self.end_on_screen();
+        self.shell_output.resize(dimensions);
+        if at_end {
+            // A smaller grid could leave the prompt just off the bottom.
+            let end = self.shell_output.buffer.end();
+            self.shell_output.scroll_into_view(end);
+        }
+        self.pty.resize(dimensions.0, dimensions.1)
+    }
+
+    /// Whether the shell is running a command: ie. whether bash has any child processes.
+    pub fn has_children(&self) -> bool {
+        let id = self.child.id().to_string();
+        let entries = match fs::read_dir("/proc") {
+            Ok(entries) => entries,
+            Err(_) => return false,
+        };
+        entries.filter_map(|e| e.ok()).any(|entry| {
+            let stat = match fs::read_to_string(entry.path().join("stat")) {
+                Ok(stat) => stat,
+                Err(_) => return false,
+            };
+            // The command name is parenthesized and may contain spaces; the parent pid is the
+            // second field after it.
+            stat.rsplit_once(')')
+                .and_then(|(_, rest)| rest.split_whitespace().nth(1))
+                == Some(id.as_str())
+        })
+    }
+
     pub fn new(
         id: usize,
         atlas: Rc<Atlas>,
@@ -164,6 +672,7 @@ impl ShellTab {
         dimensions: Coord,
         sender: Sender<Msg>,
         working_dir: PathBuf,
+        config: &ShellConfig,
     ) -> io::Result<ShellTab> {
         let rcfile = match BASE_DIRS.find_config_file(BASH_RC_FILE) {
             Some(found) => found,
@@ -177,7 +686,8 @@ impl ShellTab {
         let (lines, columns) = dimensions;
 
         // Launch a bash shell, wiring up everything.
-        let mut child = process::Command::new("/bin/bash")
+        let mut command = process::Command::new("/bin/bash");
+        command
             .args([
                 // Disables readline... we're the ones implementing editing!
                 "--noediting",
@@ -193,17 +703,19 @@ impl ShellTab {
             .current_dir(&working_dir)
             .env("LINES", lines.to_string())
             .env("COLUMNS", columns.to_string())
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
-
-        fn tail<T: Read + Send + 'static>(
-            mut stream: T,
-            id: usize,
-            sender: Sender<Msg>,
-            stderr: bool,
-        ) {
+            .env(
+                "PATH",
+                config.path_var(&env::var("PATH").unwrap_or_default()),
+            )
+            // We only follow the cursor within a line, so full-screen programs should stick to
+            // their line-at-a-time fallbacks unless told otherwise.
+            .env("TERM", &config.term);
+        if !config.lang.is_empty() {
+            command.env("LANG", &config.lang);
+        }
+        let (pty, child) = pty::Pty::spawn(&mut command, dimensions)?;
+
+        fn tail<T: Read + Send + 'static>(mut stream: T, id: usize, sender: Sender<Msg>) {
             thread::spawn(move || {
                 eprintln!("Tailing stream!");
                 let mut buffer = [0; 1024];
@@ -230,7 +742,6 @@ impl ShellTab {
                     sender.send(Msg::Tab {
                         id,
                         msg: TabMsg::ShellInput {
-                            stderr,
                             content: contents.to_string(),
                         },
                     });
@@ -239,24 +750,22 @@ impl ShellTab {
             });
         }
 
-        tail(
-            child.stdout.take().expect("taking child stdout"),
-            id,
-            sender.clone(),
-            false,
-        );
-        tail(
-            child.stderr.take().expect("taking child stderr"),
-            id,
-            sender,
-            true,
-        );
+        // The terminal interleaves stdout and stderr for us, as it would for any other program.
+        tail(pty.reader()?, id, sender);
 
+        let mut shell_output = TextWindow::new(TextBuffer::empty(), atlas, metrics, dimensions);
+        // Output is written for the terminal's width, but wrapping keeps long lines readable,
+        // and lets the output reflow if the grid changes size.
+        shell_output.set_wrap(true);
         let mut tab = ShellTab {
             title: String::new(),
             child,
-            shell_output: TextWindow::new(TextBuffer::empty(), atlas, metrics, dimensions),
+            pty,
+            shell_output,
             history: Default::default(),
+            busy: false,
+            unseen_output: false,
+            terminal: Default::default(),
         };
         tab.set_title();
         Ok(tab)
@@ -268,569 +777,4531 @@ struct TextTab {
     path: Option<PathBuf>,
     text: TextWindow,
     dirty: bool,
+    /// Bumped on every edit, so a save that finishes late can tell if the buffer has moved on.
+    edits: usize,
+    /// The value of `edits` as of the last autosave.
+    autosaved: usize,
+    read_only: bool,
+    /// Opened with � in place of bytes that weren't valid UTF-8, which saving would lose.
+    lossy: bool,
+    /// The built-in help, which can't be edited or saved anywhere.
+    help: bool,
+    /// Set while the buffer has been written to the recovery store and dropped from memory.
+    hibernated: Option<PathBuf>,
+    /// When the file was last modified as of opening or saving it, to spot changes made
+    /// elsewhere.
+    disk_mtime: Option<SystemTime>,
+    /// Set when the file has changed on disk under unsaved edits, until the user picks what to do.
+    external_change: bool,
+    /// A change on disk the user chose to ignore for now, by its modification time.
+    kept_mtime: Option<SystemTime>,
+    /// Saves of the file that the worker hasn't reported back on yet. Until they're done, a
+    /// change on disk is most likely our own.
+    saves_pending: usize,
+    /// For encrypted files, the passphrase to encrypt the buffer with when it's saved.
+    passphrase: Option<String>,
+    /// For the journal, the day being looked at.
+    journal: Option<String>,
+    /// The template profile to recognize this tab's writing with, if it has its own.
+    template_profile: Option<String>,
+    /// For files too big to load whole, the index to read lines from; the buffer then holds
+    /// only the lines starting at `first_line`.
+    large: Option<LargeFile>,
+    first_line: usize,
+    /// How many lines of the large file the buffer was loaded from, and the edit count as of
+    /// then: once the count moves on, the buffer goes back into the file's edits before any
+    /// other lines are loaded.
+    window_lines: usize,
+    window_edits: usize,
+    /// Set while a large file is being indexed in the background, when it's opened or
+    /// reloaded. It stays locked until that's done.
+    indexing: bool,
+    /// Set while following the file like `tail -f`: whatever's added to it is loaded as it's
+    /// written, and the view kept at the end.
+    follow: bool,
+    /// For a followed file, how much of it the buffer holds, so only what's been added since
+    /// needs reading.
+    read_len: Option<u64>,
+    /// Set while saving the file to run it; it runs once the save lands.
+    run_after_save: bool,
 }
 
 impl TextTab {
-    fn save(&mut self) -> io::Result<()> {
-        if let Some(path) = &self.path {
-            let write_result = std::fs::write(path, self.text.buffer.content_string());
-            if write_result.is_ok() {
-                self.dirty = false;
-            }
-            write_result
-        } else {
-            Ok(())
-        }
+    /// How to run the file as a script, if it is one.
+    fn run_command(&self) -> Option<String> {
+        let path = self.path.as_deref()?;
+        let first_line: String = self.text.buffer.contents[0].iter().collect();
+        run::command(path, &first_line, Language::from_path(path))
     }
-}
 
-struct Editor {
-    sender: Sender<Msg>,
-    metrics: Metrics,
-    config: Config,
+    /// Write a paginated copy of the buffer next to the original file, returning its path.
+    fn export(&self, config: &PrintConfig) -> io::Result<PathBuf> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| io::Error::other("no path to export next to"))?;
+        let export_path = path.with_extension("print.txt");
+        let dir = path.parent().unwrap_or_else(|| Path::new("/"));
+        let printed = paginate(
+            &expand_figures(&self.text.buffer.content_string(), dir),
+            &self.title,
+            &today(),
+            config,
+        );
+        fs::write(&export_path, printed)?;
+        Ok(export_path)
+    }
 
-    error_string: String,
+    /// Write a PDF copy of the buffer next to the original file, returning its path and any
+    /// characters the font couldn't show.
+    fn export_pdf(&self, config: &PrintConfig) -> io::Result<(PathBuf, Vec<char>)> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| io::Error::other("no path to export next to"))?;
+        let export_path = path.with_extension("pdf");
+        let dir = path.parent().unwrap_or_else(|| Path::new("/"));
+        let pdf = paginate_pdf(
+            &self.text.buffer.content_string(),
+            dir,
+            &self.title,
+            &today(),
+            config,
+        );
+        fs::write(&export_path, pdf.to_bytes())?;
+        Ok((export_path, pdf.missing()))
+    }
 
-    atlas: Rc<Atlas>,
+    /// Insert the contents of another file at the carat.
+    fn insert_file(&mut self, path: &Path) -> io::Result<()> {
+        let carat = match &self.text.selection {
+            Selection::Single { carat } => carat.coord,
+            _ => return Err(io::Error::other("no carat to insert at")),
+        };
+        let contents = fs::read_to_string(path)?;
+        self.text
+            .replace(Replace::splice(carat, TextBuffer::from_string(&contents)));
+        self.text.selection = Selection::Normal;
+        self.touch();
+        Ok(())
+    }
 
-    // tabs
-    tab: Tab,
+    /// Add the selected text to the end of an existing file, on lines of its own.
+    fn append_selection_to(&self, path: &Path) -> io::Result<()> {
+        let selected = match &self.text.selection {
+            Selection::Range { start, end } => self
+                .text
+                .buffer
+                .copy(start.coord, end.coord)
+                .content_string(),
+            _ => return Err(io::Error::other("nothing selected to append")),
+        };
+        if Cipher::for_path(path).is_some() {
+            return Err(io::Error::other("can't append to an encrypted file"));
+        }
+        let existing = fs::read(path)?;
+        let mut text = String::new();
+        if existing.last().is_some_and(|b| *b != b'\n') {
+            text.push('\n');
+        }
+        text.push_str(&selected);
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        fs::OpenOptions::new()
+            .append(true)
+            .open(path)?
+            .write_all(text.as_bytes())
+    }
 
-    meta: Meta,
-    search_window: TextWindow,
+    /// Write out the variants described by this file, returning the number of files written.
+    fn generate(&self) -> anyhow::Result<usize> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("no path to generate variants next to"))?;
+        let seed = uuid::Uuid::new_v4().as_u128() as u64;
+        let variants = generator::generate(&self.text.buffer.content_string(), &today(), seed)?;
+        let stem = path
+            .file_stem()
+            .map_or(Cow::Borrowed("variant"), |s| s.to_string_lossy());
+        for (i, contents) in variants.iter().enumerate() {
+            let mut name = format!("{stem}-v{}", i + 1);
+            if let Some(ext) = path.extension() {
+                name.push('.');
+                name.push_str(&ext.to_string_lossy());
+            }
+            fs::write(path.with_file_name(name), contents)?;
+        }
+        Ok(variants.len())
+    }
 
-    // template stuff
-    template_path: PathBuf,
-    template_offset: usize,
+    /// Save a drawing as an SVG file alongside this one, and reference it from the text.
+    fn save_sketch(&mut self, ink: &Ink) -> io::Result<()> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| io::Error::other("no path to save the sketch next to"))?;
+        let stem = path
+            .file_stem()
+            .map_or(Cow::Borrowed("sketch"), |s| s.to_string_lossy());
+        let (name, sketch_path) = (1..)
+            .map(|i| {
+                let name = format!("{stem}-sketch-{i}");
+                let sketch_path = path.with_file_name(format!("{name}.svg"));
+                (name, sketch_path)
+            })
+            .find(|(_, p)| !p.exists())
+            .expect("finding an unused sketch name");
+        fs::write(&sketch_path, ink_to_svg(ink))?;
 
-    text_stuff: TextStuff,
+        let row = match &self.text.selection {
+            Selection::Single { carat } => carat.coord.0,
+            _ => self.text.buffer.end().0,
+        };
+        self.text
+            .insert_line_after(row, &format!("![sketch]({name})"));
+        self.text.selection = Selection::Normal;
+        self.touch();
+        Ok(())
+    }
 
-    next_tab_id: usize,
-    tabs: BTreeMap<usize, TabType>,
-}
+    /// Sort the selected lines, or the whole document if there's no selection, as one edit.
+    fn sort_lines(&mut self, options: sort::SortOptions) {
+        let buffer = &self.text.buffer;
+        let last_row = buffer.contents.len() - 1;
+        let (first, last) = match &self.text.selection {
+            Selection::Range { start, end } if end.coord.1 == 0 && end.coord.0 > start.coord.0 => {
+                (start.coord.0, end.coord.0 - 1)
+            }
+            Selection::Range { start, end } => (start.coord.0, end.coord.0.min(last_row)),
+            _ => (0, last_row),
+        };
+        if first > last_row {
+            return;
+        }
+        let from = (first, 0);
+        let until = buffer.clamp((last, usize::MAX));
+        let mut lines: Vec<String> = buffer
+            .contents
+            .range(first..=last)
+            .map(|l| l.iter().collect())
+            .collect();
+        sort::sort_lines(&mut lines, options);
+        self.text.replace(Replace {
+            from,
+            until,
+            content: TextBuffer::from_string(&lines.join("\n")),
+        });
+        self.text.selection = Selection::Normal;
+    }
 
-impl Editor {
-    fn load_templates(&mut self) -> io::Result<()> {
-        let data: TemplateFile = match File::open(&self.template_path) {
-            Ok(file) => serde_json::from_reader(file)?,
-            Err(e) if e.kind() == ErrorKind::NotFound => {
-                // File does not exist, which is expected on first boot.
-                TemplateFile::default()
+    /// Evaluate the selected expression and replace it with the result; or, with just a carat,
+    /// evaluate the carat's line and write the result after an `=`. Returns the result.
+    fn calculate(&mut self) -> Result<String, String> {
+        let buffer = &self.text.buffer;
+        let (from, until, expression) = match &self.text.selection {
+            Selection::Range { start, end } if start.coord.0 == end.coord.0 => {
+                let expression = buffer.copy(start.coord, end.coord).content_string();
+                (start.coord, end.coord, expression)
+            }
+            Selection::Single { carat } => {
+                let row = carat.coord.0;
+                let line: String = buffer.contents[row].iter().collect();
+                // Anything after an existing `=` is a stale result, and gets replaced.
+                let (expression, col) = match line.rfind('=') {
+                    Some(i) => (&line[..i], line[..i].chars().count()),
+                    None => (line.trim_end(), line.trim_end().chars().count()),
+                };
+                (
+                    (row, col),
+                    buffer.clamp((row, usize::MAX)),
+                    expression.to_string(),
+                )
             }
-            Err(e) => return Err(e),
+            _ => return Err("select an expression to evaluate".to_string()),
         };
+        let result = calc::format(calc::evaluate(&expression)?);
+        let content = match &self.text.selection {
+            Selection::Range { .. } => result.clone(),
+            _ if expression.ends_with(' ') => format!("= {result}"),
+            _ => format!(" = {result}"),
+        };
+        self.text.replace(Replace {
+            from,
+            until,
+            content: TextBuffer::from_string(&content),
+        });
+        self.text.selection = Selection::Normal;
+        Ok(result)
+    }
 
-        self.text_stuff
-            .load_from_file(data, &self.metrics, &self.config);
+    /// The line of the file at the top of the screen, counting lines that aren't loaded.
+    fn top_line(&self) -> usize {
+        self.first_line + self.text.origin.0
+    }
 
+    /// For a large file, load the lines starting at `first` in place of the ones in memory,
+    /// keeping the same line at the top of the screen where it's still in range.
+    /// Undo doesn't reach back past loading other lines.
+    fn load_lines(&mut self, first: usize) -> io::Result<()> {
+        self.flush_window();
+        let large = match &self.large {
+            Some(large) => large,
+            None => return Ok(()),
+        };
+        let first = first.min(large.lines().saturating_sub(1));
+        let contents = large.read(first..first + LARGE_FILE_WINDOW)?;
+        let window_lines = large.lines().min(first + LARGE_FILE_WINDOW) - first;
+        let top = self.top_line();
+        self.text.set_buffer(TextBuffer::from_string(&contents));
+        self.text.undos.clear();
+        self.text.redos.clear();
+        self.text.origin.0 = top.saturating_sub(first);
+        self.text.selection = Selection::Normal;
+        self.first_line = first;
+        self.window_lines = window_lines;
+        self.window_edits = self.edits;
         Ok(())
     }
 
-    fn save_templates(&self) -> io::Result<()> {
-        let file_contents = TemplateFile::new(&self.text_stuff, self.metrics.height);
-        // NB: because the bulk of the data is long string content,
-        // we don't pay much extra to prettify this!
-        serde_json::to_writer_pretty(File::create(&self.template_path)?, &file_contents)?;
+    /// For a large file, put any edits to the lines in memory back into the file's edits.
+    fn flush_window(&mut self) {
+        if self.edits == self.window_edits || self.hibernated.is_some() {
+            return;
+        }
+        let lines = self.buffer_lines();
+        if let Some(large) = &mut self.large {
+            let first = self.first_line;
+            let window_lines = lines.len();
+            large.replace_lines(first..first + self.window_lines, lines);
+            self.window_lines = window_lines;
+        }
+        self.window_edits = self.edits;
+    }
+
+    /// Switch to a fresh index of the large file, dropping any edits that weren't saved.
+    fn use_index(&mut self, large: LargeFile) -> io::Result<()> {
+        self.large = Some(large);
+        self.indexing = false;
+        self.window_edits = self.edits;
+        self.dirty = false;
+        self.disk_mtime = self.path.as_deref().and_then(modified_time);
+        self.external_change = false;
+        self.load_lines(self.first_line)?;
+        if self.follow {
+            self.show_end()?;
+        }
         Ok(())
     }
 
-    fn left_margin(&self) -> i32 {
-        let (_, cols) = max_dimensions(&self.metrics);
-        let width = cols as i32 * self.metrics.width;
-        (DISPLAYWIDTH as i32 - width) / 2
+    /// Scroll so the given line of the file is at the top of the screen, loading it first if
+    /// it's part of a large file that isn't in memory.
+    fn show_line(&mut self, line: usize) -> io::Result<()> {
+        let loaded = self.first_line..self.first_line + self.text.buffer.contents.len();
+        if self.large.is_some() && !loaded.contains(&line) {
+            self.load_lines(line.saturating_sub(LARGE_FILE_WINDOW / 2))?;
+        }
+        self.text.origin = (line.saturating_sub(self.first_line), 0);
+        Ok(())
     }
 
-    fn right_margin(&self) -> i32 {
-        self.left_margin()
+    /// Scroll so the last lines of the file fill the screen.
+    fn show_end(&mut self) -> io::Result<()> {
+        let lines = match &self.large {
+            Some(large) => large.lines(),
+            None => self.text.buffer.contents.len(),
+        };
+        self.show_line(lines.saturating_sub(self.text.dimensions.0.saturating_sub(1)))
     }
 
-    pub fn report_error<A, E: Display>(&mut self, result: Result<A, E>) -> Option<A> {
-        match result {
-            Ok(a) => Some(a),
-            Err(e) => {
-                self.error_string = format!("Error: {}", e);
-                None
-            }
+    /// Once the screen gets close to either end of the lines in memory, load the ones around
+    /// it instead.
+    fn follow_scroll(&mut self) -> io::Result<()> {
+        let total = match &self.large {
+            Some(large) => large.lines(),
+            None => return Ok(()),
+        };
+        let margin = self.text.dimensions.0 * 2;
+        let row = self.text.origin.0;
+        let loaded = self.text.buffer.contents.len();
+        let near_start = self.first_line > 0 && row < margin;
+        let near_end = self.first_line + loaded < total && row + margin > loaded;
+        if near_start || near_end {
+            self.load_lines(self.top_line().saturating_sub(LARGE_FILE_WINDOW / 2))?;
         }
+        Ok(())
     }
-}
 
-impl Widget for Editor {
-    type Message = Msg;
+    /// Write the buffer out to the recovery store and free it, keeping only the path and the
+    /// scroll position. The undo history doesn't survive.
+    fn hibernate(&mut self, id: usize) -> io::Result<()> {
+        if self.hibernated.is_some() || self.passphrase.is_some() {
+            return Ok(());
+        }
+        let path = BASE_DIRS.place_data_file(format!("{RECOVERY_DIR}/tab-{id}.txt"))?;
+        fs::write(&path, self.text.buffer.content_string())?;
+        self.text.set_buffer(TextBuffer::empty());
+        self.text.undos = VecDeque::new();
+        self.text.redos = vec![];
+        self.text.selection = Selection::Normal;
+        self.hibernated = Some(path);
+        Ok(())
+    }
 
-    fn size(&self) -> Vector2<i32> {
-        Vector2::new(SCREEN_WIDTH, SCREEN_HEIGHT)
+    fn wake(&mut self) -> io::Result<()> {
+        if let Some(path) = &self.hibernated {
+            let contents = fs::read_to_string(path)?;
+            self.text.set_buffer(TextBuffer::from_string(&contents));
+            let _ = fs::remove_file(path);
+            self.hibernated = None;
+        }
+        Ok(())
     }
 
-    fn render(&self, mut view: View<Msg>) {
-        let mut header = view.split_off(Side::Top, TOP_MARGIN);
-        header.split_off(Side::Left, self.left_margin());
-        header.split_off(Side::Right, self.right_margin());
+    fn buffer_lines(&self) -> Vec<String> {
+        self.text
+            .buffer
+            .contents
+            .iter()
+            .map(|l| l.iter().collect())
+            .collect()
+    }
 
-        match self.tab {
-            Tab::Meta => {
-                let head_text = Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, &*APP_NAME);
-                head_text.render_split(&mut header, Side::Left, 0.5);
-                Spaced(
-                    40,
-                    &[Button::new(
-                        "templates",
-                        Msg::SwitchTab { tab: Tab::Template },
-                        true,
-                    )],
-                )
-                .render_placed(header, 1.0, 0.5);
+    /// Scroll to the day's section of the journal, adding it if it's not there yet.
+    fn journal_day(&mut self, date: &str) -> usize {
+        let lines = self.buffer_lines();
+        let refs: Vec<&str> = lines.iter().map(|l| l.as_str()).collect();
+        let row = match journal::locate(&refs, date) {
+            Ok(row) => row,
+            Err(row) if row < lines.len() => {
+                let heading = format!("{}\n\n", journal::heading(date));
+                self.text
+                    .replace(Replace::splice((row, 0), TextBuffer::from_string(&heading)));
+                self.touch();
+                row
             }
-            Tab::Edit(id) => {
-                match &self.tabs[&id] {
-                    TabType::Text(text_tab) => {
-                        Button::new(&text_tab.title, Msg::SwitchTab { tab: Tab::Meta }, true)
-                            .render_split(&mut header, Side::Left, 0.5);
+            Err(_) => {
+                // Leave a blank line after whatever's already there, if anything.
+                let last = lines.len() - 1;
+                let (from, separator) = if refs.iter().all(|l| l.trim().is_empty()) {
+                    ((0, 0), "")
+                } else if lines[last].is_empty() {
+                    ((last, 0), "\n")
+                } else {
+                    ((last, lines[last].chars().count()), "\n\n")
+                };
+                let heading = format!("{separator}{}", journal::heading(date));
+                self.text.replace(Replace {
+                    from,
+                    until: (last, lines[last].chars().count()),
+                    content: TextBuffer::from_string(&heading),
+                });
+                self.touch();
+                from.0 + separator.len()
+            }
+        };
+        self.text.origin = (row, 0);
+        self.journal = Some(date.to_string());
+        row
+    }
 
-                        let mut buttons = vec![];
-                        if self.config.experimental {
-                            buttons.push(Button::new(
-                                "find",
-                                Msg::SwitchTab {
-                                    tab: Tab::Search {
-                                        id,
-                                        contents: IndexedString::index(
-                                            text_tab.text.buffer.content_string(),
-                                        ),
-                                        results: vec![],
-                                    },
-                                },
-                                true,
-                            ))
-                        }
-                        buttons.extend([
-                            Button::new(
-                                "undo",
-                                Msg::Tab {
-                                    id,
-                                    msg: TabMsg::Undo,
-                                },
-                                !text_tab.text.undos.is_empty(),
-                            ),
-                            Button::new(
-                                "redo",
-                                Msg::Tab {
-                                    id,
-                                    msg: TabMsg::Redo,
-                                },
-                                !text_tab.text.redos.is_empty(),
-                            ),
-                            Button::new(
-                                "save",
-                                Msg::Tab {
-                                    id,
-                                    msg: TabMsg::Save,
-                                },
-                                text_tab.path.is_some() && text_tab.dirty,
-                            ),
-                        ]);
+    /// Add an entry to the end of the day's section, starting with the time.
+    fn journal_entry(&mut self, date: &str, time: &str) {
+        let heading = self.journal_day(date);
+        let lines = self.buffer_lines();
+        let refs: Vec<&str> = lines.iter().map(|l| l.as_str()).collect();
+        let last = journal::section_end(&refs, heading) - 1;
+        let end = (last, lines[last].chars().count());
+        let entry = format!("\n- {time} ");
+        self.text
+            .replace(Replace::splice(end, TextBuffer::from_string(&entry)));
+        self.touch();
+        let rows = self.text.dimensions.0;
+        self.text.origin = ((last + 2).saturating_sub(rows).max(heading), 0);
+    }
 
-                        Spaced(40, &buttons).render_placed(header, 1.0, 0.5);
-                    }
-                    TabType::Shell(s) => {
-                        Button::new(&s.title, Msg::SwitchTab { tab: Tab::Meta }, true)
-                            .render_split(&mut header, Side::Left, 0.5);
+    fn is_markdown(&self) -> bool {
+        self.outline_language() == Some(Language::Markdown)
+    }
 
-                        Spaced(
-                            40,
-                            &[Button::new(
-                                "submit",
-                                Msg::Tab {
-                                    id,
-                                    msg: TabMsg::SubmitShell,
-                                },
-                                true,
-                            )],
-                        )
-                        .render_placed(header, 1.0, 0.5);
-                    }
-                };
-            }
-            Tab::Template => {
-                let head_text = Button::new("templates", Msg::SwitchTab { tab: Tab::Meta }, true);
-                head_text.render_split(&mut header, Side::Left, 0.5);
-                header.leave_rest_blank();
+    /// The language to pick the outline out by, whether or not it's highlighted.
+    fn outline_language(&self) -> Option<Language> {
+        if self.help {
+            Some(Language::Markdown)
+        } else {
+            self.path.as_deref().and_then(Language::from_path)
+        }
+    }
+
+    fn touch(&mut self) {
+        self.dirty = true;
+        self.edits += 1;
+    }
+
+    fn set_path(&mut self, path: PathBuf) {
+        if Cipher::for_path(&path).is_none() {
+            self.passphrase = None;
+        }
+        self.title = path.file_name().map_or("<unnamed file>".to_string(), |p| {
+            p.to_string_lossy().into_owned()
+        });
+        self.path = Some(path);
+    }
+
+    /// Whether the file has been modified by something else since we opened or saved it.
+    fn changed_on_disk(&self) -> bool {
+        if self.saves_pending > 0 {
+            return false;
+        }
+        let now = self.path.as_deref().and_then(modified_time);
+        matches!((self.disk_mtime, now), (Some(seen), Some(now)) if seen != now)
+    }
+
+    /// What's in the file at the path, decrypted with the tab's passphrase if need be.
+    fn read_disk(&self, path: &Path) -> anyhow::Result<String> {
+        Ok(match (&self.passphrase, Cipher::for_path(path)) {
+            (Some(passphrase), Some(cipher)) => crypt::decrypt(cipher, path, passphrase)?,
+            _ => fs::read_to_string(path)?,
+        })
+    }
+
+    /// Replace the buffer with the file as it is on disk now, as one edit that can be undone.
+    fn reload(&mut self) -> anyhow::Result<()> {
+        let path = self
+            .path
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("the tab has no file"))?;
+        if let Some(large) = &mut self.large {
+            // Only a large file that's just been added to gets here; see `Editor::reload_tab`.
+            large.refresh()?;
+            self.load_lines(self.first_line)?;
+            self.disk_mtime = modified_time(&path);
+            self.external_change = false;
+            if self.follow {
+                self.show_end()?;
             }
-            Tab::Search { id, .. } => {
-                header.leave_rest_blank();
+            return Ok(());
+        }
+        if self.follow && !self.dirty && self.read_appended(&path)? {
+            self.disk_mtime = modified_time(&path);
+            self.external_change = false;
+            self.show_end()?;
+            return Ok(());
+        }
+        let contents = self.read_disk(&path)?;
+        self.read_len = Some(contents.len() as u64);
+        let current = self.text.buffer.content_string();
+        let end = self.text.buffer.end();
+        if let Some(added) = contents.strip_prefix(current.as_str()) {
+            // Usually a log that's been added to: keep the undo entry to just the new part.
+            if !added.is_empty() {
+                self.text
+                    .replace(Replace::splice(end, TextBuffer::from_string(added)));
             }
+        } else {
+            self.text.replace(Replace {
+                from: (0, 0),
+                until: end,
+                content: TextBuffer::from_string(&contents),
+            });
+            self.text.selection = Selection::Normal;
+        }
+        self.disk_mtime = modified_time(&path);
+        self.dirty = false;
+        self.external_change = false;
+        if self.follow {
+            self.show_end()?;
         }
+        Ok(())
+    }
 
-        {
-            let mut footer = view.split_off(Side::Bottom, TOP_MARGIN);
-            footer.split_off(Side::Left, self.left_margin());
-            footer.split_off(Side::Right, self.right_margin());
+    /// For a followed file that's only been added to, read just the new part onto the end of
+    /// the buffer. It's not an edit, so it isn't added to the undo history. Returns false if
+    /// the whole file needs reading instead.
+    fn read_appended(&mut self, path: &Path) -> io::Result<bool> {
+        let read_len = match self.read_len {
+            Some(read_len) if self.passphrase.is_none() => read_len,
+            _ => return Ok(false),
+        };
+        let mut file = File::open(path)?;
+        if file.metadata()?.len() < read_len {
+            return Ok(false);
+        }
+        file.seek(SeekFrom::Start(read_len))?;
+        let mut bytes = vec![];
+        file.read_to_end(&mut bytes)?;
+        // A character that's only partly written yet is left for next time.
+        let added = match std::str::from_utf8(&bytes) {
+            Ok(added) => added,
+            Err(e) if e.error_len().is_none() => std::str::from_utf8(&bytes[..e.valid_up_to()])
+                .map_err(|_| io::Error::from(ErrorKind::InvalidData))?,
+            Err(_) => return Ok(false),
+        };
+        if !added.is_empty() {
+            let end = self.text.buffer.end();
+            self.text
+                .do_replace(Replace::splice(end, TextBuffer::from_string(added)));
+        }
+        self.read_len = Some(read_len + added.len() as u64);
+        Ok(true)
+    }
 
-            let mut message = match self.tab {
-                Tab::Meta => "".to_string(),
-                Tab::Template => "".to_string(),
-                Tab::Edit(id) => {
-                    let (row, col) = match &self.tabs[&id] {
-                        TabType::Text(text_tab) => text_tab.text.origin,
-                        TabType::Shell(shell_tab) => shell_tab.shell_output.origin,
-                    };
-                    format!("[{row}:{col}] ")
-                }
-                Tab::Search { .. } => "".to_string(),
-            };
+    /// A view comparing the file at the path with the buffer, keeping the buffer's side of
+    /// every difference until told otherwise.
+    fn merge_with(&self, id: usize, path: PathBuf) -> anyhow::Result<Tab> {
+        let disk = self.read_disk(&path)?;
+        let chunks = merge::diff(&disk, &self.text.buffer.content_string());
+        let differences = chunks
+            .iter()
+            .filter(|c| matches!(c, merge::Chunk::Differ { .. }))
+            .count();
+        Ok(Tab::Merge {
+            id,
+            path,
+            chunks,
+            picks: vec![merge::Pick::Buffer; differences],
+            page: 0,
+        })
+    }
 
-            message.push_str(&self.error_string);
+    /// Where unsaved changes to this file are kept between saves: a hidden file alongside it.
+    fn recovery_path(&self) -> Option<PathBuf> {
+        if self.passphrase.is_some() {
+            // Encrypted files are never written out in the clear.
+            return None;
+        }
+        if self.large.is_some() {
+            // Writing out the whole thing every so often would be too slow.
+            return None;
+        }
+        recovery_file(self.path.as_ref()?)
+    }
 
-            let text = Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, &message);
-            text.render_placed(footer, 0.0, 0.4);
+    /// Queue a copy of the buffer to be written to the recovery file, if it's changed since the
+    /// last one. Returns whether anything needed saving.
+    fn autosave(&mut self, id: usize, saves: &mpsc::Sender<SaveJob>) -> io::Result<bool> {
+        if !self.dirty || self.edits == self.autosaved || self.hibernated.is_some() {
+            return Ok(false);
+        }
+        if let Some(job) = self.save_job(id, true) {
+            saves
+                .send(job)
+                .map_err(|_| io::Error::other("save worker has stopped"))?;
+        } else {
+            // Unnamed buffers have nowhere to go but the session file.
+            self.autosaved = self.edits;
         }
+        Ok(true)
+    }
 
-        for side in [Side::Top, Side::Bottom, Side::Left, Side::Right] {
-            view.handlers()
-                .pad(-100)
-                .on_swipe(side, Msg::Swipe { towards: side });
+    /// A job writing the buffer to its file, or to the recovery file, if it has one.
+    /// Each job for the file itself is counted as pending until its `TabMsg::Saved` comes back.
+    fn save_job(&mut self, id: usize, recovery: bool) -> Option<SaveJob> {
+        let path = if recovery {
+            self.recovery_path()?
+        } else {
+            self.path.clone()?
+        };
+        if !recovery {
+            self.flush_window();
+            self.saves_pending += 1;
         }
+        Some(SaveJob {
+            id,
+            edits: self.edits,
+            path,
+            contents: match &self.large {
+                Some(_) => String::new(),
+                None => self.text.buffer.content_string(),
+            },
+            large: self.large.clone(),
+            recovery,
+            passphrase: if recovery {
+                None
+            } else {
+                self.passphrase.clone()
+            },
+        })
+    }
 
-        match &self.tab {
-            Tab::Meta => {
-                view.split_off(Side::Left, self.left_margin());
+    fn remove_recovery(&self) {
+        if let Some(path) = self.recovery_path() {
+            let _ = fs::remove_file(path);
+        }
+    }
 
-                self.meta
-                    .path_window
-                    .borrow()
-                    .map(|message| match message {
-                        TextMessage::Write(ink) => Msg::Write { ink },
-                        TextMessage::Erase(ink) => Msg::Erase { ink },
-                    })
-                    .render_split(&mut view, Side::Top, 0.0);
+    /// Queue the current contents to be written out by the save worker.
+    /// The dirty flag is cleared when the worker reports back; see `TabMsg::Saved`.
+    fn save(&mut self, id: usize, saves: &mpsc::Sender<SaveJob>) -> io::Result<()> {
+        if let Some(job) = self.save_job(id, false) {
+            saves
+                .send(job)
+                .map_err(|_| io::Error::other("save worker has stopped"))
+        } else {
+            Ok(())
+        }
+    }
+}
 
-                view.split_off(Side::Right, self.right_margin());
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
 
-                let written_path: PathBuf = self.meta.path_window.buffer.content_string().into();
-                let entry_height = DEFAULT_CHAR_HEIGHT * 3 / 2;
+struct SaveJob {
+    id: usize,
+    edits: usize,
+    path: PathBuf,
+    contents: String,
+    /// Autosaves go to the recovery file, and don't count as saving the document.
+    recovery: bool,
+    /// Set for encrypted files, which are encrypted on the way out.
+    passphrase: Option<String>,
+    /// For a large file, the lines to write out in place of `contents`. They're put together
+    /// by the worker, since that means reading the whole file.
+    large: Option<LargeFile>,
+}
 
-                let mut buttons = view.split_off(Side::Top, entry_height);
+/// Spawn a thread that writes files on behalf of the UI, so a large save doesn't stall the pen.
+/// Jobs are handled one at a time in order, so an older save can never clobber a newer one.
+fn spawn_save_worker(sender: Sender<Msg>, verify: bool, backup: bool) -> mpsc::Sender<SaveJob> {
+    let (saves, jobs) = mpsc::channel::<SaveJob>();
+    thread::spawn(move || {
+        for job in jobs {
+            sender.send(run_save(job, verify, backup));
+        }
+    });
+    saves
+}
 
-                let written_dir = if written_path.is_dir() {
-                    written_path.clone()
-                } else {
-                    written_path
-                        .parent()
-                        .map_or(PathBuf::from("/"), |p| p.to_path_buf())
+/// Write out the file for a save job, returning the message that reports how it went. With
+/// `verify`, a save (but not an autosave) is read back to check it; with `backup`, the
+/// previous version is kept alongside.
+fn run_save(mut job: SaveJob, verify: bool, backup: bool) -> Msg {
+    if let Some(large) = job.large.take() {
+        match large.contents() {
+            Ok(contents) => job.contents = contents,
+            Err(e) => {
+                let msg = TabMsg::Saved {
+                    edits: job.edits,
+                    result: Err(format!("reading {}: {e}", large.path().display())),
+                    verified: None,
                 };
+                return Msg::Tab { id: job.id, msg };
+            }
+        }
+    }
+    let encrypted = match (&job.passphrase, Cipher::for_path(&job.path)) {
+        (Some(passphrase), Some(cipher)) => Some((cipher, passphrase)),
+        _ => None,
+    };
+    let backed_up = if backup && !job.recovery {
+        util::backup(&job.path).map_err(|e| format!("backing up: {e}"))
+    } else {
+        Ok(())
+    };
+    let mut result = backed_up.and_then(|()| match encrypted {
+        Some((cipher, passphrase)) => {
+            crypt::encrypt(cipher, &job.path, passphrase, &job.contents).map_err(|e| e.to_string())
+        }
+        None => write_atomic(&job.path, job.contents.as_bytes()).map_err(|e| e.to_string()),
+    });
+    let edits = job.edits;
+    let msg = if job.recovery {
+        TabMsg::Autosaved { edits, result }
+    } else {
+        let mut verified = None;
+        if verify && result.is_ok() {
+            // The ciphertext is different every time, so there's nothing to compare it to.
+            let expected = encrypted.is_none().then_some(job.contents.as_bytes());
+            match verify_save(&job.path, expected) {
+                Ok(summary) => verified = Some(summary),
+                Err(e) => result = Err(e),
+            }
+        }
+        TabMsg::Saved {
+            edits,
+            result,
+            verified,
+        }
+    };
+    Msg::Tab { id: job.id, msg }
+}
 
-                Spaced(
-                    40,
-                    &[
-                        Button::new("new file", Msg::New, !written_path.exists()),
-                        Button::new(
-                            "new shell",
-                            Msg::OpenShell {
-                                working_dir: written_dir,
-                            },
-                            true,
-                        ),
-                    ],
-                )
-                .render_split(&mut buttons, Side::Right, 0.5);
-
-                buttons.leave_rest_blank();
-                view.split_off(Side::Top, entry_height);
-
-                Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, "Tabs:").render_split(
-                    &mut view,
-                    Side::Top,
-                    0.0,
-                );
+/// Read back a file that was just written, and describe it by its size and a short hash. If we
+/// know what it should contain, it's an error for it to contain anything else.
+fn verify_save(path: &Path, expected: Option<&[u8]>) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("reading back: {e}"))?;
+    // A disk that can't take the write often only says so here.
+    file.sync_all().map_err(|e| format!("syncing: {e}"))?;
+    let mut written = vec![];
+    file.read_to_end(&mut written)
+        .map_err(|e| format!("reading back: {e}"))?;
+    if expected.is_some_and(|expected| expected != written.as_slice()) {
+        return Err(format!(
+            "{} doesn't match what was saved; try saving again",
+            path.display()
+        ));
+    }
+    Ok(format!("{} bytes, {}", written.len(), short_hash(&written)))
+}
 
-                for (tab_id, tab) in &self.tabs {
-                    let mut tab_view = view.split_off(Side::Top, entry_height);
-                    tab_view.split_off(Side::Left, 20);
+/// The hidden file alongside a file where its unsaved changes are kept.
+fn recovery_file(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_string_lossy();
+    Some(path.with_file_name(format!(".{name}.sill-recover")))
+}
 
-                    Button::new(
-                        tab.title(),
-                        Msg::SwitchTab {
-                            tab: Tab::Edit(*tab_id),
-                        },
-                        true,
-                    )
-                    .render_split(&mut tab_view, Side::Left, 0.5);
+/// The formatter command configured for the tab's file type, if there is one.
+fn formatter_for<'a>(config: &'a Config, text_tab: &TextTab) -> Option<&'a str> {
+    let extension = text_tab.path.as_deref()?.extension()?.to_str()?;
+    config.formatters.get(extension).map(|f| f.as_str())
+}
 
-                    match tab {
-                        TabType::Text(_) => Spaced(
-                            40,
-                            &[
-                                Button::new(
-                                    "save as",
-                                    Msg::Tab {
-                                        id: *tab_id,
-                                        msg: TabMsg::SaveAs {
-                                            path: written_path.clone(),
-                                        },
-                                    },
-                                    !written_path.exists(),
-                                ),
-                                Button::new(
-                                    "close",
-                                    Msg::Tab {
-                                        id: *tab_id,
-                                        msg: TabMsg::Quit,
-                                    },
-                                    true,
-                                ),
-                            ],
-                        )
-                        .render_split(&mut tab_view, Side::Right, 0.5),
-                        TabType::Shell(_) => Spaced(
-                            40,
-                            &[Button::new(
-                                "close",
-                                Msg::Tab {
-                                    id: *tab_id,
-                                    msg: TabMsg::Quit,
-                                },
-                                true,
-                            )],
-                        )
-                        .render_split(&mut tab_view, Side::Right, 0.5),
-                    };
-                }
+/// Spawn a thread that nudges the editor to autosave every so often.
+fn spawn_autosave_timer(sender: Sender<Msg>, every: Duration) {
+    thread::spawn(move || loop {
+        thread::sleep(every);
+        sender.send(Msg::Autosave);
+    });
+}
 
-                view.split_off(Side::Top, entry_height);
+/// Spawn a thread that nudges the editor to look for due reminders every so often.
+fn spawn_reminder_timer(sender: Sender<Msg>) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(REMINDER_CHECK_SECS));
+        sender.send(Msg::CheckReminders);
+    });
+}
 
-                Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, "Paths:").render_split(
-                    &mut view,
-                    Side::Top,
-                    0.0,
-                );
+/// Spawn a thread that nudges the editor to look for drives coming and going every so often.
+fn spawn_mount_timer(sender: Sender<Msg>) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(MOUNT_CHECK_SECS));
+        sender.send(Msg::CheckMounts);
+    });
+}
 
-                for s in &self.meta.suggested {
-                    if view.size().y < entry_height {
-                        break;
-                    }
-                    let mut suggest_view = view.split_off(Side::Top, entry_height);
-                    suggest_view.split_off(Side::Left, 20);
+/// Spawn a thread that nudges the editor to check followed files for new lines, once. It's
+/// started again each time for as long as anything's being followed.
+fn spawn_follow_timer(sender: Sender<Msg>) {
+    thread::spawn(move || {
+        thread::sleep(Duration::from_secs(FOLLOW_CHECK_SECS));
+        sender.send(Msg::FollowFiles);
+    });
+}
 
-                    let msg = if s.ends_with('/') {
-                        Msg::MetaPath {
-                            current_path: s.clone(),
-                        }
-                    } else {
-                        Msg::Open {
-                            path: PathBuf::from(s),
-                        }
-                    };
+/// Spawn a thread that nudges the editor to save the session, once, a little while after an
+/// edit.
+fn spawn_session_timer(sender: Sender<Msg>) {
+    thread::spawn(move || {
+        thread::sleep(Duration::from_secs(SESSION_SAVE_SECS));
+        sender.send(Msg::SaveSession);
+    });
+}
 
-                    Button::new(s, msg, true).render_split(&mut suggest_view, Side::Left, 0.5);
+/// Check the things that most often stop Sill from working on a device, from the shell it runs
+/// to the files it reads at startup.
+fn diagnostics(template_path: &Path, starter_templates: &str) -> Vec<diagnostics::Check> {
+    let mut checks = vec![diagnostics::Check::new(
+        "shell",
+        diagnostics::executable(Path::new("/bin/bash")),
+        "Shell tabs run /bin/bash; install it from Entware with `opkg install bash`.",
+    )];
+    let dirs = [
+        ("config directory", BASE_DIRS.get_config_home()),
+        ("data directory", BASE_DIRS.get_data_home()),
+    ];
+    for (name, dir) in dirs {
+        checks.push(diagnostics::Check::new(
+            name,
+            diagnostics::writable_dir(&dir),
+            "Sill saves its settings and templates here; check the disk isn't full or read-only.",
+        ));
+    }
+    let config = BASE_DIRS
+        .find_config_file(CONFIG_FILE)
+        .map_or(Ok(()), |path| {
+            let bytes = fs::read(&path).map_err(|e| e.to_string())?;
+            toml::from_slice::<Config>(&bytes)
+                .map(|_| ())
+                .map_err(|e| format!("{}: {e}", path.display()))
+        });
+    checks.push(diagnostics::Check::new(
+        "config file",
+        config,
+        "Fix the line it mentions, or move sill.toml aside to start over with the defaults.",
+    ));
+    checks.push(diagnostics::Check::new(
+        "templates",
+        read_template_file(template_path, starter_templates)
+            .map(|_| ())
+            .map_err(|e| format!("{}: {e}", template_path.display())),
+        "Restore templates.json from a profile export, or move it aside for the starter set.",
+    ));
+    // Characters the UI draws, beyond plain ASCII.
+    let font = panic::catch_unwind(|| "a✓✗…→".chars().all(|c| FONT.glyph(c).id().0 != 0));
+    checks.push(diagnostics::Check::new(
+        "font",
+        match font {
+            Ok(true) => Ok(()),
+            Ok(false) => Err("the built-in font is missing characters".to_string()),
+            Err(_) => Err("the built-in font failed to load".to_string()),
+        },
+        "The build is broken; reinstall Sill.",
+    ));
+    checks.push(diagnostics::Check::new(
+        "framebuffer",
+        match fs::metadata("/dev/fb0") {
+            Err(e) => Err(format!("/dev/fb0: {e}")),
+            Ok(_) => {
+                // The reMarkable 2's display is driven through a shim rather than directly.
+                let machine = fs::read_to_string("/sys/devices/soc0/machine").unwrap_or_default();
+                let preload = env::var("LD_PRELOAD").unwrap_or_default();
+                if machine.contains("reMarkable 2") && !preload.contains("rm2fb") {
+                    Err("running on a reMarkable 2 without rm2fb".to_string())
+                } else {
+                    Ok(())
                 }
             }
-            Tab::Edit(id) => {
-                match &self.tabs[id] {
-                    TabType::Text(text_tab) => {
-                        // Run the line numbers down the margin!
-                        let mut margin_view = view.split_off(Side::Left, self.left_margin());
-                        margin_view.split_off(Side::Right, 20);
-                        // Based on the top margin of the text area and the baseline height.
-                        // TODO: calculate this from other metrics.
-                        margin_view.split_off(Side::Top, 7);
-                        for row in (text_tab.text.origin.0..).take(text_tab.text.dimensions.0) {
-                            let view =
-                                margin_view.split_off(Side::Top, text_tab.text.grid_metrics.height);
-                            let text = Text::literal(
-                                text_tab.text.grid_metrics.height * 3 / 4,
-                                &*FONT,
-                                &format!("{}", row),
-                            );
-                            text.render_placed(view, 1.0, 1.0);
-                        }
-                        margin_view.leave_rest_blank();
+        },
+        "Start Sill from a launcher, which sets up the display; on a reMarkable 2, install rm2fb.",
+    ));
+    checks
+}
 
-                        text_tab
-                            .text
-                            .borrow()
-                            .map(|message| match message {
-                                TextMessage::Write(ink) => Msg::Write { ink },
-                                TextMessage::Erase(ink) => Msg::Erase { ink },
-                            })
-                            .render_split(&mut view, Side::Top, 0.0);
-                    }
-                    TabType::Shell(shell_tab) => {
-                        view.split_off(Side::Left, self.left_margin());
-                        shell_tab
-                            .shell_output
-                            .borrow()
-                            .map(|message| match message {
-                                TextMessage::Write(ink) => Msg::Write { ink },
-                                TextMessage::Erase(ink) => Msg::Erase { ink },
-                            })
-                            .render_split(&mut view, Side::Top, 0.0);
+fn read_template_file(path: &Path, starter: &str) -> io::Result<TemplateFile<'static>> {
+    match File::open(path) {
+        Ok(file) => Ok(serde_json::from_reader(file)?),
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            // File does not exist, which is expected on first boot.
+            starter_templates(starter)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// How to refer to a template profile, given its name.
+fn profile_name(profile: &str) -> &str {
+    if profile.is_empty() {
+        "default"
+    } else {
+        profile
+    }
+}
+
+/// The profile after this one, going through the default and then the configured ones in order.
+fn next_profile(profiles: &[String], current: &str) -> String {
+    let all: Vec<&str> = std::iter::once("")
+        .chain(profiles.iter().map(|p| p.as_str()))
+        .collect();
+    let next = all
+        .iter()
+        .position(|p| *p == current)
+        .map_or(0, |i| (i + 1) % all.len());
+    all[next].to_string()
+}
+
+fn starter_templates(name: &str) -> io::Result<TemplateFile<'static>> {
+    TemplateFile::starter(name).ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "no starter templates called {name:?}; try one of {}",
+                STARTER_TEMPLATES.join(", ")
+            ),
+        )
+    })
+}
+
+/// The recognizers built from the templates file, and the dictionary if there is one.
+type LoadedTemplates = (io::Result<TextStuff>, Option<io::Result<Dictionary>>);
+
+struct Editor {
+    sender: Sender<Msg>,
+    saves: mpsc::Sender<SaveJob>,
+    templates_loading: Option<mpsc::Receiver<LoadedTemplates>>,
+    metrics: Metrics,
+    config: Config,
+
+    error_string: String,
+
+    atlas: Rc<Atlas>,
+
+    // tabs
+    tab: Tab,
+    tab_actions: Option<usize>,
+
+    meta: Meta,
+    search_window: TextWindow,
+    /// Where passphrases are written, drawn masked. Cleared as soon as it's been used.
+    passphrase_window: TextWindow,
+
+    // template stuff
+    template_path: PathBuf,
+    /// The name of the template profile in use, or empty for the usual templates.
+    template_profile: String,
+    template_offset: usize,
+    /// The first column of templates on screen, for characters with more than fit in a row.
+    template_column: usize,
+    /// The template that's been tapped on, by char and index, to move or delete it.
+    template_selected: Option<Coord>,
+
+    text_stuff: TextStuff,
+
+    next_tab_id: usize,
+    tabs: BTreeMap<usize, TabType>,
+    session_path: PathBuf,
+    /// A session from a previous run, until the user chooses to restore or discard it.
+    pending_session: Option<Session>,
+    /// Recent messages, kept for bug reports if `record_secs` is set.
+    recorder: Option<Recorder>,
+    workspace: Option<Workspace>,
+    /// The usual tabs, put aside while a workspace is open and brought back when it's closed.
+    stashed_tabs: BTreeMap<usize, TabType>,
+    /// Set while the follow timer is counting down; see `arm_follow_timer`.
+    follow_timer: bool,
+    /// Set while the session timer is counting down, after an edit.
+    session_timer: bool,
+    /// An action the user's been asked about; see `ask`.
+    confirm: Option<String>,
+    /// The config as loaded from the config file, before any workspace overrides.
+    base_config: Config,
+    /// The file tree, while it's open alongside the current document.
+    tree: Option<FileTree>,
+    /// What the rename tab would do, worked out again as the pattern or selection changes.
+    rename_plan: Result<Vec<(PathBuf, PathBuf)>, String>,
+    /// Set while paging through a document, to show where we've got to.
+    show_page: bool,
+    /// Tab ids, most recently viewed first; used to pick tabs to hibernate.
+    recent_tabs: VecDeque<usize>,
+    /// USB drives and network shares, as of the last check.
+    mounts: Vec<mounts::Mount>,
+    /// `@remind` directives collected from open files.
+    reminders: reminders::Reminders,
+    reminders_path: PathBuf,
+    /// The local time reminders were last checked, so each is announced once, as it comes due.
+    reminders_checked: String,
+    /// Where the labels on files are kept.
+    labels_path: PathBuf,
+}
+
+impl Editor {
+    /// Parse the templates, build the recognizers, and read the dictionary on a background
+    /// thread, so the UI can come up first. The result is picked up when `Msg::TemplatesLoaded` arrives.
+    fn load_templates(&mut self) {
+        let (done, loaded) = mpsc::channel();
+        let template_path = self.template_path.clone();
+        let metrics = self.metrics;
+        let config = self.config.clone();
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let result =
+                read_template_file(&template_path, &config.starter_templates).map(|data| {
+                    let mut text_stuff = TextStuff::new();
+                    text_stuff.load_from_file(data, &metrics, &config);
+                    text_stuff
+                });
+            let dictionary = match config.dictionary.as_str() {
+                "" => None,
+                path => Some(Dictionary::load(&util::home_path(path))),
+            };
+            let _ = done.send((result, dictionary));
+            sender.send(Msg::TemplatesLoaded);
+        });
+        self.templates_loading = Some(loaded);
+    }
+
+    /// Switch to another template profile, saving any changes to the current one first. The new
+    /// templates load in the background, like at startup; a profile that doesn't have a file
+    /// yet starts from the starter set.
+    fn use_template_profile(&mut self, profile: String) {
+        if profile == self.template_profile {
+            return;
+        }
+        let path = match profile.as_str() {
+            "" => BASE_DIRS.place_data_file(TEMPLATE_FILE),
+            name => BASE_DIRS.place_data_file(format!("templates-{name}.json")),
+        };
+        if let Some(path) = self.report_error(path) {
+            self.report_error(self.save_templates());
+            self.template_path = path;
+            self.template_profile = profile;
+            self.load_templates();
+        }
+    }
+
+    fn save_templates(&self) -> io::Result<()> {
+        if self.templates_loading.is_some() {
+            // Nothing to save yet, and we'd clobber the real file with an empty one.
+            return Ok(());
+        }
+        let file_contents = TemplateFile::new(&self.text_stuff, &self.metrics);
+        // NB: because the bulk of the data is long string content,
+        // we don't pay much extra to prettify this!
+        serde_json::to_writer_pretty(File::create(&self.template_path)?, &file_contents)?;
+        Ok(())
+    }
+
+    /// Everything that makes up the user's setup, as opposed to their documents.
+    fn profile_files(&self) -> io::Result<Vec<ProfileFile>> {
+        let file = |name: &str, path: PathBuf| ProfileFile {
+            name: name.to_string(),
+            path,
+        };
+        let mut files = vec![
+            file(
+                "config/sill.toml",
+                BASE_DIRS.place_config_file(CONFIG_FILE)?,
+            ),
+            file(
+                "config/sill.bashrc",
+                BASE_DIRS.place_config_file(BASH_RC_FILE)?,
+            ),
+            file(
+                "data/templates.json",
+                BASE_DIRS.place_data_file(TEMPLATE_FILE)?,
+            ),
+            file("data/session.json", self.session_path.clone()),
+            file("data/labels.json", self.labels_path.clone()),
+            file("data/reminders.json", self.reminders_path.clone()),
+        ];
+        for profile in &self.base_config.template_profiles {
+            let name = format!("templates-{profile}.json");
+            let path = BASE_DIRS.place_data_file(&name)?;
+            files.push(file(&format!("data/{name}"), path));
+        }
+        if !self.base_config.dictionary.is_empty() {
+            let path = util::home_path(&self.base_config.dictionary);
+            files.push(file("data/dictionary.txt", path));
+        }
+        Ok(files)
+    }
+
+    /// The directory written in the path window, or the one containing the file written there.
+    fn export_dir(&self) -> PathBuf {
+        let written_path = PathBuf::from(self.meta.path_window.buffer.content_string());
+        if written_path.is_dir() {
+            written_path
+        } else {
+            written_path
+                .parent()
+                .map_or(PathBuf::from("/"), |p| p.to_path_buf())
+        }
+    }
+
+    fn export_profile(&mut self) -> anyhow::Result<PathBuf> {
+        let dir = self.export_dir();
+        // Make sure the bundle has the latest of everything.
+        self.save_templates()?;
+        if self.pending_session.is_none() {
+            self.session().save(&self.session_path)?;
+        }
+        let bundle = dir.join(format!("sill-profile-{}.tar", today()));
+        profile::export(&self.profile_files()?, &bundle)?;
+        Ok(bundle)
+    }
+
+    /// Where each selected file would be moved to by the pattern in the search window.
+    fn plan_rename(&self) -> anyhow::Result<Vec<(PathBuf, PathBuf)>> {
+        let files: Vec<(PathBuf, String)> = self
+            .meta
+            .selected
+            .iter()
+            .map(|path| {
+                let date = modified_time(path).map_or(String::new(), date_of);
+                (path.clone(), date)
+            })
+            .collect();
+        let pattern = self.search_window.buffer.content_string();
+        let targets = rename::plan(&pattern, &files)?;
+        Ok(files.into_iter().map(|(p, _)| p).zip(targets).collect())
+    }
+
+    /// Rename the selected files, keeping any open tabs, labels and unsaved changes with them.
+    /// Returns how many were renamed; if one fails, the ones before it stay renamed, and are
+    /// recorded as such before the error is returned.
+    fn apply_rename(&mut self) -> anyhow::Result<usize> {
+        let mut count = 0;
+        let mut failure = None;
+        for (from, to) in self.plan_rename()? {
+            if from == to {
+                continue;
+            }
+            if let Err(e) = fs::rename(&from, &to) {
+                failure =
+                    Some(anyhow::Error::new(e).context(format!("renaming {}", from.display())));
+                break;
+            }
+            self.meta.selected.remove(&from);
+            self.meta.labels.moved(&from, &to);
+            for tab in self.tabs.values_mut() {
+                if let TabType::Text(text_tab) = tab {
+                    if text_tab.path.as_ref() == Some(&from) {
+                        text_tab.set_path(to.clone());
                     }
                 }
             }
-            Tab::Template => {
-                let mut margin_view = view.split_off(Side::Left, self.left_margin());
-                let margin_placement = self.metrics.baseline as f32 / self.metrics.height as f32;
-                margin_view.split_off(
-                    Side::Top,
-                    self.metrics.height - self.metrics.baseline + GRID_BORDER,
-                );
-                for ct in self.text_stuff.templates[self.template_offset..]
-                    .iter()
-                    .take(self.max_dimensions().0)
-                {
-                    let mut view = margin_view.split_off(Side::Top, self.metrics.height);
-                    view.split_off(Side::Right, 20);
-                    let text = Text::literal(self.metrics.height, &*FONT, &format!("{}", ct.char));
-                    text.render_placed(view, 1.0, margin_placement);
+            count += 1;
+            // The file's moved either way, so this doesn't undo the rest of the bookkeeping.
+            if let (Some(old), Some(new)) = (recovery_file(&from), recovery_file(&to)) {
+                match fs::rename(old, new) {
+                    Err(e) if e.kind() != ErrorKind::NotFound => {
+                        let context = format!("moving the unsaved changes to {}", to.display());
+                        failure = Some(anyhow::Error::new(e).context(context));
+                        break;
+                    }
+                    _ => {}
                 }
-                margin_view.leave_rest_blank();
+            }
+        }
+        if count > 0 {
+            self.save_session();
+            let saved = self.meta.labels.save(&self.labels_path);
+            if failure.is_none() {
+                saved?;
+            }
+        }
+        match failure {
+            Some(e) => Err(anyhow::anyhow!("renamed {count} files, then stopped {e:#}")),
+            None => Ok(count),
+        }
+    }
 
-                let (height, width) = self.max_dimensions();
-                let height = height.min(self.text_stuff.templates.len() - self.template_offset);
+    /// Write a copy of the templates next to the path in the path window, to share.
+    fn export_templates(&self) -> anyhow::Result<PathBuf> {
+        let path = self
+            .export_dir()
+            .join(format!("sill-templates-{}.json", today()));
+        let file_contents = TemplateFile::new(&self.text_stuff, &self.metrics);
+        serde_json::to_writer_pretty(File::create(&path)?, &file_contents)?;
+        Ok(path)
+    }
 
-                draw_grid(
-                    view,
-                    &self.metrics,
-                    (height, width),
-                    |view| {
-                        view.handlers().pad(8).on_ink(|ink| Msg::Write { ink });
-                    },
-                    |row, col, mut template_view| {
-                        let row = self.template_offset + row;
-                        let maybe_char = self.text_stuff.templates.get(row);
-                        let grid =
-                            self.atlas
-                                .get_cell(GridCell::new(&self.metrics, None, false, true));
-                        if let Some(char_data) = maybe_char {
-                            if let Some(template) = char_data.templates.get(col) {
-                                template_view.annotate(&template.ink);
-                            }
-                        }
-                        template_view.draw(&*grid);
-                    },
-                );
+    /// Merge the templates file in the path window into ours, returning how many were new.
+    fn import_templates(&mut self) -> anyhow::Result<usize> {
+        let path = PathBuf::from(self.meta.path_window.buffer.content_string());
+        let data: TemplateFile = serde_json::from_reader(File::open(&path)?)?;
+        Ok(self.merge_templates(data))
+    }
+
+    fn merge_templates(&mut self, data: TemplateFile) -> usize {
+        // Loading rescales the ink, if the file came from a different font size.
+        let mut imported = TextStuff::new();
+        imported.load_from_file(data, &self.metrics, &self.config);
+        self.text_stuff.merge(imported)
+    }
+
+    /// Write out the flight recorder's log, if it's turned on, returning where it went.
+    fn save_event_log(&self) -> io::Result<Option<PathBuf>> {
+        let recorder = match &self.recorder {
+            Some(recorder) => recorder,
+            None => return Ok(None),
+        };
+        let path = BASE_DIRS.place_data_file(EVENT_LOG)?;
+        recorder.dump(&mut io::BufWriter::new(File::create(&path)?))?;
+        Ok(Some(path))
+    }
+
+    fn left_margin(&self) -> i32 {
+        let (_, cols) = max_dimensions(&self.metrics);
+        let width = cols as i32 * self.metrics.width;
+        (DISPLAYWIDTH as i32 - width) / 2
+    }
+
+    fn right_margin(&self) -> i32 {
+        self.left_margin()
+    }
+
+    pub fn report_error<A, E: Display>(&mut self, result: Result<A, E>) -> Option<A> {
+        match result {
+            Ok(a) => Some(a),
+            Err(e) => {
+                self.error_string = format!("Error: {}", e);
+                let _ = self.save_event_log();
+                None
             }
-            Tab::Search {
-                id,
-                contents,
-                results,
-            } => {
-                // TODO: line numbers!
-                view.split_off(Side::Left, self.left_margin());
+        }
+    }
+}
 
-                self.search_window
-                    .borrow()
-                    .map(|message| match message {
-                        TextMessage::Write(ink) => Msg::Write { ink },
-                        TextMessage::Erase(ink) => Msg::Erase { ink },
-                    })
-                    .render_split(&mut view, Side::Top, 0.0);
+impl Widget for Editor {
+    type Message = Msg;
 
-                for result in results {
-                    let line = contents.line(*result);
-                    let button = Button::new(line, Msg::SearchResult(*id, *result), true);
-                    button.render_split(&mut view, Side::Top, 0.0);
+    fn size(&self) -> Vector2<i32> {
+        Vector2::new(SCREEN_WIDTH, SCREEN_HEIGHT)
+    }
+
+    fn render(&self, view: View<Msg>) {
+        // We can't clean up from here, but we can ask `update` to.
+        if panic::catch_unwind(AssertUnwindSafe(|| self.render_screen(view))).is_err() {
+            self.sender.send(Msg::Crashed {
+                route: self.current_route().to_string(),
+            });
+        }
+    }
+}
+
+impl Editor {
+    fn render_screen(&self, mut view: View<Msg>) {
+        let mut header = view.split_off(Side::Top, TOP_MARGIN);
+        let header_margin = header.split_off(Side::Left, self.left_margin());
+        header.split_off(Side::Right, self.right_margin());
+
+        if let Tab::Edit(_) = self.tab {
+            let label = if self.tree.is_some() { "hide" } else { "files" };
+            Button::new(label, Msg::ToggleTree, true).render_placed(header_margin, 0.5, 0.5);
+        }
+
+        match self.tab {
+            Tab::Meta => {
+                let head_text = Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, &*APP_NAME);
+                head_text.render_split(&mut header, Side::Left, 0.5);
+                let bundle = PathBuf::from(self.meta.path_window.buffer.content_string());
+                let is_tar = bundle.extension().is_some_and(|e| e == "tar") && bundle.is_file();
+                let mut buttons = vec![
+                    Button::new("export profile", Msg::ExportProfile, true),
+                    Button::new("import profile", Msg::ImportProfile, is_tar),
+                    Button::new("templates", Msg::SwitchTab { tab: Tab::Template }, true),
+                    Button::new("journal", Msg::OpenJournal, true),
+                ];
+                if !self.config.template_profiles.is_empty() {
+                    let label = format!("using: {}", profile_name(&self.template_profile));
+                    buttons.insert(3, Button::new(&label, Msg::NextTemplateProfile, true));
+                }
+                if self.recorder.is_some() {
+                    buttons.insert(0, Button::new("save event log", Msg::SaveEventLog, true));
                 }
+                Spaced(40, &buttons).render_placed(header, 1.0, 0.5);
+            }
+            Tab::Edit(id) => {
+                let tab = &self.tabs[&id];
+                let buttons = if self.tab_actions == Some(id) {
+                    self.tab_action_buttons(id, tab)
+                } else if matches!(tab, TabType::Text(t) if t.external_change) {
+                    let tab_msg = |msg: TabMsg| Msg::Tab { id, msg };
+                    vec![
+                        Button::new("reload", tab_msg(TabMsg::Reload), true),
+                        Button::new("keep mine", tab_msg(TabMsg::KeepMine), true),
+                        Button::new(
+                            "merge",
+                            tab_msg(TabMsg::Review),
+                            matches!(tab, TabType::Text(t) if t.large.is_none()),
+                        ),
+                    ]
+                } else {
+                    match tab {
+                        TabType::Text(text_tab) if text_tab.help => vec![
+                            Button::new(
+                                "contents",
+                                Msg::SwitchTab {
+                                    tab: Tab::Contents { id },
+                                },
+                                true,
+                            ),
+                            Button::new(
+                                "preview",
+                                Msg::Tab {
+                                    id,
+                                    msg: TabMsg::Read,
+                                },
+                                true,
+                            ),
+                        ],
+                        TabType::Text(text_tab) => {
+                            let mut buttons = vec![];
+                            if generator::is_source(&text_tab.text.buffer.contents[0]) {
+                                buttons.push(Button::new(
+                                    "generate",
+                                    Msg::Tab {
+                                        id,
+                                        msg: TabMsg::Generate,
+                                    },
+                                    text_tab.path.is_some(),
+                                ));
+                            }
+                            if text_tab.passphrase.is_none() && text_tab.run_command().is_some() {
+                                buttons.push(Button::new(
+                                    "run",
+                                    Msg::Tab {
+                                        id,
+                                        msg: TabMsg::Run,
+                                    },
+                                    true,
+                                ));
+                            }
+                            if self.config.experimental {
+                                buttons.push(Button::new(
+                                    "find",
+                                    Msg::SwitchTab {
+                                        tab: Tab::Search {
+                                            id,
+                                            contents: IndexedString::index(
+                                                text_tab.text.buffer.content_string(),
+                                            ),
+                                            results: vec![],
+                                        },
+                                    },
+                                    true,
+                                ))
+                            }
+                            if text_tab.is_markdown() {
+                                buttons.push(Button::new(
+                                    "preview",
+                                    Msg::Tab {
+                                        id,
+                                        msg: TabMsg::Read,
+                                    },
+                                    true,
+                                ));
+                            }
+                            if !text_tab.read_only
+                                && matches!(text_tab.text.selection, Selection::Single { .. })
+                            {
+                                buttons.push(Button::new(
+                                    "chars",
+                                    Msg::SwitchTab {
+                                        tab: Tab::Palette { id },
+                                    },
+                                    true,
+                                ));
+                            }
+                            buttons.extend([
+                                Button::new(
+                                    "undo",
+                                    Msg::Tab {
+                                        id,
+                                        msg: TabMsg::Undo,
+                                    },
+                                    !text_tab.read_only && !text_tab.text.undos.is_empty(),
+                                ),
+                                Button::new(
+                                    "redo",
+                                    Msg::Tab {
+                                        id,
+                                        msg: TabMsg::Redo,
+                                    },
+                                    !text_tab.read_only && !text_tab.text.redos.is_empty(),
+                                ),
+                                Button::new(
+                                    "save",
+                                    Msg::Tab {
+                                        id,
+                                        msg: TabMsg::Save,
+                                    },
+                                    text_tab.path.is_some() && text_tab.dirty,
+                                ),
+                                Button::new(
+                                    "sketch",
+                                    Msg::SwitchTab {
+                                        tab: Tab::Sketch {
+                                            id,
+                                            ink: Ink::new(),
+                                        },
+                                    },
+                                    text_tab.path.is_some(),
+                                ),
+                                Button::new(
+                                    if text_tab.read_only { "unlock" } else { "lock" },
+                                    Msg::Tab {
+                                        id,
+                                        msg: TabMsg::ReadOnly(!text_tab.read_only),
+                                    },
+                                    text_tab.path.is_some() && !text_tab.indexing,
+                                ),
+                                Button::new(
+                                    "more",
+                                    Msg::SwitchTab {
+                                        tab: Tab::Commands { id },
+                                    },
+                                    true,
+                                ),
+                            ]);
+                            buttons
+                        }
+                        TabType::Shell(_) => {
+                            let mut buttons = vec![];
+                            if let Some(topic) = self.docs_topic(id) {
+                                buttons.push(Button::new("docs", Msg::Docs { topic }, true));
+                            }
+                            buttons.push(Button::new(
+                                "complete",
+                                Msg::Tab {
+                                    id,
+                                    msg: TabMsg::Complete,
+                                },
+                                true,
+                            ));
+                            buttons.push(Button::new(
+                                "submit",
+                                Msg::Tab {
+                                    id,
+                                    msg: TabMsg::SubmitShell,
+                                },
+                                true,
+                            ));
+                            for (label, byte) in [("^C", 0x03), ("^D", 0x04)] {
+                                buttons.push(Button::new(
+                                    label,
+                                    Msg::Tab {
+                                        id,
+                                        msg: TabMsg::Control(byte),
+                                    },
+                                    true,
+                                ));
+                            }
+                            buttons
+                        }
+                    }
+                };
+
+                if self.config.tab_strip {
+                    Spaced(40, &buttons).render_split(&mut header, Side::Right, 0.5);
+                    self.render_tab_strip(id, &mut header);
+                    header.leave_rest_blank();
+                } else {
+                    Button::new(tab.title(), Msg::SwitchTab { tab: Tab::Meta }, true).render_split(
+                        &mut header,
+                        Side::Left,
+                        0.5,
+                    );
+                    Spaced(40, &buttons).render_placed(header, 1.0, 0.5);
+                }
+            }
+            Tab::Practice { .. } => {
+                let back = Button::new("templates", Msg::SwitchTab { tab: Tab::Template }, true);
+                back.render_split(&mut header, Side::Left, 0.5);
+                Button::new("skip", Msg::SkipPractice, true).render_placed(header, 1.0, 0.5);
+            }
+            Tab::Labels { ref path } => {
+                Button::new("paths", Msg::SwitchTab { tab: Tab::Meta }, true).render_split(
+                    &mut header,
+                    Side::Left,
+                    0.5,
+                );
+                let label = self.search_window.buffer.content_string();
+                let label = label.trim();
+                Button::new(
+                    "add label",
+                    Msg::AddLabel(path.clone()),
+                    !label.is_empty() && !self.meta.labels.of(path).contains(&label),
+                )
+                .render_placed(header, 1.0, 0.5);
+            }
+            Tab::Rename => {
+                Button::new("paths", Msg::SwitchTab { tab: Tab::Meta }, true).render_split(
+                    &mut header,
+                    Side::Left,
+                    0.5,
+                );
+                let ready = self.rename_plan.is_ok();
+                Button::new("rename", Msg::ApplyRename, ready).render_placed(header, 1.0, 0.5);
+            }
+            Tab::Workspace { .. } => {
+                let name = self.workspace.as_ref().map_or(String::new(), |w| w.name());
+                Button::new(&name, Msg::SwitchTab { tab: Tab::Meta }, true).render_split(
+                    &mut header,
+                    Side::Left,
+                    0.5,
+                );
+                let files = Tab::Workspace {
+                    found: None,
+                    page: 0,
+                };
+                Spaced(
+                    40,
+                    &[
+                        Button::new("files", Msg::SwitchTab { tab: files }, true),
+                        Button::new("find", Msg::WorkspaceFind, true),
+                        Button::new("tasks", Msg::WorkspaceTasks, true),
+                        Button::new("close", Msg::CloseWorkspace, true),
+                    ],
+                )
+                .render_placed(header, 1.0, 0.5);
+            }
+            Tab::Template => {
+                let head_text = Button::new("templates", Msg::SwitchTab { tab: Tab::Meta }, true);
+                head_text.render_split(&mut header, Side::Left, 0.5);
+                if self.template_column > 0 {
+                    let columns = format!("  from #{}", self.template_column + 1);
+                    Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, &columns).render_split(
+                        &mut header,
+                        Side::Left,
+                        0.5,
+                    );
+                }
+                let buttons = match self.template_selected {
+                    Some(_) => vec![Button::new("delete", Msg::DeleteTemplate, true)],
+                    None => {
+                        // Only offer the import that suits what's at the focus path, to leave
+                        // room for the rest.
+                        let focus = PathBuf::from(self.meta.path_window.buffer.content_string());
+                        let import = match focus.extension().and_then(|e| e.to_str()) {
+                            Some("png") if focus.is_file() => {
+                                Some(Button::new("import sheet", Msg::ImportSheet, true))
+                            }
+                            Some("json") if focus.is_file() => {
+                                Some(Button::new("import templates", Msg::ImportTemplates, true))
+                            }
+                            _ => None,
+                        };
+                        import
+                            .into_iter()
+                            .chain([
+                                Button::new("export templates", Msg::ExportTemplates, true),
+                                Button::new("add starter set", Msg::AddStarterTemplates, true),
+                                Button::new("practice", Msg::Practice, true),
+                            ])
+                            .collect()
+                    }
+                };
+                Spaced(40, &buttons).render_placed(header, 1.0, 0.5);
+            }
+            Tab::Search { id, .. } => {
+                header.leave_rest_blank();
+            }
+            Tab::Commands { id }
+            | Tab::Clips { id }
+            | Tab::Palette { id }
+            | Tab::Alternatives { id, .. }
+            | Tab::Tasks { id }
+            | Tab::Contents { id }
+            | Tab::Completions { id, .. }
+            | Tab::Sort { id, .. }
+            | Tab::Replace { id, .. }
+            | Tab::Goto { id } => {
+                Button::new(
+                    self.tabs[&id].title(),
+                    Msg::SwitchTab { tab: Tab::Edit(id) },
+                    true,
+                )
+                .render_split(&mut header, Side::Left, 0.5);
+                header.leave_rest_blank();
+            }
+            Tab::Reader { id, .. } => {
+                Button::new(
+                    self.tabs[&id].title(),
+                    Msg::SwitchTab { tab: Tab::Edit(id) },
+                    true,
+                )
+                .render_split(&mut header, Side::Left, 0.5);
+                Button::new("edit", Msg::SwitchTab { tab: Tab::Edit(id) }, true)
+                    .render_placed(header, 1.0, 0.5);
+            }
+            Tab::Passphrase { ref path, id } => {
+                let name = path
+                    .file_name()
+                    .map_or(String::new(), |n| n.to_string_lossy().into_owned());
+                Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, &name).render_split(
+                    &mut header,
+                    Side::Left,
+                    0.5,
+                );
+                let back = match id {
+                    Some(id) => Tab::Edit(id),
+                    None => Tab::Meta,
+                };
+                let action = if id.is_some() { "save" } else { "open" };
+                Spaced(
+                    40,
+                    &[
+                        Button::new("cancel", Msg::SwitchTab { tab: back }, true),
+                        Button::new(action, Msg::Unlock, true),
+                    ],
+                )
+                .render_placed(header, 1.0, 0.5);
+            }
+            Tab::Merge { id, .. } => {
+                Button::new(
+                    self.tabs[&id].title(),
+                    Msg::SwitchTab { tab: Tab::Edit(id) },
+                    true,
+                )
+                .render_split(&mut header, Side::Left, 0.5);
+                let pick_all = |pick: merge::Pick| Msg::Tab {
+                    id,
+                    msg: TabMsg::PickAll(pick),
+                };
+                let save = Msg::Tab {
+                    id,
+                    msg: TabMsg::SaveMerged,
+                };
+                Spaced(
+                    40,
+                    &[
+                        Button::new("all disk", pick_all(merge::Pick::Disk), true),
+                        Button::new("all mine", pick_all(merge::Pick::Buffer), true),
+                        Button::new("save", save, true),
+                    ],
+                )
+                .render_placed(header, 1.0, 0.5);
+            }
+            Tab::Sketch { id, ref ink } => {
+                Button::new("sketch", Msg::SwitchTab { tab: Tab::Edit(id) }, true).render_split(
+                    &mut header,
+                    Side::Left,
+                    0.5,
+                );
+                let has_ink = ink.len() > 0;
+                Spaced(
+                    40,
+                    &[
+                        Button::new("clear", Msg::ClearSketch, has_ink),
+                        Button::new("save", Msg::SaveSketch, has_ink),
+                    ],
+                )
+                .render_placed(header, 1.0, 0.5);
+            }
+        }
+
+        {
+            let mut footer = view.split_off(Side::Bottom, TOP_MARGIN);
+            footer.split_off(Side::Left, self.left_margin());
+            footer.split_off(Side::Right, self.right_margin());
+
+            let nav_side = match self.config.nav_panel.as_str() {
+                "left" => Some(Side::Left),
+                "right" => Some(Side::Right),
+                _ => None,
+            };
+            if let (Some(side), Tab::Edit(_)) = (nav_side, &self.tab) {
+                let keys: Vec<_> = NavKey::ALL
+                    .iter()
+                    .map(|key| Button::new(key.label(), Msg::Nav { key: *key }, true))
+                    .collect();
+                Spaced(24, &keys).render_split(&mut footer, side, 0.4);
+                footer.split_off(side, 40);
+            }
+
+            if let Some(mode) = self.selection_mode() {
+                Button::new("cancel", Msg::CancelSelection, true).render_split(
+                    &mut footer,
+                    Side::Right,
+                    0.4,
+                );
+                footer.split_off(Side::Right, 40);
+                Text::builder(DEFAULT_CHAR_HEIGHT, &*FONT)
+                    .weight(0.9)
+                    .literal(&mode)
+                    .into_text()
+                    .render_split(&mut footer, Side::Right, 0.4);
+            }
+
+            let mut message = match self.tab {
+                Tab::Meta => "".to_string(),
+                Tab::Template => "".to_string(),
+                Tab::Edit(id) => {
+                    let window = match &self.tabs[&id] {
+                        TabType::Text(text_tab) => &text_tab.text,
+                        TabType::Shell(shell_tab) => &shell_tab.shell_output,
+                    };
+                    let (mut row, col) = window.origin;
+                    if let TabType::Text(text_tab) = &self.tabs[&id] {
+                        row += text_tab.first_line;
+                    }
+                    let mut position = format!("[{row}:{col}] ");
+                    if self.show_page {
+                        let (page, pages) = window.page_position();
+                        position.push_str(&format!("page {page}/{pages} "));
+                    }
+                    if window.insert {
+                        position.push_str("insert ");
+                    }
+                    if matches!(&self.tabs[&id], TabType::Text(t) if t.read_only && t.path.is_some())
+                    {
+                        position.push_str("read-only ");
+                    }
+                    if matches!(&self.tabs[&id], TabType::Text(t) if t.follow) {
+                        position.push_str("following ");
+                    }
+                    if let TabType::Shell(shell_tab) = &self.tabs[&id] {
+                        if shell_tab.busy {
+                            position.push_str("running ");
+                        } else {
+                            position.push_str("idle ");
+                        }
+                        match shell_tab.terminal.last_status {
+                            Some(0) => position.push_str("last ✓ "),
+                            Some(status) => position.push_str(&format!("last exit {status} ")),
+                            None => {}
+                        }
+                        if shell_tab.unseen_output {
+                            position.push_str("new output below ");
+                        }
+                    }
+                    if let Some(((row, col), None)) = window.bracket() {
+                        let c = window.buffer.contents[row][col];
+                        position.push_str(&format!("unmatched {c} "));
+                    }
+                    position
+                }
+                Tab::Search { .. } => "".to_string(),
+                Tab::Sketch { .. } => "".to_string(),
+                Tab::Commands { .. } => "".to_string(),
+                Tab::Clips { .. } => "".to_string(),
+                Tab::Palette { .. } => "".to_string(),
+                Tab::Alternatives { .. } => "".to_string(),
+                Tab::Passphrase { .. } => "".to_string(),
+                Tab::Tasks { .. } => "".to_string(),
+                Tab::Contents { .. } => "".to_string(),
+                Tab::Rename => "".to_string(),
+                Tab::Labels { .. } => "".to_string(),
+                Tab::Practice { ref practice } => {
+                    let (tries, misses) = practice
+                        .tally
+                        .values()
+                        .fold((0, 0), |(t, m), (tries, misses)| (t + tries, m + misses));
+                    format!("{} of {tries} read right ", tries - misses)
+                }
+                Tab::Workspace { .. } => "".to_string(),
+                Tab::Completions { .. } => "".to_string(),
+                Tab::Sort { .. } => "".to_string(),
+                Tab::Replace { .. } => "".to_string(),
+                Tab::Goto { .. } => "".to_string(),
+                Tab::Reader { ref reader, .. } => {
+                    format!("page {}/{} ", reader.page + 1, reader.pages())
+                }
+                Tab::Merge {
+                    ref path,
+                    ref picks,
+                    ..
+                } => format!("{} differences from {} ", picks.len(), path.display()),
+            };
+
+            if self.templates_loading.is_some() {
+                message.push_str("recognizer warming up... ");
+            }
+            message.push_str(&self.error_string);
+
+            let text = Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, &message);
+            text.render_placed(footer, 0.0, 0.4);
+        }
+
+        for side in [Side::Top, Side::Bottom, Side::Left, Side::Right] {
+            view.handlers()
+                .pad(-100)
+                .on_swipe(side, Msg::Swipe { towards: side });
+        }
+
+        match &self.tab {
+            Tab::Meta => {
+                view.split_off(Side::Left, self.left_margin());
+
+                self.meta
+                    .path_window
+                    .borrow()
+                    .map(|message| match message {
+                        TextMessage::Write(ink) => Msg::Write { ink },
+                        TextMessage::Erase(ink) => Msg::Erase { ink },
+                    })
+                    .render_split(&mut view, Side::Top, 0.0);
+
+                view.split_off(Side::Right, self.right_margin());
+
+                let written_path: PathBuf = self.meta.path_window.buffer.content_string().into();
+                let entry_height = DEFAULT_CHAR_HEIGHT * 3 / 2;
+
+                let mut buttons = view.split_off(Side::Top, entry_height);
+
+                let written_dir = if written_path.is_dir() {
+                    written_path.clone()
+                } else {
+                    written_path
+                        .parent()
+                        .map_or(PathBuf::from("/"), |p| p.to_path_buf())
+                };
+
+                if let Some(workspace) = &self.workspace {
+                    let files = Tab::Workspace {
+                        found: None,
+                        page: 0,
+                    };
+                    Button::new(&workspace.name(), Msg::SwitchTab { tab: files }, true)
+                        .render_split(&mut buttons, Side::Left, 0.5);
+                }
+                Spaced(
+                    40,
+                    &[
+                        Button::new("memory", Msg::MemoryReport, true),
+                        Button::new("diagnostics", Msg::Diagnose, true),
+                        Button::new(
+                            "open folder",
+                            Msg::OpenWorkspace {
+                                root: written_path.clone(),
+                            },
+                            written_path.is_dir(),
+                        ),
+                        Button::new("new file", Msg::New, !written_path.exists()),
+                        Button::new(
+                            "new shell",
+                            Msg::OpenShell {
+                                working_dir: written_dir,
+                            },
+                            true,
+                        ),
+                    ],
+                )
+                .render_split(&mut buttons, Side::Right, 0.5);
+
+                buttons.leave_rest_blank();
+                view.split_off(Side::Top, entry_height);
+
+                if let Some(session) = &self.pending_session {
+                    let mut prompt = view.split_off(Side::Top, entry_height);
+                    Spaced(
+                        40,
+                        &[
+                            Button::new("restore", Msg::RestoreSession, true),
+                            Button::new("discard", Msg::DiscardSession, true),
+                        ],
+                    )
+                    .render_split(&mut prompt, Side::Right, 0.5);
+                    Text::literal(
+                        DEFAULT_CHAR_HEIGHT,
+                        &*FONT,
+                        &format!("Reopen {} tabs from last time?", session.tabs.len()),
+                    )
+                    .render_placed(prompt, 0.0, 0.5);
+                    view.split_off(Side::Top, entry_height);
+                }
+
+                Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, "Tabs:").render_split(
+                    &mut view,
+                    Side::Top,
+                    0.0,
+                );
+
+                for (tab_id, tab) in &self.tabs {
+                    let mut tab_view = view.split_off(Side::Top, entry_height);
+                    tab_view.split_off(Side::Left, 20);
+
+                    Button::new(
+                        tab.title(),
+                        Msg::SwitchTab {
+                            tab: Tab::Edit(*tab_id),
+                        },
+                        true,
+                    )
+                    .render_split(&mut tab_view, Side::Left, 0.5);
+
+                    let badges = tab.badges();
+                    if !badges.is_empty() {
+                        tab_view.split_off(Side::Left, 20);
+                        Text::builder(DEFAULT_CHAR_HEIGHT, &*FONT)
+                            .weight(0.5)
+                            .literal(&format!("({})", badges.join(", ")))
+                            .into_text()
+                            .render_split(&mut tab_view, Side::Left, 0.5);
+                    }
+
+                    match tab {
+                        TabType::Text(text_tab) => Spaced(
+                            40,
+                            &[
+                                Button::new(
+                                    "export",
+                                    Msg::Tab {
+                                        id: *tab_id,
+                                        msg: TabMsg::Export,
+                                    },
+                                    text_tab.path.is_some() && text_tab.passphrase.is_none(),
+                                ),
+                                Button::new(
+                                    "pdf",
+                                    Msg::Tab {
+                                        id: *tab_id,
+                                        msg: TabMsg::ExportPdf,
+                                    },
+                                    text_tab.path.is_some() && text_tab.passphrase.is_none(),
+                                ),
+                                Button::new(
+                                    "insert",
+                                    Msg::Tab {
+                                        id: *tab_id,
+                                        msg: TabMsg::InsertFile {
+                                            path: written_path.clone(),
+                                        },
+                                    },
+                                    written_path.is_file()
+                                        && !text_tab.read_only
+                                        && matches!(
+                                            text_tab.text.selection,
+                                            Selection::Single { .. }
+                                        ),
+                                ),
+                                Button::new(
+                                    "append",
+                                    Msg::Tab {
+                                        id: *tab_id,
+                                        msg: TabMsg::AppendTo {
+                                            path: written_path.clone(),
+                                        },
+                                    },
+                                    written_path.is_file()
+                                        && Cipher::for_path(&written_path).is_none()
+                                        && text_tab.path.as_ref() != Some(&written_path)
+                                        && matches!(
+                                            text_tab.text.selection,
+                                            Selection::Range { .. }
+                                        ),
+                                ),
+                                Button::new(
+                                    "save as",
+                                    Msg::Tab {
+                                        id: *tab_id,
+                                        msg: TabMsg::SaveAs {
+                                            path: written_path.clone(),
+                                        },
+                                    },
+                                    !written_path.is_dir() && !text_tab.help,
+                                ),
+                                Button::new(
+                                    "close",
+                                    Msg::Tab {
+                                        id: *tab_id,
+                                        msg: TabMsg::Quit,
+                                    },
+                                    true,
+                                ),
+                            ],
+                        )
+                        .render_split(&mut tab_view, Side::Right, 0.5),
+                        TabType::Shell(_) => Spaced(
+                            40,
+                            &[Button::new(
+                                "close",
+                                Msg::Tab {
+                                    id: *tab_id,
+                                    msg: TabMsg::Quit,
+                                },
+                                true,
+                            )],
+                        )
+                        .render_split(&mut tab_view, Side::Right, 0.5),
+                    };
+                }
+
+                view.split_off(Side::Top, entry_height);
+
+                if !self.mounts.is_empty() {
+                    let mut drives_row = view.split_off(Side::Top, entry_height);
+                    Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, "Drives:").render_split(
+                        &mut drives_row,
+                        Side::Left,
+                        0.5,
+                    );
+                    drives_row.split_off(Side::Left, 40);
+                    let drives: Vec<_> = self
+                        .mounts
+                        .iter()
+                        .map(|mount| {
+                            let current_path = format!("{}/", mount.dir.display());
+                            Button::new(&mount.name(), Msg::MetaPath { current_path }, true)
+                        })
+                        .collect();
+                    Spaced(40, &drives).render_split(&mut drives_row, Side::Left, 0.5);
+                    drives_row.leave_rest_blank();
+                }
+
+                let (date, time) = local_now();
+                for (index, reminder) in self.reminders.due(&format!("{date} {time}")) {
+                    if view.size().y < entry_height * 2 {
+                        break;
+                    }
+                    let mut reminder_row = view.split_off(Side::Top, entry_height);
+                    let label = format!("{}  {}", reminder.due, reminder.text);
+                    Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, &label).render_split(
+                        &mut reminder_row,
+                        Side::Left,
+                        0.5,
+                    );
+                    let mut buttons =
+                        vec![Button::new("dismiss", Msg::DismissReminder { index }, true)];
+                    let open = Msg::Open {
+                        path: reminder.path.clone(),
+                    };
+                    buttons.push(Button::new("open", open, true));
+                    Spaced(40, &buttons).render_split(&mut reminder_row, Side::Right, 0.5);
+                    reminder_row.leave_rest_blank();
+                }
+
+                let mut paths_row = view.split_off(Side::Top, entry_height);
+                Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, "Paths:").render_split(
+                    &mut paths_row,
+                    Side::Left,
+                    0.5,
+                );
+                // Any label narrows the list down to the files that have it.
+                let filters: Vec<_> = self
+                    .meta
+                    .labels
+                    .all()
+                    .into_iter()
+                    .map(|label| {
+                        if self.meta.label_filter.as_deref() == Some(label) {
+                            Button::new(&format!("[{label}]"), Msg::FilterLabel(None), true)
+                        } else {
+                            let filter = Msg::FilterLabel(Some(label.to_string()));
+                            Button::new(label, filter, true)
+                        }
+                    })
+                    .collect();
+                if !filters.is_empty() {
+                    paths_row.split_off(Side::Left, 40);
+                    Spaced(40, &filters).render_split(&mut paths_row, Side::Left, 0.5);
+                }
+                if !self.meta.selected.is_empty() {
+                    let rename = format!("rename {} files", self.meta.selected.len());
+                    Spaced(
+                        40,
+                        &[
+                            Button::new(&rename, Msg::SwitchTab { tab: Tab::Rename }, true),
+                            Button::new("clear", Msg::ClearSelectedPaths, true),
+                        ],
+                    )
+                    .render_split(&mut paths_row, Side::Right, 0.5);
+                }
+                paths_row.leave_rest_blank();
+
+                for s in &self.meta.suggested {
+                    if view.size().y < entry_height {
+                        break;
+                    }
+                    let mut suggest_view = view.split_off(Side::Top, entry_height);
+                    suggest_view.split_off(Side::Left, 20);
+
+                    let msg = if s.ends_with('/') {
+                        Msg::MetaPath {
+                            current_path: s.clone(),
+                        }
+                    } else {
+                        Msg::Open {
+                            path: PathBuf::from(s),
+                        }
+                    };
+
+                    Button::new(s, msg, true).render_split(&mut suggest_view, Side::Left, 0.5);
+                    if !s.ends_with('/') {
+                        let path = PathBuf::from(s);
+                        let labels = self.meta.labels.of(&path);
+                        if !labels.is_empty() {
+                            suggest_view.split_off(Side::Left, 20);
+                            Text::builder(DEFAULT_CHAR_HEIGHT, &*FONT)
+                                .weight(0.5)
+                                .literal(&format!("({})", labels.join(", ")))
+                                .into_text()
+                                .render_split(&mut suggest_view, Side::Left, 0.5);
+                        }
+                        let label = if self.meta.selected.contains(&path) {
+                            "unselect"
+                        } else {
+                            "select"
+                        };
+                        Spaced(
+                            40,
+                            &[
+                                Button::new(
+                                    "labels",
+                                    Msg::SwitchTab {
+                                        tab: Tab::Labels { path: path.clone() },
+                                    },
+                                    true,
+                                ),
+                                Button::new(label, Msg::SelectPath { path }, true),
+                            ],
+                        )
+                        .render_split(&mut suggest_view, Side::Right, 0.5);
+                    }
+                }
+            }
+            Tab::Edit(id) => {
+                if let Some(tree) = &self.tree {
+                    let width = self.tree_columns() as i32 * self.metrics.width;
+                    self.render_tree(tree, view.split_off(Side::Left, width));
+                }
+                match &self.tabs[id] {
+                    TabType::Text(text_tab) => {
+                        // Run the line numbers down the margin!
+                        let mut margin_view = view.split_off(Side::Left, self.left_margin());
+                        margin_view.split_off(Side::Right, 20);
+                        // Based on the top margin of the text area and the baseline height.
+                        // TODO: calculate this from other metrics.
+                        margin_view.split_off(Side::Top, 7);
+                        // The numbers are drawn as one fragment that only changes when the
+                        // rows showing do, so the margin is left alone while the view stays put.
+                        let labels = text_tab
+                            .text
+                            .screen_rows()
+                            .into_iter()
+                            .map(|(row, col)| {
+                                if text_tab.text.wrap && col > 0 {
+                                    // A wrapped line carrying on from the row above.
+                                    "»".to_string()
+                                } else {
+                                    row.to_string()
+                                }
+                            })
+                            .collect();
+                        let numbers = LineNumbers {
+                            label_height: text_tab.text.grid_metrics.height * 3 / 4,
+                            row_height: text_tab.text.grid_metrics.height,
+                            labels,
+                        };
+                        margin_view.draw(&*self.atlas.get_line_numbers(numbers));
+
+                        text_tab
+                            .text
+                            .borrow()
+                            .map(|message| match message {
+                                TextMessage::Write(ink) => Msg::Write { ink },
+                                TextMessage::Erase(ink) => Msg::Erase { ink },
+                            })
+                            .render_split(&mut view, Side::Top, 0.0);
+                    }
+                    TabType::Shell(shell_tab) => {
+                        view.split_off(Side::Left, self.left_margin());
+                        shell_tab
+                            .shell_output
+                            .borrow()
+                            .map(|message| match message {
+                                TextMessage::Write(ink) => Msg::Write { ink },
+                                TextMessage::Erase(ink) => Msg::Erase { ink },
+                            })
+                            .render_split(&mut view, Side::Top, 0.0);
+                    }
+                }
+            }
+            Tab::Template => {
+                let mut margin_view = view.split_off(Side::Left, self.left_margin());
+                let margin_placement = self.metrics.baseline as f32 / self.metrics.height as f32;
+                margin_view.split_off(
+                    Side::Top,
+                    self.metrics.height - self.metrics.baseline + GRID_BORDER,
+                );
+                for ct in self.text_stuff.templates[self.template_offset..]
+                    .iter()
+                    .take(self.max_dimensions().0)
+                {
+                    let mut view = margin_view.split_off(Side::Top, self.metrics.height);
+                    view.split_off(Side::Right, 20);
+                    let text = Text::literal(self.metrics.height, &*FONT, &format!("{}", ct.char));
+                    text.render_placed(view, 1.0, margin_placement);
+                }
+                margin_view.leave_rest_blank();
+
+                let (height, width) = self.max_dimensions();
+                let height = height.min(self.text_stuff.templates.len() - self.template_offset);
+
+                draw_grid(
+                    view,
+                    &self.metrics,
+                    (height, width),
+                    |view| {
+                        view.handlers().pad(8).on_ink(|ink| Msg::Write { ink });
+                    },
+                    |row, col, mut template_view| {
+                        let row = self.template_offset + row;
+                        let col = self.template_column + col;
+                        let maybe_char = self.text_stuff.templates.get(row);
+                        let selected = self.template_selected == Some((row, col));
+                        let grid =
+                            self.atlas
+                                .get_cell(GridCell::new(&self.metrics, None, selected, true));
+                        if let Some(char_data) = maybe_char {
+                            if let Some(template) = char_data.templates.get(col) {
+                                template_view.annotate(&template.ink);
+                            }
+                        }
+                        template_view.draw(&*grid);
+                    },
+                );
+            }
+            Tab::Search {
+                id,
+                contents,
+                results,
+            } => {
+                // TODO: line numbers!
+                view.split_off(Side::Left, self.left_margin());
+
+                self.search_window
+                    .borrow()
+                    .map(|message| match message {
+                        TextMessage::Write(ink) => Msg::Write { ink },
+                        TextMessage::Erase(ink) => Msg::Erase { ink },
+                    })
+                    .render_split(&mut view, Side::Top, 0.0);
+
+                for result in results {
+                    let line = contents.line(*result);
+                    let button = Button::new(line, Msg::SearchResult(*id, *result), true);
+                    button.render_split(&mut view, Side::Top, 0.0);
+                }
+            }
+            Tab::Goto { id } => {
+                view.split_off(Side::Left, self.left_margin());
+                let lines = match self.tabs.get(id) {
+                    Some(TabType::Text(text_tab)) => match &text_tab.large {
+                        Some(large) => large.lines(),
+                        None => text_tab.text.buffer.contents.len(),
+                    },
+                    _ => 0,
+                };
+                Text::literal(
+                    DEFAULT_CHAR_HEIGHT,
+                    &*FONT,
+                    &format!("Go to line (1 to {lines}):"),
+                )
+                .render_split(&mut view, Side::Top, 0.0);
+
+                self.search_window
+                    .borrow()
+                    .map(|message| match message {
+                        TextMessage::Write(ink) => Msg::Write { ink },
+                        TextMessage::Erase(ink) => Msg::Erase { ink },
+                    })
+                    .render_split(&mut view, Side::Top, 0.0);
+
+                let number = self
+                    .search_window
+                    .buffer
+                    .content_string()
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|n| (1..=lines).contains(n));
+                let label = match number {
+                    Some(n) => format!("go to line {n}"),
+                    None => "go to line".to_string(),
+                };
+                Button::new(
+                    &label,
+                    Msg::SearchResult(*id, number.map_or(0, |n| n - 1)),
+                    number.is_some(),
+                )
+                .render_split(&mut view, Side::Top, 0.0);
+            }
+            Tab::Replace { id, token, .. } => {
+                view.split_off(Side::Left, self.left_margin());
+                let entry_height = DEFAULT_CHAR_HEIGHT * 3 / 2;
+                let count = match self.tabs.get(id) {
+                    Some(TabType::Text(text_tab)) => {
+                        let token: Vec<char> = token.chars().collect();
+                        text_tab.text.buffer.find_all(&token).len()
+                    }
+                    _ => 0,
+                };
+                Text::literal(
+                    DEFAULT_CHAR_HEIGHT,
+                    &*FONT,
+                    &format!("Replace “{token}” ({count} found) with:"),
+                )
+                .render_split(&mut view, Side::Top, 0.0);
+
+                self.search_window
+                    .borrow()
+                    .map(|message| match message {
+                        TextMessage::Write(ink) => Msg::Write { ink },
+                        TextMessage::Erase(ink) => Msg::Erase { ink },
+                    })
+                    .render_split(&mut view, Side::Top, 0.0);
+
+                let mut buttons = view.split_off(Side::Top, entry_height);
+                Spaced(
+                    40,
+                    &[
+                        Button::new("replace next", Msg::ReplaceNext, count > 0),
+                        Button::new("replace all", Msg::ReplaceAll, count > 0),
+                    ],
+                )
+                .render_split(&mut buttons, Side::Left, 0.5);
+            }
+            Tab::Commands { id } => {
+                view.split_off(Side::Left, self.left_margin());
+                let entry_height = DEFAULT_CHAR_HEIGHT * 3 / 2;
+                for button in self.commands(*id) {
+                    let mut entry = view.split_off(Side::Top, entry_height);
+                    button.render_split(&mut entry, Side::Left, 0.5);
+                }
+            }
+            Tab::Sort { id, options } => {
+                view.split_off(Side::Left, self.left_margin());
+                let entry_height = DEFAULT_CHAR_HEIGHT * 3 / 2;
+                let toggle = |label: &str, on: bool, toggled: sort::SortOptions| {
+                    let label = format!("[{}] {label}", if on { "x" } else { " " });
+                    Button::new(
+                        &label,
+                        Msg::SwitchTab {
+                            tab: Tab::Sort {
+                                id: *id,
+                                options: toggled,
+                            },
+                        },
+                        true,
+                    )
+                };
+                let buttons = [
+                    toggle(
+                        "numbers by value",
+                        options.numeric,
+                        sort::SortOptions {
+                            numeric: !options.numeric,
+                            ..*options
+                        },
+                    ),
+                    toggle(
+                        "ignore case",
+                        options.ignore_case,
+                        sort::SortOptions {
+                            ignore_case: !options.ignore_case,
+                            ..*options
+                        },
+                    ),
+                    toggle(
+                        "reverse",
+                        options.reverse,
+                        sort::SortOptions {
+                            reverse: !options.reverse,
+                            ..*options
+                        },
+                    ),
+                    Button::new(
+                        "sort",
+                        Msg::Tab {
+                            id: *id,
+                            msg: TabMsg::Sort(*options),
+                        },
+                        true,
+                    ),
+                ];
+                for button in buttons {
+                    let mut entry = view.split_off(Side::Top, entry_height);
+                    button.render_split(&mut entry, Side::Left, 0.5);
+                }
+            }
+            Tab::Clips { id } => {
+                view.split_off(Side::Left, self.left_margin());
+                view.split_off(Side::Right, self.right_margin());
+                let entry_height = DEFAULT_CHAR_HEIGHT * 3 / 2;
+                let can_paste = matches!(
+                    self.tabs.get(id),
+                    Some(TabType::Text(t)) if !t.read_only && matches!(t.text.selection, Selection::Single { .. })
+                );
+                let mut actions = view.split_off(Side::Top, entry_height);
+                Spaced(
+                    40,
+                    &[
+                        Button::new("paste all", Msg::PasteAllClips { id: *id }, can_paste),
+                        Button::new("clear", Msg::ClearClips, true),
+                    ],
+                )
+                .render_split(&mut actions, Side::Right, 0.5);
+                Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, "Paste all, oldest first:")
+                    .render_placed(actions, 0.0, 0.5);
+                view.split_off(Side::Top, entry_height / 2);
+                // Newest first.
+                for (index, clip) in self.text_stuff.clip_history.iter().enumerate().rev() {
+                    if view.size().y < entry_height {
+                        break;
+                    }
+                    let mut entry = view.split_off(Side::Top, entry_height);
+                    Spaced(
+                        40,
+                        &[
+                            Button::new("use", Msg::UseClip { index }, true),
+                            Button::new("paste", Msg::PasteClip { id: *id, index }, can_paste),
+                        ],
+                    )
+                    .render_split(&mut entry, Side::Right, 0.5);
+                    Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, &clip_preview(clip))
+                        .render_placed(entry, 0.0, 0.5);
+                }
+            }
+            Tab::Palette { id } => {
+                view.split_off(Side::Left, self.left_margin());
+                view.split_off(Side::Right, self.right_margin());
+                let entry_height = DEFAULT_CHAR_HEIGHT * 2;
+                let spacing = DEFAULT_CHAR_HEIGHT;
+                let mut row = view.split_off(Side::Top, entry_height);
+                for snippet in &self.config.palette {
+                    // Show line breaks and tabs, so multi-line snippets aren't a surprise.
+                    let label = snippet.replace('\n', "⏎").replace('\t', "⇥");
+                    let width = text_width(&FONT, DEFAULT_CHAR_HEIGHT, &label).ceil() as i32;
+                    if row.size().x < width {
+                        if view.size().y < entry_height {
+                            break;
+                        }
+                        row = view.split_off(Side::Top, entry_height);
+                    }
+                    let insert = Msg::InsertSnippet {
+                        id: *id,
+                        text: snippet.clone(),
+                    };
+                    Button::new(&label, insert, true).render_split(&mut row, Side::Left, 0.5);
+                    row.split_off(Side::Left, spacing.min(row.size().x));
+                }
+            }
+            Tab::Alternatives {
+                id,
+                coord,
+                candidates,
+            } => {
+                view.split_off(Side::Left, self.left_margin());
+                view.split_off(Side::Right, self.right_margin());
+                let mut row = view.split_off(Side::Top, DEFAULT_CHAR_HEIGHT * 2);
+                for c in candidates {
+                    let pick = Msg::UseAlternative {
+                        id: *id,
+                        coord: *coord,
+                        c: *c,
+                    };
+                    Button::new(&c.to_string(), pick, true).render_split(&mut row, Side::Left, 0.5);
+                    row.split_off(Side::Left, (DEFAULT_CHAR_HEIGHT * 2).min(row.size().x));
+                }
+            }
+            Tab::Tasks { id } => {
+                view.split_off(Side::Left, self.left_margin());
+                view.split_off(Side::Right, self.right_margin());
+                let entry_height = DEFAULT_CHAR_HEIGHT * 3 / 2;
+                let text_tab = match self.tabs.get(id) {
+                    Some(TabType::Text(text_tab)) => text_tab,
+                    _ => return,
+                };
+                let lines: Vec<String> = text_tab
+                    .text
+                    .buffer
+                    .contents
+                    .iter()
+                    .map(|l| l.iter().collect())
+                    .collect();
+                let tasks = tasks::scan(lines.iter().map(|l| l.as_str()));
+                if tasks.is_empty() {
+                    Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, "Nothing left to do!")
+                        .render_placed(view.split_off(Side::Top, entry_height), 0.0, 0.5);
+                }
+                for task in tasks {
+                    if view.size().y < entry_height {
+                        break;
+                    }
+                    let mut entry = view.split_off(Side::Top, entry_height);
+                    if let Some(checked) = task.checked {
+                        let label = if checked { "uncheck" } else { "check" };
+                        let toggle = Msg::Tab {
+                            id: *id,
+                            msg: TabMsg::ToggleTask { row: task.row },
+                        };
+                        Button::new(label, toggle, !text_tab.read_only).render_split(
+                            &mut entry,
+                            Side::Right,
+                            0.5,
+                        );
+                    }
+                    let line = format!("{}: {}", task.row + 1, lines[task.row].trim());
+                    Button::new(&line, Msg::SearchResult(*id, task.row), true).render_split(
+                        &mut entry,
+                        Side::Left,
+                        0.5,
+                    );
+                }
+            }
+            Tab::Practice { practice } => {
+                view.split_off(Side::Left, self.left_margin());
+                let entry_height = DEFAULT_CHAR_HEIGHT * 3 / 2;
+                let prompt = format!("Write: {}", practice.prompt);
+                Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, &prompt).render_placed(
+                    view.split_off(Side::Top, entry_height),
+                    0.0,
+                    0.5,
+                );
+                let (_, columns) = self.max_dimensions();
+                draw_grid(
+                    view.split_off(Side::Top, self.metrics.height * 3 / 2),
+                    &self.metrics,
+                    (1, columns),
+                    |view| {
+                        view.handlers().pad(8).on_ink(|ink| Msg::Write { ink });
+                    },
+                    |_, _, cell_view| {
+                        let grid =
+                            self.atlas
+                                .get_cell(GridCell::new(&self.metrics, None, false, true));
+                        cell_view.draw(&*grid);
+                    },
+                );
+                view.split_off(Side::Right, self.right_margin());
+                let mut lines = vec![];
+                if let Some((prompt, matches)) = &practice.last {
+                    let guesses: Vec<String> = matches
+                        .iter()
+                        .map(|(c, score)| format!("{c} ({score:.2})"))
+                        .collect();
+                    let verdict = match matches.first() {
+                        Some((c, _)) if c == prompt => "right",
+                        _ => "wrong",
+                    };
+                    lines.push(format!(
+                        "{prompt}: {verdict}. Read as {}",
+                        guesses.join(", ")
+                    ));
+                }
+                let weakest = practice.weakest();
+                if !weakest.is_empty() {
+                    lines.push("Misread so far:".to_string());
+                }
+                for (c, tries, misses) in weakest {
+                    lines.push(format!("  {c}: {misses} of {tries}"));
+                }
+                for line in lines {
+                    if view.size().y < entry_height {
+                        break;
+                    }
+                    Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, &line).render_placed(
+                        view.split_off(Side::Top, entry_height),
+                        0.0,
+                        0.5,
+                    );
+                }
+            }
+            Tab::Labels { path } => {
+                view.split_off(Side::Left, self.left_margin());
+                let name = path
+                    .file_name()
+                    .map_or(String::new(), |n| n.to_string_lossy().into_owned());
+                Text::literal(
+                    DEFAULT_CHAR_HEIGHT,
+                    &*FONT,
+                    &format!("Labels for {name}, or write a new one:"),
+                )
+                .render_split(&mut view, Side::Top, 0.0);
+                self.search_window
+                    .borrow()
+                    .map(|message| match message {
+                        TextMessage::Write(ink) => Msg::Write { ink },
+                        TextMessage::Erase(ink) => Msg::Erase { ink },
+                    })
+                    .render_split(&mut view, Side::Top, 0.0);
+                view.split_off(Side::Right, self.right_margin());
+                let entry_height = DEFAULT_CHAR_HEIGHT * 3 / 2;
+                let on = self.meta.labels.of(path);
+                for label in self.meta.labels.all() {
+                    if view.size().y < entry_height {
+                        break;
+                    }
+                    let mut label_row = view.split_off(Side::Top, entry_height);
+                    Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, label).render_split(
+                        &mut label_row,
+                        Side::Left,
+                        0.5,
+                    );
+                    let action = if on.contains(&label) { "remove" } else { "add" };
+                    Button::new(
+                        action,
+                        Msg::ToggleLabel(path.clone(), label.to_string()),
+                        true,
+                    )
+                    .render_split(&mut label_row, Side::Right, 0.5);
+                    label_row.leave_rest_blank();
+                }
+            }
+            Tab::Rename => {
+                view.split_off(Side::Left, self.left_margin());
+                self.search_window
+                    .borrow()
+                    .map(|message| match message {
+                        TextMessage::Write(ink) => Msg::Write { ink },
+                        TextMessage::Erase(ink) => Msg::Erase { ink },
+                    })
+                    .render_split(&mut view, Side::Top, 0.0);
+                view.split_off(Side::Right, self.right_margin());
+                let entry_height = DEFAULT_CHAR_HEIGHT * 3 / 2;
+                let lines = match &self.rename_plan {
+                    Ok(moves) => moves
+                        .iter()
+                        .map(|(from, to)| {
+                            let name = |p: &Path| {
+                                p.file_name()
+                                    .map_or(String::new(), |n| n.to_string_lossy().into_owned())
+                            };
+                            format!("{}  →  {}", name(from), name(to))
+                        })
+                        .collect(),
+                    Err(e) => vec![format!("Can't rename: {e}")],
+                };
+                for line in lines {
+                    if view.size().y < entry_height {
+                        break;
+                    }
+                    Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, &line).render_placed(
+                        view.split_off(Side::Top, entry_height),
+                        0.0,
+                        0.5,
+                    );
+                }
+            }
+            Tab::Workspace { found, page } => {
+                view.split_off(Side::Left, self.left_margin());
+                self.search_window
+                    .borrow()
+                    .map(|message| match message {
+                        TextMessage::Write(ink) => Msg::Write { ink },
+                        TextMessage::Erase(ink) => Msg::Erase { ink },
+                    })
+                    .render_split(&mut view, Side::Top, 0.0);
+                view.split_off(Side::Right, self.right_margin());
+                let workspace = match &self.workspace {
+                    Some(workspace) => workspace,
+                    None => return,
+                };
+                let entry_height = DEFAULT_CHAR_HEIGHT * 3 / 2;
+                let entries: Vec<(String, Msg)> = match found {
+                    None => workspace
+                        .files
+                        .iter()
+                        .map(|path| {
+                            let open = Msg::Open {
+                                path: workspace.root.join(path),
+                            };
+                            (path.display().to_string(), open)
+                        })
+                        .collect(),
+                    Some((_, matches)) => matches
+                        .iter()
+                        .map(|m| {
+                            let label = format!("{}:{}: {}", m.path.display(), m.row + 1, m.line);
+                            let open = Msg::OpenAt {
+                                path: workspace.root.join(&m.path),
+                                row: m.row,
+                            };
+                            (label, open)
+                        })
+                        .collect(),
+                };
+                let summary = match found {
+                    None => format!("{} files", entries.len()),
+                    Some((what, _)) => format!("{} {what}", entries.len()),
+                };
+                Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, &summary).render_placed(
+                    view.split_off(Side::Top, entry_height),
+                    0.0,
+                    0.5,
+                );
+                let per_page = (view.size().y / entry_height).max(1) as usize;
+                let start = (page * per_page).min(entries.len().saturating_sub(1));
+                for (label, msg) in entries.into_iter().skip(start).take(per_page) {
+                    let entry = view.split_off(Side::Top, entry_height);
+                    Button::new(&label, msg, true).render_placed(entry, 0.0, 0.5);
+                }
+            }
+            Tab::Passphrase { id, .. } => {
+                view.split_off(Side::Left, self.left_margin());
+                self.passphrase_window
+                    .borrow()
+                    .map(|message| match message {
+                        TextMessage::Write(ink) => Msg::Write { ink },
+                        TextMessage::Erase(ink) => Msg::Erase { ink },
+                    })
+                    .render_split(&mut view, Side::Top, 0.0);
+                let prompt = match id {
+                    Some(_) => "Write a passphrase to encrypt the file with.",
+                    None => "Write the passphrase the file was encrypted with.",
+                };
+                Text::literal(DEFAULT_CHAR_HEIGHT, &*FONT, prompt).render_placed(
+                    view.split_off(Side::Top, DEFAULT_CHAR_HEIGHT * 3 / 2),
+                    0.0,
+                    0.5,
+                );
+            }
+            Tab::Merge {
+                id,
+                chunks,
+                picks,
+                page,
+                ..
+            } => {
+                view.split_off(Side::Left, self.left_margin());
+                view.split_off(Side::Right, self.right_margin());
+                let entry_height = DEFAULT_CHAR_HEIGHT * 3 / 2;
+                // Each difference gets a row of choices, then the lines from either side; the
+                // side that won't be kept is dimmed.
+                let mut rows: Vec<(Option<usize>, String, bool)> = vec![];
+                let mut row = 0;
+                let mut index = 0;
+                for chunk in chunks {
+                    match chunk {
+                        merge::Chunk::Same(lines) => row += lines.len(),
+                        merge::Chunk::Differ { disk, buffer } => {
+                            let pick = picks[index];
+                            rows.push((Some(index), format!("line {}", row + 1), true));
+                            let keep_disk = pick != merge::Pick::Buffer;
+                            let keep_buffer = pick != merge::Pick::Disk;
+                            for line in disk {
+                                rows.push((None, format!("disk│ {line}"), keep_disk));
+                            }
+                            for line in buffer {
+                                rows.push((None, format!("mine│ {line}"), keep_buffer));
+                            }
+                            row += buffer.len();
+                            index += 1;
+                        }
+                    }
+                }
+                let per_page = (view.size().y / entry_height).max(1) as usize;
+                let start = (page * per_page).min(rows.len().saturating_sub(1));
+                for (index, label, kept) in rows.into_iter().skip(start).take(per_page) {
+                    let mut entry = view.split_off(Side::Top, entry_height);
+                    if let Some(index) = index {
+                        let choice = |label: &str, pick: merge::Pick| {
+                            let on = picks[index] == pick;
+                            Button::new(
+                                &format!("[{}] {label}", if on { "x" } else { " " }),
+                                Msg::Tab {
+                                    id: *id,
+                                    msg: TabMsg::Pick { index, pick },
+                                },
+                                true,
+                            )
+                        };
+                        Spaced(
+                            40,
+                            &[
+                                choice("disk", merge::Pick::Disk),
+                                choice("mine", merge::Pick::Buffer),
+                                choice("both", merge::Pick::Both),
+                            ],
+                        )
+                        .render_split(&mut entry, Side::Right, 0.5);
+                    }
+                    Text::builder(DEFAULT_CHAR_HEIGHT, &*FONT)
+                        .weight(if kept { 0.9 } else { 0.4 })
+                        .literal(&label)
+                        .into_text()
+                        .render_placed(entry, 0.0, 0.5);
+                }
+            }
+            Tab::Contents { id } => {
+                view.split_off(Side::Left, self.left_margin());
+                view.split_off(Side::Right, self.right_margin());
+                let entry_height = DEFAULT_CHAR_HEIGHT * 3 / 2;
+                let (text_tab, language) = match self.tabs.get(id) {
+                    Some(TabType::Text(text_tab)) => match text_tab.outline_language() {
+                        Some(language) => (text_tab, language),
+                        None => return,
+                    },
+                    _ => return,
+                };
+                let lines: Vec<String> = text_tab
+                    .text
+                    .buffer
+                    .contents
+                    .iter()
+                    .map(|l| l.iter().collect())
+                    .collect();
+                let lines: Vec<&str> = lines.iter().map(|l| l.as_str()).collect();
+                for heading in outline::outline(language, &lines) {
+                    if view.size().y < entry_height {
+                        break;
+                    }
+                    let mut entry = view.split_off(Side::Top, entry_height);
+                    entry.split_off(Side::Left, (heading.level as i32 - 1) * DEFAULT_CHAR_HEIGHT);
+                    Button::new(&heading.title, Msg::SearchResult(*id, heading.row), true)
+                        .render_placed(entry, 0.0, 0.5);
+                }
+            }
+            Tab::Completions { id, candidates } => {
+                view.split_off(Side::Left, self.left_margin());
+                view.split_off(Side::Right, self.right_margin());
+                let entry_height = DEFAULT_CHAR_HEIGHT * 3 / 2;
+                for candidate in candidates {
+                    if view.size().y < entry_height {
+                        break;
+                    }
+                    let entry = view.split_off(Side::Top, entry_height);
+                    let msg = Msg::Tab {
+                        id: *id,
+                        msg: TabMsg::UseCompletion(candidate.clone()),
+                    };
+                    Button::new(candidate, msg, true).render_placed(entry, 0.0, 0.5);
+                }
+            }
+            Tab::Reader { id, reader } => {
+                view.split_off(Side::Left, self.left_margin());
+                let mut emphasis = markdown::Emphasis::default();
+                for line in reader.page_lines() {
+                    let mut line_view = view.split_off(Side::Top, reader::READER_LINE_HEIGHT);
+                    line_view.handlers().on_tap(Msg::ReaderJump {
+                        id: *id,
+                        row: line.source_row,
+                    });
+                    if line.text.is_empty()
+                        || !matches!(line.style, LineStyle::Body | LineStyle::Quote)
+                    {
+                        emphasis = markdown::Emphasis::default();
+                    }
+                    render_reader_line(line, reader.markdown, &mut emphasis, line_view);
+                }
+            }
+            Tab::Sketch { ink, .. } => {
+                view.split_off(Side::Left, self.left_margin());
+                view.split_off(Side::Right, self.right_margin());
+                view.handlers().on_ink(|ink| Msg::Write { ink });
+                view.annotate(ink);
+                view.leave_rest_blank();
+            }
+        }
+    }
+}
+
+const NUM_SUGGESTIONS: usize = 32;
+const MAX_DIR_ENTRIES: usize = 1024;
+
+const TAB_STRIP_CHARS: usize = 8;
+const CLIP_PREVIEW_CHARS: usize = 24;
+const TAB_STRIP_SPACING: i32 = 30;
+
+/// The run of tabs to show in a strip `available` wide: the current one, then as many of its
+/// neighbours as fit, leaving room for a `marker` on each side that has tabs hidden.
+fn tab_window(
+    widths: &[i32],
+    current: usize,
+    available: i32,
+    marker: i32,
+) -> std::ops::Range<usize> {
+    let used = |window: &std::ops::Range<usize>| {
+        let hidden = (window.start > 0) as i32 + (window.end < widths.len()) as i32;
+        widths[window.clone()].iter().sum::<i32>() + hidden * marker
+    };
+    let mut window = current..current + 1;
+    loop {
+        let mut grown = false;
+        if window.end < widths.len() && used(&(window.start..window.end + 1)) <= available {
+            window.end += 1;
+            grown = true;
+        }
+        if window.start > 0 && used(&(window.start - 1..window.end)) <= available {
+            window.start -= 1;
+            grown = true;
+        }
+        if !grown {
+            return window;
+        }
+    }
+}
+
+/// Shorten a title to at most `max_chars` characters, marking any truncation.
+fn abbreviate(title: &str, max_chars: usize) -> String {
+    if title.chars().count() <= max_chars {
+        title.to_string()
+    } else {
+        let mut short: String = title.chars().take(max_chars - 1).collect();
+        short.push('…');
+        short
+    }
+}
+
+/// Draw a line of the reader view, with markdown's inline formatting if it's turned on.
+fn render_reader_line(
+    line: &ReaderLine,
+    markdown: bool,
+    emphasis: &mut markdown::Emphasis,
+    mut view: View<Msg>,
+) {
+    let height = (reader::READER_TEXT_HEIGHT as f32 * line.style.scale()) as i32;
+    match line.style {
+        LineStyle::Code => Text::literal(height, &*FONT, &line.text).render_placed(view, 0.0, 0.5),
+        LineStyle::Heading(_) => Text::builder(height, &*READER_FONT)
+            .weight(1.0)
+            .literal(&line.text)
+            .into_text()
+            .render_placed(view, 0.0, 0.5),
+        LineStyle::Body | LineStyle::Quote if markdown => {
+            let weight = if line.style == LineStyle::Quote {
+                view.split_off(Side::Left, reader::READER_TEXT_HEIGHT);
+                TEXT_WEIGHT * 0.7
+            } else {
+                TEXT_WEIGHT
+            };
+            for (style, text) in markdown::inline_spans(&line.text, emphasis) {
+                let (font, weight) = if style.code {
+                    (&*FONT, weight)
+                } else if style.strong {
+                    (&*READER_FONT, 1.0)
+                } else if style.em {
+                    (&*READER_FONT, weight * 0.7)
+                } else {
+                    (&*READER_FONT, weight)
+                };
+                Text::builder(height, font)
+                    .weight(weight)
+                    .literal(&text)
+                    .into_text()
+                    .render_split(&mut view, Side::Left, 0.5);
+            }
+            view.leave_rest_blank();
+        }
+        _ => Text::literal(height, &*READER_FONT, &line.text).render_placed(view, 0.0, 0.5),
+    }
+}
+
+/// A one-line summary of a clip, for the clipboard history.
+fn clip_preview(clip: &Clip) -> String {
+    let lines = &clip.text.contents;
+    let first = lines
+        .iter()
+        .find(|l| !l.is_empty())
+        .map_or(&lines[0], |l| l);
+    let first: String = first.iter().collect();
+    let mut preview = abbreviate(first.trim(), CLIP_PREVIEW_CHARS);
+    if lines.len() > 1 {
+        preview.push_str(&format!(" (+{} lines)", lines.len() - 1));
+    }
+    if clip.line_wise {
+        preview.push_str(" [lines]");
+    }
+    if clip.block {
+        preview.push_str(" [block]");
+    }
+    preview
+}
+
+fn full_path(path: &Path) -> Option<String> {
+    let mut string = path.to_str()?.to_string();
+    if path.is_dir() {
+        string.push('/');
+    }
+    Some(string)
+}
+
+fn suggestions(current_path: &str) -> io::Result<Vec<String>> {
+    if !current_path.starts_with('/') {
+        // All paths must be absolute.
+        return Ok(vec![]);
+    }
+    let (dir, file) = current_path.rsplit_once('/').expect("splitting /path by /");
+    let dir = if dir.is_empty() { "/" } else { dir };
+    let read = fs::read_dir(dir)?;
+    let mut results: Vec<_> = read
+        .filter_map(|r| r.ok())
+        .filter(|de| {
+            de.file_name()
+                .to_str()
+                .into_iter()
+                .any(|s| s.starts_with(file))
+        })
+        .take(MAX_DIR_ENTRIES)
+        .filter_map(|s| full_path(&s.path()))
+        .collect();
+
+    // NB: if this is slow, pull in the partial sort crate.
+    results.sort();
+    results.truncate(NUM_SUGGESTIONS);
+
+    Ok(results)
+}
+
+fn max_dimensions(metrics: &Metrics) -> Coord {
+    let rows = (SCREEN_HEIGHT - TOP_MARGIN * 2 - GRID_BORDER * 2) / metrics.height;
+    let cols = (SCREEN_WIDTH - LEFT_MARGIN * 2 - GRID_BORDER * 2) / metrics.width;
+    (rows as usize, cols as usize)
+}
+
+impl Editor {
+    fn max_dimensions(&self) -> Coord {
+        max_dimensions(&self.metrics)
+    }
+
+    /// How many columns the file tree takes from the document while it's open.
+    fn tree_columns(&self) -> usize {
+        match self.tree {
+            Some(_) => self.max_dimensions().1 * 2 / 5,
+            None => 0,
+        }
+    }
+
+    /// Draw the file tree in the left margin and the columns it's taken from the document.
+    fn render_tree(&self, tree: &FileTree, mut panel: View<Msg>) {
+        let entry_height = DEFAULT_CHAR_HEIGHT * 3 / 2;
+        let indent = DEFAULT_CHAR_HEIGHT;
+        panel.split_off(Side::Left, GRID_BORDER);
+
+        let root = tree.root.display().to_string();
+        Button::new(
+            &root,
+            Msg::MetaPath {
+                current_path: root.clone(),
+            },
+            true,
+        )
+        .render_placed(panel.split_off(Side::Top, entry_height), 0.0, 0.5);
+
+        let rows = tree.rows();
+        let mut pager = panel.split_off(Side::Bottom, entry_height);
+        let per_page = (panel.size().y / entry_height).max(1) as usize;
+        let pages = rows.len().div_ceil(per_page).max(1);
+        let page = tree.page.min(pages - 1);
+        if pages > 1 {
+            Button::new(
+                "▲",
+                Msg::TreePage {
+                    page: page.saturating_sub(1),
+                },
+                page > 0,
+            )
+            .render_split(&mut pager, Side::Left, 0.5);
+            Text::literal(
+                DEFAULT_CHAR_HEIGHT,
+                &*FONT,
+                &format!(" {}/{pages} ", page + 1),
+            )
+            .render_split(&mut pager, Side::Left, 0.5);
+            Button::new("▼", Msg::TreePage { page: page + 1 }, page + 1 < pages).render_split(
+                &mut pager,
+                Side::Left,
+                0.5,
+            );
+        }
+        pager.leave_rest_blank();
+
+        for row in rows.iter().skip(page * per_page).take(per_page) {
+            let mut entry = panel.split_off(Side::Top, entry_height);
+            // Holding the pen down on an entry brings up what can be done with it.
+            let path = row.path.clone();
+            entry.handlers().on_ink(move |ink| Msg::PressInTree {
+                path: path.clone(),
+                ink,
+            });
+            entry.split_off(Side::Left, indent * row.depth as i32);
+            if tree.selected.as_ref() == Some(&row.path) {
+                let mut actions = vec![
+                    Button::new(
+                        "focus",
+                        Msg::MetaPath {
+                            current_path: row.path.display().to_string(),
+                        },
+                        true,
+                    ),
+                    Button::new(
+                        "delete",
+                        Msg::DeleteFile {
+                            path: row.path.clone(),
+                        },
+                        true,
+                    ),
+                ];
+                if !row.is_dir {
+                    actions.insert(
+                        1,
+                        Button::new(
+                            "copy",
+                            Msg::CopyFile {
+                                path: row.path.clone(),
+                            },
+                            true,
+                        ),
+                    );
+                }
+                Spaced(30, &actions).render_placed(entry, 0.0, 0.5);
+            } else if row.is_dir {
+                let marker = if row.expanded { "▾" } else { "▸" };
+                let label = format!("{marker} {}", row.name());
+                let msg = Msg::ExpandTree {
+                    path: row.path.clone(),
+                };
+                Button::new(&label, msg, true).render_placed(entry, 0.0, 0.5);
+            } else {
+                let msg = Msg::OpenFromTree {
+                    path: row.path.clone(),
+                };
+                Button::new(&format!("  {}", row.name()), msg, true).render_placed(entry, 0.0, 0.5);
+            }
+        }
+        panel.leave_rest_blank();
+    }
+
+    /// The buttons shown in the header when the current tab's actions are open.
+    fn tab_action_buttons(&self, id: usize, tab: &TabType) -> Vec<Button<Text<Msg>>> {
+        let mut buttons = vec![];
+        if let TabType::Text(text_tab) = tab {
+            buttons.push(Button::new(
+                "save",
+                Msg::Tab {
+                    id,
+                    msg: TabMsg::Save,
+                },
+                text_tab.path.is_some() && text_tab.dirty,
+            ));
+            buttons.push(Button::new(
+                "export",
+                Msg::Tab {
+                    id,
+                    msg: TabMsg::Export,
+                },
+                text_tab.path.is_some() && text_tab.passphrase.is_none(),
+            ));
+            buttons.push(Button::new(
+                "export pdf",
+                Msg::Tab {
+                    id,
+                    msg: TabMsg::ExportPdf,
+                },
+                text_tab.path.is_some() && text_tab.passphrase.is_none(),
+            ));
+        }
+        buttons.push(Button::new(
+            "close",
+            Msg::Tab {
+                id,
+                msg: TabMsg::Quit,
+            },
+            true,
+        ));
+        buttons
+    }
+
+    /// Render abbreviated names for as many open tabs as fit in the header, scrolled so the
+    /// current one is always there. Holding the pen on the current tab shows its actions.
+    fn render_tab_strip(&self, current: usize, header: &mut View<Msg>) {
+        Button::new("menu", Msg::SwitchTab { tab: Tab::Meta }, true).render_split(
+            header,
+            Side::Left,
+            0.5,
+        );
+        let ids: Vec<usize> = self.tabs.keys().copied().collect();
+        let titles: Vec<String> = self
+            .tabs
+            .iter()
+            .map(|(id, tab)| {
+                let title = abbreviate(tab.title(), TAB_STRIP_CHARS);
+                if *id == current {
+                    format!("[{title}]")
+                } else {
+                    title
+                }
+            })
+            .collect();
+        let widths: Vec<i32> = titles
+            .iter()
+            .map(|title| Button::new(title, (), true).size().x + TAB_STRIP_SPACING)
+            .collect();
+        // Room for a marker like "‹3", either side.
+        let marker = Button::new("‹99", (), true).size().x + TAB_STRIP_SPACING;
+        let index = ids.iter().position(|id| *id == current).unwrap_or(0);
+        let shown = tab_window(&widths, index, header.size().x, marker);
+
+        // Tapping a marker switches to the nearest hidden tab that way.
+        let marker_button = |label: String, index: usize| {
+            Button::new(
+                &label,
+                Msg::SwitchTab {
+                    tab: Tab::Edit(ids[index]),
+                },
+                true,
+            )
+        };
+        if shown.start > 0 {
+            header.split_off(Side::Left, TAB_STRIP_SPACING);
+            marker_button(format!("‹{}", shown.start), shown.start - 1).render_split(
+                header,
+                Side::Left,
+                0.5,
+            );
+        }
+        for i in shown.clone() {
+            header.split_off(Side::Left, TAB_STRIP_SPACING);
+            if ids[i] == current {
+                let label = Text::builder(DEFAULT_CHAR_HEIGHT, &FONT)
+                    .weight(TEXT_WEIGHT)
+                    .literal(&titles[i])
+                    .into_text();
+                let mut slot = header.split_off(Side::Left, label.size().x);
+                slot.handlers()
+                    .on_ink(move |ink| Msg::PressTab { id: current, ink });
+                label.render_placed(slot, 0.0, 0.5);
+            } else {
+                Button::new(
+                    &titles[i],
+                    Msg::SwitchTab {
+                        tab: Tab::Edit(ids[i]),
+                    },
+                    true,
+                )
+                .render_split(header, Side::Left, 0.5);
+            }
+        }
+        if shown.end < ids.len() {
+            header.split_off(Side::Left, TAB_STRIP_SPACING);
+            marker_button(format!("{}›", ids.len() - shown.end), shown.end).render_split(
+                header,
+                Side::Left,
+                0.5,
+            );
+        }
+    }
+
+    /// The text window for the current view, if it has one the user can select in.
+    fn current_window(&mut self) -> Option<&mut TextWindow> {
+        match self.tab {
+            Tab::Meta => Some(&mut self.meta.path_window),
+            Tab::Search { .. }
+            | Tab::Replace { .. }
+            | Tab::Goto { .. }
+            | Tab::Rename
+            | Tab::Labels { .. }
+            | Tab::Workspace { .. } => Some(&mut self.search_window),
+            Tab::Passphrase { .. } => Some(&mut self.passphrase_window),
+            Tab::Edit(id) => match self.tabs.get_mut(&id)? {
+                TabType::Text(text_tab) => Some(&mut text_tab.text),
+                TabType::Shell(shell_tab) => Some(&mut shell_tab.shell_output),
+            },
+            _ => None,
+        }
+    }
+
+    /// A description of the selection in the current tab, since the grid treats ink very
+    /// differently when there is one.
+    fn selection_mode(&self) -> Option<String> {
+        let window = match self.tab {
+            Tab::Meta => &self.meta.path_window,
+            Tab::Search { .. }
+            | Tab::Replace { .. }
+            | Tab::Goto { .. }
+            | Tab::Rename
+            | Tab::Labels { .. }
+            | Tab::Workspace { .. } => &self.search_window,
+            Tab::Passphrase { .. } => &self.passphrase_window,
+            Tab::Edit(id) => match self.tabs.get(&id)? {
+                TabType::Text(text_tab) => &text_tab.text,
+                TabType::Shell(shell_tab) => &shell_tab.shell_output,
+            },
+            _ => return None,
+        };
+        match &window.selection {
+            Selection::Normal => None,
+            Selection::Single { .. } => Some("INSERT MARK".to_string()),
+            Selection::Multi { carats } => Some(format!("MULTI {} carats", carats.len())),
+            Selection::Range { start, end } => {
+                let lines = end.coord.0 - start.coord.0 + 1;
+                let plural = if lines == 1 { "line" } else { "lines" };
+                Some(format!("SELECTION {lines} {plural}"))
+            }
+            Selection::Block { start, end } => {
+                let (rows, cols) = block_bounds(start.coord, end.coord);
+                Some(format!("BLOCK {}x{}", rows.count(), cols.len()))
+            }
+        }
+    }
+
+    fn open_shell(&mut self, working_dir: PathBuf) -> io::Result<usize> {
+        let id = self.take_id();
+        let shell = ShellTab::new(
+            id,
+            self.atlas.clone(),
+            self.metrics,
+            self.max_dimensions(),
+            self.sender.clone(),
+            working_dir,
+            &self.config.shell,
+        )?;
+        self.tabs.insert(id, TabType::Shell(shell));
+        self.tab = Tab::Edit(id);
+        Ok(id)
+    }
+
+    /// Run a text tab's file as a script, in a new shell in the file's directory, so its
+    /// output can be read and it can be stopped or answered like any other command.
+    fn run_script(&mut self, id: usize) -> anyhow::Result<()> {
+        let (dir, command) = match self.tabs.get(&id) {
+            Some(TabType::Text(text_tab)) => {
+                let command = text_tab
+                    .run_command()
+                    .ok_or_else(|| anyhow::anyhow!("not a script Sill knows how to run"))?;
+                let dir = text_tab
+                    .path
+                    .as_deref()
+                    .and_then(Path::parent)
+                    .unwrap_or_else(|| Path::new("/"));
+                (dir.to_path_buf(), command)
+            }
+            _ => return Ok(()),
+        };
+        let shell = self.open_shell(dir)?;
+        if let Some(TabType::Shell(shell_tab)) = self.tabs.get_mut(&shell) {
+            let output = &mut shell_tab.shell_output;
+            let end = output.buffer.end();
+            output.replace(Replace::splice(end, TextBuffer::from_string(&command)));
+        }
+        self.handle(Msg::Tab {
+            id: shell,
+            msg: TabMsg::SubmitShell,
+        });
+        Ok(())
+    }
+
+    /// Describe the open tabs, for reopening them next time.
+    fn session(&self) -> Session {
+        let mut tabs = vec![];
+        for tab in self.tabs.values() {
+            match tab {
+                // Opened fresh every time anyway.
+                TabType::Text(text_tab) if text_tab.help => {}
+                // Would need the passphrase again, and mustn't be written out in the clear.
+                TabType::Text(text_tab) if text_tab.passphrase.is_some() => {}
+                TabType::Text(text_tab) => {
+                    // A large file's edits are only ever kept in memory.
+                    let keep_contents =
+                        text_tab.dirty && text_tab.large.is_none() || text_tab.path.is_none();
+                    if text_tab.path.is_none() && !text_tab.dirty && !text_tab.read_only {
+                        // An untouched scratch tab, like the help text; nothing to bring back.
+                        continue;
+                    }
+                    let contents = if !keep_contents {
+                        None
+                    } else if let Some(hibernated) = &text_tab.hibernated {
+                        fs::read_to_string(hibernated).ok()
+                    } else {
+                        Some(text_tab.text.buffer.content_string())
+                    };
+                    tabs.push(SessionTab::Text {
+                        title: text_tab.title.clone(),
+                        path: text_tab.path.clone(),
+                        origin: (text_tab.top_line(), text_tab.text.origin.1),
+                        contents,
+                        read_only: text_tab.read_only,
+                        wrap: text_tab.text.wrap,
+                        insert: text_tab.text.insert,
+                    });
+                }
+                TabType::Shell(shell_tab) => {
+                    if let Ok(working_dir) = shell_tab.working_dir() {
+                        let output = &shell_tab.shell_output;
+                        let input = output
+                            .buffer
+                            .copy(output.frozen_until, output.buffer.end())
+                            .content_string();
+                        tabs.push(SessionTab::Shell { working_dir, input });
+                    }
+                }
+            }
+        }
+        Session { tabs }
+    }
+
+    /// A count that goes up with every edit to any text tab.
+    fn edits(&self) -> usize {
+        self.tabs
+            .values()
+            .map(|tab| match tab {
+                TabType::Text(text_tab) => text_tab.edits,
+                TabType::Shell(_) => 0,
+            })
+            .fold(0, usize::wrapping_add)
+    }
+
+    fn save_session(&mut self) {
+        if self.pending_session.is_some() {
+            // Don't clobber the old session before the user has decided what to do with it.
+            return;
+        }
+        let result = self.session().save(&self.session_path);
+        self.report_error(result);
+    }
+
+    fn restore_session(&mut self, session: Session) {
+        for tab in session.tabs {
+            match tab {
+                SessionTab::Text {
+                    title,
+                    path,
+                    origin,
+                    contents,
+                    read_only,
+                    wrap,
+                    insert,
+                } => {
+                    let dirty = contents.is_some() && !read_only;
+                    let large = path.as_deref().is_some_and(|p| self.is_large_file(p));
+                    if let (None, Some(path), true) = (&contents, &path, large) {
+                        let id = self.open_large_file(path.clone(), origin.0);
+                        if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(&id) {
+                            text_tab.title = title;
+                            text_tab.text.set_wrap(wrap);
+                        }
+                        continue;
+                    }
+                    let (contents, lossy) = match (contents, &path) {
+                        (Some(contents), _) => (contents, false),
+                        (None, Some(path)) => match self.report_error(fs::read(path)) {
+                            Some(bytes) => match String::from_utf8(bytes) {
+                                Ok(contents) => (contents, false),
+                                Err(e) => {
+                                    (String::from_utf8_lossy(e.as_bytes()).into_owned(), true)
+                                }
+                            },
+                            None => continue,
+                        },
+                        (None, None) => continue,
+                    };
+                    let id = self.new_text_tab(path, TextBuffer::from_string(&contents));
+                    if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(&id) {
+                        text_tab.title = title;
+                        text_tab.read_only = read_only || lossy;
+                        text_tab.lossy = lossy;
+                        text_tab.dirty = dirty;
+                        text_tab.text.set_wrap(wrap);
+                        text_tab.text.insert = insert;
+                        text_tab.text.origin = origin;
+                    }
+                }
+                SessionTab::Shell { working_dir, input } => {
+                    let opened = self.open_shell(working_dir);
+                    if let Some(id) = self.report_error(opened) {
+                        if let Some(TabType::Shell(shell_tab)) = self.tabs.get_mut(&id) {
+                            // Whatever the new shell's printed so far stays put, with the input
+                            // after it, ready to edit or send.
+                            let output = &mut shell_tab.shell_output;
+                            let end = output.buffer.end();
+                            output.frozen_until = end;
+                            output
+                                .do_replace(Replace::splice(end, TextBuffer::from_string(&input)));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Keep every document and shell the size of the grid it's drawn on, which is narrower
+    /// while the file tree's open beside it.
+    fn fit_tabs(&mut self) {
+        let (rows, cols) = self.max_dimensions();
+        let dimensions = (rows, cols - self.tree_columns());
+        let mut result = Ok(());
+        for tab in self.tabs.values_mut() {
+            match tab {
+                TabType::Text(text_tab) if text_tab.text.dimensions != dimensions => {
+                    text_tab.text.resize(dimensions)
+                }
+                TabType::Text(_) => {}
+                TabType::Shell(shell_tab) => result = result.and(shell_tab.resize(dimensions)),
+            }
+        }
+        self.report_error(result);
+    }
+
+    /// Read the file tree's listing again, since files have come or gone.
+    fn refresh_tree(&mut self) {
+        if let Some(tree) = &mut self.tree {
+            let result = tree.refresh();
+            self.report_error(result);
+        }
+    }
+
+    /// Notice USB drives and network shares coming and going, so files on one that's gone don't
+    /// just fail to save with a puzzling error.
+    fn check_mounts(&mut self) {
+        let mounts = mounts::external();
+        if mounts == self.mounts {
+            return;
+        }
+        for mount in &mounts {
+            if !self.mounts.contains(mount) {
+                self.error_string = format!("{} is mounted", mount.dir.display());
+            }
+        }
+        for mount in &self.mounts {
+            if mounts.contains(mount) {
+                continue;
+            }
+            let open = self
+                .tabs
+                .values()
+                .filter(|tab| match tab {
+                    TabType::Text(text_tab) => text_tab
+                        .path
+                        .as_deref()
+                        .is_some_and(|p| mounts::containing(&self.mounts, p) == Some(mount)),
+                    TabType::Shell(_) => false,
+                })
+                .count();
+            self.error_string = if open > 0 {
+                format!(
+                    "{} was unmounted with {open} files open; mount it again or save them elsewhere",
+                    mount.dir.display()
+                )
+            } else {
+                format!("{} was unmounted", mount.dir.display())
+            };
+        }
+        self.mounts = mounts;
+        self.meta.reload_suggestions();
+    }
+
+    /// Notice if the current tab's file was changed by something else. A tab without unsaved
+    /// edits just picks up the new version; otherwise the user gets to choose.
+    fn check_disk_changes(&mut self) {
+        let id = match self.tab {
+            Tab::Edit(id) => id,
+            _ => return,
+        };
+        let text_tab = match self.tabs.get_mut(&id) {
+            Some(TabType::Text(text_tab)) => text_tab,
+            _ => return,
+        };
+        if text_tab.hibernated.is_some() || !text_tab.changed_on_disk() {
+            text_tab.external_change = false;
+            return;
+        }
+        if text_tab.external_change || text_tab.indexing {
+            return;
+        }
+        if !text_tab.dirty {
+            // A large file is most often a log that's been added to, which isn't news.
+            let quiet = text_tab.large.is_some();
+            match self.reload_tab(id) {
+                Ok(()) if quiet => {}
+                Ok(()) => {
+                    self.error_string = "Reloaded the file, which changed on disk".to_string()
+                }
+                Err(e) => {
+                    // Leave it to the user, rather than trying again every time.
+                    if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(&id) {
+                        text_tab.external_change = true;
+                    }
+                    self.error_string = format!("Error: {e}");
+                }
+            }
+        } else if text_tab.path.as_deref().and_then(modified_time) != text_tab.kept_mtime {
+            text_tab.external_change = true;
+            self.error_string =
+                "The file changed on disk: reload it, keep your version, or merge".to_string();
+        }
+    }
+
+    /// Load whatever's been added to the files being followed. Tabs with unsaved edits are left
+    /// for `check_disk_changes` to ask about.
+    fn follow_files(&mut self) {
+        let ids: Vec<usize> = self
+            .tabs
+            .iter()
+            .filter_map(|(id, tab)| match tab {
+                TabType::Text(text_tab)
+                    if text_tab.follow
+                        && !text_tab.dirty
+                        && !text_tab.indexing
+                        && text_tab.hibernated.is_none()
+                        && text_tab.changed_on_disk() =>
+                {
+                    Some(*id)
+                }
+                _ => None,
+            })
+            .collect();
+        for id in ids {
+            let result = self.reload_tab(id);
+            self.report_error(result);
+        }
+    }
+
+    /// Check with the user before doing something that can't be taken back, or that runs a
+    /// program they might not expect: the question goes in the footer, and the same action
+    /// again goes ahead.
+    fn ask(&mut self, action: String, question: String) {
+        self.confirm = Some(action);
+        self.error_string = question;
+    }
+
+    /// Whether this is the action the user was just asked about, and it's going ahead.
+    fn confirmed(&mut self, action: &str) -> bool {
+        let confirmed = self.confirm.as_deref() == Some(action);
+        if confirmed {
+            self.confirm = None;
+        }
+        confirmed
+    }
+
+    /// Start the follow timer if any tab is following its file and it isn't already going.
+    fn arm_follow_timer(&mut self) {
+        let following = self
+            .tabs
+            .values()
+            .any(|tab| matches!(tab, TabType::Text(t) if t.follow));
+        if following && !self.follow_timer {
+            self.follow_timer = true;
+            spawn_follow_timer(self.sender.clone());
+        }
+    }
+
+    fn save_labels(&mut self) {
+        let result = self.meta.labels.save(&self.labels_path);
+        self.report_error(result);
+        // A filter for a label that's gone would leave nothing to pick from.
+        if let Some(label) = &self.meta.label_filter {
+            if !self.meta.labels.all().contains(label.as_str()) {
+                self.meta.label_filter = None;
+            }
+        }
+        self.meta.reload_suggestions();
+    }
+
+    /// Collect the reminders from every open file, and announce any that have come due since
+    /// the last check. Encrypted files are left alone, so their reminders don't end up on disk
+    /// in the clear, and so are unnamed buffers, which have no file to open them from again.
+    fn check_reminders(&mut self) {
+        let mut changed = false;
+        for tab in self.tabs.values() {
+            if let TabType::Text(text_tab) = tab {
+                // Only part of a large file is in the buffer, so its reminders aren't all there.
+                let skip = text_tab.hibernated.is_some()
+                    || text_tab.passphrase.is_some()
+                    || text_tab.large.is_some();
+                let path = match &text_tab.path {
+                    Some(path) if !skip => path,
+                    _ => continue,
+                };
+                let text = text_tab.text.buffer.content_string();
+                changed |= self.reminders.scan(path, &text);
+            }
+        }
+        if changed {
+            let result = self.reminders.save(&self.reminders_path);
+            self.report_error(result);
+        }
+        let (date, time) = local_now();
+        let now = format!("{date} {time}");
+        let newly_due: Vec<String> = self
+            .reminders
+            .due(&now)
+            .filter(|(_, r)| r.due > self.reminders_checked)
+            .map(|(_, r)| r.text.clone())
+            .collect();
+        if !newly_due.is_empty() {
+            self.error_string = format!("Reminder: {}", newly_due.join("; "));
+        }
+        self.reminders_checked = now;
+    }
+
+    fn wake_tab(&mut self, id: usize) {
+        if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(&id) {
+            let result = text_tab.wake();
+            self.report_error(result);
+        }
+    }
+
+    /// Make sure the current tab is in memory, and hibernate any text tabs that haven't been
+    /// looked at recently.
+    fn hibernate_idle_tabs(&mut self) {
+        if let Some(id) = self.tab.tab_id() {
+            self.recent_tabs.retain(|t| *t != id);
+            self.recent_tabs.push_front(id);
+            self.wake_tab(id);
+        }
+        let tabs = &self.tabs;
+        self.recent_tabs.retain(|id| tabs.contains_key(id));
+
+        let live_tabs = self.config.memory.live_tabs;
+        if live_tabs == 0 {
+            return;
+        }
+        let idle: Vec<usize> = self.recent_tabs.iter().skip(live_tabs).copied().collect();
+        for id in idle {
+            if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(&id) {
+                let result = text_tab.hibernate(id);
+                self.report_error(result);
+            }
+        }
+    }
+
+    fn take_id(&mut self) -> usize {
+        let id = self.next_tab_id;
+        self.next_tab_id += 1;
+        id
+    }
+
+    fn template_at(&mut self, coord: Coord) -> &mut Template {
+        let (row, col) = coord;
+        let row = row + self.template_offset;
+        let col = col + self.template_column;
+        let ct = &mut self.text_stuff.templates[row];
+        if col >= ct.templates.len() {
+            ct.templates
+                .resize_with(col + 1, || Template::from_ink(Ink::new()));
+        }
+        &mut ct.templates[col]
+    }
+
+    fn new_text_tab(&mut self, path: Option<PathBuf>, contents: TextBuffer) -> usize {
+        let id = self.take_id();
+        let title = path
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or("<unnamed file>".to_string());
+        let mut text = TextWindow::new(
+            contents,
+            self.atlas.clone(),
+            self.metrics,
+            self.max_dimensions(),
+        );
+        text.language = self.language_for(path.as_deref());
+        // Code is full of words that aren't in any dictionary.
+        text.spellcheck = matches!(
+            path.as_deref().and_then(Language::from_path),
+            None | Some(Language::Markdown)
+        );
+        text.past_end = PastEnd::from_config(&self.config.past_end);
+        let disk_mtime = path.as_deref().and_then(modified_time);
+        self.tabs.insert(
+            id,
+            TabType::Text(TextTab {
+                title,
+                path,
+                text,
+                dirty: false,
+                edits: 0,
+                autosaved: 0,
+                read_only: false,
+                lossy: false,
+                help: false,
+                hibernated: None,
+                disk_mtime,
+                external_change: false,
+                kept_mtime: None,
+                saves_pending: 0,
+                passphrase: None,
+                journal: None,
+                template_profile: None,
+                large: None,
+                first_line: 0,
+                window_lines: 0,
+                window_edits: 0,
+                indexing: false,
+                follow: false,
+                read_len: None,
+                run_after_save: false,
+            }),
+        );
+        self.tab = Tab::Edit(id);
+        id
+    }
+
+    fn open_file(&mut self, path: PathBuf) -> Option<usize> {
+        // If we reopen meta, we're likely to want another file in the same dir.
+        if let Some(dir) = path.parent().and_then(full_path) {
+            self.meta
+                .path_window
+                .set_buffer(TextBuffer::from_string(&dir));
+            self.meta.reload_suggestions();
+        }
+
+        if Cipher::for_path(&path).is_some() {
+            self.tab = Tab::Passphrase { path, id: None };
+            return None;
+        }
+
+        let mut start = vec![];
+        let sniffed = File::open(&path).and_then(|f| f.take(8192).read_to_end(&mut start));
+        self.report_error(sniffed)?;
+        if looks_binary(&start) {
+            let opened = self.open_hex_view(&path);
+            return self.report_error(opened);
+        }
+        if self.is_large_file(&path) {
+            return Some(self.open_large_file(path, 0));
+        }
+
+        let bytes = self.report_error(fs::read(&path))?;
+        let (file_contents, valid) = match String::from_utf8(bytes) {
+            Ok(contents) => (contents, true),
+            Err(e) => (String::from_utf8_lossy(e.as_bytes()).into_owned(), false),
+        };
+        // Opening for writing doesn't change the file, but fails on a read-only mount too.
+        let writable = fs::OpenOptions::new().write(true).open(&path).is_ok();
+        let id = self.new_text_tab(Some(path), TextBuffer::from_string(&file_contents));
+        if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(&id) {
+            if !writable {
+                text_tab.read_only = true;
+                self.error_string = "Opened read-only, since the file can't be written".to_string();
+            } else if !valid {
+                // Saving would write the replacement characters over the original bytes.
+                text_tab.read_only = true;
+                text_tab.lossy = true;
+                self.error_string =
+                    "Not valid UTF-8: opened read-only, with � for the bad bytes".to_string();
+            }
+            if text_tab.recovery_path().is_some_and(|p| p.exists()) {
+                self.error_string =
+                    "Found unsaved changes; see \"recover unsaved changes\"".to_string();
+            }
+        }
+        Some(id)
+    }
+
+    /// Show the start of a binary file as a hex dump, in a tab of its own.
+    fn open_hex_view(&mut self, path: &Path) -> io::Result<usize> {
+        let mut bytes = vec![];
+        File::open(path)?
+            .take(HEX_VIEW_BYTES)
+            .read_to_end(&mut bytes)?;
+        let size = fs::metadata(path)?.len();
+        let mut dump = hex_dump(&bytes);
+        if size > bytes.len() as u64 {
+            dump.push_str(&format!("\n… the first {} of {size} bytes", bytes.len()));
+        }
+        let name = path
+            .file_name()
+            .map_or(String::new(), |n| n.to_string_lossy().into_owned());
+        let id = self.new_report_tab(format!("{name} (hex)"), &dump);
+        if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(&id) {
+            text_tab.text.spellcheck = false;
+        }
+        self.error_string = "Binary file: showing it as hex".to_string();
+        Ok(id)
+    }
+
+    /// Whether the file is too big to load whole, going by the configured limit.
+    fn is_large_file(&self, path: &Path) -> bool {
+        let limit = self.config.memory.large_file_kb;
+        limit > 0 && fs::metadata(path).is_ok_and(|m| m.len() > limit * 1024)
+    }
+
+    /// Whether the tab holds an encrypted file, whose contents mustn't be written anywhere in
+    /// the clear.
+    fn is_encrypted(&self, id: usize) -> bool {
+        matches!(self.tabs.get(&id), Some(TabType::Text(t)) if t.passphrase.is_some())
+    }
+
+    /// Open a file that's too big to load whole, read-only, with only the lines around the
+    /// screen in memory. It's shown from `line` once it's been indexed.
+    fn open_large_file(&mut self, path: PathBuf, line: usize) -> usize {
+        let id = self.new_text_tab(Some(path.clone()), TextBuffer::empty());
+        if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(&id) {
+            text_tab.text.spellcheck = false;
+            text_tab.read_only = true;
+            text_tab.indexing = true;
+            text_tab.first_line = line;
+        }
+        self.index_large_file(id, path, 0);
+        self.error_string = "Large file: indexing…".to_string();
+        id
+    }
+
+    /// Index a large file on a thread of its own, since it means reading the whole thing.
+    fn index_large_file(&self, id: usize, path: PathBuf, edits: usize) {
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let result = LargeFile::index(&path).map_err(|e| e.to_string());
+            sender.send(Msg::Tab {
+                id,
+                msg: TabMsg::Indexed { edits, result },
+            });
+        });
+    }
+
+    /// Reload a tab from disk. A large file that's only been added to just has the new lines
+    /// indexed; anything else means indexing the whole file again, in the background, and it
+    /// stays locked until that's done.
+    fn reload_tab(&mut self, id: usize) -> anyhow::Result<()> {
+        let text_tab = match self.tabs.get_mut(&id) {
+            Some(TabType::Text(text_tab)) => text_tab,
+            _ => return Ok(()),
+        };
+        let reindex = match &text_tab.large {
+            Some(large) => text_tab.dirty || large.edited() || large.replaced(),
+            None => text_tab.indexing,
+        };
+        if !reindex {
+            return text_tab.reload();
+        }
+        let path = text_tab
+            .path
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("the tab has no file"))?;
+        text_tab.indexing = true;
+        text_tab.read_only = true;
+        let edits = text_tab.edits;
+        self.index_large_file(id, path, edits);
+        Ok(())
+    }
+
+    /// Forget the passphrase that was written, along with its undo history.
+    fn clear_passphrase(&mut self) {
+        self.passphrase_window.set_buffer(TextBuffer::empty());
+        self.passphrase_window.selection = Selection::Normal;
+        self.passphrase_window.undos.clear();
+        self.passphrase_window.redos.clear();
+    }
+
+    /// Decrypt and open a file with the passphrase that was written, or set it as the
+    /// passphrase to save a tab with.
+    fn unlock(&mut self, path: PathBuf, id: Option<usize>) -> anyhow::Result<()> {
+        let passphrase = self.passphrase_window.buffer.content_string();
+        self.clear_passphrase();
+        if passphrase.is_empty() {
+            return Err(anyhow::anyhow!("no passphrase written"));
+        }
+        let cipher =
+            Cipher::for_path(&path).ok_or_else(|| anyhow::anyhow!("not an encrypted file"))?;
+        match id {
+            None => {
+                let contents = crypt::decrypt(cipher, &path, &passphrase)?;
+                let id = self.new_text_tab(Some(path), TextBuffer::from_string(&contents));
+                if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(&id) {
+                    text_tab.passphrase = Some(passphrase);
+                }
+            }
+            Some(id) => {
+                let language = self.language_for(Some(&path));
+                if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(&id) {
+                    text_tab.text.language = language;
+                    text_tab.set_path(path);
+                    text_tab.passphrase = Some(passphrase);
+                    text_tab.save(id, &self.saves)?;
+                }
+                self.tab = Tab::Edit(id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Save a tab with unsaved changes, for the save-on-switch and save-on-suspend policies.
+    /// A file that's been changed by something else isn't overwritten: the buffer goes to the
+    /// recovery file instead, to be merged by hand later. With `wait`, the save is done before
+    /// this returns, rather than queued for the save worker.
+    fn checkpoint(&mut self, id: usize, wait: bool) {
+        let job = match self.tabs.get_mut(&id) {
+            Some(TabType::Text(text_tab))
+                if text_tab.dirty
+                    && !text_tab.read_only
+                    && !text_tab.help
+                    && text_tab.hibernated.is_none() =>
+            {
+                let recovery = text_tab.changed_on_disk();
+                text_tab.save_job(id, recovery)
+            }
+            _ => None,
+        };
+        match job {
+            Some(job) if wait => {
+                let config = &self.config;
+                self.handle(run_save(job, config.verify_saves, config.backup_saves));
+            }
+            Some(job) => {
+                let result = self
+                    .saves
+                    .send(job)
+                    .map_err(|_| io::Error::other("save worker has stopped"));
+                self.report_error(result);
+            }
+            None => {}
+        }
+    }
+
+    /// The journal file from the config, relative to the home directory unless it's absolute.
+    fn journal_path(&self) -> PathBuf {
+        util::home_path(&self.config.journal)
+    }
+
+    /// Where the file tree starts: the workspace, if one's open, or else the directory of the
+    /// current file or the focus path.
+    fn tree_root(&self) -> PathBuf {
+        if let Some(workspace) = &self.workspace {
+            return workspace.root.clone();
+        }
+        let current = match self.tab.tab_id().and_then(|id| self.tabs.get(&id)) {
+            Some(TabType::Text(text_tab)) => text_tab.path.clone(),
+            Some(TabType::Shell(_)) | None => None,
+        };
+        let path =
+            current.unwrap_or_else(|| PathBuf::from(self.meta.path_window.buffer.content_string()));
+        if path.is_dir() {
+            path
+        } else {
+            path.parent()
+                .map_or(PathBuf::from("/"), |p| p.to_path_buf())
+        }
+    }
+
+    /// Open a directory as a workspace: browse and search its files, keep a separate session for
+    /// it, and apply any config overrides in its `.sill.toml`.
+    fn open_workspace(&mut self, root: PathBuf) -> anyhow::Result<()> {
+        // Everything that can go wrong goes first, so a failure leaves the current tabs alone.
+        let workspace = Workspace::open(root)?;
+        let (config, ignored) =
+            match fs::read_to_string(workspace.root.join(workspace::CONFIG_FILE)) {
+                Ok(overrides) => self.base_config.with_workspace_overrides(&overrides)?,
+                Err(e) if e.kind() == ErrorKind::NotFound => (self.base_config.clone(), vec![]),
+                Err(e) => return Err(e.into()),
+            };
+        let session_path = BASE_DIRS.place_data_file(workspace.session_file())?;
+        let session = Session::load(&session_path)?;
+
+        self.close_workspace()?;
+        self.save_session();
+        self.config = config;
+        self.session_path = session_path;
+        self.pending_session = None;
+        // Shells keep running, and unsaved edits stay in memory, while they're put aside.
+        self.stashed_tabs = std::mem::take(&mut self.tabs);
+        self.restore_session(session);
+        if let Some(root) = full_path(&workspace.root) {
+            self.meta
+                .path_window
+                .set_buffer(TextBuffer::from_string(&root));
+            self.meta.reload_suggestions();
+        }
+        self.workspace = Some(workspace);
+        self.tab = Tab::Workspace {
+            found: None,
+            page: 0,
+        };
+        if !ignored.is_empty() {
+            self.error_string = format!(
+                "Ignored {} in {}: set them in your own config instead",
+                ignored.join(", "),
+                workspace::CONFIG_FILE
+            );
+        }
+        Ok(())
+    }
+
+    /// Save the workspace's session and close its tabs, bringing back the ones that were open
+    /// before it. Does nothing if there's no workspace open.
+    fn close_workspace(&mut self) -> io::Result<()> {
+        if self.workspace.is_none() {
+            return Ok(());
+        }
+        self.save_session();
+        self.session_path = BASE_DIRS.place_data_file(SESSION_FILE)?;
+        self.tabs = std::mem::take(&mut self.stashed_tabs);
+        self.config = self.base_config.clone();
+        self.workspace = None;
+        self.tab = Tab::Meta;
+        self.arm_follow_timer();
+        Ok(())
+    }
+
+    fn language_for(&self, path: Option<&Path>) -> Option<Language> {
+        if self.config.highlight {
+            path.and_then(Language::from_path)
+        } else {
+            None
+        }
+    }
+
+    /// Open some generated text, like a report, in a new tab that can't be edited.
+    fn new_report_tab(&mut self, title: String, contents: &str) -> usize {
+        let id = self.new_text_tab(None, TextBuffer::from_string(contents));
+        if let Some(TabType::Text(tab)) = self.tabs.get_mut(&id) {
+            tab.title = title;
+            tab.read_only = true;
+        }
+        id
+    }
+
+    fn open_help(&mut self) {
+        let id = self.new_report_tab("help".to_string(), HELP_TEXT);
+        let language = self.language_for(Some(Path::new("help.md")));
+        if let Some(TabType::Text(tab)) = self.tabs.get_mut(&id) {
+            tab.help = true;
+            tab.text.language = language;
+        }
+    }
+
+    /// A rough breakdown of where memory is going: the buffers and undo history in each tab,
+    /// and the shared cache of rendered characters.
+    fn memory_report(&self) -> String {
+        let kb = |bytes: usize| bytes / 1024;
+        let mut report = String::new();
+        let mut total = 0;
+        report.push_str("Tabs:\n");
+        for tab in self.tabs.values() {
+            let window = match tab {
+                TabType::Text(t) => &t.text,
+                TabType::Shell(s) => &s.shell_output,
+            };
+            if let TabType::Text(TextTab {
+                hibernated: Some(_),
+                ..
+            }) = tab
+            {
+                report.push_str(&format!("  {}: hibernated\n", tab.title()));
+                continue;
+            }
+            let (buffer, history) = window.memory_size();
+            total += buffer + history;
+            report.push_str(&format!(
+                "  {}: {} lines, {} KB text, {} KB undo\n",
+                tab.title(),
+                window.buffer.contents.len(),
+                kb(buffer),
+                kb(history),
+            ));
+        }
+        report.push_str(&format!("  total: {} KB\n\n", kb(total)));
+
+        let (cells, labels) = self.atlas.usage();
+        let memory = &self.config.memory;
+        report.push_str(&format!(
+            "Atlas: {cells} cells, {labels} labels (flushed past {})\n\n",
+            memory.atlas_entries
+        ));
+        report.push_str(&format!(
+            "Shell output is trimmed past {} KB per tab.\n",
+            memory.shell_output_kb
+        ));
+        if memory.shell_output_lines > 0 {
+            report.push_str(&format!(
+                "Shell scrollback is kept to {} lines.\n",
+                memory.shell_output_lines
+            ));
+        }
+        if memory.live_tabs > 0 {
+            report.push_str(&format!(
+                "Text tabs are hibernated once {} others have been viewed since.\n",
+                memory.live_tabs
+            ));
+        }
+        report
+    }
+
+    /// A command name selected in the tab, to look up the docs for. Any tab will do, shells
+    /// especially.
+    fn docs_topic(&self, id: usize) -> Option<String> {
+        let window = match self.tabs.get(&id)? {
+            TabType::Text(t) => &t.text,
+            TabType::Shell(s) => &s.shell_output,
+        };
+        let topic = match &window.selection {
+            Selection::Range { start, end } if start.coord.0 == end.coord.0 => {
+                window.buffer.copy(start.coord, end.coord).content_string()
+            }
+            _ => return None,
+        };
+        let topic = topic.trim();
+        docs::is_topic(topic).then(|| topic.to_string())
+    }
+
+    /// Less common commands for a text tab, listed on their own page.
+    fn commands(&self, id: usize) -> Vec<Button<Text<Msg>>> {
+        // Replacing works on a token selected within a single line.
+        let token = match self.tabs.get(&id) {
+            Some(TabType::Text(t)) if !t.read_only => match &t.text.selection {
+                Selection::Range { start, end } if start.coord.0 == end.coord.0 => {
+                    t.text.buffer.copy(start.coord, end.coord).content_string()
+                }
+                _ => String::new(),
+            },
+            _ => String::new(),
+        };
+        let topic = self.docs_topic(id);
+        let mut buttons = vec![
+            Button::new(
+                "replace selection",
+                Msg::SwitchTab {
+                    tab: Tab::Replace {
+                        id,
+                        token: token.clone(),
+                        next: (0, 0),
+                    },
+                },
+                !token.is_empty(),
+            ),
+            Button::new(
+                "go to line",
+                Msg::SwitchTab {
+                    tab: Tab::Goto { id },
+                },
+                matches!(self.tabs.get(&id), Some(TabType::Text(_))),
+            ),
+            Button::new(
+                "reader view",
+                Msg::Tab {
+                    id,
+                    msg: TabMsg::Read,
+                },
+                true,
+            ),
+            Button::new(
+                "review changes",
+                Msg::Tab {
+                    id,
+                    msg: TabMsg::Review,
+                },
+                matches!(self.tabs.get(&id), Some(TabType::Text(t)) if t.dirty && !t.help && t.large.is_none() && t.path.as_ref().is_some_and(|p| p.is_file())),
+            ),
+            Button::new(
+                "statistics",
+                Msg::Tab {
+                    id,
+                    msg: TabMsg::Stats,
+                },
+                true,
+            ),
+            Button::new(
+                "clean up whitespace",
+                Msg::Tab {
+                    id,
+                    msg: TabMsg::Clean,
+                },
+                matches!(self.tabs.get(&id), Some(TabType::Text(t)) if !t.read_only),
+            ),
+            Button::new(
+                "evaluate expression",
+                Msg::Tab {
+                    id,
+                    msg: TabMsg::Calculate,
+                },
+                matches!(self.tabs.get(&id), Some(TabType::Text(t)) if !t.read_only && matches!(t.text.selection, Selection::Single { .. }) || !token.is_empty()),
+            ),
+            Button::new(
+                "sort lines",
+                Msg::SwitchTab {
+                    tab: Tab::Sort {
+                        id,
+                        options: Default::default(),
+                    },
+                },
+                matches!(self.tabs.get(&id), Some(TabType::Text(t)) if !t.read_only),
+            ),
+            Button::new(
+                "recover unsaved changes",
+                Msg::Tab {
+                    id,
+                    msg: TabMsg::Recover,
+                },
+                matches!(self.tabs.get(&id), Some(TabType::Text(t)) if !t.read_only && t.recovery_path().is_some_and(|p| p.exists())),
+            ),
+            match self.tabs.get(&id) {
+                Some(TabType::Text(t)) if t.follow => Button::new(
+                    "stop following",
+                    Msg::Tab {
+                        id,
+                        msg: TabMsg::Follow(false),
+                    },
+                    true,
+                ),
+                _ => Button::new(
+                    "follow changes",
+                    Msg::Tab {
+                        id,
+                        msg: TabMsg::Follow(true),
+                    },
+                    matches!(self.tabs.get(&id), Some(TabType::Text(t)) if t.path.is_some() && t.passphrase.is_none()),
+                ),
+            },
+            match self.tabs.get(&id) {
+                Some(TabType::Text(t)) if t.text.wrap => Button::new(
+                    "unwrap long lines",
+                    Msg::Tab {
+                        id,
+                        msg: TabMsg::Wrap(false),
+                    },
+                    true,
+                ),
+                _ => Button::new(
+                    "wrap long lines",
+                    Msg::Tab {
+                        id,
+                        msg: TabMsg::Wrap(true),
+                    },
+                    matches!(self.tabs.get(&id), Some(TabType::Text(_))),
+                ),
+            },
+            match self.tabs.get(&id) {
+                Some(TabType::Text(t)) if t.text.insert => Button::new(
+                    "write over text",
+                    Msg::Tab {
+                        id,
+                        msg: TabMsg::Insert(false),
+                    },
+                    true,
+                ),
+                _ => Button::new(
+                    "insert text",
+                    Msg::Tab {
+                        id,
+                        msg: TabMsg::Insert(true),
+                    },
+                    matches!(self.tabs.get(&id), Some(TabType::Text(t)) if !t.read_only),
+                ),
+            },
+            Button::new(
+                "outline",
+                Msg::SwitchTab {
+                    tab: Tab::Contents { id },
+                },
+                matches!(self.tabs.get(&id), Some(TabType::Text(t)) if t.outline_language().is_some()),
+            ),
+            Button::new(
+                "task list",
+                Msg::SwitchTab {
+                    tab: Tab::Tasks { id },
+                },
+                matches!(self.tabs.get(&id), Some(TabType::Text(_))),
+            ),
+            Button::new(
+                "clipboard history",
+                Msg::SwitchTab {
+                    tab: Tab::Clips { id },
+                },
+                !self.text_stuff.clip_history.is_empty(),
+            ),
+        ];
+        if let Some(TabType::Text(t)) = self.tabs.get(&id) {
+            if let Some(formatter) = formatter_for(&self.config, t) {
+                buttons.push(Button::new(
+                    &format!(
+                        "format with {}",
+                        formatter.split_whitespace().next().unwrap_or("")
+                    ),
+                    Msg::Tab {
+                        id,
+                        msg: TabMsg::Format,
+                    },
+                    !t.read_only,
+                ));
+            }
+        }
+        if let Some(topic) = topic {
+            buttons.push(Button::new(
+                &format!("docs for {topic}"),
+                Msg::Docs { topic },
+                true,
+            ));
+        }
+        if let Some(TabType::Text(t)) = self.tabs.get(&id) {
+            if t.journal.is_some() && !t.read_only {
+                buttons.extend([
+                    Button::new(
+                        "new journal entry",
+                        Msg::Tab {
+                            id,
+                            msg: TabMsg::JournalEntry,
+                        },
+                        true,
+                    ),
+                    Button::new(
+                        "earlier day",
+                        Msg::Tab {
+                            id,
+                            msg: TabMsg::JournalDay { later: false },
+                        },
+                        true,
+                    ),
+                    Button::new(
+                        "later day",
+                        Msg::Tab {
+                            id,
+                            msg: TabMsg::JournalDay { later: true },
+                        },
+                        true,
+                    ),
+                ]);
             }
         }
+        if let (false, Some(TabType::Text(t))) =
+            (self.config.template_profiles.is_empty(), self.tabs.get(&id))
+        {
+            let label = match &t.template_profile {
+                Some(profile) => format!("templates: {}", profile_name(profile)),
+                None => "templates: whichever is in use".to_string(),
+            };
+            buttons.push(Button::new(
+                &label,
+                Msg::Tab {
+                    id,
+                    msg: TabMsg::NextTemplateProfile,
+                },
+                true,
+            ));
+        }
+        buttons
     }
 }
 
-const NUM_SUGGESTIONS: usize = 32;
-const MAX_DIR_ENTRIES: usize = 1024;
+impl Applet for Editor {
+    type Upstream = ();
 
-fn full_path(path: &Path) -> Option<String> {
-    let mut string = path.to_str()?.to_string();
-    if path.is_dir() {
-        string.push('/');
+    fn update(&mut self, message: Self::Message) -> Option<Self::Upstream> {
+        let route = self.current_route().to_string();
+        let secret_view = matches!(self.tab, Tab::Passphrase { .. })
+            || self.tab.tab_id().is_some_and(|id| self.is_encrypted(id));
+        let secret_tab = match &message {
+            Msg::Tab { id, .. } | Msg::PressTab { id, .. } => self.is_encrypted(*id),
+            Msg::SwitchTab { tab } => tab.tab_id().is_some_and(|id| self.is_encrypted(id)),
+            _ => false,
+        };
+        if let Some(recorder) = &mut self.recorder {
+            let event = if secret_view || secret_tab {
+                // Handwriting in the passphrase field is as good as the passphrase, and any
+                // message to or from an encrypted file's views can carry its contents, like the
+                // lines of a merge; only the kind of message is recorded.
+                let kind = format!("{message:?}");
+                let kind = kind
+                    .split(|c: char| !c.is_alphanumeric())
+                    .next()
+                    .unwrap_or("");
+                format!("{kind} (encrypted; not recorded)")
+            } else {
+                describe(&message)
+            };
+            recorder.record(format!("{route}: {event}"));
+        }
+        match panic::catch_unwind(AssertUnwindSafe(|| self.handle(message))) {
+            Ok(upstream) => upstream,
+            Err(_) => {
+                self.recover_from_panic(&route);
+                None
+            }
+        }
     }
-    Some(string)
-}
 
-fn suggestions(current_path: &str) -> io::Result<Vec<String>> {
-    if !current_path.starts_with('/') {
-        // All paths must be absolute.
-        return Ok(vec![]);
+    fn current_route(&self) -> &str {
+        self.tab.route()
     }
-    let (dir, file) = current_path.rsplit_once('/').expect("splitting /path by /");
-    let dir = if dir.is_empty() { "/" } else { dir };
-    let read = fs::read_dir(dir)?;
-    let mut results: Vec<_> = read
-        .filter_map(|r| r.ok())
-        .filter(|de| {
-            de.file_name()
-                .to_str()
-                .into_iter()
-                .any(|s| s.starts_with(file))
-        })
-        .take(MAX_DIR_ENTRIES)
-        .filter_map(|s| full_path(&s.path()))
-        .collect();
-
-    // NB: if this is slow, pull in the partial sort crate.
-    results.sort();
-    results.truncate(NUM_SUGGESTIONS);
-
-    Ok(results)
-}
-
-fn max_dimensions(metrics: &Metrics) -> Coord {
-    let rows = (SCREEN_HEIGHT - TOP_MARGIN * 2 - GRID_BORDER * 2) / metrics.height;
-    let cols = (SCREEN_WIDTH - LEFT_MARGIN * 2 - GRID_BORDER * 2) / metrics.width;
-    (rows as usize, cols as usize)
 }
 
 impl Editor {
-    fn max_dimensions(&self) -> Coord {
-        max_dimensions(&self.metrics)
-    }
-
-    fn take_id(&mut self) -> usize {
-        let id = self.next_tab_id;
-        self.next_tab_id += 1;
-        id
-    }
-
-    fn template_at(&mut self, coord: Coord) -> &mut Template {
-        let (row, col) = coord;
-        let row = row + self.template_offset;
-        let ct = &mut self.text_stuff.templates[row];
-        if col >= ct.templates.len() {
-            ct.templates
-                .resize_with(col + 1, || Template::from_ink(Ink::new()));
-        }
-        &mut ct.templates[col]
-    }
-
-    fn new_text_tab(&mut self, path: Option<PathBuf>, contents: TextBuffer) {
-        let id = self.take_id();
-        let title = path
-            .as_ref()
-            .and_then(|p| p.file_name())
-            .map(|p| p.to_string_lossy().into_owned())
-            .unwrap_or("<unnamed file>".to_string());
-        self.tabs.insert(
-            id,
-            TabType::Text(TextTab {
-                title,
-                path,
-                text: TextWindow::new(
-                    contents,
-                    self.atlas.clone(),
-                    self.metrics.clone(),
-                    self.max_dimensions(),
-                ),
-                dirty: false,
-            }),
+    fn handle(&mut self, message: Msg) -> Option<()> {
+        self.atlas.trim(self.config.memory.atlas_entries);
+        self.show_page = matches!(message, Msg::Swipe { .. });
+        let previous_tab = self.tab.tab_id();
+        let previous_edits = self.edits();
+        let was_meta = matches!(self.tab, Tab::Meta);
+        let tabs_changed = matches!(
+            message,
+            Msg::SwitchTab { .. }
+                | Msg::Open { .. }
+                | Msg::OpenShell { .. }
+                | Msg::New
+                | Msg::RestoreSession
+                | Msg::OpenWorkspace { .. }
+                | Msg::OpenFromTree { .. }
+                | Msg::Unlock
+                | Msg::OpenJournal
+                | Msg::Tab {
+                    msg: TabMsg::Quit | TabMsg::Saved { .. } | TabMsg::SaveAs { .. },
+                    ..
+                }
         );
-        self.tab = Tab::Edit(id)
-    }
-}
-
-impl Applet for Editor {
-    type Upstream = ();
-
-    fn update(&mut self, message: Self::Message) -> Option<Self::Upstream> {
+        if matches!(
+            message,
+            Msg::Write { .. } | Msg::Erase { .. } | Msg::SwitchTab { .. }
+        ) {
+            // Doing anything else is as good as saying no.
+            self.confirm = None;
+        }
+        match &message {
+            Msg::Tab {
+                msg:
+                    TabMsg::Saved { .. }
+                    | TabMsg::Autosaved { .. }
+                    | TabMsg::Quit
+                    | TabMsg::ShellInput { .. },
+                ..
+            } => {}
+            Msg::Tab { id, .. } | Msg::PressTab { id, .. } => self.wake_tab(*id),
+            _ => {}
+        }
         match message {
             Msg::Write { ink, .. } => match &mut self.tab {
                 Tab::Meta => {
@@ -846,11 +5317,24 @@ impl Applet for Editor {
                     }
                 }
                 Tab::Edit(id) => match self.tabs.get_mut(id).unwrap() {
-                    TabType::Text(text_tab) => {
+                    TabType::Text(text_tab) if text_tab.read_only => {
                         if let Some(ink_type) =
                             InkType::classify(&self.metrics, ink, &text_tab.text.selection())
                         {
-                            text_tab.dirty = true;
+                            text_tab.text.ink_read_only(ink_type, &mut self.text_stuff);
+                        }
+                    }
+                    TabType::Text(text_tab) => {
+                        if let Some((coord, candidates)) = text_tab.text.tapped_recognition(&ink) {
+                            self.tab = Tab::Alternatives {
+                                id: *id,
+                                coord,
+                                candidates,
+                            };
+                        } else if let Some(ink_type) =
+                            InkType::classify(&self.metrics, ink, &text_tab.text.selection())
+                        {
+                            text_tab.touch();
                             text_tab.text.ink_row(ink_type, &mut self.text_stuff);
                         }
                     }
@@ -868,9 +5352,47 @@ impl Applet for Editor {
                     }
                 },
                 Tab::Template => {
-                    if let Some(ink_type) =
+                    // Tapping a template selects it, to move or delete it; a line from the
+                    // selected template to elsewhere in its row moves it there.
+                    let dragged = self.template_selected.and_then(|(row, col)| {
+                        let mut strokes = ink.strokes();
+                        let stroke = strokes.next()?;
+                        if strokes.next().is_some() {
+                            return None;
+                        }
+                        let (width, height) =
+                            (self.metrics.width as f32, self.metrics.height as f32);
+                        let cell = |x: f32, y: f32| {
+                            (
+                                self.template_offset + (y / height).max(0.0) as usize,
+                                self.template_column + (x / width).max(0.0) as usize,
+                            )
+                        };
+                        let (first, last) = (stroke.first()?, stroke.last()?);
+                        let to = cell(last.x, last.y);
+                        (cell(first.x, first.y) == (row, col) && to.0 == row && to.1 != col)
+                            .then_some(to.1)
+                    });
+                    let tapped = tapped_cell(&self.metrics, &ink).and_then(|(row, col)| {
+                        let at = (self.template_offset + row, self.template_column + col);
+                        let template = self.text_stuff.templates.get(at.0)?.templates.get(at.1)?;
+                        (template.ink.len() > 0).then_some(at)
+                    });
+                    if let (Some(to), Some((row, col))) = (dragged, self.template_selected) {
+                        let templates = &mut self.text_stuff.templates[row].templates;
+                        let template = templates.remove(col);
+                        let to = to.min(templates.len());
+                        templates.insert(to, template);
+                        self.template_selected = Some((row, to));
+                    } else if let Some(at) = tapped {
+                        self.template_selected = match self.template_selected {
+                            Some(selected) if selected == at => None,
+                            _ => Some(at),
+                        };
+                    } else if let Some(ink_type) =
                         InkType::classify(&self.metrics, ink, &Selection::Normal)
                     {
+                        self.template_selected = None;
                         match ink_type {
                             InkType::Strikethrough { start, end } => {
                                 if start.0 == end.0 {
@@ -893,6 +5415,21 @@ impl Applet for Editor {
                         }
                     }
                 }
+                Tab::Practice { practice } => {
+                    let tokens = match InkType::classify(&self.metrics, ink, &Selection::Normal) {
+                        Some(InkType::Glyphs { tokens }) => tokens,
+                        _ => vec![],
+                    };
+                    // One attempt at a time: the leftmost char written is the one that counts.
+                    if let Some((_, ink)) = tokens.into_iter().min_by_key(|(coord, _)| coord.1) {
+                        let points = ink_to_points(&ink, &self.metrics);
+                        let matches = self
+                            .text_stuff
+                            .char_recognizer
+                            .top_matches(&points, NUM_ALTERNATIVES + 1);
+                        practice.record(matches);
+                    }
+                }
                 Tab::Search {
                     id,
                     contents,
@@ -912,6 +5449,38 @@ impl Applet for Editor {
                             .collect();
                     }
                 }
+                Tab::Replace { .. }
+                | Tab::Goto { .. }
+                | Tab::Rename
+                | Tab::Labels { .. }
+                | Tab::Workspace { .. } => {
+                    if let Some(ink_type) =
+                        InkType::classify(&self.metrics, ink, &self.search_window.selection())
+                    {
+                        self.search_window.ink_row(ink_type, &mut self.text_stuff);
+                    }
+                }
+                Tab::Passphrase { .. } => {
+                    if let Some(ink_type) =
+                        InkType::classify(&self.metrics, ink, &self.passphrase_window.selection())
+                    {
+                        self.passphrase_window
+                            .ink_row(ink_type, &mut self.text_stuff);
+                    }
+                }
+                Tab::Sketch { ink: sketch, .. } => {
+                    sketch.append(ink, 0.5);
+                }
+                Tab::Commands { .. }
+                | Tab::Clips { .. }
+                | Tab::Palette { .. }
+                | Tab::Alternatives { .. }
+                | Tab::Tasks { .. }
+                | Tab::Contents { .. }
+                | Tab::Completions { .. }
+                | Tab::Sort { .. }
+                | Tab::Reader { .. }
+                | Tab::Merge { .. } => {}
             },
             Msg::Erase { ink } => match self.tab {
                 Tab::Meta => {
@@ -921,29 +5490,67 @@ impl Applet for Editor {
                     // TODO: something about this?
                 }
                 Tab::Edit(id) => match self.tabs.get_mut(&id) {
-                    Some(TabType::Text(tab)) => {
+                    Some(TabType::Text(tab)) if !tab.read_only => {
                         tab.text.erase(ink);
+                        tab.touch();
                     }
                     Some(TabType::Shell(tab)) => {
                         tab.shell_output.erase(ink);
                     }
                     _ => {}
                 },
-                Tab::Search { .. } => {
+                Tab::Search { .. }
+                | Tab::Replace { .. }
+                | Tab::Goto { .. }
+                | Tab::Rename
+                | Tab::Labels { .. }
+                | Tab::Workspace { .. } => {
                     self.search_window.erase(ink);
                 }
+                Tab::Passphrase { .. } => {
+                    self.passphrase_window.erase(ink);
+                }
+                Tab::Sketch { .. }
+                | Tab::Commands { .. }
+                | Tab::Clips { .. }
+                | Tab::Palette { .. }
+                | Tab::Alternatives { .. }
+                | Tab::Tasks { .. }
+                | Tab::Contents { .. }
+                | Tab::Completions { .. }
+                | Tab::Sort { .. }
+                | Tab::Reader { .. }
+                | Tab::Practice { .. }
+                | Tab::Merge { .. } => {}
             },
             Msg::SwitchTab { tab } => {
                 if matches!(self.tab, Tab::Template) {
+                    self.template_selected = None;
                     self.report_error(self.save_templates());
                     self.text_stuff.init_recognizer(&self.metrics);
                 }
                 self.error_string.clear();
+                self.tab_actions = None;
+                if let Tab::Passphrase { .. } = self.tab {
+                    self.clear_passphrase();
+                }
+                if let Tab::Replace { .. } | Tab::Goto { .. } | Tab::Rename | Tab::Labels { .. } =
+                    tab
+                {
+                    self.search_window.set_buffer(TextBuffer::empty());
+                    self.search_window.selection = Selection::Normal;
+                }
+                if let Tab::Edit(id) = tab {
+                    if let Some(TabType::Shell(shell_tab)) = self.tabs.get_mut(&id) {
+                        shell_tab.unseen_output = false;
+                    }
+                }
                 self.tab = tab;
             }
-            Msg::Swipe { towards } => match self.tab {
+            Msg::Swipe { towards } => match &mut self.tab {
                 // TODO: abstract over the pattern here.
                 Tab::Edit(id) => {
+                    let id = *id;
                     let movement = match towards {
                         Side::Top => (1, 0),
                         Side::Bottom => (-1, 0),
@@ -959,129 +5566,1223 @@ impl Applet for Editor {
                         }
                     }
                 }
-                Tab::Template => {
-                    let (rows, _) = self.max_dimensions();
-                    match towards {
-                        Side::Top => {
-                            if self.template_offset + rows < self.text_stuff.templates.len() {
-                                self.template_offset += rows - 1;
-                            }
-                        }
-                        Side::Bottom => {
-                            self.template_offset -= (rows - 1).min(self.template_offset);
+                Tab::Template => {
+                    let (rows, columns) = self.max_dimensions();
+                    match towards {
+                        Side::Top => {
+                            if self.template_offset + rows < self.text_stuff.templates.len() {
+                                self.template_offset += rows - 1;
+                                self.template_column = 0;
+                            }
+                        }
+                        Side::Bottom => {
+                            self.template_offset -= (rows - 1).min(self.template_offset);
+                            self.template_column = 0;
+                        }
+                        Side::Left => {
+                            // Page along as long as some character on screen has more to show.
+                            let widest = self.text_stuff.templates[self.template_offset..]
+                                .iter()
+                                .take(rows)
+                                .map(|ct| ct.templates.len())
+                                .max()
+                                .unwrap_or(0);
+                            if self.template_column + columns < widest + 1 {
+                                self.template_column += columns - 1;
+                            }
+                        }
+                        Side::Right => {
+                            self.template_column -= (columns - 1).min(self.template_column);
+                        }
+                    }
+                }
+                Tab::Reader { reader, .. } => match towards {
+                    Side::Top | Side::Left => reader.turn(1),
+                    Side::Bottom | Side::Right => reader.turn(-1),
+                },
+                Tab::Workspace { page, .. } | Tab::Merge { page, .. } => match towards {
+                    Side::Top | Side::Left => *page += 1,
+                    Side::Bottom | Side::Right => *page = page.saturating_sub(1),
+                },
+                _ => {
+                    // Nothing to swipe here!
+                }
+            },
+            Msg::Open { path } => {
+                self.open_file(path);
+            }
+            Msg::OpenAt { path, row } => {
+                if let Some(id) = self.open_file(path) {
+                    if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(&id) {
+                        text_tab.text.origin = (row, 0);
+                    }
+                }
+            }
+            Msg::Unlock => {
+                if let Tab::Passphrase { path, id } = self.tab.clone() {
+                    let result = self.unlock(path, id);
+                    self.report_error(result);
+                }
+            }
+            Msg::OpenWorkspace { root } => {
+                let result = self.open_workspace(root);
+                self.report_error(result);
+            }
+            Msg::CloseWorkspace => {
+                let result = self.close_workspace();
+                self.report_error(result);
+            }
+            // There may be thousands of files to read, so they're searched on another thread.
+            Msg::WorkspaceFind => {
+                let query = self
+                    .search_window
+                    .buffer
+                    .content_string()
+                    .trim()
+                    .to_string();
+                if let Some(workspace) = self.workspace.clone() {
+                    self.error_string = "Searching…".to_string();
+                    let sender = self.sender.clone();
+                    thread::spawn(move || {
+                        let found = workspace.search(&query);
+                        sender.send(Msg::WorkspaceFound(format!("lines with “{query}”"), found));
+                    });
+                }
+            }
+            Msg::WorkspaceFound(what, found) => {
+                if self.workspace.is_some() {
+                    self.error_string.clear();
+                    self.tab = Tab::Workspace {
+                        found: Some((what, found)),
+                        page: 0,
+                    };
+                }
+            }
+            Msg::ToggleTree => {
+                self.tree = match self.tree {
+                    Some(_) => None,
+                    None => self.report_error(FileTree::new(self.tree_root())),
+                };
+            }
+            Msg::ExpandTree { path } => {
+                if let Some(tree) = &mut self.tree {
+                    let result = tree.toggle(&path);
+                    self.report_error(result);
+                }
+            }
+            Msg::PressInTree { path, ink } => {
+                if let Some(tree) = &mut self.tree {
+                    if ink_type::long_press(&ink) {
+                        tree.selected = match tree.selected {
+                            Some(ref selected) if *selected == path => None,
+                            _ => Some(path),
+                        };
+                    }
+                }
+            }
+            Msg::TreePage { page } => {
+                if let Some(tree) = &mut self.tree {
+                    tree.page = page;
+                }
+            }
+            Msg::OpenFromTree { path } => {
+                self.tree = None;
+                self.open_file(path);
+            }
+            Msg::CopyFile { path } => {
+                let copy = tree::copy_path(&path);
+                let result = fs::copy(&path, &copy);
+                if self.report_error(result).is_some() {
+                    self.error_string = format!("Copied to {}", copy.display());
+                    self.refresh_tree();
+                }
+            }
+            Msg::SelectPath { path } => {
+                if !self.meta.selected.remove(&path) {
+                    self.meta.selected.insert(path);
+                }
+            }
+            Msg::ClearSelectedPaths => self.meta.selected.clear(),
+            Msg::ApplyRename => {
+                let result = self.apply_rename();
+                if let Some(count) = self.report_error(result) {
+                    self.meta.selected.clear();
+                    self.meta.reload_suggestions();
+                    self.tab = Tab::Meta;
+                    self.error_string = format!("Renamed {count} files");
+                }
+            }
+            Msg::DeleteFile { path } => {
+                let open = self.tabs.values().any(|tab| match tab {
+                    TabType::Text(text_tab) => text_tab.path.as_ref() == Some(&path),
+                    TabType::Shell(_) => false,
+                });
+                let action = format!("delete {}", path.display());
+                if open {
+                    self.error_string = "Error: file is open in a tab".to_string();
+                } else if !self.confirmed(&action) {
+                    let question = format!("Tap delete again to delete {}", path.display());
+                    self.ask(action, question);
+                } else {
+                    let result = if path.is_dir() {
+                        // Only empty directories: anything more is a job for the shell.
+                        fs::remove_dir(&path)
+                    } else {
+                        fs::remove_file(&path)
+                    };
+                    if self.report_error(result).is_some() {
+                        self.error_string = format!("Deleted {}", path.display());
+                        if self.meta.labels.removed(&path) {
+                            self.save_labels();
+                        }
+                        if let Some(tree) = &mut self.tree {
+                            tree.selected = None;
+                        }
+                        self.refresh_tree();
+                    }
+                }
+            }
+            Msg::WorkspaceTasks => {
+                if let Some(workspace) = self.workspace.clone() {
+                    self.error_string = "Looking for tasks…".to_string();
+                    let sender = self.sender.clone();
+                    thread::spawn(move || {
+                        sender.send(Msg::WorkspaceFound("tasks".to_string(), workspace.tasks()));
+                    });
+                }
+            }
+            Msg::Autosave => {
+                let mut changed = false;
+                for (id, tab) in &mut self.tabs {
+                    if let TabType::Text(text_tab) = tab {
+                        match text_tab.autosave(*id, &self.saves) {
+                            Ok(saved) => changed |= saved,
+                            Err(e) => self.error_string = format!("Error: {e}"),
+                        }
+                    }
+                }
+                if changed {
+                    self.save_session();
+                }
+                self.check_disk_changes();
+            }
+            Msg::Suspend { signal } => {
+                if self.config.save_on_suspend {
+                    let ids: Vec<usize> = self.tabs.keys().copied().collect();
+                    for id in ids {
+                        self.checkpoint(id, true);
+                    }
+                    self.save_session();
+                }
+                // The usage counts live in the template file, and they're worth keeping even
+                // when documents aren't saved here.
+                self.report_error(self.save_templates());
+                suspend::resume_default(signal);
+            }
+            Msg::Nav { key } => {
+                if let Tab::Edit(id) = self.tab {
+                    match self.tabs.get_mut(&id) {
+                        Some(TabType::Text(text_tab)) if text_tab.read_only && key.edits() => {}
+                        Some(TabType::Text(text_tab)) => {
+                            text_tab.text.nav(key);
+                            if key.edits() {
+                                text_tab.touch();
+                            }
+                        }
+                        Some(TabType::Shell(shell_tab)) => shell_tab.shell_output.nav(key),
+                        None => {}
+                    }
+                }
+            }
+            Msg::ReplaceNext | Msg::ReplaceAll => {
+                if let Tab::Replace { id, token, next } = &mut self.tab {
+                    let replacement = self.search_window.buffer.clone();
+                    let token: Vec<char> = token.chars().collect();
+                    if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(id) {
+                        text_tab.text.selection = Selection::Normal;
+                        if matches!(message, Msg::ReplaceAll) {
+                            let count = text_tab.text.replace_all(&token, &replacement);
+                            self.error_string = format!("Replaced {count} occurrences");
+                            self.tab = Tab::Edit(*id);
+                        } else if let Some(after) =
+                            text_tab.text.replace_next(&token, &replacement, *next)
+                        {
+                            *next = after;
+                            self.error_string = format!("Replaced at line {}", after.0 + 1);
+                        }
+                        text_tab.touch();
+                    }
+                }
+            }
+            Msg::UseClip { index } => {
+                if let Some(clip) = self.text_stuff.clip_history.get(index) {
+                    self.text_stuff.clipboard = Some(clip.clone());
+                    self.error_string = "Clipboard set".to_string();
+                }
+            }
+            Msg::PasteClip { id, index } => {
+                let clip = self.text_stuff.clip_history.get(index).cloned();
+                if let (Some(clip), Some(TabType::Text(text_tab))) = (clip, self.tabs.get_mut(&id))
+                {
+                    if let Selection::Single { carat } = &text_tab.text.selection {
+                        let paste = clip.paste_into(&text_tab.text.buffer, carat.coord);
+                        text_tab.text.replace(paste);
+                        text_tab.text.selection = Selection::Normal;
+                        text_tab.touch();
+                    }
+                    self.text_stuff.clipboard = Some(clip);
+                    self.tab = Tab::Edit(id);
+                }
+            }
+            Msg::UseAlternative { id, coord, c } => {
+                if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(&id) {
+                    text_tab.text.use_alternative(coord, c);
+                    text_tab.touch();
+                }
+                self.tab = Tab::Edit(id);
+            }
+            Msg::InsertSnippet { id, text } => {
+                if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(&id) {
+                    if let Selection::Single { carat } = &text_tab.text.selection {
+                        let insert = Replace::splice(carat.coord, TextBuffer::from_string(&text));
+                        text_tab.text.replace(insert);
+                        text_tab.text.selection = Selection::Normal;
+                        text_tab.touch();
+                    }
+                    self.tab = Tab::Edit(id);
+                }
+            }
+            Msg::PasteAllClips { id } => {
+                let clip = Clip::join(&self.text_stuff.clip_history);
+                if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(&id) {
+                    if let Selection::Single { carat } = &text_tab.text.selection {
+                        let paste = clip.paste_at(carat.coord);
+                        text_tab.text.replace(paste);
+                        text_tab.text.selection = Selection::Normal;
+                        text_tab.touch();
+                    }
+                    self.tab = Tab::Edit(id);
+                }
+            }
+            Msg::ClearClips => {
+                self.text_stuff.clip_history.clear();
+                if let Tab::Clips { id } = self.tab {
+                    self.tab = Tab::Edit(id);
+                }
+            }
+            Msg::DeleteTemplate => {
+                if let Some((row, col)) = self.template_selected.take() {
+                    let templates = &mut self.text_stuff.templates[row].templates;
+                    if col < templates.len() {
+                        templates.remove(col);
+                    }
+                }
+            }
+            Msg::ImportSheet => {
+                let path = PathBuf::from(self.meta.path_window.buffer.content_string());
+                if let Some(sheet) = self.report_error(worksheet::import(&path)) {
+                    let (width, height) = (self.metrics.width as f32, self.metrics.height as f32);
+                    let mut count = 0;
+                    // Each row of the sheet fills in a row of the templates, from the top of the
+                    // page we're looking at.
+                    let rows = self.text_stuff.templates[self.template_offset..].iter_mut();
+                    for (ct, cells) in rows.zip(sheet) {
+                        for strokes in cells.into_iter().filter(|s| !s.is_empty()) {
+                            let mut ink = Ink::new();
+                            for stroke in strokes {
+                                for (i, (x, y)) in stroke.into_iter().enumerate() {
+                                    ink.push(x * width, y * height, i as f32);
+                                }
+                                ink.pen_up();
+                            }
+                            ct.templates.push(Template::from_ink(ink));
+                            count += 1;
+                        }
+                    }
+                    self.error_string = format!("Imported {count} templates");
+                }
+            }
+            Msg::ImportTemplates => {
+                let result = self.import_templates();
+                if let Some(count) = self.report_error(result) {
+                    self.error_string = format!("Imported {count} new templates");
+                }
+            }
+            Msg::Practice => {
+                // Only chars with templates: there's no reading the rest.
+                let chars = self
+                    .text_stuff
+                    .templates
+                    .iter()
+                    .filter(|ct| !ct.char.is_whitespace() && !ct.templates.is_empty())
+                    .map(|ct| ct.char)
+                    .collect();
+                let practice = practice::Practice::new(chars);
+                // Switching saves any template edits and rebuilds the recognizer first.
+                return self.handle(Msg::SwitchTab {
+                    tab: Tab::Practice { practice },
+                });
+            }
+            Msg::SkipPractice => {
+                if let Tab::Practice { practice } = &mut self.tab {
+                    practice.next_prompt();
+                }
+            }
+            Msg::NextTemplateProfile => {
+                let next = next_profile(&self.config.template_profiles, &self.template_profile);
+                self.use_template_profile(next);
+            }
+            Msg::AddStarterTemplates => {
+                let name = self.config.starter_templates.clone();
+                if let Some(data) = self.report_error(starter_templates(&name)) {
+                    let count = self.merge_templates(data);
+                    self.error_string = format!("Added {count} templates from the {name} set");
+                }
+            }
+            Msg::ExportTemplates => {
+                let result = self.export_templates();
+                if let Some(path) = self.report_error(result) {
+                    self.error_string = format!("Exported templates to {}", path.display());
+                }
+            }
+            Msg::OpenJournal => {
+                let path = self.journal_path();
+                let open = self.tabs.iter().find_map(|(id, tab)| match tab {
+                    TabType::Text(text_tab) if text_tab.path.as_ref() == Some(&path) => Some(*id),
+                    _ => None,
+                });
+                let id = match open {
+                    Some(id) => {
+                        self.wake_tab(id);
+                        Some(id)
+                    }
+                    None if path.exists() => self.open_file(path),
+                    None => Some(self.new_text_tab(Some(path), TextBuffer::empty())),
+                };
+                if let Some(id) = id {
+                    if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(&id) {
+                        let (date, _) = local_now();
+                        text_tab.journal_day(&date);
+                    }
+                    self.tab = Tab::Edit(id);
+                }
+            }
+            Msg::ExportProfile => {
+                let result = self.export_profile();
+                if let Some(bundle) = self.report_error(result) {
+                    self.error_string = format!("Exported profile to {}", bundle.display());
+                }
+            }
+            Msg::ImportProfile => {
+                let bundle = PathBuf::from(self.meta.path_window.buffer.content_string());
+                let result = self
+                    .profile_files()
+                    .map_err(anyhow::Error::from)
+                    .and_then(|files| profile::import(&files, &bundle));
+                if let Some(count) = self.report_error(result) {
+                    self.load_templates();
+                    // Offer the imported tabs the same way we would at startup.
+                    let session = Session::load(&self.session_path);
+                    if let Some(session) = self.report_error(session) {
+                        if !session.tabs.is_empty() {
+                            self.pending_session = Some(session);
                         }
-                        _ => {}
                     }
+                    self.error_string =
+                        format!("Imported {count} files; restart to apply the config");
                 }
-                _ => {
-                    // Nothing to swipe here!
+            }
+            Msg::SaveEventLog => {
+                let result = self.save_event_log();
+                if let Some(Some(path)) = self.report_error(result) {
+                    self.error_string = format!("Saved event log to {}", path.display());
                 }
-            },
-            Msg::Open { path } => {
-                // If we reopen meta, we're likely to want another file in the same dir.
-                if let Some(dir) = path.parent().and_then(full_path) {
-                    self.meta.path_window.buffer = TextBuffer::from_string(&dir);
-                    self.meta.reload_suggestions();
+            }
+            Msg::Crashed { route } => self.recover_from_panic(&route),
+            Msg::CancelSelection => {
+                if let Some(window) = self.current_window() {
+                    window.selection = Selection::Normal;
                 }
-
-                if let Some(file_contents) = self.report_error(fs::read_to_string(&path)) {
-                    self.new_text_tab(Some(path), TextBuffer::from_string(&file_contents));
+            }
+            Msg::MemoryReport => {
+                let report = self.memory_report();
+                self.new_report_tab("memory".to_string(), &report);
+            }
+            Msg::Diagnose => {
+                let checks = diagnostics(&self.template_path, &self.config.starter_templates);
+                self.new_report_tab("diagnostics".to_string(), &diagnostics::report(&checks));
+            }
+            Msg::SelfTested(checks) => {
+                let problems = checks.iter().filter(|c| c.problem.is_some()).count();
+                if problems > 0 {
+                    self.error_string = format!(
+                        "Startup check found {problems} problem(s); see diagnostics in the main menu"
+                    );
+                }
+            }
+            Msg::CheckReminders => self.check_reminders(),
+            Msg::CheckMounts => self.check_mounts(),
+            Msg::SaveSession => {
+                self.session_timer = false;
+                self.save_session();
+            }
+            Msg::FollowFiles => {
+                self.follow_timer = false;
+                self.follow_files();
+                self.arm_follow_timer();
+            }
+            Msg::FilterLabel(label) => {
+                self.meta.label_filter = label;
+                self.meta.reload_suggestions();
+            }
+            Msg::ToggleLabel(path, label) => {
+                self.meta.labels.toggle(&path, &label);
+                self.save_labels();
+            }
+            Msg::AddLabel(path) => {
+                let label = self
+                    .search_window
+                    .buffer
+                    .content_string()
+                    .trim()
+                    .to_string();
+                if !label.is_empty() && !self.meta.labels.of(&path).contains(&label.as_str()) {
+                    self.meta.labels.toggle(&path, &label);
+                    self.save_labels();
+                }
+                self.search_window.set_buffer(TextBuffer::empty());
+                self.search_window.selection = Selection::Normal;
+            }
+            Msg::DismissReminder { index } => {
+                self.reminders.dismiss(index);
+                let result = self.reminders.save(&self.reminders_path);
+                self.report_error(result);
+            }
+            Msg::Docs { topic } => {
+                let path = self
+                    .config
+                    .shell
+                    .path_var(&env::var("PATH").unwrap_or_default());
+                let cols = self.max_dimensions().1;
+                let sender = self.sender.clone();
+                let help = self.confirmed(&format!("help {topic}"));
+                thread::spawn(move || {
+                    let msg = if help {
+                        let result = docs::help(&topic, &path, cols).map_err(|e| e.to_string());
+                        Msg::DocsLoaded(topic, result)
+                    } else {
+                        match docs::manual(&topic, &path, cols) {
+                            Some(text) => Msg::DocsLoaded(topic, Ok(text)),
+                            None => {
+                                let program = docs::which(&topic, &path);
+                                Msg::NoManual(topic, program)
+                            }
+                        }
+                    };
+                    sender.send(msg);
+                });
+                self.error_string = "Looking up docs...".to_string();
+            }
+            Msg::DocsLoaded(topic, result) => {
+                if let Some(text) = self.report_error(result) {
+                    self.new_report_tab(format!("docs: {topic}"), &text);
+                    self.error_string.clear();
                 }
             }
+            Msg::NoManual(topic, program) => match program {
+                Some(program) => self.ask(
+                    format!("help {topic}"),
+                    format!(
+                        "No manual page for {topic}; tap again to run {} --help",
+                        program.display()
+                    ),
+                ),
+                None => self.error_string = format!("No manual page for {topic}"),
+            },
             Msg::New => {
                 self.new_text_tab(None, TextBuffer::empty());
                 self.error_string.clear();
             }
             Msg::MetaPath { current_path } => {
-                self.meta.path_window.buffer = TextBuffer::from_string(&current_path);
+                self.meta
+                    .path_window
+                    .set_buffer(TextBuffer::from_string(&current_path));
                 self.meta.reload_suggestions();
                 self.tab = Tab::Meta;
             }
             Msg::OpenShell { working_dir } => {
-                let id = self.take_id();
-                let shell = ShellTab::new(
-                    id,
-                    self.atlas.clone(),
-                    self.metrics.clone(),
-                    self.max_dimensions(),
-                    self.sender.clone(),
-                    working_dir,
-                )
-                .unwrap();
-                self.tabs.insert(id, TabType::Shell(shell));
-                self.tab = Tab::Edit(id);
+                let result = self.open_shell(working_dir);
+                self.report_error(result);
+            }
+            Msg::RestoreSession => {
+                if let Some(session) = self.pending_session.take() {
+                    let count = session.tabs.len();
+                    self.restore_session(session);
+                    self.error_string = format!("Reopened {count} tabs");
+                }
+            }
+            Msg::DiscardSession => {
+                self.pending_session = None;
             }
             Msg::Tab {
                 id,
                 msg: TabMsg::Quit,
             } => {
-                self.tabs.remove(&id);
+                if let Some(TabType::Text(text_tab)) = self.tabs.remove(&id) {
+                    // Closing a tab discards its unsaved changes, so don't offer them back later.
+                    text_tab.remove_recovery();
+                    if let Some(path) = text_tab.hibernated {
+                        let _ = fs::remove_file(path);
+                    }
+                }
+                if self.tab_actions == Some(id) {
+                    self.tab_actions = None;
+                }
+                if self.tab.tab_id() == Some(id) {
+                    self.tab = Tab::Meta;
+                }
+            }
+            Msg::ClearSketch => {
+                if let Tab::Sketch { ink, .. } = &mut self.tab {
+                    ink.clear();
+                }
+            }
+            Msg::SaveSketch => {
+                if let Tab::Sketch { id, ink } = &self.tab {
+                    let id = *id;
+                    if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(&id) {
+                        let result = text_tab.save_sketch(ink);
+                        if self.report_error(result).is_some() {
+                            self.error_string.clear();
+                            self.tab = Tab::Edit(id);
+                        }
+                    }
+                }
+            }
+            Msg::PressTab { id, ink } => {
+                if ink_type::long_press(&ink) {
+                    self.tab_actions = if self.tab_actions == Some(id) {
+                        None
+                    } else {
+                        Some(id)
+                    };
+                }
+            }
+            Msg::Tab {
+                id,
+                msg: TabMsg::Stats,
+            } => {
+                if let Some(TabType::Text(text_tab)) = self.tabs.get(&id) {
+                    let stats = stats::Stats::new(&text_tab.text.buffer.content_string());
+                    let report = stats.report(&text_tab.title);
+                    let title = format!("stats: {}", text_tab.title);
+                    self.new_report_tab(title, &report);
+                }
+            }
+            Msg::Tab {
+                id,
+                msg: TabMsg::Read,
+            } => {
+                if let Some(TabType::Text(text_tab)) = self.tabs.get(&id) {
+                    let reader = reader::Reader::new(
+                        &text_tab.text.buffer.content_string(),
+                        SCREEN_WIDTH - self.left_margin() - self.right_margin(),
+                        SCREEN_HEIGHT - TOP_MARGIN * 2,
+                        text_tab.text.origin.0,
+                        text_tab.is_markdown(),
+                    );
+                    self.tab = Tab::Reader { id, reader };
+                }
+            }
+            Msg::TemplatesLoaded => {
+                if let Some(loading) = self.templates_loading.take() {
+                    match loading.try_recv() {
+                        Ok((result, dictionary)) => {
+                            if let Some(mut text_stuff) = self.report_error(result) {
+                                text_stuff.dictionary =
+                                    dictionary.and_then(|d| self.report_error(d));
+                                text_stuff.clipboard = self.text_stuff.clipboard.take();
+                                text_stuff.clip_history =
+                                    std::mem::take(&mut self.text_stuff.clip_history);
+                                self.text_stuff = text_stuff;
+                            }
+                        }
+                        Err(mpsc::TryRecvError::Empty) => {
+                            self.templates_loading = Some(loading);
+                        }
+                        Err(mpsc::TryRecvError::Disconnected) => {
+                            self.error_string = "Error: failed to load templates".to_string();
+                        }
+                    }
+                }
+            }
+            Msg::ReaderJump { id, row } => {
+                if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(&id) {
+                    text_tab.text.origin = (row, 0);
+                    self.tab = Tab::Edit(id);
+                }
+            }
+            Msg::Tab {
+                id,
+                msg: TabMsg::ReadOnly(false),
+            } if matches!(self.tabs.get(&id), Some(TabType::Text(t)) if t.lossy && t.read_only) => {
+                let action = format!("unlock {id}");
+                if !self.confirmed(&action) {
+                    let question = "Saving will write � over the bytes that aren't valid UTF-8: \
+                        tap unlock again to edit anyway"
+                        .to_string();
+                    self.ask(action, question);
+                } else if let Some(TabType::Text(text_tab)) = self.tabs.get_mut(&id) {
+                    text_tab.read_only = false;
+                    self.error_string.clear();
+                }
             }
             Msg::Tab { id, msg } => {
                 if let Some(tab) = self.tabs.get_mut(&id) {
                     match (msg, tab) {
-                        (TabMsg::ShellInput { stderr: _, content }, TabType::Shell(shell_tab)) => {
-                            // TODO: visual marker of stderr lines? do we care?
-                            let content_buffer = TextBuffer::from_string(&content);
-                            let content_size = content_buffer.end();
-                            shell_tab.shell_output.replace(Replace::splice(
-                                shell_tab.shell_output.frozen_until,
-                                content_buffer,
-                            ));
-                            shell_tab.shell_output.undos.clear();
-                            shell_tab.shell_output.frozen_until =
-                                add_coord(shell_tab.shell_output.frozen_until, content_size);
+                        (TabMsg::ShellInput { content }, TabType::Shell(shell_tab)) => {
+                            let at_end = shell_tab.end_on_screen();
+                            let output = &mut shell_tab.shell_output;
+                            let line_start = (output.frozen_until.0, 0);
+                            let last_line = output
+                                .buffer
+                                .copy(line_start, output.frozen_until)
+                                .content_string();
+                            let text = shell_tab.terminal.apply(&last_line, &content);
+                            output.rewrite_frozen(line_start, TextBuffer::from_string(&text));
+                            let memory = &self.config.memory;
+                            shell_tab.trim_output(
+                                memory.shell_output_kb * 1024,
+                                memory.shell_output_lines,
+                            );
 
                             // Right place for this?
                             shell_tab.set_title();
+                            shell_tab.busy = if shell_tab.terminal.marks_commands {
+                                shell_tab.terminal.running()
+                            } else {
+                                shell_tab.has_children()
+                            };
+                            if at_end {
+                                // Keep up with the output, unless the user's scrolled back.
+                                let end = shell_tab.shell_output.buffer.end();
+                                shell_tab.shell_output.scroll_into_view(end);
+                            }
+                            if !at_end || !matches!(self.tab, Tab::Edit(current) if current == id) {
+                                shell_tab.unseen_output = true;
+                            }
+                        }
+                        (TabMsg::Control(byte), TabType::Shell(shell_tab)) => {
+                            // The terminal turns these into signals for the running command, or
+                            // the end of its input, just as if they'd been typed.
+                            if let Err(e) = shell_tab.pty.writer().write_all(&[byte]) {
+                                self.error_string = e.to_string();
+                            }
                         }
                         (TabMsg::SubmitShell, TabType::Shell(shell_tab)) => {
-                            shell_tab.shell_output.replace(Replace::splice(
-                                shell_tab.shell_output.buffer.end(),
-                                TextBuffer::from_string("\n"),
-                            ));
-                            let buffer = shell_tab.shell_output.buffer.copy(
-                                shell_tab.shell_output.frozen_until,
-                                shell_tab.shell_output.buffer.end(),
-                            );
-                            let command = buffer.content_string();
-                            if let Some(stdin) = &mut shell_tab.child.stdin {
-                                if let Err(e) = stdin.write(command.as_bytes()) {
-                                    self.error_string = e.to_string();
-                                }
+                            let output = &mut shell_tab.shell_output;
+                            let (from, until) = (output.frozen_until, output.buffer.end());
+                            let command = output.buffer.copy(from, until).content_string() + "\n";
+                            // The terminal echoes the line back as output, so it's taken out of
+                            // the transcript here to show up there instead. If the program's
+                            // turned echo off, it's likely a password: it's masked, with no hint
+                            // of its length, and kept out of the history.
+                            let echoing = shell_tab.pty.echoing().unwrap_or(false);
+                            let shown = if echoing {
+                                ""
+                            } else {
+                                "••••••••\n"
+                            };
+                            output.replace(Replace {
+                                from,
+                                until,
+                                content: TextBuffer::from_string(shown),
+                            });
+                            if let Err(e) = shell_tab.pty.writer().write_all(command.as_bytes()) {
+                                self.error_string = e.to_string();
+                            }
+                            if !command.trim().is_empty() {
+                                shell_tab.terminal.command_sent();
                             }
-                            shell_tab.history.push_back(buffer);
+                            if echoing {
+                                shell_tab
+                                    .history
+                                    .push_back(TextBuffer::from_string(&command));
+                            }
+                            shell_tab.busy = true;
                             shell_tab.shell_output.frozen_until =
                                 shell_tab.shell_output.buffer.end();
                         }
-                        (TabMsg::SaveAs { path }, TabType::Text(text_tab)) => {
-                            if !path.exists() && path.parent().iter().any(|p| p.is_dir()) {
-                                text_tab.path = Some(path);
-                                // TODO: share this logic!
-                                text_tab.title = text_tab
-                                    .path
-                                    .as_ref()
-                                    .and_then(|p| p.file_name())
-                                    .map(|p| p.to_string_lossy().into_owned())
-                                    .unwrap_or("<unnamed file>".to_string());
-                                let saved = text_tab.save();
+                        (TabMsg::SaveAs { path }, TabType::Text(text_tab)) if !text_tab.help => {
+                            let needs_passphrase =
+                                Cipher::for_path(&path).is_some() && text_tab.passphrase.is_none();
+                            if needs_passphrase && !path.exists() {
+                                self.tab = Tab::Passphrase { path, id: Some(id) };
+                            } else if path.is_file() {
+                                let merge = text_tab.merge_with(id, path);
+                                if let Some(tab) = self.report_error(merge) {
+                                    self.tab = tab;
+                                }
+                            } else if !path.exists() && path.parent().iter().any(|p| p.is_dir()) {
+                                if self.config.highlight {
+                                    text_tab.text.language = Language::from_path(&path);
+                                }
+                                text_tab.set_path(path);
+                                let saved = text_tab.save(id, &self.saves);
+                                if self.report_error(saved).is_some() {
+                                    self.tab = Tab::Edit(id)
+                                };
+                            }
+                        }
+                        (TabMsg::Pick { index, pick }, TabType::Text(_)) => {
+                            if let Tab::Merge {
+                                id: merging, picks, ..
+                            } = &mut self.tab
+                            {
+                                match picks.get_mut(index) {
+                                    Some(picked) if *merging == id => *picked = pick,
+                                    _ => {}
+                                }
+                            }
+                        }
+                        (TabMsg::PickAll(pick), TabType::Text(_)) => {
+                            if let Tab::Merge {
+                                id: merging,
+                                picks,
+                                page,
+                                ..
+                            } = &mut self.tab
+                            {
+                                if *merging == id {
+                                    picks.fill(pick);
+                                    *page = 0;
+                                }
+                            }
+                        }
+                        (TabMsg::SaveMerged, TabType::Text(text_tab)) if !text_tab.help => {
+                            // The file's only put together now, however many picks it took.
+                            let merged = match &self.tab {
+                                Tab::Merge {
+                                    id: merging,
+                                    path,
+                                    chunks,
+                                    picks,
+                                    ..
+                                } if *merging == id => {
+                                    Some((path.clone(), merge::resolve(chunks, picks)))
+                                }
+                                _ => None,
+                            };
+                            if let Some((path, contents)) = merged {
+                                if contents != text_tab.text.buffer.content_string() {
+                                    let end = text_tab.text.buffer.end();
+                                    text_tab.text.replace(Replace {
+                                        from: (0, 0),
+                                        until: end,
+                                        content: TextBuffer::from_string(&contents),
+                                    });
+                                    text_tab.text.selection = Selection::Normal;
+                                    text_tab.touch();
+                                }
+                                if text_tab.path.as_ref() != Some(&path) {
+                                    if self.config.highlight {
+                                        text_tab.text.language = Language::from_path(&path);
+                                    }
+                                    text_tab.set_path(path);
+                                }
+                                // Whatever's on disk now has been merged in.
+                                text_tab.disk_mtime = None;
+                                let saved = text_tab.save(id, &self.saves);
                                 if self.report_error(saved).is_some() {
                                     self.tab = Tab::Edit(id)
                                 };
                             }
                         }
-                        (TabMsg::Undo, TabType::Text(text_tab)) => {
+                        (TabMsg::Review, TabType::Text(text_tab)) if text_tab.large.is_none() => {
+                            if let Some(path) = text_tab.path.clone() {
+                                let merge = text_tab.merge_with(id, path);
+                                if let Some(tab) = self.report_error(merge) {
+                                    self.tab = tab;
+                                }
+                            }
+                        }
+                        (TabMsg::Reload, TabType::Text(_)) => {
+                            let result = self.reload_tab(id);
+                            self.report_error(result);
+                        }
+                        (TabMsg::KeepMine, TabType::Text(text_tab)) => {
+                            // Saving will still offer to merge, rather than clobber the change.
+                            text_tab.kept_mtime = text_tab.path.as_deref().and_then(modified_time);
+                            text_tab.external_change = false;
+                            self.error_string.clear();
+                        }
+                        (TabMsg::ReadOnly(read_only), TabType::Text(text_tab))
+                            if text_tab.path.is_some() && !text_tab.indexing =>
+                        {
+                            text_tab.read_only = read_only;
+                        }
+                        (TabMsg::Undo, TabType::Text(text_tab)) if !text_tab.read_only => {
                             text_tab.text.undo();
-                            text_tab.dirty = true;
+                            text_tab.touch();
                         }
-                        (TabMsg::Redo, TabType::Text(text_tab)) => {
+                        (TabMsg::Redo, TabType::Text(text_tab)) if !text_tab.read_only => {
                             text_tab.text.redo();
-                            text_tab.dirty = true;
+                            text_tab.touch();
+                        }
+                        (TabMsg::Save, TabType::Text(text_tab)) => match &text_tab.path {
+                            // There's no merging a large file: it'd mean diffing the whole thing.
+                            Some(_) if text_tab.large.is_some() && text_tab.changed_on_disk() => {
+                                self.error_string =
+                                    "The file changed on disk: reload it, or save as a new file"
+                                        .to_string();
+                            }
+                            Some(path)
+                                if text_tab.changed_on_disk()
+                                    || self.config.review_saves
+                                        && path.is_file()
+                                        && text_tab.large.is_none() =>
+                            {
+                                let merge = text_tab.merge_with(id, path.clone());
+                                if let Some(tab) = self.report_error(merge) {
+                                    self.tab = tab;
+                                }
+                            }
+                            _ => {
+                                let result = text_tab.save(id, &self.saves);
+                                self.report_error(result);
+                            }
+                        },
+                        (
+                            TabMsg::Saved {
+                                edits,
+                                result,
+                                verified,
+                            },
+                            TabType::Text(text_tab),
+                        ) => {
+                            text_tab.saves_pending = text_tab.saves_pending.saturating_sub(1);
+                            text_tab.read_len = None;
+                            // If the user kept writing while the save was in flight, the buffer
+                            // is still dirty even though the write succeeded.
+                            if result.is_ok() {
+                                text_tab.disk_mtime =
+                                    text_tab.path.as_deref().and_then(modified_time);
+                            }
+                            if result.is_ok() && edits == text_tab.edits {
+                                text_tab.dirty = false;
+                                text_tab.remove_recovery();
+                            }
+                            // With the file saved, a fresh index of it can stand in for the
+                            // edits kept in memory.
+                            let reindex = match (&text_tab.large, &text_tab.path) {
+                                (Some(large), Some(path))
+                                    if result.is_ok()
+                                        && edits == text_tab.edits
+                                        && (large.edited() || large.path() != path) =>
+                                {
+                                    Some(path.clone())
+                                }
+                                _ => None,
+                            };
+                            let run = text_tab.run_after_save && result.is_ok();
+                            text_tab.run_after_save = false;
+                            let result = result.map_err(|e| {
+                                match text_tab.path.as_deref().and_then(Path::parent) {
+                                    Some(dir) if !dir.exists() => format!(
+                                        "{} is gone; was it unmounted? Save as to keep your changes",
+                                        dir.display()
+                                    ),
+                                    _ => e,
+                                }
+                            });
+                            if self.report_error(result).is_some() {
+                                if let Some(verified) = verified {
+                                    self.error_string = format!("Saved and read back: {verified}");
+                                }
+                            }
+                            if let Some(path) = reindex {
+                                self.index_large_file(id, path, edits);
+                            }
+                            if run {
+                                let result = self.run_script(id);
+                                self.report_error(result);
+                            }
+                        }
+                        (TabMsg::Indexed { edits, result }, TabType::Text(text_tab)) => {
+                            match result {
+                                // After a save, the new index only stands if nothing's been
+                                // written since; until then, the old one reads back just as well.
+                                Ok(large) if text_tab.indexing || edits == text_tab.edits => {
+                                    let lines = large.lines();
+                                    let opened = text_tab.large.is_none();
+                                    let result = text_tab.use_index(large);
+                                    if self.report_error(result).is_some() && opened {
+                                        self.error_string =
+                                            format!("Large file: {lines} lines, opened read-only");
+                                    }
+                                }
+                                Ok(_) => {}
+                                Err(e) => self.error_string = format!("Error: {e}"),
+                            }
                         }
-                        (TabMsg::Save, TabType::Text(text_tab)) => {
-                            let result = text_tab.save();
+                        (TabMsg::Autosaved { edits, result }, TabType::Text(text_tab)) => {
+                            if result.is_ok() && text_tab.dirty {
+                                text_tab.autosaved = edits;
+                            } else if result.is_ok() {
+                                // Saved properly while the autosave was in flight.
+                                text_tab.remove_recovery();
+                            }
                             self.report_error(result);
                         }
+                        (TabMsg::NextTemplateProfile, TabType::Text(text_tab)) => {
+                            // Cycles from following whatever's in use, through every profile.
+                            text_tab.template_profile = match &text_tab.template_profile {
+                                None => Some(String::new()),
+                                Some(profile) => {
+                                    let next =
+                                        next_profile(&self.config.template_profiles, profile);
+                                    (!next.is_empty()).then_some(next)
+                                }
+                            };
+                        }
+                        (TabMsg::Recover, TabType::Text(text_tab)) if !text_tab.read_only => {
+                            match text_tab.recovery_path().map(fs::read_to_string) {
+                                Some(Ok(contents)) => {
+                                    let end = text_tab.text.buffer.end();
+                                    text_tab.text.replace(Replace {
+                                        from: (0, 0),
+                                        until: end,
+                                        content: TextBuffer::from_string(&contents),
+                                    });
+                                    text_tab.text.selection = Selection::Normal;
+                                    text_tab.touch();
+                                    text_tab.autosaved = text_tab.edits;
+                                    self.error_string = "Recovered unsaved changes".to_string();
+                                }
+                                Some(Err(e)) => self.error_string = format!("Error: {e}"),
+                                None => {}
+                            }
+                            self.tab = Tab::Edit(id);
+                        }
+                        (TabMsg::Clean, TabType::Text(text_tab)) if !text_tab.read_only => {
+                            let text = text_tab.text.buffer.content_string();
+                            let (cleaned, cleanup) = cleanup::clean(&text, &self.config.cleanup);
+                            if cleanup.is_empty() {
+                                self.error_string = "Nothing to clean up".to_string();
+                            } else {
+                                let end = text_tab.text.buffer.end();
+                                text_tab.text.replace(Replace {
+                                    from: (0, 0),
+                                    until: end,
+                                    content: TextBuffer::from_string(&cleaned),
+                                });
+                                text_tab.text.selection = Selection::Normal;
+                                text_tab.touch();
+                                self.error_string = cleanup.summary();
+                            }
+                            self.tab = Tab::Edit(id);
+                        }
+                        (TabMsg::Format, TabType::Text(text_tab)) if !text_tab.read_only => {
+                            let formatter = formatter_for(&self.config, text_tab);
+                            if let (Some(path), Some(formatter)) =
+                                (text_tab.path.clone(), formatter)
+                            {
+                                let formatter = formatter.to_string();
+                                let text = text_tab.text.buffer.content_string();
+                                let edits = text_tab.edits;
+                                let sender = self.sender.clone();
+                                thread::spawn(move || {
+                                    let result = format::run(
+                                        &formatter,
+                                        &path,
+                                        &text,
+                                        Duration::from_secs(FORMAT_TIMEOUT_SECS),
+                                    )
+                                    .map_err(|e| e.to_string());
+                                    sender.send(Msg::Tab {
+                                        id,
+                                        msg: TabMsg::Formatted { edits, result },
+                                    });
+                                });
+                                self.error_string = "Formatting...".to_string();
+                            }
+                            self.tab = Tab::Edit(id);
+                        }
+                        (TabMsg::Formatted { edits, result }, TabType::Text(text_tab)) => {
+                            match result {
+                                Err(e) => self.error_string = format!("Error: {e}"),
+                                Ok(_) if edits != text_tab.edits || text_tab.read_only => {
+                                    self.error_string =
+                                        "Changed while formatting; try again".to_string();
+                                }
+                                Ok(formatted)
+                                    if formatted == text_tab.text.buffer.content_string() =>
+                                {
+                                    self.error_string = "Already formatted".to_string();
+                                }
+                                Ok(formatted) => {
+                                    // One replace for the whole buffer, so it's a single undo.
+                                    let end = text_tab.text.buffer.end();
+                                    text_tab.text.replace(Replace {
+                                        from: (0, 0),
+                                        until: end,
+                                        content: TextBuffer::from_string(&formatted),
+                                    });
+                                    text_tab.text.selection = Selection::Normal;
+                                    text_tab.touch();
+                                    self.error_string = "Formatted".to_string();
+                                }
+                            }
+                        }
+                        (TabMsg::Calculate, TabType::Text(text_tab)) if !text_tab.read_only => {
+                            match text_tab.calculate() {
+                                Ok(result) => {
+                                    text_tab.touch();
+                                    self.error_string = format!("= {result}");
+                                }
+                                Err(e) => self.error_string = format!("Error: {e}"),
+                            }
+                            self.tab = Tab::Edit(id);
+                        }
+                        (TabMsg::Follow(follow), TabType::Text(text_tab))
+                            if text_tab.path.is_some() =>
+                        {
+                            text_tab.follow = follow;
+                            text_tab.read_len = None;
+                            if follow {
+                                let result = text_tab.show_end();
+                                self.report_error(result);
+                                self.arm_follow_timer();
+                            }
+                            self.tab = Tab::Edit(id);
+                        }
+                        (TabMsg::Wrap(wrap), TabType::Text(text_tab)) => {
+                            text_tab.text.set_wrap(wrap);
+                            self.tab = Tab::Edit(id);
+                        }
+                        (TabMsg::Insert(insert), TabType::Text(text_tab)) => {
+                            text_tab.text.insert = insert;
+                            self.tab = Tab::Edit(id);
+                        }
+                        (TabMsg::Complete, TabType::Shell(shell_tab)) => {
+                            let result = shell_tab.complete();
+                            match self.report_error(result) {
+                                Some(candidates) if candidates.is_empty() => {
+                                    self.error_string = "No completions".to_string();
+                                }
+                                Some(candidates) if candidates.len() > 1 => {
+                                    self.tab = Tab::Completions { id, candidates };
+                                }
+                                _ => {}
+                            }
+                        }
+                        (TabMsg::UseCompletion(candidate), TabType::Shell(shell_tab)) => {
+                            shell_tab.replace_last_word(&candidate);
+                            self.tab = Tab::Edit(id);
+                        }
+                        (TabMsg::ToggleTask { row }, TabType::Text(text_tab))
+                            if !text_tab.read_only =>
+                        {
+                            let buffer = &text_tab.text.buffer;
+                            if let Some(line) = buffer.contents.get(row) {
+                                let line: String = line.iter().collect();
+                                if let Some(toggled) = tasks::toggle(&line) {
+                                    let until = buffer.clamp((row, usize::MAX));
+                                    text_tab.text.replace(Replace {
+                                        from: (row, 0),
+                                        until,
+                                        content: TextBuffer::from_string(&toggled),
+                                    });
+                                    text_tab.touch();
+                                }
+                            }
+                        }
+                        (TabMsg::Sort(options), TabType::Text(text_tab)) if !text_tab.read_only => {
+                            text_tab.sort_lines(options);
+                            text_tab.touch();
+                            self.tab = Tab::Edit(id);
+                        }
+                        (TabMsg::Run, TabType::Text(text_tab)) if text_tab.passphrase.is_none() => {
+                            if !text_tab.dirty {
+                                let result = self.run_script(id);
+                                self.report_error(result);
+                            } else if text_tab.changed_on_disk() {
+                                self.error_string =
+                                    "The file changed on disk; save it before running it"
+                                        .to_string();
+                            } else {
+                                match text_tab.save(id, &self.saves) {
+                                    Ok(()) => text_tab.run_after_save = true,
+                                    Err(e) => self.error_string = format!("Error: {e}"),
+                                }
+                            }
+                        }
+                        (TabMsg::Generate, TabType::Text(text_tab)) => {
+                            let result = text_tab.generate();
+                            if let Some(count) = self.report_error(result) {
+                                self.error_string = format!("Generated {count} variants");
+                            }
+                        }
+                        (TabMsg::InsertFile { path }, TabType::Text(text_tab))
+                            if !text_tab.read_only =>
+                        {
+                            let result = text_tab.insert_file(&path);
+                            if self.report_error(result).is_some() {
+                                self.tab = Tab::Edit(id);
+                            }
+                        }
+                        (TabMsg::AppendTo { path }, TabType::Text(text_tab)) => {
+                            let result = text_tab.append_selection_to(&path);
+                            if self.report_error(result).is_some() {
+                                self.error_string = format!("Appended to {}", path.display());
+                            }
+                        }
+                        (TabMsg::Export, TabType::Text(text_tab))
+                            if text_tab.passphrase.is_none() =>
+                        {
+                            let result = text_tab.export(&self.config.print);
+                            if let Some(path) = self.report_error(result) {
+                                self.error_string = format!("Exported to {}", path.display());
+                            }
+                        }
+                        (TabMsg::JournalEntry, TabType::Text(text_tab)) => {
+                            let (date, time) = local_now();
+                            text_tab.journal_entry(&date, &time);
+                            self.tab = Tab::Edit(id);
+                        }
+                        (TabMsg::JournalDay { later }, TabType::Text(text_tab)) => {
+                            self.tab = Tab::Edit(id);
+                            let (today, _) = local_now();
+                            let lines = text_tab.buffer_lines();
+                            let refs: Vec<&str> = lines.iter().map(|l| l.as_str()).collect();
+                            let date = text_tab.journal.clone().unwrap_or(today);
+                            match journal::adjacent(&refs, &date, later) {
+                                Some((day, row)) => {
+                                    text_tab.text.origin = (row, 0);
+                                    text_tab.journal = Some(day);
+                                }
+                                None => {
+                                    let which = if later { "later" } else { "earlier" };
+                                    self.error_string = format!("No {which} days in the journal");
+                                }
+                            }
+                        }
+                        (TabMsg::ExportPdf, TabType::Text(text_tab))
+                            if text_tab.passphrase.is_none() =>
+                        {
+                            let result = text_tab.export_pdf(&self.config.print);
+                            if let Some((path, missing)) = self.report_error(result) {
+                                self.error_string = if missing.is_empty() {
+                                    format!("Exported to {}", path.display())
+                                } else {
+                                    format!(
+                                        "Exported to {}, but the font has no {}",
+                                        path.display(),
+                                        missing.into_iter().collect::<String>()
+                                    )
+                                };
+                            }
+                        }
                         _ => {}
                     }
                 } else {
@@ -1092,30 +6793,136 @@ impl Applet for Editor {
                 self.tab = Tab::Edit(id);
                 match self.tabs.get_mut(&id).unwrap() {
                     TabType::Text(tab) => {
-                        tab.text.origin = (line, 0);
+                        let result = tab.show_line(line);
+                        self.report_error(result);
                     }
                     TabType::Shell(_) => {}
                 }
             }
         }
 
+        self.fit_tabs();
+        if let Tab::Rename = self.tab {
+            self.rename_plan = self.plan_rename().map_err(|e| e.to_string());
+        }
+        if matches!(self.tab, Tab::Meta) && !was_meta {
+            // The drives are listed there, so make sure they're current.
+            self.check_mounts();
+        }
+        if let Tab::Edit(id) = self.tab {
+            match self.tabs.get_mut(&id) {
+                Some(TabType::Text(text_tab)) => {
+                    let result = text_tab.follow_scroll();
+                    self.report_error(result);
+                }
+                Some(TabType::Shell(shell_tab)) if shell_tab.end_on_screen() => {
+                    shell_tab.unseen_output = false;
+                }
+                _ => {}
+            }
+            if let Some(TabType::Text(text_tab)) = self.tabs.get(&id) {
+                if let Some(profile) = text_tab.template_profile.clone() {
+                    self.use_template_profile(profile);
+                }
+            }
+        }
+        if let Some(left) = previous_tab.filter(|id| Some(*id) != self.tab.tab_id()) {
+            if self.config.save_on_switch {
+                self.checkpoint(left, false);
+            }
+        }
+        if previous_tab != self.tab.tab_id() {
+            self.check_disk_changes();
+        }
+        self.hibernate_idle_tabs();
+        if tabs_changed {
+            self.save_session();
+        } else if self.edits() != previous_edits && !self.session_timer {
+            // Unsaved changes live in the session, so it's saved again soon after any edit.
+            self.session_timer = true;
+            spawn_session_timer(self.sender.clone());
+        }
         None
     }
 
-    fn current_route(&self) -> &str {
-        match self.tab {
-            Tab::Meta => "meta",
-            Tab::Edit { .. } => "edit",
-            Tab::Template => "template",
-            Tab::Search { .. } => "search",
+    /// Something panicked: keep everything the user might lose, log what happened, and get back
+    /// to somewhere safe. If the main menu itself is what's broken, give up and exit.
+    fn recover_from_panic(&mut self, route: &str) {
+        let report = format!(
+            "{} panic in {route}: {}\n",
+            today(),
+            LAST_PANIC.lock().unwrap()
+        );
+        let log = BASE_DIRS.place_data_file(CRASH_LOG).and_then(|path| {
+            fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?
+                .write_all(report.as_bytes())
+        });
+        if let Err(e) = log {
+            eprintln!("Unable to write crash log: {e}");
+        }
+
+        // Named files get a recovery file alongside, as they would from an autosave; unnamed
+        // ones are kept in the session.
+        for tab in self.tabs.values() {
+            if let TabType::Text(text_tab) = tab {
+                if !text_tab.dirty || text_tab.hibernated.is_some() {
+                    continue;
+                }
+                if let Some(path) = text_tab.recovery_path() {
+                    if let Err(e) = fs::write(&path, text_tab.text.buffer.content_string()) {
+                        eprintln!("Unable to write {}: {e}", path.display());
+                    }
+                }
+            }
+        }
+        self.save_session();
+        if let Err(e) = self.save_templates() {
+            eprintln!("Unable to save templates: {e}");
+        }
+        if let Err(e) = self.save_event_log() {
+            eprintln!("Unable to write event log: {e}");
         }
+
+        if route == "meta" {
+            process::exit(1);
+        }
+        self.tab = Tab::Meta;
+        self.error_string = format!("Something went wrong in {route}; see {CRASH_LOG}");
     }
 }
 
 fn main() -> anyhow::Result<()> {
+    // For when Sill won't start: check the setup without touching the display.
+    if env::args().nth(1).as_deref() == Some("--check") {
+        let template_path = BASE_DIRS.place_data_file(TEMPLATE_FILE)?;
+        let config: Config = BASE_DIRS
+            .find_config_file(CONFIG_FILE)
+            .and_then(|path| toml::from_slice(&fs::read(path).ok()?).ok())
+            .unwrap_or_default();
+        let checks = diagnostics(&template_path, &config.starter_templates);
+        print!("{}", diagnostics::report(&checks));
+        let ok = checks.iter().all(|c| c.problem.is_none());
+        process::exit(if ok { 0 } else { 1 });
+    }
+
     let mut app = app::App::new();
 
+    // Keep the details of any panic around for the crash log, as well as printing them as usual.
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if let Ok(mut last) = LAST_PANIC.lock() {
+            *last = info.to_string();
+        }
+        default_hook(info);
+    }));
+
     let template_path = BASE_DIRS.place_data_file(TEMPLATE_FILE)?;
+    let session_path = BASE_DIRS.place_data_file(SESSION_FILE)?;
+    let reminders_path = BASE_DIRS.place_data_file(REMINDERS_FILE)?;
+    let labels_path = BASE_DIRS.place_data_file(LABELS_FILE)?;
 
     let config: Config = {
         let config_path = BASE_DIRS.place_config_file(CONFIG_FILE)?;
@@ -1152,29 +6959,101 @@ fn main() -> anyhow::Result<()> {
     ));
 
     let mut search_window = meta.path_window.clone();
-    search_window.buffer = TextBuffer::empty();
+    search_window.set_buffer(TextBuffer::empty());
+    let mut passphrase_window = search_window.clone();
+    passphrase_window.masked = true;
+
+    let recorder =
+        (config.record_secs > 0).then(|| Recorder::new(Duration::from_secs(config.record_secs)));
 
     let mut component = Component::with_sender(app.wakeup(), |sender| {
         let mut widget = Editor {
+            saves: spawn_save_worker(sender.clone(), config.verify_saves, config.backup_saves),
+            templates_loading: None,
             sender,
             template_path,
+            template_profile: String::new(),
             metrics: metrics.clone(),
-            config,
+            config: config.clone(),
             error_string: "".to_string(),
             atlas: atlas.clone(),
             tab: Tab::Meta,
+            tab_actions: None,
             template_offset: 0,
+            template_column: 0,
+            template_selected: None,
             text_stuff: TextStuff::new(),
             next_tab_id: 0,
             tabs: BTreeMap::new(),
+            recent_tabs: VecDeque::new(),
+            mounts: mounts::external(),
+            reminders: reminders::Reminders::default(),
+            reminders_path,
+            labels_path,
+            reminders_checked: String::new(),
+            show_page: false,
+            session_path,
+            pending_session: None,
+            recorder,
+            workspace: None,
+            stashed_tabs: BTreeMap::new(),
+            follow_timer: false,
+            session_timer: false,
+            confirm: None,
+            base_config: config,
+            tree: None,
+            rename_plan: Ok(vec![]),
             search_window,
+            passphrase_window,
             meta,
         };
 
-        let load_result = widget.load_templates();
-        widget.report_error(load_result);
+        widget.load_templates();
+
+        let reminders = reminders::Reminders::load(&widget.reminders_path);
+        if let Some(reminders) = widget.report_error(reminders) {
+            widget.reminders = reminders;
+        }
+        let labels = labels::Labels::load(&widget.labels_path);
+        if let Some(labels) = widget.report_error(labels) {
+            widget.meta.labels = labels;
+        }
+        // Anything already due shows in the main menu; only what comes due later is announced.
+        let (date, time) = local_now();
+        widget.reminders_checked = format!("{date} {time}");
+        spawn_reminder_timer(widget.sender.clone());
+        spawn_mount_timer(widget.sender.clone());
+
+        let template_path = widget.template_path.clone();
+        let starter_templates = widget.config.starter_templates.clone();
+        let sender = widget.sender.clone();
+        thread::spawn(move || {
+            sender.send(Msg::SelfTested(diagnostics(
+                &template_path,
+                &starter_templates,
+            )))
+        });
 
-        widget.new_text_tab(None, TextBuffer::from_string(HELP_TEXT));
+        if widget.config.autosave_secs > 0 {
+            let every = Duration::from_secs(widget.config.autosave_secs);
+            spawn_autosave_timer(widget.sender.clone(), every);
+        }
+
+        if widget.config.save_on_suspend {
+            let sender = widget.sender.clone();
+            let result = suspend::watch(move |signal| sender.send(Msg::Suspend { signal }));
+            widget.report_error(result);
+        }
+
+        widget.open_help();
+
+        let session = Session::load(&widget.session_path);
+        if let Some(session) = widget.report_error(session) {
+            if !session.tabs.is_empty() {
+                widget.pending_session = Some(session);
+                widget.tab = Tab::Meta;
+            }
+        }
 
         widget
     });
@@ -1186,7 +7065,7 @@ fn main() -> anyhow::Result<()> {
 
 #[cfg(test)]
 mod test {
-    use crate::Config;
+    use crate::{next_profile, tab_window, Config};
 
     #[test]
     fn test_default_config() {
@@ -1194,4 +7073,36 @@ mod test {
         let conf: Config = toml::from_str(&conf).expect("loading known_valid config");
         assert_eq!(conf, Config::default())
     }
+
+    #[test]
+    fn test_config_overrides() {
+        let (conf, _) = Config::default()
+            .with_workspace_overrides("highlight = false\n[cleanup]\nuse_tabs = true")
+            .unwrap();
+        assert!(!conf.highlight);
+        assert!(conf.cleanup.use_tabs);
+        assert_eq!(conf.cleanup.tab_width, Config::default().cleanup.tab_width);
+    }
+
+    #[test]
+    fn test_tab_window() {
+        let widths = [10; 6];
+        // Everything fits, so there's no need for markers.
+        assert_eq!(tab_window(&widths, 0, 60, 5), 0..6);
+        // The current tab stays in view, with room for a marker either side.
+        assert_eq!(tab_window(&widths, 5, 40, 5), 3..6);
+        assert_eq!(tab_window(&widths, 3, 40, 5), 2..5);
+        // Even if it doesn't fit at all.
+        assert_eq!(tab_window(&widths, 3, 5, 5), 3..4);
+    }
+
+    #[test]
+    fn test_next_profile() {
+        let profiles = vec!["math".to_string(), "greek".to_string()];
+        assert_eq!(next_profile(&profiles, ""), "math");
+        assert_eq!(next_profile(&profiles, "math"), "greek");
+        assert_eq!(next_profile(&profiles, "greek"), "");
+        assert_eq!(next_profile(&profiles, "gone"), "");
+        assert_eq!(next_profile(&[], ""), "");
+    }
 }
@@ -0,0 +1,220 @@
+/// What to do with a new line typed after a list item.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Continuation {
+    /// Start the new line with this marker, indentation included.
+    Marker(String),
+    /// The item was empty, so the list is over: drop the marker instead of continuing it.
+    End,
+}
+
+/// The list marker at the start of the line, if any, as `(indent, marker)`: eg. `("  ", "- ")`
+/// or `("", "12. ")`. Checkboxes are included in the marker.
+pub fn list_marker(line: &str) -> Option<(&str, &str)> {
+    let body = line.trim_start();
+    let indent = &line[..(line.len() - body.len())];
+    let marker_len = if body.starts_with("- ") || body.starts_with("* ") || body.starts_with("+ ") {
+        2
+    } else {
+        let digits = body.chars().take_while(|c| c.is_ascii_digit()).count();
+        let rest = &body[digits..];
+        if digits > 0 && (rest.starts_with(". ") || rest.starts_with(") ")) {
+            digits + 2
+        } else {
+            return None;
+        }
+    };
+    let rest = &body[marker_len..];
+    let checkbox = ["[ ] ", "[x] ", "[X] "]
+        .iter()
+        .find(|c| rest.starts_with(*c))
+        .map_or(0, |c| c.len());
+    Some((indent, &body[..(marker_len + checkbox)]))
+}
+
+/// How to continue a list when a new line is started after the given one.
+pub fn list_continuation(line: &str) -> Option<Continuation> {
+    let (indent, marker) = list_marker(line)?;
+    if line[(indent.len() + marker.len())..].trim().is_empty() {
+        return Some(Continuation::End);
+    }
+    let digits: String = marker.chars().take_while(|c| c.is_ascii_digit()).collect();
+    let next = if digits.is_empty() {
+        marker.to_string()
+    } else {
+        let n: u64 = digits.parse().ok()?;
+        format!("{}{}", n + 1, &marker[digits.len()..])
+    };
+    // New items start unchecked.
+    let next = next.replace("[x] ", "[ ] ").replace("[X] ", "[ ] ");
+    Some(Continuation::Marker(format!("{indent}{next}")))
+}
+
+/// The inline formatting in effect at some point in a line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Emphasis {
+    pub strong: bool,
+    pub em: bool,
+    pub code: bool,
+}
+
+/// Split a line into runs with the same formatting, dropping the `**`, `*`, `_`, and backtick
+/// markers. `state` carries formatting over from the previous line of the paragraph.
+pub fn inline_spans(line: &str, state: &mut Emphasis) -> Vec<(Emphasis, String)> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut spans = vec![];
+    let mut current = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let len = if !state.code && chars[i..].starts_with(&['*', '*']) {
+            2
+        } else {
+            1
+        };
+        let prev = i.checked_sub(1).map(|j| chars[j]);
+        let next = chars.get(i + len).copied();
+        let is_on = |on: bool| {
+            // Markers open before a word and close after one, so `2 * 3` and snake_case are left
+            // as they are.
+            let fits = if on {
+                prev.is_some_and(|p| !p.is_whitespace())
+            } else {
+                next.is_some_and(|n| !n.is_whitespace())
+            };
+            let intra_word = c == '_'
+                && prev.is_some_and(char::is_alphanumeric)
+                && next.is_some_and(char::is_alphanumeric);
+            fits && !intra_word
+        };
+        let before = *state;
+        let flag = match c {
+            '`' => Some(&mut state.code),
+            _ if state.code => None,
+            '*' if len == 2 => Some(&mut state.strong),
+            '*' | '_' => Some(&mut state.em),
+            _ => None,
+        };
+        match flag {
+            Some(flag) if c == '`' || is_on(*flag) => {
+                *flag = !*flag;
+                if !current.is_empty() {
+                    spans.push((before, std::mem::take(&mut current)));
+                }
+            }
+            _ => current.extend(&chars[i..i + len]),
+        }
+        i += len;
+    }
+    if !current.is_empty() {
+        spans.push((*state, current));
+    }
+    spans
+}
+
+/// A heading in a markdown document.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Heading {
+    pub row: usize,
+    pub level: usize,
+    pub title: String,
+}
+
+/// Find the `#` headings in a document, skipping anything in a fenced code block.
+pub fn headings<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<Heading> {
+    let mut in_fence = false;
+    let mut headings = vec![];
+    for (row, line) in lines.into_iter().enumerate() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        let level = line.chars().take_while(|c| *c == '#').count();
+        if in_fence || level == 0 || !line[level..].starts_with(' ') {
+            continue;
+        }
+        headings.push(Heading {
+            row,
+            level,
+            title: line[level..].trim().to_string(),
+        });
+    }
+    headings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_continuation() {
+        let marker = |s: &str| Some(Continuation::Marker(s.to_string()));
+        assert_eq!(list_continuation("- milk"), marker("- "));
+        assert_eq!(list_continuation("  * eggs"), marker("  * "));
+        assert_eq!(list_continuation("9. nine"), marker("10. "));
+        assert_eq!(list_continuation("3) three"), marker("4) "));
+        assert_eq!(list_continuation("- [x] done"), marker("- [ ] "));
+        assert_eq!(list_continuation("- "), Some(Continuation::End));
+        assert_eq!(list_continuation("2. [ ] "), Some(Continuation::End));
+        assert_eq!(list_continuation("plain text"), None);
+        assert_eq!(list_continuation("-not a list"), None);
+    }
+
+    #[test]
+    fn test_headings() {
+        let text = "# Sill\nintro\n```\n# not a heading\n```\n## Using it\n#hashtag";
+        assert_eq!(
+            headings(text.lines()),
+            vec![
+                Heading {
+                    row: 0,
+                    level: 1,
+                    title: "Sill".to_string()
+                },
+                Heading {
+                    row: 5,
+                    level: 2,
+                    title: "Using it".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inline_spans() {
+        let plain = Emphasis::default();
+        let strong = Emphasis {
+            strong: true,
+            ..plain
+        };
+        let em = Emphasis { em: true, ..plain };
+        let code = Emphasis {
+            code: true,
+            ..plain
+        };
+        let mut state = plain;
+        assert_eq!(
+            inline_spans("a **bold** and _em_ `x*y`", &mut state),
+            vec![
+                (plain, "a ".to_string()),
+                (strong, "bold".to_string()),
+                (plain, " and ".to_string()),
+                (em, "em".to_string()),
+                (plain, " ".to_string()),
+                (code, "x*y".to_string()),
+            ]
+        );
+        assert_eq!(
+            inline_spans("2 * 3 in snake_case", &mut state),
+            vec![(plain, "2 * 3 in snake_case".to_string())]
+        );
+        // Carried over from one line to the next.
+        assert_eq!(
+            inline_spans("*two", &mut state),
+            vec![(em, "two".to_string())]
+        );
+        assert_eq!(
+            inline_spans("lines* done", &mut state),
+            vec![(em, "lines".to_string()), (plain, " done".to_string())]
+        );
+    }
+}
@@ -0,0 +1,156 @@
+/// Past this many cells in the comparison table, the changed middle of the files is treated as
+/// one big difference instead of being compared line by line.
+const MAX_TABLE: usize = 1 << 22;
+
+/// A run of lines that's either the same in both versions of a file, or differs between them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Chunk {
+    Same(Vec<String>),
+    Differ {
+        disk: Vec<String>,
+        buffer: Vec<String>,
+    },
+}
+
+/// Which side of a difference to keep when merging.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pick {
+    Disk,
+    Buffer,
+    Both,
+}
+
+fn push_same(chunks: &mut Vec<Chunk>, line: &str) {
+    match chunks.last_mut() {
+        Some(Chunk::Same(lines)) => lines.push(line.to_string()),
+        _ => chunks.push(Chunk::Same(vec![line.to_string()])),
+    }
+}
+
+fn push_differ(chunks: &mut Vec<Chunk>, from_disk: Option<&str>, from_buffer: Option<&str>) {
+    if !matches!(chunks.last(), Some(Chunk::Differ { .. })) {
+        chunks.push(Chunk::Differ {
+            disk: vec![],
+            buffer: vec![],
+        });
+    }
+    if let Some(Chunk::Differ { disk, buffer }) = chunks.last_mut() {
+        disk.extend(from_disk.map(str::to_string));
+        buffer.extend(from_buffer.map(str::to_string));
+    }
+}
+
+/// Compare the file on disk with the buffer, line by line.
+pub fn diff(disk: &str, buffer: &str) -> Vec<Chunk> {
+    let a: Vec<&str> = disk.split('\n').collect();
+    let b: Vec<&str> = buffer.split('\n').collect();
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (middle_a, middle_b) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut chunks = vec![];
+    for line in &a[..prefix] {
+        push_same(&mut chunks, line);
+    }
+    if (middle_a.len() + 1) * (middle_b.len() + 1) > MAX_TABLE {
+        for line in middle_a {
+            push_differ(&mut chunks, Some(line), None);
+        }
+        for line in middle_b {
+            push_differ(&mut chunks, None, Some(line));
+        }
+    } else {
+        // The classic longest-common-subsequence table, walked forwards.
+        let (n, m) = (middle_a.len(), middle_b.len());
+        let mut table = vec![0usize; (n + 1) * (m + 1)];
+        let at = |i: usize, j: usize| i * (m + 1) + j;
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                table[at(i, j)] = if middle_a[i] == middle_b[j] {
+                    table[at(i + 1, j + 1)] + 1
+                } else {
+                    table[at(i + 1, j)].max(table[at(i, j + 1)])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && middle_a[i] == middle_b[j] {
+                push_same(&mut chunks, middle_a[i]);
+                i += 1;
+                j += 1;
+            } else if j == m || (i < n && table[at(i + 1, j)] >= table[at(i, j + 1)]) {
+                push_differ(&mut chunks, Some(middle_a[i]), None);
+                i += 1;
+            } else {
+                push_differ(&mut chunks, None, Some(middle_b[j]));
+                j += 1;
+            }
+        }
+    }
+    for line in &a[a.len() - suffix..] {
+        push_same(&mut chunks, line);
+    }
+    chunks
+}
+
+/// Put the file back together, with one pick for each chunk that differs.
+pub fn resolve(chunks: &[Chunk], picks: &[Pick]) -> String {
+    let mut picks = picks.iter();
+    let mut lines: Vec<&str> = vec![];
+    for chunk in chunks {
+        match chunk {
+            Chunk::Same(same) => lines.extend(same.iter().map(|l| l.as_str())),
+            Chunk::Differ { disk, buffer } => {
+                let pick = picks.next().copied().unwrap_or(Pick::Buffer);
+                if pick != Pick::Buffer {
+                    lines.extend(disk.iter().map(|l| l.as_str()));
+                }
+                if pick != Pick::Disk {
+                    lines.extend(buffer.iter().map(|l| l.as_str()));
+                }
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let disk = "title\nmilk\neggs\nbread\nend";
+        let buffer = "title\nmilk\ncheese\nbread\nend\nextra";
+        let chunks = diff(disk, buffer);
+        let lines = |ls: &[&str]| ls.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            chunks,
+            vec![
+                Chunk::Same(lines(&["title", "milk"])),
+                Chunk::Differ {
+                    disk: lines(&["eggs"]),
+                    buffer: lines(&["cheese"])
+                },
+                Chunk::Same(lines(&["bread", "end"])),
+                Chunk::Differ {
+                    disk: lines(&[]),
+                    buffer: lines(&["extra"])
+                },
+            ]
+        );
+        assert_eq!(resolve(&chunks, &[Pick::Buffer, Pick::Buffer]), buffer);
+        assert_eq!(resolve(&chunks, &[Pick::Disk, Pick::Disk]), disk);
+        assert_eq!(
+            resolve(&chunks, &[Pick::Both, Pick::Disk]),
+            "title\nmilk\neggs\ncheese\nbread\nend"
+        );
+        assert_eq!(diff("same", "same"), vec![Chunk::Same(lines(&["same"]))]);
+    }
+}
@@ -0,0 +1,112 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Filesystems that live on another machine.
+const NETWORK_TYPES: [&str; 6] = ["nfs", "nfs4", "cifs", "smb3", "sshfs", "fuse.sshfs"];
+
+/// A filesystem that isn't part of the device itself: a USB stick or a network share.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Mount {
+    pub dir: PathBuf,
+    pub kind: String,
+}
+
+impl Mount {
+    /// A short name for a button: the last part of the mount point.
+    pub fn name(&self) -> String {
+        self.dir
+            .file_name()
+            .map_or("/".to_string(), |n| n.to_string_lossy().into_owned())
+    }
+}
+
+/// Pick the external filesystems out of a mount table, in the format of `/proc/mounts`. Storage
+/// on USB shows up as a SCSI disk, since the device's own storage is on MMC.
+pub fn parse(table: &str) -> Vec<Mount> {
+    table
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (device, dir, kind) = (fields.next()?, fields.next()?, fields.next()?);
+            let external = device.starts_with("/dev/sd") || NETWORK_TYPES.contains(&kind);
+            external.then(|| Mount {
+                dir: PathBuf::from(unescape(dir)),
+                kind: kind.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The mount table escapes spaces and the like as three octal digits, like `\040`.
+fn unescape(field: &str) -> String {
+    let mut result = String::new();
+    let mut rest = field;
+    while let Some(i) = rest.find('\\') {
+        result.push_str(&rest[..i]);
+        let code = rest
+            .get(i + 1..i + 4)
+            .and_then(|o| u8::from_str_radix(o, 8).ok());
+        match code {
+            Some(code) => {
+                result.push(code as char);
+                rest = &rest[i + 4..];
+            }
+            None => {
+                result.push('\\');
+                rest = &rest[i + 1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// The external filesystems mounted right now.
+pub fn external() -> Vec<Mount> {
+    fs::read_to_string("/proc/mounts")
+        .map(|table| parse(&table))
+        .unwrap_or_default()
+}
+
+/// The mount that a path is on, if it's on one of these.
+pub fn containing<'a>(mounts: &'a [Mount], path: &Path) -> Option<&'a Mount> {
+    mounts
+        .iter()
+        .filter(|m| path.starts_with(&m.dir))
+        .max_by_key(|m| m.dir.as_os_str().len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let table = "\
+/dev/root / ext4 ro,relatime 0 0
+/dev/mmcblk2p4 /home ext4 rw,relatime 0 0
+/dev/sda1 /media/USB\\040STICK vfat rw,relatime 0 0
+nas:/export/notes /mnt/notes nfs4 rw,relatime 0 0
+proc /proc proc rw 0 0
+";
+        let mounts = parse(table);
+        assert_eq!(
+            mounts,
+            vec![
+                Mount {
+                    dir: PathBuf::from("/media/USB STICK"),
+                    kind: "vfat".to_string(),
+                },
+                Mount {
+                    dir: PathBuf::from("/mnt/notes"),
+                    kind: "nfs4".to_string(),
+                },
+            ]
+        );
+        assert_eq!(mounts[0].name(), "USB STICK");
+        let on = |path: &str| containing(&mounts, Path::new(path)).map(|m| m.name());
+        assert_eq!(on("/mnt/notes/todo.md"), Some("notes".to_string()));
+        assert_eq!(on("/mnt/notesy"), None);
+        assert_eq!(on("/home/root/todo.md"), None);
+    }
+}
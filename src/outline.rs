@@ -0,0 +1,137 @@
+use crate::highlight::Language;
+use crate::markdown;
+use crate::markdown::Heading;
+
+/// Definitions that start a line of Rust, after any visibility and qualifiers.
+const RUST_ITEMS: &[&str] = &[
+    "fn ", "struct ", "enum ", "trait ", "impl ", "impl<", "mod ",
+];
+const RUST_QUALIFIERS: &[&str] = &["async ", "const ", "unsafe ", "extern \"C\" "];
+
+/// The outline of a document: its headings, or the definitions in a source file, each with a
+/// level to indent it by. Each language picks out its own; plain text has none.
+pub fn outline(language: Language, lines: &[&str]) -> Vec<Heading> {
+    match language {
+        Language::Markdown => markdown::headings(lines.iter().copied()),
+        Language::Rust => code(lines, rust_item),
+        Language::Python => code(lines, python_item),
+        Language::Shell => code(lines, shell_item),
+    }
+}
+
+/// Definitions in source code are nested by how far they're indented.
+fn code(lines: &[&str], item: fn(&str) -> Option<String>) -> Vec<Heading> {
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(row, line)| {
+            let trimmed = line.trim_start();
+            let indent: usize = line[..line.len() - trimmed.len()]
+                .chars()
+                .map(|c| if c == '\t' { 4 } else { 1 })
+                .sum();
+            Some(Heading {
+                row,
+                level: indent / 4 + 1,
+                title: item(trimmed)?,
+            })
+        })
+        .collect()
+}
+
+/// Cut a definition off before its arguments or body, leaving the name and what kind it is.
+fn signature(line: &str, stops: &[char]) -> String {
+    line.split(stops).next().unwrap_or(line).trim().to_string()
+}
+
+fn rust_item(line: &str) -> Option<String> {
+    let mut rest = line;
+    if let Some(after) = rest.strip_prefix("pub") {
+        // Skip over `pub(crate)` and the like.
+        rest = after.split_once(' ')?.1;
+    }
+    while let Some(after) = RUST_QUALIFIERS.iter().find_map(|q| rest.strip_prefix(q)) {
+        rest = after;
+    }
+    if !RUST_ITEMS.iter().any(|item| rest.starts_with(item)) {
+        return None;
+    }
+    if rest.starts_with("fn ") {
+        Some(signature(rest, &['(', '<']))
+    } else {
+        Some(signature(rest, &['{', ';', '(']))
+    }
+}
+
+fn python_item(line: &str) -> Option<String> {
+    let rest = line.strip_prefix("async ").unwrap_or(line);
+    if rest.starts_with("def ") || rest.starts_with("class ") {
+        Some(signature(rest, &['(', ':']))
+    } else {
+        None
+    }
+}
+
+fn shell_item(line: &str) -> Option<String> {
+    if let Some(name) = line.strip_prefix("function ") {
+        return Some(signature(name, &['(', '{', ' ']));
+    }
+    let (name, rest) = line.split_once('(')?;
+    let name = name.trim_end();
+    let is_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    (is_name && rest.trim_start().starts_with(')')).then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn titles(language: Language, text: &str) -> Vec<(usize, usize, String)> {
+        let lines: Vec<&str> = text.lines().collect();
+        outline(language, &lines)
+            .into_iter()
+            .map(|h| (h.row, h.level, h.title))
+            .collect()
+    }
+
+    #[test]
+    fn test_outline() {
+        let rust = "\
+use std::fs;
+pub(crate) struct Tab {
+impl<T> Tab<T> {
+    pub async fn load(path: &Path) -> Tab {
+        let fn_name = 1;
+    }
+}
+const fn size() {}
+";
+        assert_eq!(
+            titles(Language::Rust, rust),
+            vec![
+                (1, 1, "struct Tab".to_string()),
+                (2, 1, "impl<T> Tab<T>".to_string()),
+                (3, 2, "fn load".to_string()),
+                (7, 1, "fn size".to_string()),
+            ]
+        );
+
+        let python = "class Tab(Base):\n    def load(self):\n        pass\n";
+        assert_eq!(
+            titles(Language::Python, python),
+            vec![
+                (0, 1, "class Tab".to_string()),
+                (1, 2, "def load".to_string()),
+            ]
+        );
+
+        let shell = "backup() {\n  cp a b\n}\nfunction restore {\necho (hi)\n";
+        assert_eq!(
+            titles(Language::Shell, shell),
+            vec![(0, 1, "backup".to_string()), (3, 1, "restore".to_string())]
+        );
+    }
+}
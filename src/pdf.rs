@@ -0,0 +1,367 @@
+use crate::font::{FONT, FONT_BYTES};
+use anyhow::bail;
+use rusttype::Scale;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs::File;
+use std::path::Path;
+
+/// PDF measures glyphs in thousandths of the font size.
+const GLYPH_UNITS: f32 = 1000.0;
+
+/// How wide each character is, as a fraction of the font size.
+pub fn char_width() -> f32 {
+    let advance = FONT.glyph(' ').scaled(Scale::uniform(GLYPH_UNITS));
+    advance.h_metrics().advance_width / GLYPH_UNITS
+}
+
+/// A bitmap to draw on the pages, kept deflated and filtered just as PNG does it, since that's
+/// a format PDF readers can decode too.
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    gray: bool,
+    data: Vec<u8>,
+}
+
+impl Image {
+    /// Read a PNG of any sort, dropping any transparency onto a white background.
+    pub fn read_png(path: &Path) -> anyhow::Result<Image> {
+        let mut decoder = png::Decoder::new(File::open(path)?);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        let over_white = |value: u8, alpha: u8| {
+            let (value, alpha) = (value as u32, alpha as u32);
+            ((value * alpha + 255 * (255 - alpha)) / 255) as u8
+        };
+        let (gray, samples): (bool, Vec<u8>) = match info.color_type {
+            png::ColorType::Grayscale => (true, buffer[..info.buffer_size()].to_vec()),
+            png::ColorType::Rgb => (false, buffer[..info.buffer_size()].to_vec()),
+            png::ColorType::GrayscaleAlpha => (
+                true,
+                buffer[..info.buffer_size()]
+                    .chunks(2)
+                    .map(|px| over_white(px[0], px[1]))
+                    .collect(),
+            ),
+            png::ColorType::Rgba => (
+                false,
+                buffer[..info.buffer_size()]
+                    .chunks(4)
+                    .flat_map(|px| [0, 1, 2].map(|i| over_white(px[i], px[3])))
+                    .collect(),
+            ),
+            other => bail!("unexpected PNG color type {other:?}"),
+        };
+
+        // Re-encode it, so the samples come out deflated, and keep only the compressed data.
+        let mut encoded = vec![];
+        let mut encoder = png::Encoder::new(&mut encoded, info.width, info.height);
+        encoder.set_color(if gray {
+            png::ColorType::Grayscale
+        } else {
+            png::ColorType::Rgb
+        });
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&samples)?;
+        let mut data = vec![];
+        let mut chunks = &encoded[8..];
+        while chunks.len() >= 12 {
+            let len = u32::from_be_bytes([chunks[0], chunks[1], chunks[2], chunks[3]]) as usize;
+            if &chunks[4..8] == b"IDAT" {
+                data.extend_from_slice(&chunks[8..8 + len]);
+            }
+            chunks = &chunks[12 + len..];
+        }
+        Ok(Image {
+            width: info.width,
+            height: info.height,
+            gray,
+            data,
+        })
+    }
+}
+
+/// A page of a PDF, built up as a content stream. Coordinates are in points from the top-left
+/// corner, like everything else in the app; they're flipped when the page is drawn.
+pub struct Page {
+    height: f32,
+    ops: String,
+    /// The images drawn on this page, by their index in the document.
+    images: Vec<usize>,
+    /// The glyphs drawn on this page, and the characters they stand for.
+    glyphs: BTreeMap<u16, char>,
+    /// Characters the font has no glyph for.
+    missing: Vec<char>,
+}
+
+impl Page {
+    /// Draw a line of text in the monospace font, with its baseline at `y`. Characters the
+    /// font doesn't have are drawn as an empty box, and noted in `Pdf::missing`.
+    pub fn text(&mut self, x: f32, y: f32, size: f32, text: &str) {
+        // The font is embedded with each glyph's id as its code, so any glyph can be shown.
+        let mut glyphs = String::with_capacity(text.len() * 4);
+        for c in text.chars() {
+            let c = if c == '\t' { ' ' } else { c };
+            let id = FONT.glyph(c).id().0 as u16;
+            if id == 0 {
+                self.missing.push(c);
+            } else {
+                self.glyphs.entry(id).or_insert(c);
+            }
+            let _ = write!(glyphs, "{id:04X}");
+        }
+        let _ = writeln!(
+            self.ops,
+            "BT /F1 {size:.2} Tf {x:.2} {:.2} Td <{glyphs}> Tj ET",
+            self.height - y
+        );
+    }
+
+    /// Draw a stroke through the given points, with round ends and joins.
+    pub fn polyline(&mut self, points: &[(f32, f32)], width: f32) {
+        let mut points = points.iter();
+        let (x, y) = match points.next() {
+            Some(first) => first,
+            None => return,
+        };
+        let _ = write!(
+            self.ops,
+            "{width:.2} w 1 J 1 j {x:.2} {:.2} m",
+            self.height - y
+        );
+        for (x, y) in points {
+            let _ = write!(self.ops, " {x:.2} {:.2} l", self.height - y);
+        }
+        self.ops.push_str(" S\n");
+    }
+
+    /// Draw an image added with `Pdf::add_image`, stretched to fill the given box.
+    pub fn image(&mut self, image: usize, x: f32, y: f32, width: f32, height: f32) {
+        let _ = writeln!(
+            self.ops,
+            "q {width:.2} 0 0 {height:.2} {x:.2} {:.2} cm /Im{image} Do Q",
+            self.height - y - height
+        );
+        if !self.images.contains(&image) {
+            self.images.push(image);
+        }
+    }
+}
+
+/// A minimal PDF writer: pages of monospace text, line drawings and images, and nothing else.
+pub struct Pdf {
+    width: f32,
+    height: f32,
+    images: Vec<Image>,
+    pages: Vec<Page>,
+}
+
+/// A stream object, with its length added to the dictionary.
+fn stream(dict: &str, data: &[u8]) -> Vec<u8> {
+    let mut object = format!("<<{dict} /Length {} >>\nstream\n", data.len()).into_bytes();
+    object.extend_from_slice(data);
+    object.extend_from_slice(b"\nendstream");
+    object
+}
+
+impl Pdf {
+    /// A new document, with pages of the given size in points.
+    pub fn new(width: f32, height: f32) -> Pdf {
+        Pdf {
+            width,
+            height,
+            images: vec![],
+            pages: vec![],
+        }
+    }
+
+    /// Add an image to the document, returning the index to draw it with.
+    pub fn add_image(&mut self, image: Image) -> usize {
+        self.images.push(image);
+        self.images.len() - 1
+    }
+
+    /// Start a new page at the end of the document and return it.
+    pub fn add_page(&mut self) -> &mut Page {
+        self.pages.push(Page {
+            height: self.height,
+            ops: String::new(),
+            images: vec![],
+            glyphs: BTreeMap::new(),
+            missing: vec![],
+        });
+        self.pages.last_mut().unwrap()
+    }
+
+    /// The characters drawn that the font has no glyph for, each once, in order.
+    pub fn missing(&self) -> Vec<char> {
+        let mut missing = vec![];
+        for &c in self.pages.iter().flat_map(|page| &page.missing) {
+            if !missing.contains(&c) {
+                missing.push(c);
+            }
+        }
+        missing
+    }
+
+    /// A map from the glyphs drawn back to their characters, so text can be copied out.
+    fn to_unicode(&self) -> String {
+        let glyphs: BTreeMap<u16, char> = self
+            .pages
+            .iter()
+            .flat_map(|page| &page.glyphs)
+            .map(|(&id, &c)| (id, c))
+            .collect();
+        let mut cmap = String::from(
+            "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+             /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+             1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+        );
+        let glyphs: Vec<_> = glyphs.into_iter().collect();
+        // Readers only allow 100 mappings per block.
+        for block in glyphs.chunks(100) {
+            let _ = writeln!(cmap, "{} beginbfchar", block.len());
+            for (id, c) in block {
+                let utf16: String = c
+                    .encode_utf16(&mut [0; 2])
+                    .iter()
+                    .map(|unit| format!("{unit:04X}"))
+                    .collect();
+                let _ = writeln!(cmap, "<{id:04X}> <{utf16}>");
+            }
+            cmap.push_str("endbfchar\n");
+        }
+        cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+        cmap
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Objects 1-2 are the catalog and the page tree; 3-7 are the font, its glyphs'
+        // metrics, its descriptor, the font file, and the map back to text; then the images;
+        // then a page and its contents for each page.
+        let image_id = |i: usize| 8 + i;
+        let page_id = |i: usize| 8 + self.images.len() + i * 2;
+        let scale = Scale::uniform(GLYPH_UNITS);
+        let advance = char_width() * GLYPH_UNITS;
+        let v_metrics = FONT.v_metrics(scale);
+        let (ascent, descent) = (v_metrics.ascent, v_metrics.descent);
+        let mut objects: Vec<Vec<u8>> = vec![
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            format!(
+                "<< /Type /Pages /Count {} /Kids [{}] >>",
+                self.pages.len(),
+                (0..self.pages.len())
+                    .map(|i| format!("{} 0 R", page_id(i)))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )
+            .into_bytes(),
+            b"<< /Type /Font /Subtype /Type0 /BaseFont /Inconsolata /Encoding /Identity-H /DescendantFonts [4 0 R] /ToUnicode 7 0 R >>"
+                .to_vec(),
+            format!(
+                "<< /Type /Font /Subtype /CIDFontType2 /BaseFont /Inconsolata /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> /FontDescriptor 5 0 R /DW {advance:.0} /CIDToGIDMap /Identity >>"
+            )
+            .into_bytes(),
+            format!(
+                "<< /Type /FontDescriptor /FontName /Inconsolata /Flags 33 /FontBBox [0 {descent:.0} {advance:.0} {ascent:.0}] /ItalicAngle 0 /Ascent {ascent:.0} /Descent {descent:.0} /CapHeight {ascent:.0} /StemV 80 /FontFile2 6 0 R >>"
+            )
+            .into_bytes(),
+            stream(&format!(" /Length1 {}", FONT_BYTES.len()), FONT_BYTES),
+            stream("", self.to_unicode().as_bytes()),
+        ];
+        for image in &self.images {
+            let (space, colors) = if image.gray {
+                ("DeviceGray", 1)
+            } else {
+                ("DeviceRGB", 3)
+            };
+            objects.push(stream(
+                &format!(
+                    " /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /{space} /BitsPerComponent 8 /Filter /FlateDecode /DecodeParms << /Predictor 15 /Colors {colors} /BitsPerComponent 8 /Columns {} >>",
+                    image.width, image.height, image.width
+                ),
+                &image.data,
+            ));
+        }
+        for (i, page) in self.pages.iter().enumerate() {
+            let images: String = page
+                .images
+                .iter()
+                .map(|&image| format!(" /Im{image} {} 0 R", image_id(image)))
+                .collect();
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.0} {:.0}] /Resources << /Font << /F1 3 0 R >> /XObject <<{images} >> >> /Contents {} 0 R >>",
+                    self.width,
+                    self.height,
+                    page_id(i) + 1
+                )
+                .into_bytes(),
+            );
+            objects.push(stream("", page.ops.as_bytes()));
+        }
+
+        let mut out = b"%PDF-1.4\n".to_vec();
+        let mut offsets = vec![];
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            out.extend_from_slice(object);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = out.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(table, "{offset:010} 00000 n ");
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        );
+        out.extend_from_slice(table.as_bytes());
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pdf() {
+        let mut pdf = Pdf::new(100.0, 200.0);
+        let page = pdf.add_page();
+        page.text(10.0, 20.0, 10.0, "hé ☃");
+        page.polyline(&[(0.0, 0.0), (10.0, 50.0)], 1.0);
+        assert_eq!(pdf.missing(), vec!['☃']);
+        let bytes = pdf.to_bytes();
+        // The embedded font isn't text, but everything else is.
+        let text = String::from_utf8_lossy(&bytes);
+        let id = |c: char| FONT.glyph(c).id().0 as u16;
+        let glyphs = format!("{:04X}{:04X}{:04X}0000", id('h'), id('é'), id(' '));
+        assert!(text.contains(&format!(
+            "BT /F1 10.00 Tf 10.00 180.00 Td <{glyphs}> Tj ET\n"
+        )));
+        assert!(text.contains(&format!("<{:04X}> <00E9>\n", id('é'))));
+        assert!(text.contains("1.00 w 1 J 1 j 0.00 200.00 m 10.00 150.00 l S\n"));
+
+        // The xref table should point at the start of each object.
+        let find = |needle: &[u8]| bytes.windows(needle.len()).position(|w| w == needle);
+        let xref = find(b"\nxref\n").unwrap() + 1;
+        let entries: Vec<usize> = String::from_utf8_lossy(&bytes[xref..])
+            .lines()
+            .skip(3)
+            .take_while(|l| l.ends_with(" n "))
+            .map(|l| l[..10].parse().unwrap())
+            .collect();
+        assert_eq!(entries.len(), 9);
+        for (i, offset) in entries.into_iter().enumerate() {
+            assert!(bytes[offset..].starts_with(format!("{} 0 obj", i + 1).as_bytes()));
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A round of writing prompted characters, to see how well the recognizer reads them without
+/// touching a real document.
+#[derive(Clone)]
+pub struct Practice {
+    chars: Vec<char>,
+    pub prompt: char,
+    /// What the recognizer made of the last attempt: the prompt, and its best guesses with
+    /// their scores.
+    pub last: Option<(char, Vec<(char, f32)>)>,
+    /// Attempts and misses so far, by char.
+    pub tally: BTreeMap<char, (usize, usize)>,
+    seed: u64,
+}
+
+impl Practice {
+    pub fn new(chars: Vec<char>) -> Practice {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(1, |d| d.as_nanos() as u64)
+            | 1;
+        Practice::with_seed(chars, seed)
+    }
+
+    fn with_seed(chars: Vec<char>, seed: u64) -> Practice {
+        let mut practice = Practice {
+            prompt: chars.first().copied().unwrap_or('a'),
+            chars,
+            last: None,
+            tally: BTreeMap::new(),
+            seed,
+        };
+        practice.next_prompt();
+        practice
+    }
+
+    fn random(&mut self) -> u64 {
+        // xorshift64: plenty random for picking flashcards.
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        self.seed
+    }
+
+    /// Pick another char to write. Chars that have been misread come up more often, so the
+    /// weak ones get a proper workout.
+    pub fn next_prompt(&mut self) {
+        let weight = |tally: &BTreeMap<char, (usize, usize)>, c: &char| {
+            1 + 2 * tally.get(c).map_or(0, |(_, misses)| *misses) as u64
+        };
+        let total: u64 = self.chars.iter().map(|c| weight(&self.tally, c)).sum();
+        if total == 0 {
+            return;
+        }
+        let mut pick = self.random() % total;
+        for c in &self.chars {
+            let w = weight(&self.tally, c);
+            if pick < w {
+                self.prompt = *c;
+                return;
+            }
+            pick -= w;
+        }
+    }
+
+    /// Note what the recognizer made of an attempt at the prompt, and move on to the next.
+    pub fn record(&mut self, matches: Vec<(char, f32)>) {
+        let hit = matches.first().is_some_and(|(c, _)| *c == self.prompt);
+        let (tries, misses) = self.tally.entry(self.prompt).or_default();
+        *tries += 1;
+        if !hit {
+            *misses += 1;
+        }
+        self.last = Some((self.prompt, matches));
+        self.next_prompt();
+    }
+
+    /// The chars that have been misread, worst first, with their attempts and misses.
+    pub fn weakest(&self) -> Vec<(char, usize, usize)> {
+        let mut weak: Vec<_> = self
+            .tally
+            .iter()
+            .filter(|(_, (_, misses))| *misses > 0)
+            .map(|(c, (tries, misses))| (*c, *tries, *misses))
+            .collect();
+        weak.sort_by(|(_, lt, lm), (_, rt, rm)| (rm * lt).cmp(&(lm * rt)).then(rm.cmp(lm)));
+        weak
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_practice() {
+        let mut practice = Practice::with_seed(vec!['a', 'o', 'x'], 42);
+        assert!(['a', 'o', 'x'].contains(&practice.prompt));
+
+        practice.prompt = 'o';
+        practice.record(vec![('a', 1.0), ('o', 1.2)]);
+        practice.prompt = 'o';
+        practice.record(vec![('o', 1.0)]);
+        practice.prompt = 'a';
+        practice.record(vec![('o', 1.0)]);
+        assert_eq!(practice.last, Some(('a', vec![('o', 1.0)])));
+        assert_eq!(practice.tally[&'o'], (2, 1));
+        assert_eq!(practice.weakest(), vec![('a', 1, 1), ('o', 2, 1)]);
+
+        // Misreads come up more often.
+        let mut counts = BTreeMap::new();
+        for _ in 0..1000 {
+            practice.next_prompt();
+            *counts.entry(practice.prompt).or_insert(0) += 1;
+        }
+        assert!(counts[&'a'] > counts[&'x']);
+    }
+}
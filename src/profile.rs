@@ -0,0 +1,88 @@
+use crate::util;
+use anyhow::Context;
+use std::fs::File;
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+
+/// A file that's part of a user's profile: its name in the archive, and where it lives on this
+/// device.
+pub struct ProfileFile {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Bundle up the profile files into a tar archive at the given path. Files that don't exist yet
+/// are left out. Returns the number of files written.
+pub fn export(files: &[ProfileFile], to: &Path) -> anyhow::Result<usize> {
+    let mut archive = tar::Builder::new(File::create(to)?);
+    let mut count = 0;
+    for file in files {
+        match File::open(&file.path) {
+            Ok(mut contents) => {
+                archive
+                    .append_file(&file.name, &mut contents)
+                    .with_context(|| format!("adding {}", file.name))?;
+                count += 1;
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_context(|| format!("reading {}", file.path.display())),
+        }
+    }
+    archive.finish()?;
+    Ok(count)
+}
+
+/// Restore the profile files from an archive made by `export`, overwriting the current ones.
+/// Anything in the archive that isn't a known profile file is ignored, so a stray archive can't
+/// write anywhere else. Returns the number of files restored.
+pub fn import(files: &[ProfileFile], from: &Path) -> anyhow::Result<usize> {
+    let mut archive = tar::Archive::new(File::open(from)?);
+    let mut count = 0;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        if let Some(file) = files.iter().find(|f| f.name == name) {
+            let mut contents = vec![];
+            entry.read_to_end(&mut contents)?;
+            // A file that's half written when the power goes is worse than the old one.
+            util::write_atomic(&file.path, &contents)
+                .with_context(|| format!("writing {}", file.path.display()))?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_round_trip() {
+        let dir = TempDir::new("profile");
+        let files = |prefix: &str| {
+            vec![
+                ProfileFile {
+                    name: "config/sill.toml".to_string(),
+                    path: dir.join(format!("{prefix}-sill.toml")),
+                },
+                ProfileFile {
+                    name: "data/templates.json".to_string(),
+                    path: dir.join(format!("{prefix}-templates.json")),
+                },
+            ]
+        };
+        fs::write(dir.join("old-sill.toml"), "cell_height = 50").unwrap();
+
+        let archive = dir.join("profile.tar");
+        assert_eq!(export(&files("old"), &archive).unwrap(), 1);
+        assert_eq!(import(&files("new"), &archive).unwrap(), 1);
+        assert_eq!(
+            fs::read_to_string(dir.join("new-sill.toml")).unwrap(),
+            "cell_height = 50"
+        );
+        assert!(!dir.join("new-templates.json").exists());
+    }
+}
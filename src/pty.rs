@@ -0,0 +1,156 @@
+use std::fs::File;
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Stdio};
+use std::ptr;
+
+fn check(result: libc::c_int) -> io::Result<libc::c_int> {
+    if result == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(result)
+    }
+}
+
+/// The controlling side of a pseudo-terminal. Programs run on the other side see a real
+/// terminal, so things like `isatty` checks and password prompts work. Full-screen programs
+/// mostly don't: only the last line of output is redrawn in place, and `TERM` is `dumb` unless
+/// configured otherwise.
+pub struct Pty {
+    master: File,
+}
+
+impl Pty {
+    /// A fresh pseudo-terminal: the controlling side, and the file for the other side.
+    pub fn open() -> io::Result<(Pty, File)> {
+        let (mut master, mut slave) = (-1, -1);
+        check(unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                ptr::null_mut(),
+                ptr::null(),
+                ptr::null(),
+            )
+        })?;
+        let (master, slave) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+        check(unsafe { libc::fcntl(master.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) })?;
+        Ok((Pty { master }, slave))
+    }
+
+    /// Whether the terminal echoes its input back as output, as it does unless the program
+    /// reading it asked it not to; say, to read a password.
+    pub fn echoing(&self) -> io::Result<bool> {
+        let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+        check(unsafe { libc::tcgetattr(self.master.as_raw_fd(), &mut termios) })?;
+        Ok(termios.c_lflag & libc::ECHO != 0)
+    }
+
+    /// Run the command in a new session, with a fresh pseudo-terminal as its controlling
+    /// terminal and all of stdin, stdout, and stderr.
+    pub fn spawn(
+        command: &mut Command,
+        (rows, columns): (usize, usize),
+    ) -> io::Result<(Pty, Child)> {
+        let (pty, slave) = Pty::open()?;
+        pty.resize(rows, columns)?;
+        command
+            .stdin(Stdio::from(slave.try_clone()?))
+            .stdout(Stdio::from(slave.try_clone()?))
+            .stderr(Stdio::from(slave));
+        controlled_by(command, 0);
+        let child = command.spawn()?;
+        Ok((pty, child))
+    }
+
+    /// Tell the programs on the other side how big the terminal is; they're sent `SIGWINCH`
+    /// if it changed.
+    pub fn resize(&self, rows: usize, columns: usize) -> io::Result<()> {
+        let size = libc::winsize {
+            ws_row: rows.min(u16::MAX as usize) as u16,
+            ws_col: columns.min(u16::MAX as usize) as u16,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        check(unsafe { libc::ioctl(self.master.as_raw_fd(), libc::TIOCSWINSZ, &size) })?;
+        Ok(())
+    }
+
+    /// A handle to read the terminal output from, eg. on another thread.
+    pub fn reader(&self) -> io::Result<File> {
+        self.master.try_clone()
+    }
+
+    /// The handle to write input to.
+    pub fn writer(&mut self) -> &mut File {
+        &mut self.master
+    }
+}
+
+/// Run the command in a new session, detached from our terminal, with the terminal on the given
+/// file descriptor as its controlling one; that's the one it gets by opening `/dev/tty`.
+pub fn controlled_by(command: &mut Command, fd: libc::c_int) {
+    unsafe {
+        command.pre_exec(move || {
+            check(libc::setsid())?;
+            check(libc::ioctl(fd, libc::TIOCSCTTY, 0))?;
+            Ok(())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_spawn() {
+        let mut command = Command::new("/bin/sh");
+        command.args([
+            "-c",
+            "read line; test -t 0 && stty size && echo \"got $line\"; stty -echo; read secret",
+        ]);
+        let (mut pty, mut child) = Pty::spawn(&mut command, (24, 60)).unwrap();
+        let mut reader = pty.reader().unwrap();
+        assert!(pty.echoing().unwrap());
+        pty.writer().write_all(b"hello\n").unwrap();
+
+        // The output can arrive in any number of reads, so gather it until it's all there or
+        // it's clearly not coming.
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buffer = [0; 1024];
+            while let Ok(read @ 1..) = reader.read(&mut buffer) {
+                if sender.send(buffer[..read].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+        // The terminal echoes the input, like any other.
+        let expected = "hello\r\n24 60\r\ngot hello\r\n";
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut output = String::new();
+        while output.len() < expected.len() {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match receiver.recv_timeout(left) {
+                Ok(bytes) => output.push_str(&String::from_utf8_lossy(&bytes)),
+                Err(_) => break,
+            }
+        }
+        assert_eq!(output, expected);
+
+        // Until the program turns that off.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while pty.echoing().unwrap() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!pty.echoing().unwrap());
+        pty.writer().write_all(b"hunter2\n").unwrap();
+        child.wait().unwrap();
+    }
+}
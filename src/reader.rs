@@ -0,0 +1,254 @@
+use crate::font::{text_width, READER_FONT};
+
+pub const READER_TEXT_HEIGHT: i32 = 36;
+pub const READER_LINE_HEIGHT: i32 = READER_TEXT_HEIGHT * 3 / 2;
+
+/// How to draw a line. Any document can have code; headings and quotes are only picked out of
+/// markdown, where their markers are dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineStyle {
+    Body,
+    Heading(usize),
+    Quote,
+    Code,
+}
+
+impl LineStyle {
+    /// The size of the text, relative to the body.
+    pub fn scale(self) -> f32 {
+        match self {
+            LineStyle::Heading(1) => 1.5,
+            LineStyle::Heading(2) => 1.25,
+            LineStyle::Heading(_) => 1.1,
+            _ => 1.0,
+        }
+    }
+}
+
+/// A line of reflowed text, along with the buffer row it starts on.
+#[derive(Clone)]
+pub struct ReaderLine {
+    pub text: String,
+    pub source_row: usize,
+    pub style: LineStyle,
+}
+
+fn is_list_item(line: &str) -> bool {
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    line.starts_with("- ")
+        || line.starts_with("* ")
+        || (digits > 0 && line[digits..].starts_with(". "))
+}
+
+/// Greedily fill lines with words, breaking wherever the next word wouldn't fit.
+fn wrap(
+    words: &mut Vec<(&str, usize)>,
+    style: LineStyle,
+    max_width: f32,
+    measure: &impl Fn(&str) -> f32,
+    lines: &mut Vec<ReaderLine>,
+) {
+    let max_width = max_width / style.scale();
+    let mut current = String::new();
+    let mut source_row = 0;
+    for (word, row) in words.drain(..) {
+        if current.is_empty() {
+            current.push_str(word);
+            source_row = row;
+        } else {
+            let candidate = format!("{current} {word}");
+            if measure(&candidate) > max_width {
+                lines.push(ReaderLine {
+                    text: std::mem::replace(&mut current, word.to_string()),
+                    source_row,
+                    style,
+                });
+                source_row = row;
+            } else {
+                current = candidate;
+            }
+        }
+    }
+    if !current.is_empty() {
+        lines.push(ReaderLine {
+            text: current,
+            source_row,
+            style,
+        });
+    }
+}
+
+/// Reflow text into lines that fit the given width. Consecutive lines of prose are joined into
+/// paragraphs, while headings, list items, quotes and code keep their own lines. Markdown gets
+/// its headings and quotes styled, and list items bulleted.
+pub fn layout(
+    text: &str,
+    max_width: f32,
+    markdown: bool,
+    measure: impl Fn(&str) -> f32,
+) -> Vec<ReaderLine> {
+    let mut lines = vec![];
+    let mut words: Vec<(&str, usize)> = vec![];
+    let mut style = LineStyle::Body;
+    let mut in_fence = false;
+    let code_line = |text: &str, row| ReaderLine {
+        text: text.trim_end().to_string(),
+        source_row: row,
+        style: LineStyle::Code,
+    };
+    for (row, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            wrap(&mut words, style, max_width, &measure, &mut lines);
+            in_fence = !in_fence;
+            if !markdown {
+                lines.push(code_line(line, row));
+            }
+            continue;
+        }
+        if in_fence {
+            lines.push(code_line(line, row));
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            wrap(&mut words, style, max_width, &measure, &mut lines);
+            if matches!(lines.last(), Some(l) if !l.text.is_empty()) {
+                lines.push(ReaderLine {
+                    text: String::new(),
+                    source_row: row,
+                    style: LineStyle::Body,
+                });
+            }
+            continue;
+        }
+
+        if line.starts_with("    ") || line.starts_with('\t') {
+            wrap(&mut words, style, max_width, &measure, &mut lines);
+            lines.push(code_line(line, row));
+            continue;
+        }
+
+        let heading = trimmed.starts_with('#');
+        let quote = trimmed.starts_with('>');
+        let line_style = match (markdown, heading, quote) {
+            (true, true, _) => {
+                LineStyle::Heading(trimmed.chars().take_while(|c| *c == '#').count())
+            }
+            (true, _, true) => LineStyle::Quote,
+            _ => LineStyle::Body,
+        };
+        let starts_block = heading || quote || is_list_item(trimmed);
+        // Quotes carry on over several lines, as long as each starts with a `>`.
+        if line_style != style || (starts_block && line_style != LineStyle::Quote) {
+            wrap(&mut words, style, max_width, &measure, &mut lines);
+        }
+        style = line_style;
+
+        let content = match style {
+            LineStyle::Heading(_) => trimmed.trim_start_matches('#'),
+            LineStyle::Quote => trimmed.trim_start_matches('>'),
+            _ => trimmed,
+        };
+        let content = match content.split_once(' ') {
+            Some((marker, rest)) if markdown && ["-", "*", "+"].contains(&marker) => {
+                words.push(("\u{2022}", row));
+                rest
+            }
+            _ => content,
+        };
+        words.extend(content.split_whitespace().map(|w| (w, row)));
+        if heading {
+            wrap(&mut words, style, max_width, &measure, &mut lines);
+            style = LineStyle::Body;
+        }
+    }
+    wrap(&mut words, style, max_width, &measure, &mut lines);
+    lines
+}
+
+/// A paginated, reflowed view of a document.
+#[derive(Clone)]
+pub struct Reader {
+    pub markdown: bool,
+    pub lines: Vec<ReaderLine>,
+    pub lines_per_page: usize,
+    pub page: usize,
+}
+
+impl Reader {
+    pub fn new(text: &str, width: i32, height: i32, start_row: usize, markdown: bool) -> Reader {
+        let lines = layout(text, width as f32, markdown, |s| {
+            text_width(&READER_FONT, READER_TEXT_HEIGHT, s)
+        });
+        let lines_per_page = (height / READER_LINE_HEIGHT).max(1) as usize;
+        let start_line = lines
+            .iter()
+            .position(|l| l.source_row >= start_row)
+            .unwrap_or(0);
+        Reader {
+            markdown,
+            lines,
+            lines_per_page,
+            page: start_line / lines_per_page,
+        }
+    }
+
+    pub fn pages(&self) -> usize {
+        (self.lines.len().max(1) - 1) / self.lines_per_page + 1
+    }
+
+    pub fn page_lines(&self) -> &[ReaderLine] {
+        let start = (self.page * self.lines_per_page).min(self.lines.len());
+        let end = (start + self.lines_per_page).min(self.lines.len());
+        &self.lines[start..end]
+    }
+
+    pub fn turn(&mut self, delta: isize) {
+        let page = (self.page as isize + delta).max(0) as usize;
+        self.page = page.min(self.pages() - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout() {
+        let text = "# Title\nsome words\nthat wrap\n\n- item one\n- item two";
+        let lines = layout(text, 10.0, false, |s| s.len() as f32);
+        let texts: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "# Title",
+                "some words",
+                "that wrap",
+                "",
+                "- item one",
+                "- item two"
+            ]
+        );
+        let rows: Vec<usize> = lines.iter().map(|l| l.source_row).collect();
+        assert_eq!(rows, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_markdown_layout() {
+        let text = "## Title\n> a quote\n> goes on\n- item\n```\nlet x\n```\nafter";
+        let lines = layout(text, 40.0, true, |s| s.len() as f32);
+        let lines: Vec<(&str, LineStyle)> =
+            lines.iter().map(|l| (l.text.as_str(), l.style)).collect();
+        assert_eq!(
+            lines,
+            vec![
+                ("Title", LineStyle::Heading(2)),
+                ("a quote goes on", LineStyle::Quote),
+                ("\u{2022} item", LineStyle::Body),
+                ("let x", LineStyle::Code),
+                ("after", LineStyle::Body),
+            ]
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// A flight recorder: keeps a log of the last few minutes of events, so there's something to
+/// attach to a bug report when things go wrong.
+pub struct Recorder {
+    window: Duration,
+    started: Instant,
+    events: VecDeque<(Duration, String)>,
+}
+
+impl Recorder {
+    pub fn new(window: Duration) -> Recorder {
+        Recorder {
+            window,
+            started: Instant::now(),
+            events: VecDeque::new(),
+        }
+    }
+
+    pub fn record(&mut self, event: String) {
+        self.record_at(self.started.elapsed(), event);
+    }
+
+    fn record_at(&mut self, at: Duration, event: String) {
+        let cutoff = at.saturating_sub(self.window);
+        while self.events.front().is_some_and(|(t, _)| *t < cutoff) {
+            self.events.pop_front();
+        }
+        self.events.push_back((at, event));
+    }
+
+    /// Write out the log, one event per line, with the seconds since startup.
+    pub fn dump(&self, out: &mut impl Write) -> io::Result<()> {
+        for (at, event) in &self.events {
+            writeln!(out, "{:>10.3} {event}", at.as_secs_f64())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window() {
+        let mut recorder = Recorder::new(Duration::from_secs(60));
+        recorder.record_at(Duration::from_secs(1), "old".to_string());
+        recorder.record_at(Duration::from_millis(30500), "newer".to_string());
+        recorder.record_at(Duration::from_secs(62), "newest".to_string());
+        let mut out = vec![];
+        recorder.dump(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "    30.500 newer\n    62.000 newest\n"
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::util;
+
+/// The directive that starts a reminder, anywhere in a line.
+const DIRECTIVE: &str = "@remind ";
+
+/// A reminder found in a file, like `@remind 2024-06-01 09:00 grade exams`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Reminder {
+    pub path: PathBuf,
+    /// When it's due, as `YYYY-MM-DD HH:MM` in local time, so it sorts.
+    pub due: String,
+    pub text: String,
+}
+
+impl Reminder {
+    fn key(&self) -> String {
+        format!("{} {}", self.due, self.text)
+    }
+}
+
+/// Parse the reminder in a line, if any, as its due time and text. The time is optional, and
+/// defaults to the start of the day.
+pub fn parse_line(line: &str) -> Option<(String, String)> {
+    let rest = &line[line.find(DIRECTIVE)? + DIRECTIVE.len()..];
+    let mut words = rest.split_whitespace().peekable();
+    let date = words.next()?;
+    let is_date = date.len() == 10
+        && date.char_indices().all(|(i, c)| {
+            if i == 4 || i == 7 {
+                c == '-'
+            } else {
+                c.is_ascii_digit()
+            }
+        });
+    if !is_date {
+        return None;
+    }
+    let is_time = |w: &&str| {
+        w.len() == 5
+            && w.char_indices()
+                .all(|(i, c)| if i == 2 { c == ':' } else { c.is_ascii_digit() })
+    };
+    let time = match words.peek() {
+        Some(w) if is_time(w) => words.next()?,
+        _ => "00:00",
+    };
+    let text = words.collect::<Vec<_>>().join(" ");
+    Some((format!("{date} {time}"), text))
+}
+
+/// Every reminder collected so far, and the ones that have been dealt with, kept so they
+/// survive restarts and the files they came from being closed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Reminders {
+    pub reminders: Vec<Reminder>,
+    dismissed: BTreeSet<String>,
+}
+
+impl Reminders {
+    pub fn load(path: &Path) -> io::Result<Reminders> {
+        util::load_json(path)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        util::save_json(path, self)
+    }
+
+    /// Replace the reminders from a file with the ones in its latest text. Returns whether
+    /// anything changed.
+    pub fn scan(&mut self, path: &Path, text: &str) -> bool {
+        let mut found: Vec<Reminder> = text
+            .lines()
+            .filter_map(parse_line)
+            .map(|(due, text)| Reminder {
+                path: path.to_path_buf(),
+                due,
+                text,
+            })
+            .filter(|r| !self.dismissed.contains(&r.key()))
+            .collect();
+        found.sort_by(|a, b| a.due.cmp(&b.due));
+        let old: Vec<&Reminder> = self.reminders.iter().filter(|r| r.path == path).collect();
+        if old.len() == found.len() && old.iter().zip(&found).all(|(o, f)| *o == f) {
+            return false;
+        }
+        self.reminders.retain(|r| r.path != path);
+        self.reminders.append(&mut found);
+        self.reminders.sort_by(|a, b| a.due.cmp(&b.due));
+        true
+    }
+
+    /// The reminders due by the given time, in the same format as theirs.
+    pub fn due(&self, now: &str) -> impl Iterator<Item = (usize, &Reminder)> {
+        let now = now.to_string();
+        self.reminders
+            .iter()
+            .enumerate()
+            .filter(move |(_, r)| r.due <= now)
+    }
+
+    /// Stop showing a reminder, and don't bring it back even if it's still in its file.
+    pub fn dismiss(&mut self, index: usize) {
+        if index < self.reminders.len() {
+            let reminder = self.reminders.remove(index);
+            self.dismissed.insert(reminder.key());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reminders() {
+        assert_eq!(
+            parse_line("- @remind 2024-06-01 09:00 grade exams"),
+            Some(("2024-06-01 09:00".to_string(), "grade exams".to_string()))
+        );
+        assert_eq!(
+            parse_line("@remind 2024-06-01 call home"),
+            Some(("2024-06-01 00:00".to_string(), "call home".to_string()))
+        );
+        assert_eq!(parse_line("@remind tomorrow call home"), None);
+        assert_eq!(parse_line("no directive here"), None);
+
+        let mut reminders = Reminders::default();
+        let path = Path::new("/notes.md");
+        let text = "@remind 2024-06-02 later\n@remind 2024-06-01 10:00 sooner\n";
+        assert!(reminders.scan(path, text));
+        assert!(!reminders.scan(path, text));
+        let due: Vec<_> = reminders
+            .due("2024-06-01 12:00")
+            .map(|(_, r)| r.text.as_str())
+            .collect();
+        assert_eq!(due, vec!["sooner"]);
+
+        reminders.dismiss(0);
+        reminders.scan(path, text);
+        assert_eq!(reminders.reminders.len(), 1);
+        assert_eq!(reminders.reminders[0].text, "later");
+    }
+}
@@ -0,0 +1,107 @@
+use anyhow::{anyhow, bail};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+/// The new name for a file, following the pattern.
+///
+/// `s/old/new/` replaces the first `old` in the name, or every one with a trailing `g`. Anything
+/// else is the whole new name, where `{name}` stands for the old name without its extension,
+/// `{ext}` for the extension with its dot, `{date}` for the file's date, and `{n}` for its place
+/// in the list, from 1. An empty pattern leaves the name as it is.
+pub fn rename(pattern: &str, name: &str, date: &str, n: usize) -> anyhow::Result<String> {
+    if pattern.is_empty() {
+        return Ok(name.to_string());
+    }
+    if let Some(rest) = pattern.strip_prefix("s/") {
+        let mut parts = rest.splitn(3, '/');
+        let (old, new) = match (parts.next(), parts.next()) {
+            (Some(old), Some(new)) if !old.is_empty() => (old, new),
+            _ => bail!("expected s/old/new/"),
+        };
+        return match parts.next().unwrap_or("") {
+            "" => Ok(name.replacen(old, new, 1)),
+            "g" => Ok(name.replace(old, new)),
+            flags => Err(anyhow!("unknown flags: {flags}")),
+        };
+    }
+    let (stem, ext) = match name.rfind('.') {
+        Some(i) if i > 0 => name.split_at(i),
+        _ => (name, ""),
+    };
+    Ok(pattern
+        .replace("{name}", stem)
+        .replace("{ext}", ext)
+        .replace("{date}", date)
+        .replace("{n}", &n.to_string()))
+}
+
+/// Where each of the files should be moved to, given their dates. Fails if any of the new names
+/// would be unusable, or would overwrite another file.
+pub fn plan(pattern: &str, files: &[(PathBuf, String)]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut targets = vec![];
+    let mut seen = BTreeSet::new();
+    for (i, (path, date)) in files.iter().enumerate() {
+        let name = path
+            .file_name()
+            .ok_or_else(|| anyhow!("{} has no file name", path.display()))?
+            .to_string_lossy();
+        let new_name = rename(pattern, &name, date, i + 1)?;
+        if new_name.is_empty() || new_name.contains('/') || new_name == "." || new_name == ".." {
+            bail!("{name:?} would be renamed to {new_name:?}");
+        }
+        let target = path.with_file_name(&new_name);
+        if !seen.insert(target.clone()) {
+            bail!("more than one file would be named {new_name:?}");
+        }
+        if target != *path && target.exists() {
+            bail!("{new_name:?} already exists");
+        }
+        targets.push(target);
+    }
+    Ok(targets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rename() {
+        let date = "2024-09-02";
+        assert_eq!(rename("", "notes.md", date, 1).unwrap(), "notes.md");
+        assert_eq!(
+            rename("s/week/wk/", "week1-week.md", date, 1).unwrap(),
+            "wk1-week.md"
+        );
+        assert_eq!(
+            rename("s/week/wk/g", "week1-week.md", date, 1).unwrap(),
+            "wk1-wk.md"
+        );
+        assert_eq!(
+            rename("{date}-{name}{ext}", "notes.md", date, 1).unwrap(),
+            "2024-09-02-notes.md"
+        );
+        assert_eq!(
+            rename("lecture-{n}{ext}", "notes.md", date, 3).unwrap(),
+            "lecture-3.md"
+        );
+        assert_eq!(
+            rename("{name}-old", ".bashrc", date, 1).unwrap(),
+            ".bashrc-old"
+        );
+        assert!(rename("s//x/", "notes.md", date, 1).is_err());
+        assert!(rename("s/a/b/q", "notes.md", date, 1).is_err());
+
+        let dir = PathBuf::from("/nonexistent");
+        let files = vec![
+            (dir.join("a.md"), date.to_string()),
+            (dir.join("b.md"), date.to_string()),
+        ];
+        assert_eq!(
+            plan("{n}{ext}", &files).unwrap(),
+            vec![dir.join("1.md"), dir.join("2.md")]
+        );
+        assert!(plan("same.md", &files).is_err());
+        assert!(plan("{name}/x", &files).is_err());
+    }
+}
@@ -0,0 +1,367 @@
+use std::ops::{Bound, Index, IndexMut, Range, RangeBounds};
+use std::sync::Arc;
+
+/// How many lines a leaf holds before it's split in two.
+const LEAF_LINES: usize = 64;
+
+/// The lines of a buffer, kept as a balanced tree of short runs of lines. Editing a line only
+/// copies the run it's in and the path down to it, so an edit near the top of a big file
+/// doesn't shift every line after it; and since the runs are shared, cloning the whole thing,
+/// say for undo, costs next to nothing.
+#[derive(Clone, Default)]
+pub struct Rope {
+    root: Arc<Node>,
+}
+
+#[derive(Clone)]
+enum Node {
+    Leaf(Vec<Vec<char>>),
+    Branch {
+        left: Arc<Node>,
+        right: Arc<Node>,
+        len: usize,
+        height: usize,
+    },
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Node::Leaf(vec![])
+    }
+}
+
+impl Node {
+    fn len(&self) -> usize {
+        match self {
+            Node::Leaf(lines) => lines.len(),
+            Node::Branch { len, .. } => *len,
+        }
+    }
+
+    fn height(&self) -> usize {
+        match self {
+            Node::Leaf(_) => 0,
+            Node::Branch { height, .. } => *height,
+        }
+    }
+
+    fn children(&self) -> (&Arc<Node>, &Arc<Node>) {
+        match self {
+            Node::Branch { left, right, .. } => (left, right),
+            Node::Leaf(_) => unreachable!("only branches have children"),
+        }
+    }
+}
+
+fn leaf(lines: Vec<Vec<char>>) -> Arc<Node> {
+    Arc::new(Node::Leaf(lines))
+}
+
+fn branch(left: Arc<Node>, right: Arc<Node>) -> Arc<Node> {
+    Arc::new(Node::Branch {
+        len: left.len() + right.len(),
+        height: 1 + left.height().max(right.height()),
+        left,
+        right,
+    })
+}
+
+/// A balanced tree of the lines, in leaves of up to `LEAF_LINES`.
+fn build(mut lines: Vec<Vec<char>>) -> Arc<Node> {
+    if lines.len() <= LEAF_LINES {
+        return leaf(lines);
+    }
+    let leaves = lines.len().div_ceil(LEAF_LINES);
+    let right = lines.split_off(leaves / 2 * LEAF_LINES);
+    branch(build(lines), build(right))
+}
+
+/// The lines of the left tree followed by those of the right, kept balanced: no branch's
+/// subtrees differ in height by more than one.
+fn join(left: Arc<Node>, right: Arc<Node>) -> Arc<Node> {
+    if left.len() == 0 {
+        return right;
+    }
+    if right.len() == 0 {
+        return left;
+    }
+    if let (Node::Leaf(l), Node::Leaf(r)) = (&*left, &*right) {
+        if l.len() + r.len() <= LEAF_LINES {
+            return leaf([&l[..], &r[..]].concat());
+        }
+    }
+    let (lh, rh) = (left.height(), right.height());
+    if lh > rh + 1 {
+        let (a, b) = left.children();
+        let joined = join(b.clone(), right);
+        if joined.height() <= a.height() + 1 {
+            return branch(a.clone(), joined);
+        }
+        // One too tall: rotate it back into balance.
+        let (c, d) = joined.children();
+        if c.height() > d.height() {
+            let (c1, c2) = c.children();
+            branch(branch(a.clone(), c1.clone()), branch(c2.clone(), d.clone()))
+        } else {
+            branch(branch(a.clone(), c.clone()), d.clone())
+        }
+    } else if rh > lh + 1 {
+        let (a, b) = right.children();
+        let joined = join(left, a.clone());
+        if joined.height() <= b.height() + 1 {
+            return branch(joined, b.clone());
+        }
+        let (c, d) = joined.children();
+        if d.height() > c.height() {
+            let (d1, d2) = d.children();
+            branch(branch(c.clone(), d1.clone()), branch(d2.clone(), b.clone()))
+        } else {
+            branch(c.clone(), branch(d.clone(), b.clone()))
+        }
+    } else {
+        branch(left, right)
+    }
+}
+
+/// The first `at` lines, and the rest.
+fn split(node: &Arc<Node>, at: usize) -> (Arc<Node>, Arc<Node>) {
+    if at == 0 {
+        return (Arc::default(), node.clone());
+    }
+    if at >= node.len() {
+        return (node.clone(), Arc::default());
+    }
+    match &**node {
+        Node::Leaf(lines) => (leaf(lines[..at].to_vec()), leaf(lines[at..].to_vec())),
+        Node::Branch { left, right, .. } => {
+            if at <= left.len() {
+                let (a, b) = split(left, at);
+                (a, join(b, right.clone()))
+            } else {
+                let (a, b) = split(right, at - left.len());
+                (join(left.clone(), a), b)
+            }
+        }
+    }
+}
+
+/// The parts of the leaves that fall within the range of lines, in order.
+fn collect<'a>(node: &'a Node, range: Range<usize>, out: &mut Vec<&'a [Vec<char>]>) {
+    if range.start >= range.end {
+        return;
+    }
+    match node {
+        Node::Leaf(lines) => out.push(&lines[range]),
+        Node::Branch { left, right, .. } => {
+            let mid = left.len();
+            collect(left, range.start.min(mid)..range.end.min(mid), out);
+            collect(
+                right,
+                range.start.max(mid) - mid..range.end.max(mid) - mid,
+                out,
+            );
+        }
+    }
+}
+
+fn line_mut(node: &mut Arc<Node>, row: usize) -> Option<&mut Vec<char>> {
+    match Arc::make_mut(node) {
+        Node::Leaf(lines) => lines.get_mut(row),
+        Node::Branch { left, right, .. } => {
+            if row < left.len() {
+                line_mut(left, row)
+            } else {
+                let row = row - left.len();
+                line_mut(right, row)
+            }
+        }
+    }
+}
+
+impl Rope {
+    pub fn len(&self) -> usize {
+        self.root.len()
+    }
+
+    pub fn get(&self, row: usize) -> Option<&Vec<char>> {
+        let mut node = &*self.root;
+        let mut row = row;
+        loop {
+            match node {
+                Node::Leaf(lines) => return lines.get(row),
+                Node::Branch { left, right, .. } => {
+                    if row < left.len() {
+                        node = left;
+                    } else {
+                        row -= left.len();
+                        node = right;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The line, to edit in place. Only the run of lines it's in is copied, if it's shared.
+    pub fn get_mut(&mut self, row: usize) -> Option<&mut Vec<char>> {
+        if row >= self.len() {
+            return None;
+        }
+        line_mut(&mut self.root, row)
+    }
+
+    pub fn last_mut(&mut self) -> Option<&mut Vec<char>> {
+        let last = self.len().checked_sub(1)?;
+        self.get_mut(last)
+    }
+
+    /// The lines in the range, in order.
+    pub fn range(
+        &self,
+        rows: impl RangeBounds<usize>,
+    ) -> impl DoubleEndedIterator<Item = &Vec<char>> {
+        let mut runs = vec![];
+        collect(&self.root, self.bounds(rows), &mut runs);
+        runs.into_iter().flatten()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Vec<char>> {
+        self.range(..)
+    }
+
+    pub fn extend(&mut self, lines: impl IntoIterator<Item = Vec<char>>) {
+        let lines = build(lines.into_iter().collect());
+        self.root = join(self.root.clone(), lines);
+    }
+
+    /// Replace the lines in the range with the given ones.
+    pub fn splice(&mut self, rows: impl RangeBounds<usize>, lines: Vec<Vec<char>>) {
+        let rows = self.bounds(rows);
+        let (before, rest) = split(&self.root, rows.start);
+        let (_, after) = split(&rest, rows.len());
+        self.root = join(join(before, build(lines)), after);
+    }
+
+    /// Remove the lines in the range, returning them.
+    pub fn drain(&mut self, rows: impl RangeBounds<usize>) -> Vec<Vec<char>> {
+        let rows = self.bounds(rows);
+        let removed = self.range(rows.clone()).cloned().collect();
+        self.splice(rows, vec![]);
+        removed
+    }
+
+    fn bounds(&self, rows: impl RangeBounds<usize>) -> Range<usize> {
+        let len = self.len();
+        let start = match rows.start_bound() {
+            Bound::Included(&s) => s,
+            Bound::Excluded(&s) => s + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match rows.end_bound() {
+            Bound::Included(&e) => e + 1,
+            Bound::Excluded(&e) => e,
+            Bound::Unbounded => len,
+        };
+        assert!(
+            start <= end && end <= len,
+            "rows {start}..{end} out of 0..{len}"
+        );
+        start..end
+    }
+}
+
+impl From<Vec<Vec<char>>> for Rope {
+    fn from(lines: Vec<Vec<char>>) -> Self {
+        Rope { root: build(lines) }
+    }
+}
+
+impl FromIterator<Vec<char>> for Rope {
+    fn from_iter<T: IntoIterator<Item = Vec<char>>>(lines: T) -> Self {
+        Rope::from(lines.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl IntoIterator for Rope {
+    type Item = Vec<char>;
+    type IntoIter = std::vec::IntoIter<Vec<char>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter().cloned().collect::<Vec<_>>().into_iter()
+    }
+}
+
+impl Index<usize> for Rope {
+    type Output = Vec<char>;
+
+    fn index(&self, row: usize) -> &Vec<char> {
+        match self.get(row) {
+            Some(line) => line,
+            None => panic!("row {row} out of {} lines", self.len()),
+        }
+    }
+}
+
+impl IndexMut<usize> for Rope {
+    fn index_mut(&mut self, row: usize) -> &mut Vec<char> {
+        let len = self.len();
+        match self.get_mut(row) {
+            Some(line) => line,
+            None => panic!("row {row} out of {len} lines"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(range: Range<usize>) -> Vec<Vec<char>> {
+        range.map(|i| i.to_string().chars().collect()).collect()
+    }
+
+    fn balanced(node: &Node) -> bool {
+        match node {
+            Node::Leaf(lines) => lines.len() <= LEAF_LINES,
+            Node::Branch { left, right, .. } => {
+                left.height().abs_diff(right.height()) <= 1 && balanced(left) && balanced(right)
+            }
+        }
+    }
+
+    #[test]
+    fn test_rope() {
+        let mut model = lines(0..1000);
+        let mut rope = Rope::from(model.clone());
+        let snapshot = rope.clone();
+        // Edits all over, including plenty that add and remove lines, checked against a Vec.
+        let mut seed = 7usize;
+        for i in 0..500 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345) % (1 << 31);
+            let start = seed % (model.len() + 1);
+            let end = (start + seed % 5).min(model.len());
+            let new = lines(i..i + seed % 4);
+            model.splice(start..end, new.clone());
+            rope.splice(start..end, new);
+            if let Some(line) = rope.get_mut(start) {
+                line.push('!');
+                model[start].push('!');
+            }
+        }
+        assert!(balanced(&rope.root));
+        assert_eq!(rope.len(), model.len());
+        assert!(rope.iter().eq(model.iter()));
+        assert!(rope.range(10..20).rev().eq(model[10..20].iter().rev()));
+        assert_eq!(rope[42], model[42]);
+        assert!(rope.get(model.len()).is_none());
+
+        // The clone from before the edits doesn't see any of them.
+        assert!(snapshot.iter().eq(lines(0..1000).iter()));
+
+        let drained = rope.drain(..10);
+        assert_eq!(drained, model.drain(..10).collect::<Vec<_>>());
+        rope.extend([vec!['x']]);
+        model.push(vec!['x']);
+        assert!(rope.iter().eq(model.iter()));
+        assert!(balanced(&rope.root));
+    }
+}
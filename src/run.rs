@@ -0,0 +1,55 @@
+use crate::highlight::Language;
+use std::path::Path;
+
+/// The command line to run a script with, if it's one we know how to run: whatever its `#!`
+/// line names, or else the usual interpreter for its language. Going through the interpreter
+/// means the file doesn't need to be executable.
+pub fn command(path: &Path, first_line: &str, language: Option<Language>) -> Option<String> {
+    let interpreter = match first_line.strip_prefix("#!").map(str::trim) {
+        Some(shebang) if !shebang.is_empty() => shebang,
+        _ => match language? {
+            Language::Shell => "bash",
+            Language::Python => "python3",
+            Language::Rust | Language::Markdown => return None,
+        },
+    };
+    Some(format!("{interpreter} {}", quote(&path.to_string_lossy())))
+}
+
+/// Quote a word for the shell, so spaces and the like don't split it up.
+pub fn quote(word: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "/._-+,:=@".contains(c);
+    if !word.is_empty() && word.chars().all(plain) {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command() {
+        let script = Path::new("/home/root/backup.sh");
+        assert_eq!(
+            command(script, "#!/usr/bin/env python3", Some(Language::Shell)),
+            Some("/usr/bin/env python3 /home/root/backup.sh".to_string())
+        );
+        assert_eq!(
+            command(script, "set -e", Some(Language::Shell)),
+            Some("bash /home/root/backup.sh".to_string())
+        );
+        assert_eq!(
+            command(Path::new("my notes.py"), "", Some(Language::Python)),
+            Some("python3 'my notes.py'".to_string())
+        );
+        assert_eq!(
+            command(Path::new("a.md"), "# Notes", Some(Language::Markdown)),
+            None
+        );
+        assert_eq!(command(Path::new("a.txt"), "hello", None), None);
+        assert_eq!(quote("it's"), "'it'\\''s'");
+    }
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::util;
+
+/// The open tabs, saved so they can be brought back after sill exits or the device restarts.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Session {
+    pub tabs: Vec<SessionTab>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionTab {
+    Text {
+        title: String,
+        path: Option<PathBuf>,
+        origin: (usize, usize),
+        /// The buffer itself, for unsaved changes or tabs with no file behind them.
+        contents: Option<String>,
+        #[serde(default)]
+        read_only: bool,
+        #[serde(default)]
+        wrap: bool,
+        #[serde(default)]
+        insert: bool,
+    },
+    Shell {
+        working_dir: PathBuf,
+        /// Input that had been written but not yet sent to the shell.
+        input: String,
+    },
+}
+
+impl Session {
+    /// Read a saved session, or an empty one if there is none.
+    pub fn load(path: &Path) -> io::Result<Session> {
+        util::load_json(path)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        util::save_json(path, self)
+    }
+}
@@ -0,0 +1,98 @@
+use std::cmp::Ordering;
+
+/// How to compare lines when sorting them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SortOptions {
+    /// Compare runs of digits by their value, so `9` sorts before `10`.
+    pub numeric: bool,
+    pub reverse: bool,
+    pub ignore_case: bool,
+}
+
+/// Split a line into alternating runs of digits and non-digits.
+fn chunks(line: &str) -> Vec<&str> {
+    let mut chunks = vec![];
+    let mut start = 0;
+    let mut digits = None;
+    for (i, c) in line.char_indices() {
+        let is_digit = c.is_ascii_digit();
+        if digits.is_some() && digits != Some(is_digit) {
+            chunks.push(&line[start..i]);
+            start = i;
+        }
+        digits = Some(is_digit);
+    }
+    if start < line.len() {
+        chunks.push(&line[start..]);
+    }
+    chunks
+}
+
+fn compare_numeric(a: &str, b: &str) -> Ordering {
+    for (a, b) in chunks(a).into_iter().zip(chunks(b)) {
+        let is_number = |s: &str| s.starts_with(|c: char| c.is_ascii_digit());
+        let ordering = if is_number(a) && is_number(b) {
+            let (a, b) = (a.trim_start_matches('0'), b.trim_start_matches('0'));
+            a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+        } else {
+            a.cmp(b)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    chunks(a).len().cmp(&chunks(b).len())
+}
+
+pub fn compare(a: &str, b: &str, options: SortOptions) -> Ordering {
+    let (a, b) = if options.ignore_case {
+        (a.to_lowercase(), b.to_lowercase())
+    } else {
+        (a.to_string(), b.to_string())
+    };
+    let ordering = if options.numeric {
+        compare_numeric(&a, &b)
+    } else {
+        a.cmp(&b)
+    };
+    if options.reverse {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+pub fn sort_lines(lines: &mut [String], options: SortOptions) {
+    lines.sort_by(|a, b| compare(a, b, options));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_lines() {
+        let mut lines: Vec<String> = ["item 10", "Item 9", "item 9b", "item 009"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        sort_lines(&mut lines, SortOptions::default());
+        assert_eq!(lines, ["Item 9", "item 009", "item 10", "item 9b"]);
+
+        let options = SortOptions {
+            numeric: true,
+            ignore_case: true,
+            reverse: false,
+        };
+        sort_lines(&mut lines, options);
+        assert_eq!(lines, ["Item 9", "item 009", "item 9b", "item 10"]);
+
+        let options = SortOptions {
+            reverse: true,
+            ..options
+        };
+        sort_lines(&mut lines, options);
+        assert_eq!(lines, ["item 10", "item 9b", "Item 9", "item 009"]);
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+const NUM_FREQUENT_WORDS: usize = 20;
+
+/// A rough syllable count: the number of runs of vowels, ignoring a trailing silent `e`.
+fn syllables(word: &str) -> usize {
+    let is_vowel = |c: char| "aeiouy".contains(c);
+    let chars: Vec<char> = word.chars().flat_map(|c| c.to_lowercase()).collect();
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for &c in &chars {
+        let vowel = is_vowel(c);
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+    if count > 1 && chars.ends_with(&['e']) && !chars.ends_with(&['l', 'e']) {
+        count -= 1;
+    }
+    count.max(1)
+}
+
+pub struct Stats {
+    pub lines: usize,
+    pub characters: usize,
+    pub words: usize,
+    pub letters: usize,
+    pub sentences: usize,
+    pub syllables: usize,
+    pub frequencies: Vec<(String, usize)>,
+}
+
+impl Stats {
+    pub fn new(text: &str) -> Stats {
+        let words: Vec<&str> = text
+            .split(|c: char| !(c.is_alphanumeric() || c == '\''))
+            .map(|w| w.trim_matches('\''))
+            .filter(|w| w.chars().any(char::is_alphabetic))
+            .collect();
+
+        let mut sentences = 0;
+        let mut in_terminator = false;
+        for c in text.chars() {
+            let terminator = matches!(c, '.' | '!' | '?');
+            if terminator && !in_terminator {
+                sentences += 1;
+            }
+            in_terminator = terminator;
+        }
+        if sentences == 0 && !words.is_empty() {
+            sentences = 1;
+        }
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for word in &words {
+            *counts.entry(word.to_lowercase()).or_default() += 1;
+        }
+        let mut frequencies: Vec<(String, usize)> = counts.into_iter().collect();
+        frequencies.sort_by(|(a_word, a), (b_word, b)| b.cmp(a).then(a_word.cmp(b_word)));
+        frequencies.truncate(NUM_FREQUENT_WORDS);
+
+        Stats {
+            lines: text.lines().count(),
+            characters: text.chars().count(),
+            words: words.len(),
+            letters: words.iter().map(|w| w.chars().count()).sum(),
+            sentences,
+            syllables: words.iter().map(|w| syllables(w)).sum(),
+            frequencies,
+        }
+    }
+
+    pub fn words_per_sentence(&self) -> f32 {
+        self.words as f32 / self.sentences.max(1) as f32
+    }
+
+    pub fn syllables_per_word(&self) -> f32 {
+        self.syllables as f32 / self.words.max(1) as f32
+    }
+
+    /// The Flesch reading-ease score: higher is easier, and 60-70 is "plain English".
+    pub fn reading_ease(&self) -> f32 {
+        206.835 - 1.015 * self.words_per_sentence() - 84.6 * self.syllables_per_word()
+    }
+
+    /// The Flesch-Kincaid grade level: roughly, the years of schooling needed to follow the text.
+    pub fn grade_level(&self) -> f32 {
+        0.39 * self.words_per_sentence() + 11.8 * self.syllables_per_word() - 15.59
+    }
+
+    pub fn report(&self, title: &str) -> String {
+        let mut report = format!("Statistics for {title}\n\n");
+        report.push_str(&format!("Lines:            {}\n", self.lines));
+        report.push_str(&format!("Characters:       {}\n", self.characters));
+        report.push_str(&format!("Words:            {}\n", self.words));
+        report.push_str(&format!("Sentences:        {}\n", self.sentences));
+        if self.words > 0 {
+            report.push_str(&format!(
+                "Words/sentence:   {:.1}\n",
+                self.words_per_sentence()
+            ));
+            report.push_str(&format!(
+                "Letters/word:     {:.1}\n",
+                self.letters as f32 / self.words as f32
+            ));
+            report.push_str(&format!("Reading ease:     {:.1}\n", self.reading_ease()));
+            report.push_str(&format!("Grade level:      {:.1}\n", self.grade_level()));
+            report.push_str("\nMost frequent words:\n");
+            for (word, count) in &self.frequencies {
+                report.push_str(&format!("  {word:<16}{count}\n"));
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let stats = Stats::new("The cat sat. The cat ran away!\nDone");
+        assert_eq!(stats.lines, 2);
+        assert_eq!(stats.words, 8);
+        assert_eq!(stats.sentences, 2);
+        assert_eq!(stats.frequencies[0], ("cat".to_string(), 2));
+        assert_eq!(syllables("away"), 2);
+        assert_eq!(syllables("table"), 2);
+        assert_eq!(syllables("done"), 1);
+    }
+}
@@ -0,0 +1,62 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::unix::io::FromRawFd;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::thread;
+
+/// The signals that mean we're about to stop running for a while: launchers send `SIGTSTP` to
+/// put an app in the background, and `SIGTERM` or `SIGHUP` come before shutting down.
+const SIGNALS: [libc::c_int; 3] = [libc::SIGTSTP, libc::SIGTERM, libc::SIGHUP];
+
+/// The write end of the pipe that the signal handler passes signals down.
+static PIPE: AtomicI32 = AtomicI32::new(-1);
+
+extern "C" fn forward(signal: libc::c_int) {
+    // Writing to a pipe is one of the few things that's safe to do in a signal handler.
+    let byte = signal as u8;
+    unsafe {
+        libc::write(
+            PIPE.load(Ordering::Relaxed),
+            &byte as *const u8 as *const _,
+            1,
+        );
+    }
+}
+
+/// Catch the suspend signals, and call `on_signal` with each one from a thread of its own.
+/// The default action doesn't happen: that's up to the callback.
+pub fn watch(mut on_signal: impl FnMut(libc::c_int) + Send + 'static) -> io::Result<()> {
+    let mut fds = [-1; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut reader = unsafe { File::from_raw_fd(fds[0]) };
+    PIPE.store(fds[1], Ordering::Relaxed);
+    for signal in SIGNALS {
+        let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+        action.sa_sigaction = forward as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESTART;
+        if unsafe { libc::sigaction(signal, &action, std::ptr::null_mut()) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    thread::spawn(move || {
+        let mut byte = [0u8];
+        while reader.read_exact(&mut byte).is_ok() {
+            on_signal(byte[0] as libc::c_int);
+        }
+    });
+    Ok(())
+}
+
+/// Carry on with what the signal would have done if we hadn't caught it: stop until we're
+/// continued, or exit.
+pub fn resume_default(signal: libc::c_int) {
+    if signal == libc::SIGTSTP {
+        unsafe {
+            libc::raise(libc::SIGSTOP);
+        }
+    } else {
+        std::process::exit(0);
+    }
+}
@@ -0,0 +1,94 @@
+use crate::markdown;
+use std::ops::Range;
+
+const MARKERS: &[&str] = &["TODO", "FIXME"];
+
+/// A line that looks like something left to do.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Task {
+    pub row: usize,
+    /// For checkbox items, whether the box is ticked.
+    pub checked: Option<bool>,
+}
+
+/// Whether the line is a checkbox list item, and if so whether it's ticked.
+pub fn checkbox(line: &str) -> Option<bool> {
+    let (_, marker) = markdown::list_marker(line)?;
+    if marker.ends_with("[ ] ") {
+        Some(false)
+    } else if marker.ends_with("[x] ") || marker.ends_with("[X] ") {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// The columns of the `[ ]` on a checkbox line, brackets included, and whether it's ticked.
+pub fn checkbox_cells(line: &str) -> Option<(Range<usize>, bool)> {
+    let checked = checkbox(line)?;
+    let (indent, marker) = markdown::list_marker(line)?;
+    let end = indent.chars().count() + marker.chars().count() - 1;
+    Some((end - 3..end, checked))
+}
+
+/// Find the lines with a TODO or FIXME marker or a checkbox, in order. Ticked boxes are
+/// included, so they can be unticked again.
+pub fn scan<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<Task> {
+    lines
+        .into_iter()
+        .enumerate()
+        .filter_map(|(row, line)| {
+            let checked = checkbox(line);
+            let marked = MARKERS.iter().any(|m| line.contains(m));
+            (checked.is_some() || marked).then_some(Task { row, checked })
+        })
+        .collect()
+}
+
+/// Flip the checkbox on the line between `[ ]` and `[x]`, if it has one.
+pub fn toggle(line: &str) -> Option<String> {
+    let (indent, marker) = markdown::list_marker(line)?;
+    let box_start = indent.len() + marker.len() - 4;
+    let ticked = match checkbox(line)? {
+        true => "[ ]",
+        false => "[x]",
+    };
+    Some(format!(
+        "{}{ticked}{}",
+        &line[..box_start],
+        &line[(box_start + 3)..]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_and_toggle() {
+        let text = "# Plan\n- [ ] milk\n  - [x] eggs\nTODO: call back\n- plain item";
+        let tasks = scan(text.lines());
+        assert_eq!(
+            tasks,
+            vec![
+                Task {
+                    row: 1,
+                    checked: Some(false)
+                },
+                Task {
+                    row: 2,
+                    checked: Some(true)
+                },
+                Task {
+                    row: 3,
+                    checked: None
+                },
+            ]
+        );
+        assert_eq!(toggle("- [ ] milk"), Some("- [x] milk".to_string()));
+        assert_eq!(toggle("  3. [X] eggs"), Some("  3. [ ] eggs".to_string()));
+        assert_eq!(toggle("TODO: call back"), None);
+        assert_eq!(checkbox_cells("  - [x] eggs"), Some((4..7, true)));
+        assert_eq!(checkbox_cells("- plain item"), None);
+    }
+}
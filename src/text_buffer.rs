@@ -1,3 +1,7 @@
+use std::ops::{Range, RangeInclusive};
+
+use crate::rope::Rope;
+
 type Coord = (usize, usize);
 
 pub fn add_coord(a: Coord, b: Coord) -> Coord {
@@ -8,6 +12,89 @@ pub fn add_coord(a: Coord, b: Coord) -> Coord {
     }
 }
 
+/// The run of spaces that an arc drawn from column `from` to column `to` joins up: the first gap
+/// in between with a word on either side, as the range of columns to remove.
+pub fn joined_gap(line: &[char], from: usize, to: usize) -> Option<Range<usize>> {
+    let last = to.min(line.len().checked_sub(1)?);
+    let start = (from..=last).find(|i| line[*i].is_whitespace())?;
+    let end = (start..line.len()).find(|i| !line[*i].is_whitespace())?;
+    // A little slack at the right, since the arc may stop just short of the next word.
+    let bounded = start > 0 && !line[start - 1].is_whitespace() && end <= to + 1;
+    bounded.then_some(start..end)
+}
+
+/// The columns to delete for a strike through columns `from` to `to`: every word it touches,
+/// whole, along with the spaces after the last one, or before the first if it ends the line,
+/// so the words either side are left one gap apart.
+pub fn struck_words(line: &[char], from: usize, to: usize) -> Option<Range<usize>> {
+    let to = to.min(line.len());
+    let first = (from..to).find(|i| !line[*i].is_whitespace())?;
+    let last = (first..to).rev().find(|i| !line[*i].is_whitespace())?;
+    let mut start = first;
+    while start > 0 && !line[start - 1].is_whitespace() {
+        start -= 1;
+    }
+    let mut end = last + 1;
+    while end < line.len() && !line[end].is_whitespace() {
+        end += 1;
+    }
+    let after = (end..line.len())
+        .find(|i| !line[*i].is_whitespace())
+        .unwrap_or(line.len());
+    if after < line.len() {
+        end = after;
+    } else {
+        end = line.len();
+        while start > 0 && line[start - 1].is_whitespace() {
+            start -= 1;
+        }
+    }
+    Some(start..end)
+}
+
+/// Swap what's either side of the column: the two characters, or, if it's in the space between
+/// two words, the words. At the end of the line, the last two characters swap. Returns the
+/// columns to replace and what to replace them with.
+pub fn transpose(line: &[char], col: usize) -> Option<(Range<usize>, Vec<char>)> {
+    let col = col.min(line.len());
+    let space = |i: usize| line[i].is_whitespace();
+    let in_gap = (col < line.len() && space(col)) || (col > 0 && space(col - 1));
+    if !in_gap {
+        let col = if col == line.len() {
+            col.checked_sub(1)?
+        } else {
+            col
+        };
+        return (col > 0).then(|| (col - 1..col + 1, vec![line[col], line[col - 1]]));
+    }
+    let mut gap_start = col;
+    while gap_start > 0 && space(gap_start - 1) {
+        gap_start -= 1;
+    }
+    let mut gap_end = col;
+    while gap_end < line.len() && space(gap_end) {
+        gap_end += 1;
+    }
+    let mut start = gap_start;
+    while start > 0 && !space(start - 1) {
+        start -= 1;
+    }
+    let mut end = gap_end;
+    while end < line.len() && !space(end) {
+        end += 1;
+    }
+    if start == gap_start || end == gap_end {
+        return None;
+    }
+    let swapped = [
+        &line[gap_end..end],
+        &line[gap_start..gap_end],
+        &line[start..gap_start],
+    ]
+    .concat();
+    Some((start..end, swapped))
+}
+
 pub fn diff_coord(a: Coord, b: Coord) -> Coord {
     let (a, b) = if a < b { (a, b) } else { (b, a) };
     if a.0 == b.0 {
@@ -17,9 +104,34 @@ pub fn diff_coord(a: Coord, b: Coord) -> Coord {
     }
 }
 
+/// What to do with writing below the last line of the buffer.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PastEnd {
+    /// Write on the end of the last line instead, in the order it was written.
+    Clamp,
+    /// Add blank lines down to where the writing is, and start it at the left margin.
+    Lines,
+    /// Add blank lines, and indent the writing to where it was written.
+    #[default]
+    Spaces,
+}
+
+impl PastEnd {
+    pub fn from_config(value: &str) -> PastEnd {
+        match value {
+            "clamp" => PastEnd::Clamp,
+            "lines" => PastEnd::Lines,
+            _ => PastEnd::Spaces,
+        }
+    }
+}
+
+const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+const QUOTES: [char; 3] = ['"', '\'', '`'];
+
 #[derive(Clone)]
 pub struct TextBuffer {
-    pub contents: Vec<Vec<char>>,
+    pub contents: Rope,
 }
 
 impl Default for TextBuffer {
@@ -31,19 +143,21 @@ impl Default for TextBuffer {
 impl TextBuffer {
     pub fn empty() -> TextBuffer {
         TextBuffer {
-            contents: vec![vec![]],
+            contents: Rope::from(vec![vec![]]),
         }
     }
 
     pub fn from_string(str: &str) -> TextBuffer {
-        let contents: Vec<_> = str.split('\n').map(|line| line.chars().collect()).collect();
+        let contents = str.split('\n').map(|line| line.chars().collect()).collect();
         TextBuffer { contents }
     }
 
     pub fn padding(coord: Coord) -> TextBuffer {
         let mut contents = vec![vec![]; coord.0];
         contents.push(vec![' '; coord.1]);
-        TextBuffer { contents }
+        TextBuffer {
+            contents: contents.into(),
+        }
     }
 
     /// Given a coordinate, find the nearest valid coordinate in the text.
@@ -61,6 +175,47 @@ impl TextBuffer {
         }
     }
 
+    /// Where characters written at the given coordinates should go. Writes within the text, or
+    /// past the end of a line, stay put; ones below the last line move as the mode says.
+    pub fn past_end(&self, coords: &[Coord], mode: PastEnd) -> Vec<Coord> {
+        let last = self.contents.len() - 1;
+        let margin = coords
+            .iter()
+            .filter(|(row, _)| *row > last)
+            .map(|(_, col)| *col)
+            .min()
+            .unwrap_or(0);
+        // Clamped writing starts after the last line and anything written on the end of it, and
+        // each char goes after the one before, keeping any gap between them on the same row.
+        let mut clamped: Option<(Coord, usize)> = None;
+        let start = coords
+            .iter()
+            .filter(|(row, _)| *row == last)
+            .map(|(_, col)| col + 1)
+            .fold(self.contents[last].len(), usize::max);
+        coords
+            .iter()
+            .map(|&(row, col)| match mode {
+                _ if row <= last => (row, col),
+                PastEnd::Clamp => {
+                    let at = match clamped {
+                        None => start,
+                        Some(((prev_row, prev_col), prev_at))
+                            if prev_row == row && col > prev_col =>
+                        {
+                            prev_at + (col - prev_col)
+                        }
+                        Some((_, prev_at)) => prev_at + 1,
+                    };
+                    clamped = Some(((row, col), at));
+                    (last, at)
+                }
+                PastEnd::Lines => (row, col - margin),
+                PastEnd::Spaces => (row, col),
+            })
+            .collect()
+    }
+
     pub fn split_off(&mut self, at: Coord) -> TextBuffer {
         let (row, col) = self.clamp(at);
         let insert_row = &mut self.contents[row];
@@ -68,7 +223,9 @@ impl TextBuffer {
         let mut result = Vec::with_capacity(self.contents.len() - row);
         result.push(trailer);
         result.extend(self.contents.drain((row + 1)..));
-        TextBuffer { contents: result }
+        TextBuffer {
+            contents: result.into(),
+        }
     }
 
     pub fn append(&mut self, buffer: TextBuffer) {
@@ -94,13 +251,28 @@ impl TextBuffer {
             replace
         };
 
-        let trailer = self.split_off(replace.until);
-        let undo_content = self.split_off(replace.from);
-        self.append(replace.content);
-        let undo_until = self.end();
-        self.append(trailer);
+        // Splice in just the rows the edit touches. The rope only copies the runs of lines
+        // they're in, so the rest of the buffer, and any undo snapshots, are left alone.
+        let until = self.clamp(replace.until);
+        let from = self.clamp(replace.from).min(until);
+        let undo_content = self.copy(from, until);
+        let (from_row, from_col) = from;
+        let (until_row, until_col) = until;
+        let mut lines: Vec<Vec<char>> = replace.content.contents.into_iter().collect();
+        let last = lines.len() - 1;
+        let undo_until = match last {
+            0 => (from_row, from_col + lines[0].len()),
+            _ => (from_row + last, lines[last].len()),
+        };
+        lines[last].extend_from_slice(&self.contents[until_row][until_col..]);
+        lines[0].splice(0..0, self.contents[from_row][..from_col].iter().copied());
+        if from_row == until_row && last == 0 {
+            self.contents[from_row] = lines.pop().unwrap();
+        } else {
+            self.contents.splice(from_row..=until_row, lines);
+        }
         Replace {
-            from: replace.from,
+            from,
             until: undo_until,
             content: undo_content,
         }
@@ -115,14 +287,284 @@ impl TextBuffer {
             vec![self.contents[from_row][from_col..until_col].to_vec()]
         } else {
             let mut contents = vec![self.contents[from_row][from_col..].to_vec()];
-            contents.extend(self.contents[(from_row + 1)..until_row].iter().cloned());
+            contents.extend(self.contents.range((from_row + 1)..until_row).cloned());
             contents.push(self.contents[until_row][..until_col].to_vec());
             contents
         };
 
+        TextBuffer {
+            contents: contents.into(),
+        }
+    }
+
+    /// Approximate heap usage of the buffer, in bytes.
+    pub fn memory_size(&self) -> usize {
+        let lines: usize = self
+            .contents
+            .iter()
+            .map(|l| l.capacity() * std::mem::size_of::<char>())
+            .sum();
+        lines + self.contents.len() * std::mem::size_of::<Vec<char>>()
+    }
+
+    /// Copy a range for the clipboard. A range that starts and ends at the beginning of a line
+    /// holds whole lines, and is pasted as whole lines too.
+    pub fn clip(&self, from: Coord, until: Coord) -> Clip {
+        Clip {
+            text: self.copy(from, until),
+            line_wise: from.1 == 0 && until.1 == 0 && until.0 > from.0,
+            block: false,
+        }
+    }
+
+    /// The columns `cols` of each of the rows, padded with spaces so every line is the full
+    /// width of the block.
+    pub fn copy_block(&self, rows: RangeInclusive<usize>, cols: Range<usize>) -> TextBuffer {
+        let contents = rows
+            .map(|row| {
+                let line = self.contents.get(row).map_or(&[][..], |l| &l[..]);
+                let (start, end) = (cols.start.min(line.len()), cols.end.min(line.len()));
+                let mut part = line[start..end].to_vec();
+                part.resize(cols.len(), ' ');
+                part
+            })
+            .collect();
         TextBuffer { contents }
     }
 
+    /// An edit that removes the columns `cols` from each of the rows, closing up the gap.
+    pub fn remove_block(&self, rows: RangeInclusive<usize>, cols: Range<usize>) -> Replace {
+        self.edit_lines(rows, |_, line| {
+            if line.len() > cols.start {
+                line.drain(cols.start..cols.end.min(line.len()));
+            }
+        })
+    }
+
+    /// An edit that puts each line of the block into the lines from the coordinate down, all at
+    /// the same column, adding lines and spaces as needed so the block's columns stay lined up.
+    pub fn paste_block(&self, (row, col): Coord, block: &TextBuffer) -> Replace {
+        let rows = row..=(row + block.contents.len() - 1);
+        self.edit_lines(rows, |i, line| {
+            if line.len() < col {
+                line.resize(col, ' ');
+            }
+            line.splice(col..col, block.contents[i].iter().copied());
+        })
+    }
+
+    /// Rewrite each of a run of lines, as a single edit. Lines past the end start out empty.
+    fn edit_lines(
+        &self,
+        rows: RangeInclusive<usize>,
+        mut edit: impl FnMut(usize, &mut Vec<char>),
+    ) -> Replace {
+        let (first, last) = (*rows.start(), *rows.end());
+        let mut contents: Vec<Vec<char>> = rows
+            .map(|row| self.contents.get(row).cloned().unwrap_or_default())
+            .collect();
+        for (i, line) in contents.iter_mut().enumerate() {
+            edit(i, line);
+        }
+        Replace {
+            from: (first, 0),
+            until: (last, self.contents.get(last).map_or(0, |l| l.len())),
+            content: TextBuffer {
+                contents: contents.into(),
+            },
+        }
+    }
+
+    /// The start of every non-overlapping occurrence of the token, in order.
+    pub fn find_all(&self, token: &[char]) -> Vec<Coord> {
+        let mut found = vec![];
+        if token.is_empty() {
+            return found;
+        }
+        for (row, line) in self.contents.iter().enumerate() {
+            let mut col = 0;
+            while col + token.len() <= line.len() {
+                if &line[col..(col + token.len())] == token {
+                    found.push((row, col));
+                    col += token.len();
+                } else {
+                    col += 1;
+                }
+            }
+        }
+        found
+    }
+
+    /// The char at the coordinate, with line breaks reported as newlines.
+    fn char_at(&self, (row, col): Coord) -> Option<char> {
+        let line = self.contents.get(row)?;
+        match line.get(col) {
+            Some(c) => Some(*c),
+            None if col == line.len() && row + 1 < self.contents.len() => Some('\n'),
+            None => None,
+        }
+    }
+
+    fn step_forward(&self, (row, col): Coord) -> Option<Coord> {
+        let len = self.contents.get(row)?.len();
+        if col < len {
+            Some((row, col + 1))
+        } else if row + 1 < self.contents.len() {
+            Some((row + 1, 0))
+        } else {
+            None
+        }
+    }
+
+    fn step_back(&self, (row, col): Coord) -> Option<Coord> {
+        if col > 0 {
+            Some((row, col - 1))
+        } else if row > 0 {
+            Some((row - 1, self.contents[row - 1].len()))
+        } else {
+            None
+        }
+    }
+
+    /// If there's a bracket or quote at the coordinate, where its partner is, if anywhere. Pairs
+    /// of the same brackets in between are skipped over, and the search gives up after
+    /// `max_lines` lines. Quotes only pair up within a line, and an escaped quote doesn't count.
+    pub fn matching_bracket(&self, (row, col): Coord, max_lines: usize) -> Option<Option<Coord>> {
+        let line = self.contents.get(row)?;
+        let c = *line.get(col)?;
+        if QUOTES.contains(&c) {
+            let is_quote = |i: usize| line[i] == c && (i == 0 || line[i - 1] != '\\');
+            if col > 0 && line[col - 1] == '\\' {
+                return None;
+            }
+            let before = (0..col).filter(|i| is_quote(*i)).count();
+            let partner = if before % 2 == 0 {
+                (col + 1..line.len()).find(|i| is_quote(*i))
+            } else {
+                (0..col).rev().find(|i| is_quote(*i))
+            };
+            return Some(partner.map(|i| (row, i)));
+        }
+        let (forward, open, close) = BRACKETS.iter().find_map(|&(open, close)| {
+            if c == open {
+                Some((true, open, close))
+            } else if c == close {
+                Some((false, open, close))
+            } else {
+                None
+            }
+        })?;
+        let mut depth = 0usize;
+        let mut step = |at: Coord| {
+            let c = self.contents[at.0][at.1];
+            if c == open && forward || c == close && !forward {
+                depth += 1;
+            } else if c == open || c == close {
+                if depth == 0 {
+                    return true;
+                }
+                depth -= 1;
+            }
+            false
+        };
+        if forward {
+            let last = (row + max_lines).min(self.contents.len() - 1);
+            for r in row..=last {
+                let start = if r == row { col + 1 } else { 0 };
+                for i in start..self.contents[r].len() {
+                    if step((r, i)) {
+                        return Some(Some((r, i)));
+                    }
+                }
+            }
+        } else {
+            for r in (row.saturating_sub(max_lines)..=row).rev() {
+                let end = if r == row {
+                    col
+                } else {
+                    self.contents[r].len()
+                };
+                for i in (0..end).rev() {
+                    if step((r, i)) {
+                        return Some(Some((r, i)));
+                    }
+                }
+            }
+        }
+        Some(None)
+    }
+
+    /// The start of the next word after the coordinate, where words are runs of non-whitespace.
+    pub fn next_word(&self, at: Coord) -> Coord {
+        let mut at = self.clamp(at);
+        let is_word = |at| matches!(self.char_at(at), Some(c) if !c.is_whitespace());
+        while is_word(at) {
+            match self.step_forward(at) {
+                Some(next) => at = next,
+                None => return at,
+            }
+        }
+        while !is_word(at) {
+            match self.step_forward(at) {
+                Some(next) => at = next,
+                None => return at,
+            }
+        }
+        at
+    }
+
+    /// The start of the word before the coordinate.
+    pub fn prev_word(&self, at: Coord) -> Coord {
+        let mut at = self.clamp(at);
+        let is_word = |at| matches!(self.char_at(at), Some(c) if !c.is_whitespace());
+        loop {
+            match self.step_back(at) {
+                Some(prev) if !is_word(prev) => at = prev,
+                _ => break,
+            }
+        }
+        loop {
+            match self.step_back(at) {
+                Some(prev) if is_word(prev) => at = prev,
+                _ => break,
+            }
+        }
+        at
+    }
+
+    fn is_blank(&self, row: usize) -> bool {
+        self.contents[row].iter().all(|c| c.is_whitespace())
+    }
+
+    /// The first blank line after the paragraph at or below the given row, or the end of the text.
+    pub fn next_paragraph(&self, row: usize) -> Coord {
+        let rows = self.contents.len();
+        let mut row = (row + 1).min(rows);
+        while row < rows && self.is_blank(row) {
+            row += 1;
+        }
+        while row < rows && !self.is_blank(row) {
+            row += 1;
+        }
+        if row < rows {
+            (row, 0)
+        } else {
+            self.end()
+        }
+    }
+
+    /// The last blank line before the paragraph at or above the given row, or the start of the text.
+    pub fn prev_paragraph(&self, row: usize) -> Coord {
+        let mut row = row.min(self.contents.len() - 1);
+        while row > 0 && self.is_blank(row - 1) {
+            row -= 1;
+        }
+        while row > 0 && !self.is_blank(row - 1) {
+            row -= 1;
+        }
+        (row.saturating_sub(1), 0)
+    }
+
     pub fn end(&self) -> Coord {
         let row = self.contents.len() - 1;
         (row, self.contents[row].len())
@@ -175,6 +617,65 @@ impl Replace {
     }
 }
 
+#[derive(Clone)]
+pub struct Clip {
+    pub text: TextBuffer,
+    pub line_wise: bool,
+    /// Cut from a block selection: pasted in as a column, one line into each row.
+    pub block: bool,
+}
+
+impl Clip {
+    /// Paste the clip at the given coordinate: line-wise clips go in above the current line,
+    /// instead of splitting it.
+    pub fn paste_at(&self, (row, col): Coord) -> Replace {
+        let at = if self.line_wise { (row, 0) } else { (row, col) };
+        Replace::splice(at, self.text.clone())
+    }
+
+    /// Paste the clip into the buffer at the given coordinate, keeping a block clip's columns
+    /// lined up.
+    pub fn paste_into(&self, buffer: &TextBuffer, at: Coord) -> Replace {
+        if self.block {
+            buffer.paste_block(at, &self.text)
+        } else {
+            self.paste_at(at)
+        }
+    }
+
+    /// Paste the clip in place of a block of the buffer, as a single edit so it undoes in one
+    /// step.
+    pub fn paste_over_block(
+        &self,
+        buffer: &TextBuffer,
+        rows: RangeInclusive<usize>,
+        cols: Range<usize>,
+    ) -> Replace {
+        let col = cols.start;
+        let mut edit = buffer.remove_block(rows, cols);
+        // The removal rewrites whole lines, so the paste can go into its content directly.
+        let paste = self.paste_into(&edit.content, (0, col));
+        edit.content.replace(paste);
+        edit
+    }
+
+    /// Gather several clips into one, each on its own line, for pasting as a block.
+    pub fn join<'a>(clips: impl IntoIterator<Item = &'a Clip>) -> Clip {
+        let mut joined = String::new();
+        for clip in clips {
+            joined.push_str(&clip.text.content_string());
+            if !clip.line_wise {
+                joined.push('\n');
+            }
+        }
+        Clip {
+            text: TextBuffer::from_string(&joined),
+            line_wise: true,
+            block: false,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct IndexedString {
     string: String,
@@ -223,6 +724,182 @@ mod tests {
         assert_eq!(undo.content.content_string().as_str(), "");
     }
 
+    #[test]
+    fn test_replace_in_place() {
+        let mut buffer = TextBuffer::from_string("one\ntwo\nthree\nfour");
+        let undo = buffer.replace(Replace {
+            from: (1, 1),
+            until: (2, 3),
+            content: TextBuffer::from_string("x\ny\nz"),
+        });
+        assert_eq!(buffer.content_string(), "one\ntx\ny\nzee\nfour");
+        assert_eq!((undo.from, undo.until), ((1, 1), (3, 1)));
+        buffer.replace(undo);
+        assert_eq!(buffer.content_string(), "one\ntwo\nthree\nfour");
+
+        let undo = buffer.replace(Replace::write((0, 1), 'z'));
+        assert_eq!(buffer.content_string(), "oze\ntwo\nthree\nfour");
+        assert_eq!(undo.content.content_string(), "n");
+        buffer.replace(Replace::remove((2, 2), (9, 0)));
+        assert_eq!(buffer.content_string(), "oze\ntwo\nth");
+    }
+
+    #[test]
+    fn test_past_end() {
+        let buffer = TextBuffer::from_string("one\ntwo");
+        let coords = [(1, 5), (4, 6), (4, 7)];
+        assert_eq!(
+            buffer.past_end(&coords, PastEnd::Clamp),
+            vec![(1, 5), (1, 6), (1, 7)]
+        );
+        assert_eq!(
+            buffer.past_end(&[(3, 2), (4, 6), (4, 8), (4, 1)], PastEnd::Clamp),
+            vec![(1, 3), (1, 4), (1, 6), (1, 7)]
+        );
+        assert_eq!(
+            buffer.past_end(&coords, PastEnd::Lines),
+            vec![(1, 5), (4, 0), (4, 1)]
+        );
+        assert_eq!(buffer.past_end(&coords, PastEnd::Spaces), coords.to_vec());
+
+        let mut buffer = buffer;
+        buffer.replace(Replace::write((4, 2), 'x'));
+        assert_eq!(buffer.content_string(), "one\ntwo\n\n\n  x");
+    }
+
+    #[test]
+    fn test_block() {
+        let mut table = TextBuffer::from_string("a   1  x\nbb  22\nccc 333 z");
+        let block = table.copy_block(0..=2, 4..7);
+        assert_eq!(block.content_string(), "1  \n22 \n333");
+
+        let undo = table.replace(table.remove_block(0..=2, 4..7));
+        assert_eq!(table.content_string(), "a   x\nbb  \nccc  z");
+        table.replace(undo);
+        assert_eq!(table.content_string(), "a   1  x\nbb  22\nccc 333 z");
+
+        let mut short = TextBuffer::from_string("one\ntwo");
+        short.replace(short.paste_block((1, 5), &block));
+        assert_eq!(short.content_string(), "one\ntwo  1  \n     22 \n     333");
+
+        let clip = Clip {
+            text: TextBuffer::from_string("A\nB"),
+            line_wise: false,
+            block: true,
+        };
+        let undo = table.replace(clip.paste_over_block(&table, 1..=2, 4..7));
+        assert_eq!(table.content_string(), "a   1  x\nbb  A\nccc B z");
+        table.replace(undo);
+        assert_eq!(table.content_string(), "a   1  x\nbb  22\nccc 333 z");
+    }
+
+    #[test]
+    fn test_matching_bracket() {
+        let buffer = TextBuffer::from_string("f(a[0], (b)) {\n  \"x\\\"y\" ]\n}");
+        assert_eq!(buffer.matching_bracket((0, 1), 10), Some(Some((0, 11))));
+        assert_eq!(buffer.matching_bracket((0, 11), 10), Some(Some((0, 1))));
+        assert_eq!(buffer.matching_bracket((0, 3), 10), Some(Some((0, 5))));
+        assert_eq!(buffer.matching_bracket((0, 13), 10), Some(Some((2, 0))));
+        assert_eq!(buffer.matching_bracket((2, 0), 10), Some(Some((0, 13))));
+        assert_eq!(buffer.matching_bracket((0, 13), 1), Some(None));
+        assert_eq!(buffer.matching_bracket((1, 2), 10), Some(Some((1, 7))));
+        assert_eq!(buffer.matching_bracket((1, 7), 10), Some(Some((1, 2))));
+        assert_eq!(buffer.matching_bracket((1, 9), 10), Some(None));
+        assert_eq!(buffer.matching_bracket((0, 0), 10), None);
+    }
+
+    #[test]
+    fn test_joined_gap() {
+        let line: Vec<char> = "to gether  again".chars().collect();
+        assert_eq!(joined_gap(&line, 1, 3), Some(2..3));
+        assert_eq!(joined_gap(&line, 0, 4), Some(2..3));
+        assert_eq!(joined_gap(&line, 8, 11), Some(9..11));
+        // Nothing to join in the middle of a word, or at the end of the line.
+        assert_eq!(joined_gap(&line, 3, 6), None);
+        assert_eq!(joined_gap(&line, 14, 20), None);
+        // The arc has to reach the next word.
+        assert_eq!(joined_gap(&line, 7, 9), None);
+    }
+
+    #[test]
+    fn test_struck_words() {
+        let strike = |text: &str, from, to| {
+            let mut line: Vec<char> = text.chars().collect();
+            line.drain(struck_words(&line, from, to)?);
+            Some(line.into_iter().collect::<String>())
+        };
+        assert_eq!(strike("a quick brown fox", 3, 5).unwrap(), "a brown fox");
+        assert_eq!(strike("a quick brown fox", 1, 11).unwrap(), "a fox");
+        assert_eq!(
+            strike("a quick brown fox", 14, 20).unwrap(),
+            "a quick brown"
+        );
+        assert_eq!(strike("  indented line", 2, 9).unwrap(), "  line");
+        assert_eq!(strike("gap   here", 3, 6), None);
+    }
+
+    #[test]
+    fn test_transpose() {
+        let swap = |text: &str, col| {
+            let mut line: Vec<char> = text.chars().collect();
+            let (range, swapped) = transpose(&line, col)?;
+            line.splice(range, swapped);
+            Some(line.into_iter().collect::<String>())
+        };
+        assert_eq!(swap("teh cat", 2).as_deref(), Some("the cat"));
+        assert_eq!(swap("teh", 3).as_deref(), Some("the"));
+        assert_eq!(swap("cat the  sat", 4).as_deref(), Some("the cat  sat"));
+        assert_eq!(swap("cat the  sat", 8).as_deref(), Some("cat sat  the"));
+        assert_eq!(swap("cat ", 4), None);
+        assert_eq!(swap("a", 0), None);
+        assert_eq!(swap("", 0), None);
+    }
+
+    #[test]
+    fn test_line_wise_paste() {
+        let mut buffer = TextBuffer::from_string("one\ntwo\nthree");
+        let clip = buffer.clip((1, 0), (2, 0));
+        assert!(clip.line_wise);
+        buffer.replace(clip.paste_at((0, 2)));
+        assert_eq!(buffer.content_string(), "two\none\ntwo\nthree");
+
+        let clip = buffer.clip((1, 1), (2, 0));
+        assert!(!clip.line_wise);
+        buffer.replace(clip.paste_at((0, 2)));
+        assert_eq!(buffer.content_string(), "twne\no\none\ntwo\nthree");
+    }
+
+    #[test]
+    fn test_join_clips() {
+        let buffer = TextBuffer::from_string("one\ntwo\nthree");
+        let clips = [buffer.clip((0, 0), (0, 3)), buffer.clip((1, 0), (2, 0))];
+        let joined = Clip::join(&clips);
+        assert!(joined.line_wise);
+        assert_eq!(joined.text.content_string(), "one\ntwo\n");
+    }
+
+    #[test]
+    fn test_find_all() {
+        let buffer = TextBuffer::from_string("aaa x\nx aa");
+        let token: Vec<char> = "aa".chars().collect();
+        assert_eq!(buffer.find_all(&token), vec![(0, 0), (1, 2)]);
+    }
+
+    #[test]
+    fn test_navigation() {
+        let buffer = TextBuffer::from_string("one two\n  three\n\nfour\nfive\n\nsix");
+        assert_eq!(buffer.next_word((0, 1)), (0, 4));
+        assert_eq!(buffer.next_word((0, 4)), (1, 2));
+        assert_eq!(buffer.prev_word((1, 2)), (0, 4));
+        assert_eq!(buffer.prev_word((0, 5)), (0, 4));
+        assert_eq!(buffer.prev_word((0, 2)), (0, 0));
+        assert_eq!(buffer.next_paragraph(0), (2, 0));
+        assert_eq!(buffer.next_paragraph(2), (5, 0));
+        assert_eq!(buffer.next_paragraph(5), (6, 3));
+        assert_eq!(buffer.prev_paragraph(4), (2, 0));
+        assert_eq!(buffer.prev_paragraph(1), (0, 0));
+    }
+
     #[test]
     fn test_copy() {
         let waistcoat = TextBuffer::from_string("waistcoat\n");
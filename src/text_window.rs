@@ -1,4 +1,7 @@
-use crate::util::rotate_queue;
+use crate::dictionary::{is_word_char, Dictionary};
+use crate::highlight::{highlight_line, Language, Style};
+use crate::rope::Rope;
+use crate::util::{code_point_before, decode_code_points, rotate_queue};
 use crate::*;
 use armrest::dollar::Points;
 use armrest::ink::Ink;
@@ -6,18 +9,74 @@ use armrest::ui::{View, Widget};
 use std::cmp::Ordering;
 use std::collections::{BTreeSet, VecDeque};
 use std::mem;
+use std::ops::{Range, RangeInclusive};
 use std::rc::Rc;
 use textwrap;
 use textwrap::Options;
 
 const NUM_RECENT_RECOGNITIONS: usize = 10;
+/// How many other guesses to offer when a recently-written cell is tapped.
+pub const NUM_ALTERNATIVES: usize = 4;
+/// Characters the recognizer wasn't sure of are drawn about as light as comments.
+const UNSURE_WEIGHT: u8 = 100;
 const NUM_UNDOS: usize = 64;
+/// How far to look for the partner of a bracket, in lines.
+const BRACKET_SEARCH_LINES: usize = 500;
 
 pub enum TextMessage {
     Write(Ink),
     Erase(Ink),
 }
 
+/// The keys in the on-screen navigation panel.
+#[derive(Clone, Copy, Debug)]
+pub enum NavKey {
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Backspace,
+    Enter,
+}
+
+impl NavKey {
+    pub const ALL: [NavKey; 10] = [
+        NavKey::Left,
+        NavKey::Right,
+        NavKey::Up,
+        NavKey::Down,
+        NavKey::PageUp,
+        NavKey::PageDown,
+        NavKey::Home,
+        NavKey::End,
+        NavKey::Backspace,
+        NavKey::Enter,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            NavKey::Up => "↑",
+            NavKey::Down => "↓",
+            NavKey::Left => "←",
+            NavKey::Right => "→",
+            NavKey::PageUp => "pg↑",
+            NavKey::PageDown => "pg↓",
+            NavKey::Home => "home",
+            NavKey::End => "end",
+            NavKey::Backspace => "⌫",
+            NavKey::Enter => "⏎",
+        }
+    }
+
+    pub fn edits(self) -> bool {
+        matches!(self, NavKey::Backspace | NavKey::Enter)
+    }
+}
+
 #[derive(Clone)]
 
 pub struct Carat {
@@ -25,11 +84,33 @@ pub struct Carat {
     pub ink: Ink,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub enum Selection<T = Carat> {
     Normal,
-    Single { carat: T },
-    Range { start: T, end: T },
+    Single {
+        carat: T,
+    },
+    Range {
+        start: T,
+        end: T,
+    },
+    /// Several insertion points at once; what's written at one is repeated at all of them.
+    Multi {
+        carats: Vec<T>,
+    },
+    /// The rectangle with a carat at two opposite corners, for working on columns.
+    Block {
+        start: T,
+        end: T,
+    },
+}
+
+/// The rows and columns of a block selection with carats at the given corners.
+pub fn block_bounds(start: Coord, end: Coord) -> (RangeInclusive<usize>, Range<usize>) {
+    (
+        start.0.min(end.0)..=start.0.max(end.0),
+        start.1.min(end.1)..start.1.max(end.1),
+    )
 }
 
 impl<T> Default for Selection<T> {
@@ -43,6 +124,8 @@ pub struct Recognition {
     coord: Coord,
     ink: Ink,
     recognized_as: char,
+    /// The next-best guesses for the ink, best first.
+    alternatives: Vec<char>,
     overwrites: Vec<Ink>,
 }
 
@@ -58,6 +141,23 @@ pub struct TextWindow {
     pub undos: VecDeque<Replace>,
     pub redos: Vec<Replace>,
     tentative_recognitions: VecDeque<Recognition>,
+    /// Cells where the recognizer couldn't tell the best match from the next best, drawn light
+    /// so they're easy to check.
+    unsure: BTreeSet<Coord>,
+    /// If set, the text is drawn with syntax highlighting for this language.
+    pub language: Option<Language>,
+    /// Wrap long lines onto the following screen rows, instead of running off the edge.
+    pub wrap: bool,
+    /// Draw every character as a bullet, for passphrases. Nothing written in a masked window
+    /// is learned from, so passphrases don't leak into the templates or their usage counts.
+    pub masked: bool,
+    /// Fix up written words with the dictionary, if there is one.
+    pub spellcheck: bool,
+    /// Where writing below the last line ends up.
+    pub past_end: PastEnd,
+    /// Push the rest of the line right to make room for new characters, instead of writing over
+    /// what's there.
+    pub insert: bool,
 }
 
 impl TextWindow {
@@ -78,14 +178,100 @@ impl TextWindow {
             undos: VecDeque::new(),
             redos: vec![],
             tentative_recognitions: VecDeque::new(),
+            unsure: BTreeSet::new(),
+            language: None,
+            wrap: false,
+            masked: false,
+            spellcheck: false,
+            past_end: PastEnd::default(),
+            insert: false,
+        }
+    }
+
+    /// The buffer position shown at the start of each screen row. Without wrapping, that's
+    /// just the origin shifted down; with it, a long line takes up several rows in turn.
+    pub fn screen_rows(&self) -> Vec<Coord> {
+        let (rows, _) = self.dimensions;
+        let mut result = Vec::with_capacity(rows);
+        let mut at = self.origin;
+        while result.len() < rows {
+            result.push(at);
+            at = self.next_segment(at);
         }
+        result
+    }
+
+    /// The start of the screen row after the one starting at the given position.
+    fn next_segment(&self, (row, col): Coord) -> Coord {
+        let cols = self.dimensions.1.max(1);
+        // The end-of-line marker takes up a cell too.
+        let len = self.buffer.contents.get(row).map_or(0, |l| l.len());
+        if self.wrap && col + cols <= len {
+            (row, col + cols)
+        } else {
+            (row + 1, if self.wrap { 0 } else { col })
+        }
+    }
+
+    /// The start of the screen row before the one starting at the given position.
+    fn prev_segment(&self, (row, col): Coord) -> Coord {
+        let cols = self.dimensions.1.max(1);
+        if !self.wrap {
+            (row.saturating_sub(1), col)
+        } else if col >= cols {
+            (row, col - cols)
+        } else if row == 0 {
+            (0, 0)
+        } else {
+            let len = self.buffer.contents.get(row - 1).map_or(0, |l| l.len());
+            (row - 1, len / cols * cols)
+        }
+    }
+
+    /// The start of the wrapped screen row that holds the given position.
+    fn segment_of(&self, (row, col): Coord) -> Coord {
+        let cols = self.dimensions.1.max(1);
+        let len = self.buffer.contents.get(row).map_or(0, |l| l.len());
+        (row, col.min(len) / cols * cols)
+    }
+
+    /// Fit the window to a grid of a different size. Wrapped lines wrap at the new width, and
+    /// the top of the window stays where it was.
+    pub fn resize(&mut self, dimensions: Coord) {
+        self.dimensions = dimensions;
+        if self.wrap {
+            self.origin = self.segment_of(self.origin);
+        }
+    }
+
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+        self.origin = if wrap {
+            self.segment_of((self.origin.0, 0))
+        } else {
+            (self.origin.0, 0)
+        };
     }
 
     pub fn selection(&self) -> Selection<Coord> {
+        let screen_rows = self.screen_rows();
         let onscreen = |(row, col): Coord| {
-            let (o_row, o_col) = self.origin;
-            if row >= o_row && col >= o_col {
-                Some((row - o_row, col - o_col))
+            if !self.wrap {
+                let (o_row, o_col) = self.origin;
+                return if row >= o_row && col >= o_col {
+                    Some((row - o_row, col - o_col))
+                } else {
+                    None
+                };
+            }
+            // The last screen row of a line also holds anything written past its end.
+            let i = screen_rows
+                .iter()
+                .rposition(|&(r, start)| r == row && start <= col)?;
+            let offset = col - screen_rows[i].1;
+            let last_segment = screen_rows.get(i + 1).map(|next| next.0) != Some(row);
+            if offset < self.dimensions.1 || last_segment {
+                Some((i, offset))
             } else {
                 None
             }
@@ -103,10 +289,36 @@ impl TextWindow {
                 (Some(carat), None) => Selection::Single { carat },
                 (None, None) => Selection::Normal,
             },
+            Selection::Multi { carats } => {
+                let carats: Vec<_> = carats.iter().filter_map(|c| onscreen(c.coord)).collect();
+                if carats.is_empty() {
+                    Selection::Normal
+                } else {
+                    Selection::Multi { carats }
+                }
+            }
+            Selection::Block { start, end } => match (onscreen(start.coord), onscreen(end.coord)) {
+                (Some(start), Some(end)) => Selection::Block { start, end },
+                (None, Some(carat)) => Selection::Single { carat },
+                (Some(carat), None) => Selection::Single { carat },
+                (None, None) => Selection::Normal,
+            },
         }
     }
 
     pub fn page_relative(&mut self, (row_d, col_d): (isize, isize)) {
+        if self.wrap {
+            // Page by screen rows, so a run of long lines isn't skipped over.
+            let stride = self.dimensions.0.saturating_sub(5).max(1);
+            for _ in 0..stride {
+                self.origin = match row_d.cmp(&0) {
+                    Ordering::Greater => self.next_segment(self.origin),
+                    Ordering::Less => self.prev_segment(self.origin),
+                    Ordering::Equal => self.origin,
+                };
+            }
+            return;
+        }
         let (row, col) = &mut self.origin;
         fn page_round(current: usize, delta: isize, size: usize) -> usize {
             // It's useful to stride less than a whole page, to preserve some context.
@@ -118,7 +330,30 @@ impl TextWindow {
         *col = page_round(*col, col_d, self.dimensions.1);
     }
 
+    /// Which page the view is on, and how many there are, counting pages as `page_relative`
+    /// strides through them.
+    pub fn page_position(&self) -> (usize, usize) {
+        let stride = (self.dimensions.0 as isize - 5).max(1) as usize;
+        let page = self.origin.0 / stride + 1;
+        let pages = (self.buffer.contents.len().max(1) - 1) / stride + 1;
+        (page, pages.max(page))
+    }
+
     pub fn scroll_into_view(&mut self, coord: Coord) {
+        if self.wrap {
+            let segment = self.segment_of(coord);
+            if segment < self.origin {
+                self.origin = segment;
+            } else if !self.screen_rows().contains(&segment) {
+                // Scroll down until it's on the bottom row.
+                let mut origin = segment;
+                for _ in 1..self.dimensions.0 {
+                    origin = self.prev_segment(origin);
+                }
+                self.origin = origin;
+            }
+            return;
+        }
         fn clamp_relative(value: usize, reference: usize, dimension: usize) -> usize {
             value.clamp(reference.saturating_sub(dimension - 1), reference)
         }
@@ -139,7 +374,15 @@ impl TextWindow {
                 };
                 Selection::Range { start, end }
             }
-            Selection::Range { .. } => {
+            Selection::Multi { mut carats } => {
+                // Each new carat joins the rest, so they can be placed one at a time.
+                if !carats.iter().any(|c| c.coord == carat.coord) {
+                    carats.push(carat);
+                    carats.sort_by_key(|c| c.coord);
+                }
+                Selection::Multi { carats }
+            }
+            Selection::Range { .. } | Selection::Block { .. } => {
                 // Maybe eventually I'll prevent this case, but for now let's just reset.
                 Selection::Normal
             }
@@ -175,9 +418,26 @@ impl TextWindow {
                 true
             }
         });
+        if self.unsure.range(from..).next().is_some() {
+            let moved = self.unsure.split_off(&from);
+            self.unsure.extend(
+                moved
+                    .into_iter()
+                    .filter(|c| *c >= old_until)
+                    .map(|c| add_coord(new_until, diff_coord(old_until, c))),
+            );
+        }
         undo
     }
 
+    /// Swap in a whole new buffer. Anything we remember about cells in the old one, like
+    /// recent recognitions or unsure cells, no longer applies.
+    pub fn set_buffer(&mut self, buffer: TextBuffer) {
+        self.buffer = buffer;
+        self.tentative_recognitions.clear();
+        self.unsure.clear();
+    }
+
     pub fn replace(&mut self, replace: Replace) {
         // Avoid editing the frozen section of the buffer.
         let undo = self.do_replace(replace);
@@ -185,6 +445,121 @@ impl TextWindow {
         self.redos.clear(); // No longer valid!
     }
 
+    /// Approximate heap usage of the buffer and of the undo history, in bytes.
+    pub fn memory_size(&self) -> (usize, usize) {
+        let history = self
+            .undos
+            .iter()
+            .chain(self.redos.iter())
+            .map(|r| r.content.memory_size())
+            .sum();
+        (self.buffer.memory_size(), history)
+    }
+
+    /// Drop up to `rows` whole lines from the start of the frozen section, moving everything
+    /// else up to match. Returns the number of lines actually removed.
+    pub fn trim_frozen(&mut self, rows: usize) -> usize {
+        let rows = rows.min(self.frozen_until.0);
+        if rows == 0 {
+            return 0;
+        }
+        self.buffer.contents.drain(..rows);
+        let shift = |(row, col): Coord| (row - rows, col);
+        self.frozen_until = shift(self.frozen_until);
+        self.origin.0 = self.origin.0.saturating_sub(rows);
+
+        // Anything that refers to the trimmed lines is gone for good.
+        self.undos.retain(|r| r.from.0 >= rows);
+        self.redos.retain(|r| r.from.0 >= rows);
+        for r in self.undos.iter_mut().chain(self.redos.iter_mut()) {
+            r.from = shift(r.from);
+            r.until = shift(r.until);
+        }
+        self.tentative_recognitions.retain_mut(|r| {
+            if r.coord.0 < rows {
+                false
+            } else {
+                r.coord = shift(r.coord);
+                true
+            }
+        });
+        self.unsure = mem::take(&mut self.unsure)
+            .into_iter()
+            .filter(|c| c.0 >= rows)
+            .map(shift)
+            .collect();
+        self.selection = match mem::take(&mut self.selection) {
+            Selection::Single { mut carat } if carat.coord.0 >= rows => {
+                carat.coord = shift(carat.coord);
+                Selection::Single { carat }
+            }
+            Selection::Range { mut start, mut end } if start.coord.0 >= rows => {
+                start.coord = shift(start.coord);
+                end.coord = shift(end.coord);
+                Selection::Range { start, end }
+            }
+            Selection::Block { mut start, mut end } if start.coord.0 >= rows => {
+                start.coord = shift(start.coord);
+                end.coord = shift(end.coord);
+                Selection::Block { start, end }
+            }
+            Selection::Multi { mut carats } => {
+                carats.retain(|c| c.coord.0 >= rows);
+                for carat in &mut carats {
+                    carat.coord = shift(carat.coord);
+                }
+                if carats.is_empty() {
+                    Selection::Normal
+                } else {
+                    Selection::Multi { carats }
+                }
+            }
+            _ => Selection::Normal,
+        };
+        rows
+    }
+
+    /// Replace the end of the frozen section, from the given position onwards, with new output,
+    /// and extend the frozen section to cover it; output can go back and redraw its last line.
+    /// This isn't an edit the user can undo; instead, any pending undo and redo entries are
+    /// shifted past the new content so they still apply to the same text.
+    pub fn rewrite_frozen(&mut self, from: Coord, content: TextBuffer) {
+        let at = self.frozen_until;
+        let size = content.end();
+        // Unfreeze just long enough to make the edit.
+        self.frozen_until = from;
+        let undo = self.do_replace(Replace {
+            from,
+            until: at,
+            content,
+        });
+        let new_until = undo.until;
+        let shift = |coord: Coord| {
+            if coord < at {
+                coord
+            } else {
+                add_coord(new_until, diff_coord(at, coord))
+            }
+        };
+        for entry in self.undos.iter_mut().chain(self.redos.iter_mut()) {
+            entry.from = shift(entry.from);
+            entry.until = shift(entry.until);
+        }
+        self.frozen_until = add_coord(from, size);
+    }
+
+    /// Insert a whole line of text below the given row, as a single undoable edit.
+    pub fn insert_line_after(&mut self, row: usize, line: &str) {
+        let next = row + 1;
+        if next < self.buffer.contents.len() {
+            let content = TextBuffer::from_string(&format!("{line}\n"));
+            self.replace(Replace::splice((next, 0), content));
+        } else {
+            let content = TextBuffer::from_string(&format!("\n{line}"));
+            self.replace(Replace::splice(self.buffer.end(), content));
+        }
+    }
+
     pub fn undo(&mut self) {
         if let Some(undo) = self.undos.pop_back() {
             self.scroll_into_view(undo.from);
@@ -201,7 +576,134 @@ impl TextWindow {
         }
     }
 
+    /// If the ink is a tap on a recently-written cell, where that cell is and what else the
+    /// recognizer thought it might be.
+    pub fn tapped_recognition(&self, ink: &Ink) -> Option<(Coord, Vec<char>)> {
+        let coord = self.relative(tapped_cell(&self.grid_metrics, ink)?);
+        let recognition = self
+            .tentative_recognitions
+            .iter()
+            .find(|r| r.coord == coord)?;
+        let current = self.buffer.contents.get(coord.0)?.get(coord.1)?;
+        (*current == recognition.recognized_as && !recognition.alternatives.is_empty())
+            .then(|| (coord, recognition.alternatives.clone()))
+    }
+
+    /// Swap a recently-written cell for one of its alternatives. This counts as overwriting
+    /// it, so the ink may end up as a template for the character picked.
+    pub fn use_alternative(&mut self, coord: Coord, c: char) {
+        self.swap_recognition(coord, c, true);
+    }
+
+    /// Check the words that were just written against the dictionary, fixing any that are one
+    /// close call away from a real word. Returns the cells that were changed.
+    fn spellcheck_row(
+        &mut self,
+        dictionary: &Dictionary,
+        guesses: &[(Coord, Vec<(char, f32)>)],
+    ) -> Vec<(Coord, char)> {
+        let row = match guesses.first() {
+            Some(((row, _), _)) => *row,
+            None => return vec![],
+        };
+        let line = match self.buffer.contents.get(row) {
+            Some(line) => line.clone(),
+            None => return vec![],
+        };
+        let is_word = |col: usize| line.get(col).is_some_and(|c| is_word_char(*c));
+        let mut fixes = vec![];
+        let mut checked = vec![];
+        for &((_, col), _) in guesses {
+            if !is_word(col) {
+                continue;
+            }
+            let start = (0..col).rev().find(|c| !is_word(*c)).map_or(0, |c| c + 1);
+            if checked.contains(&start) {
+                continue;
+            }
+            checked.push(start);
+            let end = (col..=line.len())
+                .find(|c| !is_word(*c))
+                .unwrap_or(line.len());
+            let letters: Vec<Vec<(char, f32)>> = (start..end)
+                .map(|col| {
+                    guesses
+                        .iter()
+                        .find(|(at, _)| *at == (row, col))
+                        .map_or_else(|| vec![(line[col], 0.0)], |(_, m)| m.clone())
+                })
+                .collect();
+            if let Some((i, c)) = dictionary.correct(&letters) {
+                let coord = (row, start + i);
+                // A letter that's dropped out of the recent recognitions is left as it is.
+                if self.swap_recognition(coord, c, false) {
+                    self.unsure.insert(coord);
+                    fixes.push((coord, c));
+                }
+            }
+        }
+        fixes
+    }
+
+    /// Replace a recently-written cell with another of the characters it might have been. If
+    /// `learn`, the change counts as a correction, and the ink may end up a template for it.
+    /// Returns whether the cell was still recent enough to change.
+    fn swap_recognition(&mut self, coord: Coord, c: char, learn: bool) -> bool {
+        let index = match self
+            .tentative_recognitions
+            .iter()
+            .position(|r| r.coord == coord)
+        {
+            Some(index) => index,
+            None => return false,
+        };
+        let mut recognition = self
+            .tentative_recognitions
+            .remove(index)
+            .expect("removing just-discovered match");
+        self.replace(Replace::write(coord, c));
+        let previous = mem::replace(&mut recognition.recognized_as, c);
+        for alternative in &mut recognition.alternatives {
+            if *alternative == c {
+                *alternative = previous;
+            }
+        }
+        if learn {
+            recognition.overwrites.push(recognition.ink.clone());
+        }
+        self.tentative_recognitions.push_back(recognition);
+        true
+    }
+
+    /// The bracket or quote next to the carat, or else the one just written, and where its
+    /// partner is, if it has one.
+    pub fn bracket(&self) -> Option<(Coord, Option<Coord>)> {
+        let candidates = match &self.selection {
+            Selection::Single { carat } => {
+                let (row, col) = carat.coord;
+                let before = col.checked_sub(1).map(|col| (row, col));
+                before.into_iter().chain([(row, col)]).collect()
+            }
+            Selection::Normal => self
+                .tentative_recognitions
+                .back()
+                .map(|r| r.coord)
+                .into_iter()
+                .collect(),
+            _ => vec![],
+        };
+        candidates.into_iter().find_map(|at| {
+            let partner = self.buffer.matching_bracket(at, BRACKET_SEARCH_LINES)?;
+            Some((at, partner))
+        })
+    }
+
     fn relative(&self, coord: Coord) -> Coord {
+        if self.wrap {
+            if let Some(&(row, start)) = self.screen_rows().get(coord.0) {
+                return (row, start + coord.1);
+            }
+        }
         (self.origin.0 + coord.0, self.origin.1 + coord.1)
     }
 
@@ -219,34 +721,52 @@ impl TextWindow {
             }
         }
 
-        let mut iter = to_erase.into_iter();
-        if let Some((row, col)) = iter.next() {
-            let mut start = (row, col);
-            let mut end = (row, col + 1);
+        // Rubbing out a carat mark cancels the selection, and leaves the text alone.
+        let carats = match &self.selection {
+            Selection::Normal => vec![],
+            Selection::Single { carat } => vec![carat.coord],
+            Selection::Range { start, end } | Selection::Block { start, end } => {
+                vec![start.coord, end.coord]
+            }
+            Selection::Multi { carats } => carats.iter().map(|c| c.coord).collect(),
+        };
+        let touches_carat = carats.iter().any(|&(row, col)| {
+            to_erase.contains(&(row, col)) || col > 0 && to_erase.contains(&(row, col - 1))
+        });
+        if touches_carat {
+            self.selection = Selection::Normal;
+            return;
+        }
+
+        let (first, last) = match (to_erase.iter().next(), to_erase.iter().next_back()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return,
+        };
 
-            while let Some(coord) = iter.next() {
-                if coord == end {
-                    // we can expand the current run
-                    end = (end.0, end.1 + 1);
-                } else {
-                    // replace and begin anew
-                    start = self.buffer.clamp(start);
-                    end = self.buffer.clamp(end);
-                    self.replace(Replace {
-                        from: start,
-                        until: end,
-                        content: TextBuffer::padding(diff_coord(start, end)),
-                    });
-                    start = coord;
-                    end = (coord.0, coord.1 + 1);
+        // Blank out the whole affected span as a single replace, so one gesture is one buffer
+        // edit and one entry in the undo history, however many runs of cells it covers.
+        let start = self.buffer.clamp(first);
+        let end = self.buffer.clamp((last.0, last.1 + 1));
+        if start >= end {
+            return;
+        }
+        let mut content = self.buffer.copy(start, end);
+        let mut changed = false;
+        for (row, col) in to_erase {
+            let row = row - start.0;
+            let col = if row == 0 { col - start.1 } else { col };
+            if let Some(c) = content.contents.get_mut(row).and_then(|l| l.get_mut(col)) {
+                if *c != ' ' {
+                    *c = ' ';
+                    changed = true;
                 }
             }
-            start = self.buffer.clamp(start);
-            end = self.buffer.clamp(end);
+        }
+        if changed {
             self.replace(Replace {
                 from: start,
                 until: end,
-                content: TextBuffer::padding(diff_coord(start, end)),
+                content,
             });
         }
     }
@@ -256,7 +776,7 @@ impl TextWindow {
         let line: &[char] = &query.contents[0];
 
         fn find_in(
-            contents: &Vec<Vec<char>>,
+            contents: &Rope,
             query: &[char],
             rows: impl Iterator<Item = usize>,
         ) -> Option<(usize, usize)> {
@@ -293,15 +813,321 @@ impl TextWindow {
         }
     }
 
+    /// Apply an edit at each of the given carats, as a single undoable change.
+    /// The edit is passed the carat's position relative to the start of its first line.
+    fn edit_at_carats(&mut self, carats: &[Coord], edit: impl Fn(Coord) -> Replace) {
+        let mut carats = carats.to_vec();
+        carats.sort();
+        let (first, last) = match (carats.first(), carats.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return,
+        };
+        let from = (first.0, 0);
+        let until = self.buffer.clamp((last.0, usize::MAX));
+        let mut content = self.buffer.copy(from, until);
+        // Back to front, so each edit leaves the positions of the remaining carats alone.
+        for (row, col) in carats.into_iter().rev() {
+            content.replace(edit((row - from.0, col)));
+        }
+        self.replace(Replace {
+            from,
+            until,
+            content,
+        });
+    }
+
+    /// Handle a key from the navigation panel. With a single carat, keys move it and edit around
+    /// it like a keyboard cursor; otherwise they scroll the view.
+    pub fn nav(&mut self, key: NavKey) {
+        let carat = match &self.selection {
+            Selection::Single { carat } => carat.coord,
+            _ => {
+                let (row, col) = self.origin;
+                match key {
+                    NavKey::Up => self.origin = self.prev_segment(self.origin),
+                    NavKey::Down => self.origin = self.next_segment(self.origin),
+                    NavKey::Left | NavKey::Right if self.wrap => {}
+                    NavKey::Left => self.origin = (row, col.saturating_sub(1)),
+                    NavKey::Right => self.origin = (row, col + 1),
+                    NavKey::PageUp => self.page_relative((-1, 0)),
+                    NavKey::PageDown => self.page_relative((1, 0)),
+                    NavKey::Home => self.origin = (0, 0),
+                    NavKey::End => {
+                        let last = self.buffer.contents.len() - 1;
+                        self.origin = (last.saturating_sub(self.dimensions.0 - 1), 0);
+                        if self.wrap {
+                            self.scroll_into_view(self.buffer.end());
+                        }
+                    }
+                    NavKey::Backspace | NavKey::Enter => {}
+                }
+                return;
+            }
+        };
+
+        let (row, col) = carat;
+        match key {
+            NavKey::Up => self.move_carat((row.saturating_sub(1), col)),
+            NavKey::Down => self.move_carat((row + 1, col)),
+            NavKey::Left => self.move_carat((row, col.saturating_sub(1))),
+            NavKey::Right => self.move_carat((row, col + 1)),
+            NavKey::PageUp => {
+                let rows = self.dimensions.0.saturating_sub(5).max(1);
+                self.move_carat((row.saturating_sub(rows), col))
+            }
+            NavKey::PageDown => {
+                let rows = self.dimensions.0.saturating_sub(5).max(1);
+                self.move_carat((row + rows, col))
+            }
+            NavKey::Home => self.move_carat((row, 0)),
+            NavKey::End => self.move_carat(self.buffer.clamp((row, usize::MAX))),
+            NavKey::Backspace => {
+                let carat = self.buffer.clamp(carat);
+                let before = match carat {
+                    (_, col) if col > 0 => (carat.0, col - 1),
+                    (row, _) if row > 0 => (row - 1, self.buffer.contents[row - 1].len()),
+                    _ => return,
+                };
+                self.replace(Replace::remove(before, carat));
+                self.move_carat(before);
+            }
+            NavKey::Enter => {
+                let to = self.newline(carat);
+                self.move_carat(to);
+            }
+        }
+    }
+
+    /// Break the line at the given position, returning where the text after the break ends up.
+    /// After a list item, the new line starts with the next list marker; after an empty item,
+    /// the marker is dropped to end the list.
+    fn newline(&mut self, at: Coord) -> Coord {
+        let (row, col) = self.buffer.clamp(at);
+        let before: String = self.buffer.contents[row][..col].iter().collect();
+        match markdown::list_continuation(&before) {
+            Some(markdown::Continuation::End) => {
+                self.replace(Replace::remove((row, 0), (row, col)));
+                (row, 0)
+            }
+            Some(markdown::Continuation::Marker(marker)) => {
+                let content = TextBuffer::from_string(&format!("\n{marker}"));
+                self.replace(Replace::splice((row, col), content));
+                (row + 1, marker.chars().count())
+            }
+            None => {
+                self.replace(Replace::splice((row, col), TextBuffer::from_string("\n")));
+                (row + 1, 0)
+            }
+        }
+    }
+
+    fn move_carat(&mut self, to: Coord) {
+        if let Selection::Single { carat } = &mut self.selection {
+            carat.coord = to;
+            self.scroll_into_view(to);
+        }
+    }
+
+    /// Replace every occurrence of the token, as a single undoable edit. Returns the count.
+    pub fn replace_all(&mut self, token: &[char], replacement: &TextBuffer) -> usize {
+        let found = self.buffer.find_all(token);
+        self.edit_at_carats(&found, |at| Replace {
+            from: at,
+            until: (at.0, at.1 + token.len()),
+            content: replacement.clone(),
+        });
+        found.len()
+    }
+
+    /// Replace the first occurrence of the token at or after the given position, wrapping around
+    /// to the top if needed. Returns the position just after the replacement.
+    pub fn replace_next(
+        &mut self,
+        token: &[char],
+        replacement: &TextBuffer,
+        from: Coord,
+    ) -> Option<Coord> {
+        let found = self.buffer.find_all(token);
+        let at = found
+            .iter()
+            .find(|c| **c >= from)
+            .or_else(|| found.first())
+            .copied()?;
+        self.replace(Replace {
+            from: at,
+            until: (at.0, at.1 + token.len()),
+            content: replacement.clone(),
+        });
+        self.scroll_into_view(at);
+        Some(add_coord(at, replacement.end()))
+    }
+
+    /// Remove every occurrence of the selected token, leaving a carat where each one was; a paste
+    /// then puts the clipboard in at all of them.
+    fn select_all(&mut self, start: Coord, end: Coord) {
+        let ink = match &self.selection {
+            Selection::Range { start, .. } => start.ink.clone(),
+            _ => return,
+        };
+        let token = self.buffer.copy(start, end).contents[0].clone();
+        let found = self.buffer.find_all(&token);
+        if found.is_empty() {
+            return;
+        }
+        self.edit_at_carats(&found, |at| Replace::remove(at, (at.0, at.1 + token.len())));
+
+        // Each removal pulls the rest of its line to the left.
+        let mut carats: Vec<Carat> = vec![];
+        for (row, col) in found {
+            let earlier = carats.iter().filter(|c| c.coord.0 == row).count();
+            carats.push(Carat {
+                coord: (row, col - earlier * token.len()),
+                ink: ink.clone(),
+            });
+        }
+        self.selection = Selection::Multi { carats };
+    }
+
+    /// If the ink landed entirely on a checkbox, tick it (or untick it, for a scratch-out)
+    /// instead of writing over the brackets. Returns whether the box was toggled.
+    fn toggle_checkbox(&mut self, coords: &[Coord], scratch: bool) -> bool {
+        let row = match coords.first() {
+            Some((row, _)) if coords.iter().all(|c| c.0 == *row) => *row,
+            _ => return false,
+        };
+        let line: String = match self.buffer.contents.get(row) {
+            Some(line) => line.iter().collect(),
+            None => return false,
+        };
+        match tasks::checkbox_cells(&line) {
+            Some((cells, checked))
+                if checked == scratch && coords.iter().all(|c| cells.contains(&c.1)) =>
+            {
+                let mark = if scratch { ' ' } else { 'x' };
+                self.replace(Replace::write((row, cells.start + 1), mark));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Repeat some just-written text at every other carat, at the same offset from the carat.
+    /// Characters that were spliced in are spliced in at the other carats too, which pushes
+    /// along any carats and text further along the same line. Everything repeated for one
+    /// piece of writing is undone in one go.
+    fn mirror_at_carats(&mut self, written: &[(Coord, char, bool)]) {
+        let mut carats: Vec<Carat> = match &self.selection {
+            Selection::Multi { carats } => carats.clone(),
+            _ => return,
+        };
+        let (first, last) = match (carats.first(), carats.last()) {
+            (Some(first), Some(last)) => (first.coord.0, last.coord.0),
+            _ => return,
+        };
+        let lines = self.buffer.contents.len();
+        let from = self.buffer.clamp((first, 0));
+        let content = self
+            .buffer
+            .copy(from, self.buffer.clamp((last, usize::MAX)));
+        let mut edited = false;
+        let mut written = written.to_vec();
+        // Shift everything at or after the spliced cell along by one, besides the given carat.
+        fn push_along(carats: &mut [Carat], (row, col): Coord, except: usize) {
+            for (i, carat) in carats.iter_mut().enumerate() {
+                if i != except && carat.coord.0 == row && carat.coord.1 >= col {
+                    carat.coord.1 += 1;
+                }
+            }
+        }
+        for i in 0..written.len() {
+            let ((row, col), c, spliced) = written[i];
+            let origin = (0..carats.len())
+                .filter(|&k| carats[k].coord.0 == row && carats[k].coord.1 <= col)
+                .max_by_key(|&k| carats[k].coord);
+            let origin = match origin {
+                Some(origin) => origin,
+                None => continue,
+            };
+            if spliced {
+                push_along(&mut carats, (row, col), origin);
+            }
+            let offset = col - carats[origin].coord.1;
+            for k in (0..carats.len()).filter(|&k| k != origin) {
+                let at = (carats[k].coord.0, carats[k].coord.1 + offset);
+                edited = true;
+                if !spliced {
+                    self.do_replace(Replace::write(at, c));
+                    continue;
+                }
+                self.do_replace(Replace::splice(at, TextBuffer::from_string(&c.to_string())));
+                push_along(&mut carats, at, k);
+                for (later, _, _) in &mut written[i + 1..] {
+                    if later.0 == at.0 && later.1 >= at.1 {
+                        later.1 += 1;
+                    }
+                }
+            }
+        }
+        self.selection = Selection::Multi { carats };
+        if edited {
+            // Writing past the end of the buffer adds lines after the last carat's.
+            let last = last + self.buffer.contents.len() - lines;
+            let until = self.buffer.clamp((last, usize::MAX));
+            rotate_queue(
+                &mut self.undos,
+                Replace {
+                    from,
+                    until,
+                    content,
+                },
+                NUM_UNDOS,
+            );
+            self.redos.clear();
+        }
+    }
+
+    /// Handle ink in a buffer that mustn't change: carats and selections work as usual, and a
+    /// big C copies, but anything that would edit the text is ignored.
+    pub fn ink_read_only(&mut self, ink_type: InkType, text_stuff: &mut TextStuff) {
+        match ink_type {
+            InkType::Carat { .. } | InkType::Cancel => self.ink_row(ink_type, text_stuff),
+            InkType::BigGlyph { token } if token.len() > 1 => {
+                let best_match = text_stuff
+                    .big_recognizer
+                    .best_match(&Points::normalize(&token), f32::MAX);
+                if matches!(best_match, Some('C') | Some('R')) {
+                    self.ink_row(InkType::BigGlyph { token }, text_stuff);
+                }
+            }
+            _ => {}
+        }
+    }
+
     pub fn ink_row(&mut self, ink_type: InkType, text_stuff: &mut TextStuff) {
         match ink_type {
             InkType::Scratch { at } => {
                 let coord = self.relative(at);
+                if self.toggle_checkbox(&[coord], true) {
+                    return;
+                }
                 self.replace(Replace::write(coord, ' '));
             }
             InkType::Glyphs { tokens } => {
+                let coords: Vec<Coord> = tokens.iter().map(|(c, _)| self.relative(*c)).collect();
+                let coords = self.buffer.past_end(&coords, self.past_end);
+                if self.toggle_checkbox(&coords, false) {
+                    return;
+                }
+                let mut written = vec![];
+                let mut guesses = vec![];
+                // With several carats, what's written goes in at each of them without writing
+                // over whatever follows.
+                let insert = self.insert || matches!(self.selection, Selection::Multi { .. });
+                // Once part of the ink's been spliced in, any recent recognitions further along
+                // the line have moved, so they can't be what the rest of it was written over.
+                let mut spliced = false;
                 // TODO: a little coalescing perhaps?
-                for (col, ink) in tokens {
+                for (coord, (_, ink)) in coords.into_iter().zip(tokens) {
                     // So, this is a slightly awkward little dance. The key observation is that
                     // if the system mispredicts a character, the user will almost always try
                     // and overwrite the bad guess again to "fix up" the text; and when that
@@ -321,15 +1147,18 @@ impl TextWindow {
                     // and track how well the candidates do. Candidates that are reliable get
                     // promoted to the main template list. We presumably will still get this
                     // wrong, but at least users can prune bad ones from there if needed.
-                    let coord = self.relative(col);
-                    if let Some(c) = text_stuff
-                        .char_recognizer
-                        .best_match(&ink_to_points(&ink, &self.grid_metrics), f32::MAX)
-                    {
+                    let matches = text_stuff.char_recognizer.top_matches(
+                        &ink_to_points(&ink, &self.grid_metrics),
+                        NUM_ALTERNATIVES + 1,
+                    );
+                    if let Some((c, _)) = matches.first().copied() {
+                        if !self.masked {
+                            text_stuff.record_use(c);
+                        }
                         let overwrites = if let Some(index) = self
                             .tentative_recognitions
                             .iter()
-                            .position(|r| r.coord == coord)
+                            .position(|r| r.coord == coord && !spliced)
                         {
                             let mut prev = self
                                 .tentative_recognitions
@@ -340,15 +1169,30 @@ impl TextWindow {
                         } else {
                             vec![]
                         };
+                        // Rewriting a character that was just written fixes it, even when
+                        // inserting.
+                        let edit = if insert && overwrites.is_empty() {
+                            spliced = true;
+                            Replace::splice(coord, TextBuffer::from_string(&c.to_string()))
+                        } else {
+                            Replace::write(coord, c)
+                        };
 
                         let recon = Recognition {
                             coord,
                             ink,
                             recognized_as: c,
+                            alternatives: matches[1..].iter().map(|(c, _)| *c).collect(),
                             overwrites,
                         };
 
-                        self.replace(Replace::write(coord, c));
+                        let splice = edit.from == edit.until;
+                        self.replace(edit);
+                        if !is_sure(&matches) {
+                            self.unsure.insert(coord);
+                        }
+                        written.push((coord, c, splice));
+                        guesses.push((coord, matches));
 
                         if let Some(r) = rotate_queue(
                             &mut self.tentative_recognitions,
@@ -356,17 +1200,38 @@ impl TextWindow {
                             NUM_RECENT_RECOGNITIONS,
                         ) {
                             dbg!(r.recognized_as, r.overwrites.len());
-                            for ink in r.overwrites {
+                            let overwrites = if self.masked { vec![] } else { r.overwrites };
+                            for ink in overwrites {
                                 let points = ink_to_points(&ink, &self.grid_metrics);
                                 text_stuff.on_overwrite(ink, points, r.recognized_as);
                             }
                         }
                     }
                 }
+                if let (true, Some(dictionary)) = (self.spellcheck, &text_stuff.dictionary) {
+                    for (coord, c) in self.spellcheck_row(dictionary, &guesses) {
+                        for entry in written.iter_mut().filter(|(at, _, _)| *at == coord) {
+                            entry.1 = c;
+                        }
+                    }
+                }
+                self.mirror_at_carats(&written);
             }
             InkType::Strikethrough { start, end } => {
                 self.replace(Replace::remove(self.relative(start), self.relative(end)));
             }
+            InkType::DeleteWords { start, end } => {
+                let (row, from) = self.relative(start);
+                let (_, to) = self.relative(end);
+                let struck = self
+                    .buffer
+                    .contents
+                    .get(row)
+                    .and_then(|line| struck_words(line, from, to));
+                if let Some(struck) = struck {
+                    self.replace(Replace::remove((row, struck.start), (row, struck.end)));
+                }
+            }
             InkType::Carat { at, ink } => {
                 let coord = self.relative(at);
                 self.carat(Carat { coord, ink });
@@ -379,28 +1244,86 @@ impl TextWindow {
                 let best_match = text_stuff
                     .big_recognizer
                     .best_match(&Points::normalize(&ink), f32::MAX);
+                // T and R are easily recognized in other big glyphs, and both edit the text, so
+                // they have to be written distinctly: a T in two strokes, and an R two rows tall.
+                let strokes = ink.strokes().count();
+                let rows_tall =
+                    (ink.y_range.max - ink.y_range.min) / self.grid_metrics.height as f32;
+                if let Selection::Multi { carats } = &self.selection {
+                    let carats: Vec<Coord> = carats.iter().map(|c| c.coord).collect();
+                    match best_match {
+                        Some('V') => {
+                            if let Some(clip) = &text_stuff.clipboard {
+                                self.edit_at_carats(&carats, |at| clip.paste_at(at));
+                            }
+                            self.selection = Selection::Normal;
+                        }
+                        Some('S') | Some('>') => {
+                            self.edit_at_carats(&carats, |at| {
+                                Replace::splice(at, TextBuffer::padding((0, 1)))
+                            });
+                            self.selection = Selection::Normal;
+                        }
+                        _ => {}
+                    }
+                    return;
+                }
+                if let Selection::Block { start, end } = &self.selection {
+                    let (rows, cols) = block_bounds(start.coord, end.coord);
+                    match best_match {
+                        Some('X') | Some('C') => {
+                            text_stuff.set_clipboard(Clip {
+                                text: self.buffer.copy_block(rows.clone(), cols.clone()),
+                                line_wise: false,
+                                block: true,
+                            });
+                            if best_match == Some('X') {
+                                self.replace(self.buffer.remove_block(rows, cols));
+                            }
+                            self.selection = Selection::Normal;
+                        }
+                        Some('<') => {
+                            self.replace(self.buffer.remove_block(rows, cols));
+                            self.selection = Selection::Normal;
+                        }
+                        Some('V') => {
+                            if let Some(clip) = &text_stuff.clipboard {
+                                self.replace(clip.paste_over_block(&self.buffer, rows, cols));
+                            }
+                            self.selection = Selection::Normal;
+                        }
+                        _ => {}
+                    }
+                    return;
+                }
                 let (start, end) = match &self.selection {
                     Selection::Normal => unreachable!("checked in matches! above."),
                     Selection::Single { carat } => (carat.coord, carat.coord),
                     Selection::Range { start, end } => (start.coord, end.coord),
+                    Selection::Multi { .. } => unreachable!("handled above."),
+                    Selection::Block { .. } => unreachable!("handled above."),
                 };
                 match best_match {
                     Some('X') if start != end => {
-                        text_stuff.clipboard = Some(self.buffer.copy(start, end));
+                        text_stuff.set_clipboard(self.buffer.clip(start, end));
                         self.replace(Replace::remove(start, end));
                         self.selection = Selection::Normal;
                     }
                     Some('C') if start != end => {
-                        text_stuff.clipboard = Some(self.buffer.copy(start, end));
+                        text_stuff.set_clipboard(self.buffer.clip(start, end));
                         self.selection = Selection::Normal;
                     }
                     Some('V') => {
-                        if let Some(buffer) = &text_stuff.clipboard {
-                            self.replace(Replace {
-                                from: start,
-                                until: end,
-                                content: buffer.clone(),
-                            });
+                        if let Some(clip) = &text_stuff.clipboard {
+                            if start == end {
+                                self.replace(clip.paste_into(&self.buffer, start));
+                            } else {
+                                self.replace(Replace {
+                                    from: start,
+                                    until: end,
+                                    content: clip.text.clone(),
+                                });
+                            }
                         }
                         self.selection = Selection::Normal;
                     }
@@ -437,6 +1360,96 @@ impl TextWindow {
                         });
                         self.selection = Selection::Normal;
                     }
+                    Some('|') if start.0 < end.0 => {
+                        // A column of carats, one per line, all at the column of the first.
+                        if let Selection::Range { start: first, .. } = &self.selection {
+                            let ink = first.ink.clone();
+                            let carats = (start.0..=end.0)
+                                .map(|row| Carat {
+                                    coord: (row, start.1),
+                                    ink: ink.clone(),
+                                })
+                                .collect();
+                            self.selection = Selection::Multi { carats };
+                        }
+                    }
+                    Some('R') if start.0 != end.0 && start.1 != end.1 && rows_tall >= 2.0 => {
+                        // The same two carats, as the corners of a block.
+                        if let Selection::Range { start, end } = mem::take(&mut self.selection) {
+                            self.selection = Selection::Block { start, end };
+                        }
+                    }
+                    Some('+') => {
+                        // Start placing carats by hand: keep the ones we have, and add to them.
+                        let carats = match mem::take(&mut self.selection) {
+                            Selection::Single { carat } => vec![carat],
+                            Selection::Range { start, end } => vec![start, end],
+                            _ => vec![],
+                        };
+                        self.selection = Selection::Multi { carats };
+                    }
+                    Some('W') if start == end => self.move_carat(self.buffer.next_word(start)),
+                    Some('B') if start == end => self.move_carat(self.buffer.prev_word(start)),
+                    Some('H') if start == end => self.move_carat((start.0, 0)),
+                    Some('E') if start == end => {
+                        self.move_carat(self.buffer.clamp((start.0, usize::MAX)))
+                    }
+                    Some('}') if start == end => {
+                        self.move_carat(self.buffer.next_paragraph(start.0))
+                    }
+                    Some('{') if start == end => {
+                        self.move_carat(self.buffer.prev_paragraph(start.0))
+                    }
+                    Some('U') if start == end => {
+                        // Turn the code point just before the carat into its character. The
+                        // carat may be below the last line, where there's nothing to turn.
+                        let found = self
+                            .buffer
+                            .contents
+                            .get(start.0)
+                            .and_then(|line| code_point_before(line, start.1));
+                        if let Some((from, c)) = found {
+                            self.replace(Replace {
+                                from: (start.0, from),
+                                until: start,
+                                content: TextBuffer::from_string(&c.to_string()),
+                            });
+                        }
+                        self.selection = Selection::Normal;
+                    }
+                    Some('U') => {
+                        let text = self.buffer.copy(start, end).content_string();
+                        let decoded = decode_code_points(&text);
+                        if decoded != text {
+                            self.replace(Replace {
+                                from: start,
+                                until: end,
+                                content: TextBuffer::from_string(&decoded),
+                            });
+                        }
+                        self.selection = Selection::Normal;
+                    }
+                    Some('T') if start == end && strokes == 2 => {
+                        // Below the last line there's nothing to swap.
+                        let found = self
+                            .buffer
+                            .contents
+                            .get(start.0)
+                            .and_then(|line| transpose(line, start.1));
+                        if let Some((range, swapped)) = found {
+                            self.replace(Replace {
+                                from: (start.0, range.start),
+                                until: (start.0, range.end),
+                                content: TextBuffer::from_string(
+                                    &swapped.into_iter().collect::<String>(),
+                                ),
+                            });
+                        }
+                        self.selection = Selection::Normal;
+                    }
+                    Some('A') if start != end && start.0 == end.0 => {
+                        self.select_all(start, end);
+                    }
                     Some('N') if start != end && start.0 == end.0 => {
                         self.find_token(start, end, true);
                     }
@@ -446,6 +1459,62 @@ impl TextWindow {
                     _ => {}
                 }
             }
+            InkType::Cancel => {
+                self.selection = Selection::Normal;
+            }
+            InkType::Join { start, end } => {
+                let (row, from) = self.relative(start);
+                let (_, to) = self.relative(end);
+                let gap = self
+                    .buffer
+                    .contents
+                    .get(row)
+                    .and_then(|line| joined_gap(line, from, to));
+                if let Some(gap) = gap {
+                    self.replace(Replace::remove((row, gap.start), (row, gap.end)));
+                }
+            }
+            InkType::JoinLines { at } => {
+                let (row, col) = self.relative(at);
+                let lines = &self.buffer.contents;
+                // Only from past the end of the line, so a stray stroke over the text is harmless.
+                let (line, next) = match (lines.get(row), lines.get(row + 1)) {
+                    (Some(line), Some(next)) if col >= line.len() => (line, next),
+                    _ => return,
+                };
+                let indent = next.iter().take_while(|c| c.is_whitespace()).count();
+                let space = match line.last() {
+                    Some(c) if !c.is_whitespace() && indent < next.len() => " ",
+                    _ => "",
+                };
+                self.replace(Replace {
+                    from: (row, line.len()),
+                    until: (row + 1, indent),
+                    content: TextBuffer::from_string(space),
+                });
+            }
+            InkType::SplitLine { at } => {
+                let (row, col) = self.relative(at);
+                if self
+                    .buffer
+                    .contents
+                    .get(row)
+                    .is_some_and(|l| col <= l.len())
+                {
+                    self.newline((row, col));
+                }
+            }
+            InkType::Split { at } => {
+                let (row, col) = self.relative(at);
+                let within = self
+                    .buffer
+                    .contents
+                    .get(row)
+                    .is_some_and(|line| col > 0 && col < line.len());
+                if within {
+                    self.replace(Replace::splice((row, col), TextBuffer::from_string(" ")));
+                }
+            }
             InkType::LineTo { coord } => {
                 if let Selection::Single { carat } = &self.selection {
                     let coord = self.relative(coord);
@@ -477,7 +1546,28 @@ impl Widget for TextWindow {
     }
 
     fn render(&self, view: View<Self::Message>) {
-        let (row_origin, col_origin) = self.origin;
+        let screen_rows = self.screen_rows();
+        // Figure directives stand in for a drawing, so we dim them to mark them as placeholders.
+        let figure_rows: Vec<bool> = screen_rows
+            .iter()
+            .map(|(row, _)| matches!(self.buffer.contents.get(*row), Some(l) if is_figure_line(l)))
+            .collect();
+        let styles: Vec<Vec<Style>> = match self.language {
+            Some(language) => screen_rows
+                .iter()
+                .map(|(row, _)| match self.buffer.contents.get(*row) {
+                    Some(line) => highlight_line(language, line),
+                    None => vec![],
+                })
+                .collect(),
+            None => vec![],
+        };
+        // Underline a bracket near the carat along with its partner, so it's easy to see what
+        // it closes.
+        let brackets: Vec<Coord> = match self.bracket() {
+            Some((at, Some(partner))) => vec![at, partner],
+            _ => vec![],
+        };
         draw_grid(
             view,
             &self.grid_metrics,
@@ -487,8 +1577,8 @@ impl Widget for TextWindow {
                 view.handlers().on_erase(TextMessage::Erase);
             },
             |row_offset, col_offset, mut view| {
-                let row = row_origin + row_offset;
-                let col = col_origin + col_offset;
+                let (row, col_start) = screen_rows[row_offset];
+                let col = col_start + col_offset;
                 let coord = (row, col);
 
                 let (underline, draw_guidelines) = match &self.selection {
@@ -499,6 +1589,12 @@ impl Widget for TextWindow {
                         }
                         (false, false)
                     }
+                    Selection::Multi { carats } => {
+                        for carat in carats.iter().filter(|c| c.coord == coord) {
+                            view.annotate(&carat.ink);
+                        }
+                        (false, false)
+                    }
                     Selection::Range { start, end } => {
                         if coord == start.coord {
                             view.annotate(&start.ink);
@@ -509,16 +1605,40 @@ impl Widget for TextWindow {
                         let in_selection = coord >= start.coord && coord < end.coord;
                         (in_selection, false)
                     }
+                    Selection::Block { start, end } => {
+                        if coord == start.coord {
+                            view.annotate(&start.ink);
+                        }
+                        if coord == end.coord {
+                            view.annotate(&end.ink);
+                        }
+                        let (rows, cols) = block_bounds(start.coord, end.coord);
+                        (rows.contains(&row) && cols.contains(&col), false)
+                    }
                 };
 
+                let underline = underline || brackets.contains(&coord);
+
                 let line = self.buffer.contents.get(row);
                 let char = line
                     .map(|l| match col.cmp(&l.len()) {
                         Ordering::Less => {
                             let ch = l[col];
                             match ch {
+                                _ if self.masked => Some(('•', 80)),
                                 '\t' => Some(('⇨', 80)),
-                                other => Some((other, 230)),
+                                other if figure_rows[row_offset] => Some((other, 80)),
+                                other => {
+                                    let style = styles
+                                        .get(row_offset)
+                                        .and_then(|s| s.get(col))
+                                        .map_or(Style::Plain, |s| *s);
+                                    if self.unsure.contains(&coord) {
+                                        Some((other, UNSURE_WEIGHT))
+                                    } else {
+                                        Some((other, style.weight()))
+                                    }
+                                }
                             }
                         }
                         Ordering::Equal => {
@@ -544,3 +1664,146 @@ impl Widget for TextWindow {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(text: &str, dimensions: Coord) -> TextWindow {
+        TextWindow::new(
+            TextBuffer::from_string(text),
+            Rc::new(Atlas::new()),
+            Metrics::new(DEFAULT_CHAR_HEIGHT),
+            dimensions,
+        )
+    }
+
+    fn carat(coord: Coord) -> Carat {
+        Carat {
+            coord,
+            ink: Ink::new(),
+        }
+    }
+
+    fn splice(w: &mut TextWindow, at: Coord, text: &str) {
+        w.replace(Replace::splice(at, TextBuffer::from_string(text)));
+    }
+
+    #[test]
+    fn test_rewrite_frozen() {
+        // Like a shell: a command half-written after the prompt, when more output comes in and
+        // redraws the prompt's line.
+        let mut w = window("$ ", (10, 40));
+        w.frozen_until = (0, 2);
+        splice(&mut w, (0, 2), "ls");
+        w.rewrite_frozen((0, 0), TextBuffer::from_string("done\n$ "));
+        assert_eq!(w.buffer.content_string(), "done\n$ ls");
+        assert_eq!(w.frozen_until, (1, 2));
+
+        // Undo and redo still apply to the command, now on the next line.
+        w.undo();
+        assert_eq!(w.buffer.content_string(), "done\n$ ");
+        w.redo();
+        assert_eq!(w.buffer.content_string(), "done\n$ ls");
+    }
+
+    #[test]
+    fn test_erase() {
+        let mut w = window("hello world", (10, 40));
+        let (width, height) = (w.grid_metrics.width as f32, w.grid_metrics.height as f32);
+        // Two strokes in one gesture, over "hello" and "wo".
+        let mut ink = Ink::new();
+        for (from, to) in [(0.5, 4.5), (6.5, 7.5)] {
+            ink.push(from * width, height / 2.0, 0.0);
+            ink.push(to * width, height / 2.0, 0.1);
+            ink.pen_up();
+        }
+        w.erase(ink);
+        assert_eq!(w.buffer.content_string(), "        rld");
+        // It's one edit, however many runs of cells it blanks out.
+        assert_eq!(w.undos.len(), 1);
+        w.undo();
+        assert_eq!(w.buffer.content_string(), "hello world");
+    }
+
+    #[test]
+    fn test_mirror_at_carats() {
+        let mut w = window("ab\ncd\nef", (10, 40));
+        w.selection = Selection::Multi {
+            carats: vec![carat((0, 1)), carat((1, 1)), carat((2, 1))],
+        };
+        // "XY" written in at the first carat, as `ink_row` does it, is repeated at the others.
+        splice(&mut w, (0, 1), "X");
+        splice(&mut w, (0, 2), "Y");
+        w.mirror_at_carats(&[((0, 1), 'X', true), ((0, 2), 'Y', true)]);
+        assert_eq!(w.buffer.content_string(), "aXYb\ncXYd\neXYf");
+        // All the repeats come out in one go, leaving the original writing.
+        w.undo();
+        assert_eq!(w.buffer.content_string(), "aXYb\ncd\nef");
+
+        // Carats later on the same line are pushed along by each splice.
+        let mut w = window("abcd", (10, 40));
+        w.selection = Selection::Multi {
+            carats: vec![carat((0, 0)), carat((0, 2))],
+        };
+        splice(&mut w, (0, 0), "X");
+        w.mirror_at_carats(&[((0, 0), 'X', true)]);
+        assert_eq!(w.buffer.content_string(), "XabXcd");
+        match &w.selection {
+            Selection::Multi { carats } => {
+                let coords: Vec<Coord> = carats.iter().map(|c| c.coord).collect();
+                assert_eq!(coords, vec![(0, 0), (0, 3)]);
+            }
+            _ => panic!("expected the carats to stay"),
+        }
+    }
+
+    #[test]
+    fn test_trim_frozen() {
+        let mut w = window("0\n1\n2\n3\n4", (10, 40));
+        w.frozen_until = (3, 0);
+        splice(&mut w, (4, 1), "x");
+        w.selection = Selection::Single {
+            carat: carat((4, 0)),
+        };
+        assert_eq!(w.trim_frozen(2), 2);
+        assert_eq!(w.buffer.content_string(), "2\n3\n4x");
+        assert_eq!(w.frozen_until, (1, 0));
+        match &w.selection {
+            Selection::Single { carat } => assert_eq!(carat.coord, (2, 0)),
+            _ => panic!("expected the carat to stay"),
+        }
+        // The undo history moves up along with the text.
+        w.undo();
+        assert_eq!(w.buffer.content_string(), "2\n3\n4");
+        // Only frozen lines are trimmed.
+        assert_eq!(w.trim_frozen(10), 1);
+        assert_eq!(w.buffer.content_string(), "3\n4");
+    }
+
+    #[test]
+    fn test_wrap() {
+        let mut w = window("abcdefghij\nxy", (4, 4));
+        w.set_wrap(true);
+        assert_eq!(w.screen_rows(), vec![(0, 0), (0, 4), (0, 8), (1, 0)]);
+        // Screen positions map to the buffer, and back.
+        assert_eq!(w.relative((1, 2)), (0, 6));
+        w.selection = Selection::Single {
+            carat: carat((0, 9)),
+        };
+        assert!(matches!(w.selection(), Selection::Single { carat: (2, 1) }));
+        // Anything past the end of a line goes on its last row.
+        w.selection = Selection::Single {
+            carat: carat((0, 12)),
+        };
+        assert!(matches!(w.selection(), Selection::Single { carat: (2, 4) }));
+
+        // Resizing wraps at the new width; the end-of-line marker takes a cell of its own.
+        w.resize((4, 5));
+        assert_eq!(w.screen_rows(), vec![(0, 0), (0, 5), (0, 10), (1, 0)]);
+        // Scrolling back up to a position stops at the row it's on, not the start of its line.
+        w.origin = (1, 0);
+        w.scroll_into_view((0, 7));
+        assert_eq!(w.origin, (0, 5));
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Stop listing after this many rows; past that, the tree isn't much use for browsing anyway.
+const MAX_ROWS: usize = 1024;
+
+/// A browsable view of a directory: which subdirectories are expanded, and which entry (if any)
+/// has its file operations showing. The listing is read when something changes, not every
+/// time the tree is drawn.
+#[derive(Clone, Debug)]
+pub struct FileTree {
+    pub root: PathBuf,
+    expanded: BTreeSet<PathBuf>,
+    pub selected: Option<PathBuf>,
+    rows: Vec<TreeRow>,
+    /// Which page of rows is showing.
+    pub page: usize,
+}
+
+/// One visible entry in the tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeRow {
+    pub path: PathBuf,
+    pub depth: usize,
+    pub is_dir: bool,
+    pub expanded: bool,
+}
+
+impl TreeRow {
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map_or(String::new(), |n| n.to_string_lossy().into_owned())
+    }
+}
+
+impl FileTree {
+    pub fn new(root: PathBuf) -> io::Result<FileTree> {
+        let mut tree = FileTree {
+            root,
+            expanded: BTreeSet::new(),
+            selected: None,
+            rows: vec![],
+            page: 0,
+        };
+        tree.refresh()?;
+        Ok(tree)
+    }
+
+    /// Expand a collapsed directory, or collapse an expanded one.
+    pub fn toggle(&mut self, path: &Path) -> io::Result<()> {
+        if !self.expanded.remove(path) {
+            self.expanded.insert(path.to_path_buf());
+        }
+        self.refresh()
+    }
+
+    /// The visible entries, in order: directories first, then files, each sorted by name, with
+    /// the contents of expanded directories just after them. Hidden files are left out.
+    pub fn rows(&self) -> &[TreeRow] {
+        &self.rows
+    }
+
+    /// Read the listing again, after files have been added or removed.
+    pub fn refresh(&mut self) -> io::Result<()> {
+        let mut rows = vec![];
+        self.list(&self.root, 0, &mut rows)?;
+        self.rows = rows;
+        Ok(())
+    }
+
+    fn list(&self, dir: &Path, depth: usize, rows: &mut Vec<TreeRow>) -> io::Result<()> {
+        let mut entries: Vec<(bool, PathBuf)> = fs::read_dir(dir)?
+            .filter_map(|e| e.ok())
+            .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+            .map(|e| (!e.path().is_dir(), e.path()))
+            .collect();
+        entries.sort();
+        for (is_file, path) in entries {
+            if rows.len() >= MAX_ROWS {
+                break;
+            }
+            let expanded = !is_file && self.expanded.contains(&path);
+            rows.push(TreeRow {
+                path: path.clone(),
+                depth,
+                is_dir: !is_file,
+                expanded,
+            });
+            if expanded {
+                // An unreadable directory just shows up empty.
+                let _ = self.list(&path, depth + 1, rows);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An unused name for a copy of the file, alongside it: `notes-copy.md`, `notes-copy-2.md`...
+pub fn copy_path(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map_or(String::new(), |s| s.to_string_lossy().into_owned());
+    let ext = path
+        .extension()
+        .map_or(String::new(), |e| format!(".{}", e.to_string_lossy()));
+    (1..)
+        .map(|i| match i {
+            1 => path.with_file_name(format!("{stem}-copy{ext}")),
+            i => path.with_file_name(format!("{stem}-copy-{i}{ext}")),
+        })
+        .find(|p| !p.exists())
+        .expect("finding an unused copy name")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    #[test]
+    fn test_tree() {
+        let root = TempDir::new("tree");
+        fs::create_dir_all(root.join("notes/old")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("a.md"), "").unwrap();
+        fs::write(root.join("notes/plan.md"), "").unwrap();
+
+        let mut tree = FileTree::new(root.to_path_buf()).unwrap();
+        let names = |tree: &FileTree| -> Vec<(String, usize)> {
+            tree.rows().iter().map(|r| (r.name(), r.depth)).collect()
+        };
+        assert_eq!(names(&tree), vec![("notes".into(), 0), ("a.md".into(), 0)]);
+        tree.toggle(&root.join("notes")).unwrap();
+        assert_eq!(
+            names(&tree),
+            vec![
+                ("notes".into(), 0),
+                ("old".into(), 1),
+                ("plan.md".into(), 1),
+                ("a.md".into(), 0)
+            ]
+        );
+        tree.toggle(&root.join("notes")).unwrap();
+        assert_eq!(names(&tree).len(), 2);
+
+        // The listing only changes when it's read again.
+        fs::write(root.join("b.md"), "").unwrap();
+        assert_eq!(names(&tree).len(), 2);
+        tree.refresh().unwrap();
+        assert_eq!(names(&tree).len(), 3);
+
+        assert_eq!(copy_path(&root.join("a.md")), root.join("a-copy.md"));
+        fs::write(root.join("a-copy.md"), "").unwrap();
+        assert_eq!(copy_path(&root.join("a.md")), root.join("a-copy-2.md"));
+    }
+}
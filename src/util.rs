@@ -1,4 +1,16 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 pub fn rotate_queue<T>(queue: &mut VecDeque<T>, value: T, capacity: usize) -> Option<T> {
     let popped = if queue.len() == capacity {
@@ -11,3 +23,348 @@ pub fn rotate_queue<T>(queue: &mut VecDeque<T>, value: T, capacity: usize) -> Op
 
     popped
 }
+
+/// Today's date (in UTC) as `YYYY-MM-DD`.
+pub fn today() -> String {
+    date_of(SystemTime::now())
+}
+
+/// The date (in UTC) of a point in time, as `YYYY-MM-DD`.
+pub fn date_of(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (year, month, day) = civil_date((secs / 86400) as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// The local date as `YYYY-MM-DD` and time of day as `HH:MM`, in the system's time zone.
+pub fn local_now() -> (String, String) {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs()) as i64;
+    let offset = unsafe {
+        let time = secs as libc::time_t;
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&time, &mut tm).is_null() {
+            0
+        } else {
+            tm.tm_gmtoff as i64
+        }
+    };
+    let local = secs + offset;
+    let (year, month, day) = civil_date(local.div_euclid(86400));
+    let minutes = local.rem_euclid(86400) / 60;
+    (
+        format!("{year:04}-{month:02}-{day:02}"),
+        format!("{:02}:{:02}", minutes / 60, minutes % 60),
+    )
+}
+
+/// A short fingerprint of some data, for telling at a glance whether two copies are the same:
+/// the 32-bit FNV-1a hash, in hex. Not for anything that needs to resist tampering.
+pub fn short_hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0x811c9dc5u32, |hash, b| {
+        (hash ^ *b as u32).wrapping_mul(0x01000193)
+    });
+    format!("{hash:08x}")
+}
+
+/// Convert a count of days since the Unix epoch to a (year, month, day) triple.
+/// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+pub fn civil_date(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Parse a code point written as `U+` and some hex digits, like `U+00E9`.
+pub fn parse_code_point(s: &str) -> Option<char> {
+    let hex = s.strip_prefix("U+").or_else(|| s.strip_prefix("u+"))?;
+    if hex.is_empty() || hex.len() > 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()?.try_into().ok()
+}
+
+/// Replace every code point written out in the text, like `U+00E9`, with the character itself.
+/// Anything that isn't a valid code point is left alone.
+pub fn decode_code_points(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if matches!(chars[i], 'U' | 'u') && chars.get(i + 1) == Some(&'+') {
+            let digits = chars[i + 2..]
+                .iter()
+                .take(6)
+                .take_while(|c| c.is_ascii_hexdigit())
+                .count();
+            let written: String = chars[i..i + 2 + digits].iter().collect();
+            if let Some(c) = parse_code_point(&written) {
+                result.push(c);
+                i += 2 + digits;
+                continue;
+            }
+        }
+        result.push(chars[i]);
+        i += 1;
+    }
+    result
+}
+
+/// If the line has a code point written out just before the column, where it starts and the
+/// character it stands for.
+pub fn code_point_before(line: &[char], col: usize) -> Option<(usize, char)> {
+    let col = col.min(line.len());
+    (2..=8.min(col)).map(|len| col - len).find_map(|start| {
+        let written: String = line[start..col].iter().collect();
+        parse_code_point(&written).map(|c| (start, c))
+    })
+}
+
+/// Read a value saved with `save_json`, or the default if it hasn't been saved yet.
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path) -> io::Result<T> {
+    match File::open(path) {
+        Ok(file) => Ok(serde_json::from_reader(io::BufReader::new(file))?),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Save a value as JSON, atomically, for `load_json` to read back.
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    write_atomic(path, &serde_json::to_vec(value)?)
+}
+
+/// Write a file by way of a temporary file alongside it, so a crash part way through leaves
+/// either the old version or the new one, and never half of each. The new file keeps the old
+/// one's permissions, and a symlink is followed rather than replaced.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name to save to"))?;
+    let temp = path.with_file_name(format!(".{}.sill-tmp", name.to_string_lossy()));
+    let permissions = fs::metadata(&path).map(|m| m.permissions()).ok();
+    // Left over from a crash, maybe; it has to be created fresh for the mode to apply.
+    let _ = fs::remove_file(&temp);
+    let result = (|| {
+        // Never more open than the original, even for a moment.
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(permissions.as_ref().map_or(0o666, |p| p.mode()))
+            .open(&temp)?;
+        if let Some(permissions) = permissions {
+            // The umask can take away from the mode above; this puts it back as it was.
+            file.set_permissions(permissions)?;
+        }
+        file.write_all(contents)?;
+        // Without this, the rename can reach the disk before the data does.
+        file.sync_all()?;
+        fs::rename(&temp, &path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+        return result;
+    }
+    // And without this, the rename itself might not survive a crash.
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::File::open(dir)?.sync_all()
+}
+
+/// A path from the config file: relative to the home directory, unless it's absolute.
+pub fn home_path(path: &str) -> PathBuf {
+    let home = env::var_os("HOME").map_or(PathBuf::from("/"), PathBuf::from);
+    home.join(path)
+}
+
+/// Copy a file to `name~` alongside it, before it's overwritten. Nothing to back up is fine.
+pub fn backup(path: &Path) -> io::Result<()> {
+    let mut backup = path.as_os_str().to_os_string();
+    backup.push("~");
+    match fs::copy(path, PathBuf::from(backup)) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        other => other.map(|_| ()),
+    }
+}
+
+/// Whether the start of a file looks like binary data rather than text. Text in any encoding
+/// worth showing doesn't contain NUL bytes.
+pub fn looks_binary(bytes: &[u8]) -> bool {
+    bytes.contains(&0)
+}
+
+/// Bytes as hex, eight to a line so it fits on the screen: the offset, the bytes, and any
+/// printable ASCII among them.
+pub fn hex_dump(bytes: &[u8]) -> String {
+    let mut dump = String::new();
+    for (i, row) in bytes.chunks(8).enumerate() {
+        let hex: Vec<String> = row.iter().map(|b| format!("{b:02x}")).collect();
+        let ascii: String = row
+            .iter()
+            .map(|&b| match b {
+                b' '..=b'~' => b as char,
+                _ => '.',
+            })
+            .collect();
+        if i > 0 {
+            dump.push('\n');
+        }
+        dump.push_str(&format!("{:08x}  {:23}  {ascii}", i * 8, hex.join(" ")));
+    }
+    dump
+}
+
+/// Run a command to the end, writing the input to its stdin, if there is any, and collecting
+/// everything it writes out. A command that hasn't finished within the timeout is killed, and
+/// that's a `TimedOut` error.
+pub fn run_with_timeout(
+    command: &mut Command,
+    stdin: Option<&[u8]>,
+    timeout: Duration,
+) -> io::Result<Output> {
+    let mut child = command
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Everything's fed and drained on threads of its own, so a command that fills up one pipe
+    // while we're busy with another can't get stuck.
+    let writer = match (stdin, child.stdin.take()) {
+        (Some(input), Some(mut pipe)) => {
+            let input = input.to_vec();
+            Some(thread::spawn(move || pipe.write_all(&input)))
+        }
+        _ => None,
+    };
+    let read_all = |mut pipe: Box<dyn Read + Send>| {
+        thread::spawn(move || {
+            let mut bytes = vec![];
+            let _ = pipe.read_to_end(&mut bytes);
+            bytes
+        })
+    };
+    let stdout = read_all(Box::new(child.stdout.take().expect("piped stdout")));
+    let stderr = read_all(Box::new(child.stderr.take().expect("piped stderr")));
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if started.elapsed() > timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(
+                ErrorKind::TimedOut,
+                "didn't finish, so it was stopped",
+            ));
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    // A command that gives up early may not read all its input; its status says why.
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// A fresh directory for a test's files, removed again when it's dropped.
+#[cfg(test)]
+pub struct TempDir(PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    pub fn new(name: &str) -> TempDir {
+        let dir = env::temp_dir().join(format!("sill-{name}-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_points() {
+        assert_eq!(parse_code_point("U+00E9"), Some('é'));
+        assert_eq!(parse_code_point("U+D800"), None);
+        assert_eq!(parse_code_point("U+"), None);
+        assert_eq!(
+            decode_code_points("caf U+00E9 and u+2192, not U+ or U+XYZ"),
+            "caf é and →, not U+ or U+XYZ"
+        );
+        let line: Vec<char> = "see U+00E9".chars().collect();
+        assert_eq!(code_point_before(&line, line.len()), Some((4, 'é')));
+        assert_eq!(code_point_before(&line, 3), None);
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::new("write-atomic");
+        let path = dir.join("notes.txt");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+        backup(&path).unwrap();
+        write_atomic(&path, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::read_to_string(dir.join("notes.txt~")).unwrap(), "old");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(!dir.join(".notes.txt.sill-tmp").exists());
+        assert!(backup(&dir.join("missing.txt")).is_ok());
+    }
+
+    #[test]
+    fn test_hex_dump() {
+        assert!(looks_binary(b"\x7fELF\x02\x01\x00"));
+        assert!(!looks_binary("café\n".as_bytes()));
+        assert_eq!(
+            hex_dump(b"\x7fELF\x02\x01\x01\x00hi\n"),
+            "00000000  7f 45 4c 46 02 01 01 00  .ELF....\n00000008  68 69 0a                 hi."
+        );
+        assert_eq!(hex_dump(b""), "");
+    }
+
+    #[test]
+    fn test_short_hash() {
+        assert_eq!(short_hash(b""), "811c9dc5");
+        assert_eq!(short_hash(b"a"), "e40c292c");
+    }
+}
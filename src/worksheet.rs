@@ -0,0 +1,261 @@
+use anyhow::{anyhow, bail};
+use std::fs::File;
+use std::ops::Range;
+use std::path::Path;
+
+/// Pixels darker than this count as ink.
+const DARK: u8 = 128;
+/// Rows or columns of pixels that are at least this dark all the way across are grid lines.
+const LINE_FILL: f32 = 0.5;
+/// Ignore this fraction of each cell at the edges, so the grid lines don't bleed in.
+const CELL_INSET: f32 = 0.1;
+
+/// A greyscale image, one byte per pixel, row by row.
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn read_png(path: &Path) -> anyhow::Result<Image> {
+        let mut decoder = png::Decoder::new(File::open(path)?);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        let channels = info.color_type.samples();
+        let pixels = buffer[..info.buffer_size()]
+            .chunks(channels)
+            .map(|px| match px {
+                [gray] | [gray, _] => *gray,
+                [r, g, b] | [r, g, b, _] => ((*r as u32 + *g as u32 + *b as u32) / 3) as u8,
+                _ => 255,
+            })
+            .collect();
+        Ok(Image {
+            width: info.width as usize,
+            height: info.height as usize,
+            pixels,
+        })
+    }
+
+    fn dark(&self, x: usize, y: usize) -> bool {
+        self.pixels[y * self.width + x] < DARK
+    }
+}
+
+/// Find the grid lines in a profile of how much of each row (or column) is dark, returning the
+/// middle of each line.
+fn grid_lines(profile: &[f32]) -> Vec<usize> {
+    let mut lines = vec![];
+    let mut start = None;
+    for (i, fill) in profile.iter().chain([&0.0]).enumerate() {
+        match (start, *fill >= LINE_FILL) {
+            (None, true) => start = Some(i),
+            (Some(s), false) => {
+                lines.push((s + i - 1) / 2);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    lines
+}
+
+/// The spans between neighbouring grid lines, skipping any too thin to hold a character.
+fn spans(lines: &[usize]) -> Vec<Range<usize>> {
+    lines
+        .windows(2)
+        .map(|w| w[0]..w[1])
+        .filter(|r| r.len() > 8)
+        .collect()
+}
+
+/// A stroke, as a list of points scaled to the cell: `(0, 0)` is the top left, `(1, 1)` the
+/// bottom right.
+pub type Stroke = Vec<(f32, f32)>;
+
+/// Find the grid on a scanned worksheet, and trace the handwriting in every cell. Returns the
+/// strokes for each cell, row by row; empty cells have no strokes.
+pub fn read_sheet(image: &Image) -> anyhow::Result<Vec<Vec<Vec<Stroke>>>> {
+    let row_fill: Vec<f32> = (0..image.height)
+        .map(|y| (0..image.width).filter(|x| image.dark(*x, y)).count() as f32)
+        .map(|n| n / image.width as f32)
+        .collect();
+    let col_fill: Vec<f32> = (0..image.width)
+        .map(|x| (0..image.height).filter(|y| image.dark(x, *y)).count() as f32)
+        .map(|n| n / image.height as f32)
+        .collect();
+    let rows = spans(&grid_lines(&row_fill));
+    let cols = spans(&grid_lines(&col_fill));
+    if rows.is_empty() || cols.is_empty() {
+        bail!("couldn't find a grid in the image");
+    }
+    Ok(rows
+        .iter()
+        .map(|ys| cols.iter().map(|xs| trace_cell(image, xs, ys)).collect())
+        .collect())
+}
+
+/// Thin the ink in a cell down to lines a pixel wide, and follow them to get strokes.
+fn trace_cell(image: &Image, xs: &Range<usize>, ys: &Range<usize>) -> Vec<Stroke> {
+    let inset = |r: &Range<usize>| {
+        let margin = (r.len() as f32 * CELL_INSET).ceil() as usize;
+        (r.start + margin)..(r.end - margin)
+    };
+    let (inner_xs, inner_ys) = (inset(xs), inset(ys));
+    let (width, height) = (inner_xs.len(), inner_ys.len());
+    let mut bitmap: Vec<bool> = inner_ys
+        .clone()
+        .flat_map(|y| inner_xs.clone().map(move |x| (x, y)))
+        .map(|(x, y)| image.dark(x, y))
+        .collect();
+    thin(&mut bitmap, width, height);
+
+    let scale = |x: usize, y: usize| {
+        (
+            (x + inner_xs.start - xs.start) as f32 / xs.len() as f32,
+            (y + inner_ys.start - ys.start) as f32 / ys.len() as f32,
+        )
+    };
+    let neighbours = |i: usize| {
+        let (x, y) = ((i % width) as isize, (i / width) as isize);
+        NEIGHBOURS.iter().filter_map(move |(dx, dy)| {
+            let (nx, ny) = (x + dx, y + dy);
+            let inside = nx >= 0 && ny >= 0 && (nx as usize) < width && (ny as usize) < height;
+            inside.then(|| ny as usize * width + nx as usize)
+        })
+    };
+
+    let mut strokes = vec![];
+    loop {
+        // Start from the end of a line where possible, so each one is traced in one go.
+        let remaining = bitmap.iter().enumerate().filter(|(_, on)| **on);
+        let start = remaining
+            .clone()
+            .map(|(i, _)| i)
+            .find(|i| neighbours(*i).filter(|n| bitmap[*n]).count() == 1)
+            .or_else(|| remaining.map(|(i, _)| i).next());
+        let mut at = match start {
+            Some(at) => at,
+            None => break,
+        };
+        let mut stroke = vec![];
+        loop {
+            bitmap[at] = false;
+            stroke.push(scale(at % width, at / width));
+            match neighbours(at).find(|n| bitmap[*n]) {
+                Some(next) => at = next,
+                None => break,
+            }
+        }
+        // Specks of dust or stray marks aren't worth keeping.
+        if stroke.len() > 2 {
+            strokes.push(stroke);
+        }
+    }
+    strokes
+}
+
+/// Clockwise from the top.
+const NEIGHBOURS: [(isize, isize); 8] = [
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+];
+
+/// Zhang-Suen thinning: peel pixels off the edges of each shape until only a skeleton one pixel
+/// wide is left.
+fn thin(bitmap: &mut [bool], width: usize, height: usize) {
+    let get = |bitmap: &[bool], x: usize, y: usize, (dx, dy): (isize, isize)| {
+        let (nx, ny) = (x as isize + dx, y as isize + dy);
+        nx >= 0
+            && ny >= 0
+            && (nx as usize) < width
+            && (ny as usize) < height
+            && bitmap[ny as usize * width + nx as usize]
+    };
+    loop {
+        let mut changed = false;
+        for pass in 0..2 {
+            let mut remove = vec![];
+            for y in 0..height {
+                for x in 0..width {
+                    if !bitmap[y * width + x] {
+                        continue;
+                    }
+                    let p: Vec<bool> = NEIGHBOURS.iter().map(|d| get(bitmap, x, y, *d)).collect();
+                    let count = p.iter().filter(|on| **on).count();
+                    let transitions = (0..8).filter(|i| !p[*i] && p[(i + 1) % 8]).count();
+                    let (n, e, s, w) = (p[0], p[2], p[4], p[6]);
+                    // Not all three of the given neighbours are set.
+                    let gap = |a: bool, b: bool, c: bool| !(a && b && c);
+                    let clear = if pass == 0 {
+                        gap(n, e, s) && gap(e, s, w)
+                    } else {
+                        gap(n, e, w) && gap(n, s, w)
+                    };
+                    if (2..=6).contains(&count) && transitions == 1 && clear {
+                        remove.push(y * width + x);
+                    }
+                }
+            }
+            changed |= !remove.is_empty();
+            for i in remove {
+                bitmap[i] = false;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+/// Read a worksheet image and return its strokes; see `read_sheet`.
+pub fn import(path: &Path) -> anyhow::Result<Vec<Vec<Vec<Stroke>>>> {
+    let image = Image::read_png(path).map_err(|e| anyhow!("reading {}: {e}", path.display()))?;
+    read_sheet(&image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_sheet() {
+        // A 2x2 grid of 40px cells, with a thick diagonal stroke in the top right.
+        let (width, height) = (81, 81);
+        let mut pixels = vec![255; width * height];
+        for i in 0..81 {
+            for line in [0, 40, 80] {
+                pixels[line * width + i] = 0;
+                pixels[i * width + line] = 0;
+            }
+        }
+        for t in 10..30 {
+            for thickness in 0..3 {
+                pixels[(t + thickness) * width + 40 + t] = 0;
+            }
+        }
+        let image = Image {
+            width,
+            height,
+            pixels,
+        };
+        let sheet = read_sheet(&image).unwrap();
+        assert_eq!(sheet.len(), 2);
+        assert_eq!(sheet[0].len(), 2);
+        assert!(sheet[0][0].is_empty());
+        assert!(sheet[1][1].is_empty());
+        let strokes = &sheet[0][1];
+        assert_eq!(strokes.len(), 1);
+        let (x, y) = strokes[0][0];
+        assert!(x > 0.1 && x < 0.9 && y > 0.1 && y < 0.9);
+    }
+}
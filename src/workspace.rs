@@ -0,0 +1,156 @@
+use crate::tasks;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Per-workspace config overrides, kept at the root of the workspace.
+pub const CONFIG_FILE: &str = ".sill.toml";
+/// Stop walking the tree after this many files; anything bigger isn't a notes folder.
+const MAX_FILES: usize = 4096;
+/// Files bigger than this are skipped when searching.
+const MAX_SEARCH_BYTES: u64 = 1 << 20;
+const MAX_MATCHES: usize = 256;
+
+/// A directory opened as a project: the files in it, for browsing and searching.
+#[derive(Clone)]
+pub struct Workspace {
+    pub root: PathBuf,
+    /// Paths relative to the root, in order. Hidden files and directories are left out.
+    pub files: Vec<PathBuf>,
+}
+
+/// A line in one of the workspace's files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Match {
+    pub path: PathBuf,
+    pub row: usize,
+    pub line: String,
+}
+
+fn walk(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)?.filter_map(|e| e.ok()).collect();
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        if files.len() >= MAX_FILES {
+            break;
+        }
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            walk(root, &path, files)?;
+        } else if file_type.is_file() {
+            if let Ok(relative) = path.strip_prefix(root) {
+                files.push(relative.to_path_buf());
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Workspace {
+    pub fn open(root: PathBuf) -> io::Result<Workspace> {
+        let mut files = vec![];
+        walk(&root, &root, &mut files)?;
+        Ok(Workspace { root, files })
+    }
+
+    pub fn name(&self) -> String {
+        self.root
+            .file_name()
+            .map_or("/".to_string(), |n| n.to_string_lossy().into_owned())
+    }
+
+    /// Where this workspace's session is kept, relative to the data directory.
+    pub fn session_file(&self) -> String {
+        let escaped = self
+            .root
+            .to_string_lossy()
+            .replace('%', "%25")
+            .replace('/', "%2F");
+        format!("workspaces/{escaped}.json")
+    }
+
+    /// Every line in the matching files that the predicate picks out, up to a limit. Files that
+    /// are too big or aren't text are skipped.
+    fn scan(&self, mut find: impl FnMut(&[&str]) -> Vec<usize>) -> Vec<Match> {
+        let mut matches = vec![];
+        for path in &self.files {
+            let full_path = self.root.join(path);
+            let small = fs::metadata(&full_path).is_ok_and(|m| m.len() <= MAX_SEARCH_BYTES);
+            let contents = match fs::read_to_string(&full_path) {
+                Ok(contents) if small => contents,
+                _ => continue,
+            };
+            let lines: Vec<&str> = contents.lines().collect();
+            for row in find(&lines) {
+                matches.push(Match {
+                    path: path.clone(),
+                    row,
+                    line: lines[row].trim().to_string(),
+                });
+                if matches.len() >= MAX_MATCHES {
+                    return matches;
+                }
+            }
+        }
+        matches
+    }
+
+    /// The lines containing the query, in every file.
+    pub fn search(&self, query: &str) -> Vec<Match> {
+        self.scan(|lines| {
+            (0..lines.len())
+                .filter(|row| lines[*row].contains(query))
+                .collect()
+        })
+    }
+
+    /// The TODOs and unticked checkboxes in every file.
+    pub fn tasks(&self) -> Vec<Match> {
+        self.scan(|lines| {
+            tasks::scan(lines.iter().copied())
+                .into_iter()
+                .filter(|task| task.checked != Some(true))
+                .map(|task| task.row)
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    #[test]
+    fn test_workspace() {
+        let root = TempDir::new("workspace");
+        fs::create_dir_all(root.join("notes")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("todo.md"), "- [ ] milk\n- [x] eggs\n").unwrap();
+        fs::write(root.join("notes/plan.txt"), "intro\nTODO: milk the cow\n").unwrap();
+        fs::write(root.join(".git/config"), "milk").unwrap();
+
+        let workspace = Workspace::open(root.to_path_buf()).unwrap();
+        assert_eq!(
+            workspace.files,
+            vec![PathBuf::from("notes/plan.txt"), PathBuf::from("todo.md")]
+        );
+        let found: Vec<(PathBuf, usize)> = workspace
+            .search("milk")
+            .into_iter()
+            .map(|m| (m.path, m.row))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (PathBuf::from("notes/plan.txt"), 1),
+                (PathBuf::from("todo.md"), 0)
+            ]
+        );
+        assert_eq!(workspace.tasks().len(), 2);
+    }
+}